@@ -0,0 +1,199 @@
+// Copyright (c) 2020 Anatoly Ikorsky
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use mysql_common::value::{
+    convert::{ConvIr, FromValue, FromValueError},
+    Value,
+};
+
+use std::marker::PhantomData;
+
+/// Wrapper that decodes a MySQL `BIT(n)` value.
+///
+/// MySQL returns `BIT(n)` values as big-endian byte strings, so they can't be converted to
+/// integers directly. Use `Bit<bool>` for `BIT(1)` and `Bit<u64>` for `BIT(n)` where `n <= 64`.
+/// Wider values should be taken as `Vec<u8>`.
+///
+/// ```rust
+/// # use mysql_async::{from_value, Bit, Value};
+/// let value = Value::Bytes(vec![0x01, 0x02]);
+/// assert_eq!(from_value::<Bit<u64>>(value), Bit(0x0102));
+/// assert_eq!(from_value::<Bit<bool>>(Value::Bytes(vec![0x01])), Bit(true));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Bit<T>(pub T);
+
+impl<T> Bit<T> {
+    /// Returns the wrapped value.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl From<Bit<u64>> for Value {
+    fn from(Bit(x): Bit<u64>) -> Value {
+        Value::UInt(x)
+    }
+}
+
+impl From<Bit<bool>> for Value {
+    fn from(Bit(x): Bit<bool>) -> Value {
+        Value::Int(x as i64)
+    }
+}
+
+/// Interprets the given `BIT` value as an unsigned integer.
+fn bit_to_u64(v: &Value) -> Option<u64> {
+    match *v {
+        Value::Bytes(ref bytes) if !bytes.is_empty() && bytes.len() <= 8 => Some(
+            bytes
+                .iter()
+                .fold(0_u64, |acc, byte| (acc << 8) | u64::from(*byte)),
+        ),
+        Value::Int(x) if x >= 0 => Some(x as u64),
+        Value::UInt(x) => Some(x),
+        _ => None,
+    }
+}
+
+/// Intermediate result of a `Value`-to-`Bit<T>` conversion.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BitIr<T> {
+    value: Value,
+    output: u64,
+    _phantom: PhantomData<T>,
+}
+
+impl ConvIr<Bit<u64>> for BitIr<u64> {
+    fn new(v: Value) -> Result<Self, FromValueError> {
+        match bit_to_u64(&v) {
+            Some(output) => Ok(BitIr {
+                value: v,
+                output,
+                _phantom: PhantomData,
+            }),
+            None => Err(FromValueError(v)),
+        }
+    }
+
+    fn commit(self) -> Bit<u64> {
+        Bit(self.output)
+    }
+
+    fn rollback(self) -> Value {
+        self.value
+    }
+}
+
+impl ConvIr<Bit<bool>> for BitIr<bool> {
+    fn new(v: Value) -> Result<Self, FromValueError> {
+        match bit_to_u64(&v) {
+            Some(output @ 0) | Some(output @ 1) => Ok(BitIr {
+                value: v,
+                output,
+                _phantom: PhantomData,
+            }),
+            _ => Err(FromValueError(v)),
+        }
+    }
+
+    fn commit(self) -> Bit<bool> {
+        Bit(self.output == 1)
+    }
+
+    fn rollback(self) -> Value {
+        self.value
+    }
+}
+
+impl FromValue for Bit<u64> {
+    type Intermediate = BitIr<u64>;
+}
+
+impl FromValue for Bit<bool> {
+    type Intermediate = BitIr<bool>;
+}
+
+#[cfg(test)]
+mod test {
+    use mysql_common::value::{convert::from_value_opt, Value};
+
+    use super::Bit;
+    use crate::{prelude::*, test_misc::get_opts, Conn};
+
+    #[test]
+    fn should_convert_bit() {
+        let value = Value::Bytes(vec![0x01]);
+        assert_eq!(
+            from_value_opt::<Bit<bool>>(value.clone()).unwrap(),
+            Bit(true)
+        );
+        assert_eq!(from_value_opt::<Bit<u64>>(value).unwrap(), Bit(1));
+
+        let value = Value::Bytes(vec![0x00]);
+        assert_eq!(from_value_opt::<Bit<bool>>(value).unwrap(), Bit(false));
+
+        let value = Value::Bytes(vec![0xff; 8]);
+        assert_eq!(from_value_opt::<Bit<u64>>(value).unwrap(), Bit(u64::MAX));
+
+        let value = Value::Bytes(vec![0x12, 0x34, 0x56]);
+        assert_eq!(
+            from_value_opt::<Bit<u64>>(value.clone()).unwrap(),
+            Bit(0x12_34_56)
+        );
+        assert!(from_value_opt::<Bit<bool>>(value).is_err());
+
+        assert_eq!(from_value_opt::<Bit<u64>>(Value::Int(5)).unwrap(), Bit(5));
+        assert!(from_value_opt::<Bit<u64>>(Value::Int(-5)).is_err());
+
+        // wider than 64 bits
+        assert!(from_value_opt::<Bit<u64>>(Value::Bytes(vec![0x01; 9])).is_err());
+        assert!(from_value_opt::<Bit<u64>>(Value::Bytes(vec![])).is_err());
+        assert!(from_value_opt::<Bit<u64>>(Value::NULL).is_err());
+        assert_eq!(
+            from_value_opt::<Option<Bit<u64>>>(Value::NULL).unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn should_decode_bit_columns() -> super::super::Result<()> {
+        let mut conn = Conn::new(get_opts()).await?;
+        conn.query_drop("CREATE TEMPORARY TABLE bits (a BIT(1), b BIT(8), c BIT(64))")
+            .await?;
+        conn.exec_drop(
+            "INSERT INTO bits (a, b, c) VALUES (?, ?, ?), (?, ?, ?)",
+            (
+                Bit(true),
+                Bit(0b1010_0101_u64),
+                Bit(u64::MAX),
+                Bit(false),
+                Bit(0_u64),
+                Bit(0x0102_0304_0506_0708_u64),
+            ),
+        )
+        .await?;
+
+        let expected = vec![
+            (Bit(true), Bit(0b1010_0101), Bit(u64::MAX)),
+            (Bit(false), Bit(0), Bit(0x0102_0304_0506_0708)),
+        ];
+        let query = "SELECT a, b, c FROM bits ORDER BY b DESC";
+        let text: Vec<(Bit<bool>, Bit<u64>, Bit<u64>)> = conn.query(query).await?;
+        assert_eq!(text, expected);
+        let binary: Vec<(Bit<bool>, Bit<u64>, Bit<u64>)> = conn.exec(query, ()).await?;
+        assert_eq!(binary, expected);
+
+        // raw representation is still available
+        let raw: Vec<Vec<u8>> = conn.query("SELECT c FROM bits ORDER BY b DESC").await?;
+        assert_eq!(raw[1], vec![1, 2, 3, 4, 5, 6, 7, 8]);
+
+        conn.disconnect().await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,111 @@
+// Copyright (c) 2020 Anatoly Ikorsky
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use crate::{error::*, prelude::Queryable, Conn, Opts};
+
+/// Handle, that is able to kill a query or a connection from another task
+/// (see [`Conn::abort_handle`]).
+///
+/// Every call opens a side connection using options of the original connection
+/// and issues `KILL` using the captured connection id.
+#[derive(Debug, Clone)]
+pub struct ConnAbortHandle {
+    id: u32,
+    opts: Opts,
+}
+
+impl ConnAbortHandle {
+    /// Returns the id of the target connection.
+    pub fn connection_id(&self) -> u32 {
+        self.id
+    }
+
+    /// Terminates the statement, that the target connection is currently executing,
+    /// leaving the connection intact (`KILL QUERY`).
+    pub async fn kill_query(&self) -> Result<()> {
+        self.kill("KILL QUERY").await
+    }
+
+    /// Terminates the target connection (`KILL CONNECTION`).
+    pub async fn kill_connection(&self) -> Result<()> {
+        self.kill("KILL CONNECTION").await
+    }
+
+    async fn kill(&self, command: &str) -> Result<()> {
+        let mut conn = Conn::new(self.opts.clone()).await?;
+        let result = conn.query_drop(format!("{} {}", command, self.id)).await;
+        conn.disconnect().await?;
+        result
+    }
+}
+
+impl Conn {
+    /// Returns a handle, that could be used to kill a query or this connection
+    /// while it is borrowed by a running query.
+    ///
+    /// ```rust
+    /// # use mysql_async::{prelude::*, test_misc::get_opts, Conn};
+    /// # #[tokio::main]
+    /// # async fn main() -> mysql_async::Result<()> {
+    /// let mut conn = Conn::new(get_opts()).await?;
+    /// let handle = conn.abort_handle();
+    ///
+    /// tokio::spawn(async move {
+    ///     tokio::time::delay_for(std::time::Duration::from_millis(100)).await;
+    ///     handle.kill_query().await
+    /// });
+    ///
+    /// // `SLEEP` returns `1` if interrupted
+    /// let interrupted: Option<u8> = conn.query_first("SELECT SLEEP(10)").await?;
+    /// assert_eq!(interrupted, Some(1));
+    /// # conn.disconnect().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn abort_handle(&self) -> ConnAbortHandle {
+        ConnAbortHandle {
+            id: self.id(),
+            opts: self.opts().clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures_util::future::join;
+
+    use std::time::{Duration, Instant};
+
+    use crate::{prelude::*, test_misc::get_opts, Conn};
+
+    #[tokio::test]
+    async fn should_abort_query_and_connection_via_handle() -> super::Result<()> {
+        let mut conn = Conn::new(get_opts()).await?;
+        let handle = conn.abort_handle();
+        assert_eq!(handle.connection_id(), conn.id());
+
+        let start = Instant::now();
+        let (result, killed) = join(conn.query_first("SELECT SLEEP(10)"), async {
+            tokio::time::delay_for(Duration::from_millis(500)).await;
+            handle.kill_query().await
+        })
+        .await;
+        killed?;
+        // `SLEEP` returns `1` if interrupted
+        assert_eq!(result?, Some(1_u8));
+        assert!(start.elapsed() < Duration::from_secs(10));
+
+        // the connection is still usable
+        conn.ping().await?;
+
+        handle.kill_connection().await?;
+        assert!(conn.ping().await.is_err());
+
+        Ok(())
+    }
+}
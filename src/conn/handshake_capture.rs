@@ -0,0 +1,67 @@
+// Copyright (c) 2020 Anatoly Ikorsky
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+/// Direction of a captured packet.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum FrameDirection {
+    /// Packet sent by the server.
+    ServerToClient,
+    /// Packet sent by the driver.
+    ClientToServer,
+}
+
+/// Packet of the connection phase, captured for debugging (see [`crate::Opts::capture_handshake`]).
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct HandshakeFrame {
+    /// Direction of the packet.
+    pub direction: FrameDirection,
+    /// Sequence id of the packet.
+    pub seq_id: u8,
+    /// Payload of the packet (without the packet header).
+    pub payload: Vec<u8>,
+}
+
+/// Shared buffer for captured handshake packets.
+///
+/// Two buffers are equal if they point to the same vector.
+#[derive(Clone)]
+pub(crate) struct HandshakeCapture(pub(crate) Arc<Mutex<Vec<HandshakeFrame>>>);
+
+impl HandshakeCapture {
+    /// Appends a frame to the buffer.
+    pub(crate) fn push(&self, direction: FrameDirection, seq_id: u8, payload: &[u8]) {
+        let frame = HandshakeFrame {
+            direction,
+            seq_id,
+            payload: payload.to_vec(),
+        };
+        match self.0.lock() {
+            Ok(mut frames) => frames.push(frame),
+            Err(poisoned) => poisoned.into_inner().push(frame),
+        }
+    }
+}
+
+impl PartialEq for HandshakeCapture {
+    fn eq(&self, other: &HandshakeCapture) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for HandshakeCapture {}
+
+impl fmt::Debug for HandshakeCapture {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "HandshakeCapture(Arc<Mutex<Vec<HandshakeFrame>>>)")
+    }
+}
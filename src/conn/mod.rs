@@ -9,43 +9,55 @@
 pub use mysql_common::named_params;
 
 use mysql_common::{
-    constants::DEFAULT_MAX_ALLOWED_PACKET,
+    constants::{SessionStateType, DEFAULT_MAX_ALLOWED_PACKET},
     crypto,
+    io::ReadMysqlExt,
     packets::{
-        parse_auth_switch_request, parse_err_packet, parse_handshake_packet, parse_ok_packet,
-        AuthPlugin, AuthSwitchRequest, ErrPacket, HandshakeResponse, OkPacket, OkPacketKind,
-        SslRequest,
+        column_from_payload, parse_auth_switch_request, parse_err_packet, parse_handshake_packet,
+        parse_ok_packet, AuthPlugin, AuthSwitchRequest, ComStmtClose, ErrPacket, HandshakeResponse,
+        OkPacket, OkPacketKind, SessionStateChange, SessionStateInfo, SslRequest,
     },
+    row::convert::FromRow,
 };
+use tokio::sync::OwnedSemaphorePermit;
 
 use std::{
     borrow::Cow,
+    collections::{HashMap, VecDeque},
     fmt,
     future::Future,
-    mem,
+    io, mem,
+    net::{SocketAddr, ToSocketAddrs},
+    panic::Location,
     pin::Pin,
     str::FromStr,
+    sync::{Arc, Mutex},
     time::{Duration, Instant},
 };
 
 use crate::{
-    conn::{pool::Pool, stmt_cache::StmtCache},
+    conn::{handshake_capture::FrameDirection, pool::Pool, stmt_cache::StmtCache},
     consts::{CapabilityFlags, Command, StatusFlags},
     error::*,
+    from_value_opt,
     io::Stream,
     opts::Opts,
     queryable::{
         query_result::{QueryResult, ResultSetMeta},
-        transaction::TxStatus,
+        transaction::{is_implicit_rollback, TxStatus},
         BinaryProtocol, Queryable, TextProtocol,
     },
-    OptsBuilder,
+    Column, OptsBuilder, Row, SlowQuery, Value, Warning,
 };
 
+pub mod abort_handle;
+pub mod handshake_capture;
+#[cfg(feature = "legacy-auth")]
+mod old_password;
 pub mod pool;
 pub mod stmt_cache;
 
-/// Helper that asynchronously disconnects the givent connection on the default tokio executor.
+/// Helper that asynchronously disconnects the givent connection on the current runtime.
 fn disconnect(mut conn: Conn) {
     let disconnected = conn.inner.disconnected;
 
@@ -53,21 +65,29 @@ fn disconnect(mut conn: Conn) {
     conn.inner.disconnected = true;
 
     if !disconnected {
-        // We shouldn't call tokio::spawn if unwinding
+        // We shouldn't spawn if unwinding
         if std::thread::panicking() {
             return;
         }
 
         // Server will report broken connection if spawn fails.
         // this might fail if, say, the runtime is shutting down, but we've done what we could
-        if let Ok(handle) = tokio::runtime::Handle::try_current() {
-            handle.spawn(async move {
-                if let Ok(conn) = conn.cleanup_for_pool().await {
-                    let _ = conn.disconnect().await;
-                }
-            });
-        }
+        crate::runtime::try_spawn(async move {
+            if let Ok(conn) = conn.cleanup_for_pool().await {
+                let _ = conn.disconnect().await;
+            }
+        });
+    }
+}
+
+/// Reads PEM encoded server RSA public key from the given file.
+async fn read_server_public_key(path: &std::path::Path) -> Result<Vec<u8>> {
+    let key = tokio::fs::read(path).await?;
+    if !key.starts_with(b"-----BEGIN ") {
+        let message = format!("{} is not a PEM encoded public key", path.display());
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, message).into());
     }
+    Ok(key)
 }
 
 /// Mysql connection
@@ -82,14 +102,64 @@ struct ConnInner {
     last_err_packet: Option<ErrPacket<'static>>,
     pool: Option<Pool>,
     pending_result: Option<ResultSetMeta>,
+    /// Remaining parts of a split multi-statement query (see `Opts::split_oversized_queries`).
+    pending_queries: VecDeque<String>,
     tx_status: TxStatus,
     opts: Opts,
     last_io: Instant,
     wait_timeout: Duration,
+    /// Value of `innodb_rollback_on_timeout` (see `TxStatus::RolledBackByServer`).
+    rollback_on_timeout: bool,
     stmt_cache: StmtCache,
+    /// Statements prepared within the current transaction (see `Opts::isolate_tx_statements`).
+    tx_stmt_cache: StmtCache,
+    /// Statement cache is bypassed if `false` (see `Conn::set_stmt_cache_enabled`).
+    stmt_cache_enabled: bool,
+    /// Ids of dropped `OwnedStatement`s, that are closed before the next command.
+    dropped_stmts: Arc<Mutex<Vec<u32>>>,
+    /// `true` if `CLIENT_OPTIONAL_RESULTSET_METADATA` capability was negotiated.
+    optional_metadata: bool,
+    /// `true` if `resultset_metadata` session variable is `NONE`.
+    metadata_suppressed: bool,
+    /// Number of statements, that are prepared on the server side by this connection.
+    prepared_stmt_count: usize,
+    /// `true` while a step of a result processing, that can't be resumed, is in progress.
+    unresumable_step: bool,
+    /// Columns of the statement being executed, to decode a result set without metadata.
+    expected_columns: Option<Arc<[Column]>>,
+    /// Sequence id of the next packet, while the connection phase is captured
+    /// (see `Opts::capture_handshake`).
+    handshake_seq_id: Option<u8>,
     nonce: Vec<u8>,
     auth_plugin: AuthPlugin<'static>,
     auth_switched: bool,
+    /// Number of re-scrambles requested after the first auth switch (see `MAX_AUTH_RESCRAMBLES`).
+    auth_rescrambles: u8,
+    /// Text of the last executed statement (see `Opts::retain_last_statement`).
+    last_statement: Option<Arc<str>>,
+    /// Effective session `sql_mode`, that was set according to `Opts::sql_mode`.
+    sql_mode: Option<String>,
+    /// Cached session time zone (see `Conn::time_zone`).
+    time_zone: Option<String>,
+    /// Warnings produced by init commands (see `Conn::init_warnings`).
+    init_warnings: Vec<(String, Vec<Warning>)>,
+    /// Warnings of the last query (see `Opts::capture_warnings`).
+    captured_warnings: Vec<Warning>,
+    /// `true` while `SHOW WARNINGS` is executed to capture warnings.
+    capturing_warnings: bool,
+    /// Default database, as set on connect or via `Conn::select_db`
+    /// (or as reported by the server, see `Conn::current_schema`).
+    db_name: Option<String>,
+    /// `true` if the session access mode is `READ ONLY` (see `Conn::set_read_only`).
+    read_only: bool,
+    /// Permit of a pool's connection limiter (see `PoolOpts::with_limiter`).
+    limiter_permit: Option<OwnedSemaphorePermit>,
+    /// Location of the caller of a pending query (see `Opts::tag_caller_location`).
+    caller_location: Option<&'static Location<'static>>,
+    /// Number of savepoints created by `Transaction::nested` (used to name the next one).
+    savepoints: u64,
+    /// Generation of the pool this connection belongs to.
+    pool_epoch: usize,
     /// Connection is already disconnected.
     disconnected: bool,
 }
@@ -120,16 +190,40 @@ impl ConnInner {
             version: (0, 0, 0),
             id: 0,
             pending_result: None,
+            pending_queries: VecDeque::new(),
             pool: None,
             tx_status: TxStatus::None,
             last_io: Instant::now(),
             wait_timeout: Duration::from_secs(0),
+            rollback_on_timeout: false,
             stmt_cache: StmtCache::new(opts.stmt_cache_size()),
+            tx_stmt_cache: StmtCache::new(opts.stmt_cache_size()),
+            stmt_cache_enabled: true,
+            dropped_stmts: Default::default(),
+            optional_metadata: false,
+            metadata_suppressed: false,
+            expected_columns: None,
+            prepared_stmt_count: 0,
+            unresumable_step: false,
             socket: opts.socket().map(Into::into),
+            db_name: opts.db_name().map(Into::into),
             opts,
+            handshake_seq_id: None,
             nonce: Vec::default(),
             auth_plugin: AuthPlugin::MysqlNativePassword,
             auth_switched: false,
+            auth_rescrambles: 0,
+            last_statement: None,
+            time_zone: None,
+            sql_mode: None,
+            init_warnings: Vec::new(),
+            captured_warnings: Vec::new(),
+            capturing_warnings: false,
+            read_only: false,
+            limiter_permit: None,
+            caller_location: None,
+            savepoints: 0,
+            pool_epoch: 0,
             disconnected: false,
         }
     }
@@ -142,6 +236,97 @@ impl ConnInner {
             .as_mut()
             .ok_or(DriverError::ConnectionClosed.into())
     }
+
+    /// Returns `true` if the given auth switch request should be honored.
+    ///
+    /// The first switch is always allowed. Subsequent switch is only allowed if it's a
+    /// re-scramble, i.e. it requests the current plugin with a new nonce.
+    fn auth_switch_allowed(&self, auth_switch_request: &AuthSwitchRequest<'_>) -> bool {
+        !self.auth_switched
+            || (auth_switch_request.auth_plugin() == &self.auth_plugin
+                && auth_switch_request.plugin_data() != &*self.nonce)
+    }
+}
+
+/// Outcome of a single-row `INSERT ... ON DUPLICATE KEY UPDATE` (see [`Conn::upsert_outcome`]).
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum UpsertOutcome {
+    /// New row was inserted (affected rows is `1`).
+    Inserted,
+    /// Existing row was updated (affected rows is `2`).
+    Updated,
+    /// Existing row was left as is (affected rows is `0`).
+    Unchanged,
+}
+
+/// Counts of a bulk DML statement, parsed from the info string of its OK packet
+/// (see [`Conn::bulk_info`]).
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct BulkDmlInfo {
+    /// Number of processed rows (`Records` or `Rows matched` for `UPDATE`).
+    pub records: u64,
+    /// Number of rows, that duplicated existing unique values (for `LOAD DATA` it's the sum
+    /// of `Deleted` and `Skipped` rows, i.e. rows replaced or ignored). Always `0` for `UPDATE`.
+    pub duplicates: u64,
+    /// Number of warnings.
+    pub warnings: u64,
+}
+
+impl BulkDmlInfo {
+    /// Parses one of the following info strings:
+    ///
+    /// *   `Records: 3  Duplicates: 1  Warnings: 0` (`INSERT`, `ALTER TABLE`);
+    /// *   `Records: 3  Deleted: 0  Skipped: 1  Warnings: 0` (`LOAD DATA`);
+    /// *   `Rows matched: 3  Changed: 2  Warnings: 0` (`UPDATE`).
+    fn parse(info: &str) -> Option<Self> {
+        let mut keys = Vec::new();
+        let mut values = Vec::new();
+        for pair in info.trim().split("  ") {
+            let mut parts = pair.splitn(2, ": ");
+            keys.push(parts.next()?);
+            values.push(parts.next()?.trim().parse::<u64>().ok()?);
+        }
+        match (&*keys, &*values) {
+            (["Records", "Duplicates", "Warnings"], &[records, duplicates, warnings]) => {
+                Some(Self {
+                    records,
+                    duplicates,
+                    warnings,
+                })
+            }
+            (
+                ["Records", "Deleted", "Skipped", "Warnings"],
+                &[records, deleted, skipped, warnings],
+            ) => Some(Self {
+                records,
+                duplicates: deleted + skipped,
+                warnings,
+            }),
+            (["Rows matched", "Changed", "Warnings"], &[records, _, warnings]) => Some(Self {
+                records,
+                duplicates: 0,
+                warnings,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// State of a connection as seen by the client (see [`Conn::is_healthy`]).
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Health {
+    /// Connection is safe to use.
+    Idle,
+    /// Connection is alive, but needs a cleanup before it could be used.
+    Busy {
+        /// Result of a previous query is not yet consumed.
+        pending_result: bool,
+        /// Connection is in a transaction (or a dropped transaction requires a rollback).
+        in_transaction: bool,
+    },
+    /// Connection is disconnected or broken
+    /// (e.g. processing of a result was interrupted at a point, where it can't be resumed).
+    Dead,
 }
 
 /// MySql server connection.
@@ -176,6 +361,23 @@ impl Conn {
             .unwrap_or_default()
     }
 
+    /// Interprets the number of rows affected by the last single-row
+    /// `INSERT ... ON DUPLICATE KEY UPDATE` query.
+    ///
+    /// Returns `None` if there was no previous query on the connection or if the number
+    /// of affected rows doesn't correspond to a single-row upsert.
+    ///
+    /// Note that the interpretation relies on `CLIENT_FOUND_ROWS` capability being disabled
+    /// (as it is in this library), so that unchanged row gives `0` affected rows.
+    pub fn upsert_outcome(&self) -> Option<UpsertOutcome> {
+        match self.inner.last_ok_packet.as_ref()?.affected_rows() {
+            0 => Some(UpsertOutcome::Unchanged),
+            1 => Some(UpsertOutcome::Inserted),
+            2 => Some(UpsertOutcome::Updated),
+            _ => None,
+        }
+    }
+
     /// Text information, as reported by the server in the last OK packet, or an empty string.
     pub fn info(&self) -> Cow<'_, str> {
         self.inner
@@ -185,6 +387,29 @@ impl Conn {
             .unwrap_or_else(|| "".into())
     }
 
+    /// Parses [`Conn::info`] of the last bulk `INSERT`, `UPDATE` or `LOAD DATA` statement.
+    ///
+    /// Returns `None` if the info string is empty or has an unknown format
+    /// (e.g. it's empty for a single-row `INSERT`).
+    pub fn bulk_info(&self) -> Option<BulkDmlInfo> {
+        BulkDmlInfo::parse(&self.info())
+    }
+
+    /// Remote address of this connection.
+    ///
+    /// This is the address the connection was actually established to, i.e. after the host name
+    /// resolution. Returns `None` for a connection via socket or if the connection is closed.
+    pub fn peer_addr(&self) -> Option<SocketAddr> {
+        self.inner.stream.as_ref()?.peer_addr()
+    }
+
+    /// Local address of this connection.
+    ///
+    /// Returns `None` for a connection via socket or if the connection is closed.
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.inner.stream.as_ref()?.local_addr()
+    }
+
     /// Number of warnings, as reported by the server in the last OK packet, or `0`.
     pub fn get_warnings(&self) -> u16 {
         self.inner
@@ -194,10 +419,64 @@ impl Conn {
             .unwrap_or_default()
     }
 
+    /// Warnings produced by init commands of this connection (see [`Opts::init`]).
+    ///
+    /// Contains an init command along with its warnings for every command,
+    /// that produced warnings. Note that if [`Opts::strict_init`] is `true`, then
+    /// the connection fails instead.
+    pub fn init_warnings(&self) -> &[(String, Vec<Warning>)] {
+        &self.inner.init_warnings
+    }
+
+    /// Warnings of the last query, if [`Opts::capture_warnings`] is `true`.
+    ///
+    /// Empty if the query produced no warnings or its result is not yet consumed.
+    pub fn captured_warnings(&self) -> &[Warning] {
+        &self.inner.captured_warnings
+    }
+
+    /// Clears warnings captured for the previous query.
+    pub(crate) fn clear_captured_warnings(&mut self) {
+        if !self.inner.capturing_warnings {
+            self.inner.captured_warnings.clear();
+        }
+    }
+
+    /// Reads warnings of the last query, if [`Opts::capture_warnings`] is `true`
+    /// and the server reported warnings.
+    pub(crate) async fn capture_warnings(&mut self) -> Result<()> {
+        if !self.inner.opts.capture_warnings()
+            || self.inner.capturing_warnings
+            || self.get_warnings() == 0
+        {
+            return Ok(());
+        }
+
+        // `SHOW WARNINGS` overwrites the status of the last query
+        let status = self.inner.status;
+        let last_ok_packet = self.inner.last_ok_packet.clone();
+
+        self.inner.capturing_warnings = true;
+        let warnings = self.show_warnings().await;
+        self.inner.capturing_warnings = false;
+
+        self.inner.status = status;
+        self.inner.last_ok_packet = last_ok_packet;
+        self.inner.captured_warnings = warnings?;
+        Ok(())
+    }
+
     pub(crate) fn stream_mut(&mut self) -> Result<&mut Stream> {
         self.inner.stream_mut()
     }
 
+    /// Returns a buffer of a consumed packet to the stream (see `Opts::reuse_buffers`).
+    pub(crate) fn reuse_buffer(&mut self, buffer: Vec<u8>) {
+        if let Some(stream) = self.inner.stream.as_mut() {
+            stream.reuse_buffer(buffer);
+        }
+    }
+
     pub(crate) fn capabilities(&self) -> CapabilityFlags {
         self.inner.capabilities
     }
@@ -221,8 +500,16 @@ impl Conn {
         }
     }
 
-    /// Handles OK packet.
-    pub(crate) fn handle_ok(&mut self, ok_packet: OkPacket<'static>) {
+    /// Handles OK packet (`payload` is the raw packet).
+    pub(crate) fn handle_ok(&mut self, ok_packet: OkPacket<'static>, payload: &[u8]) {
+        if ok_packet.session_state_info().is_some() {
+            // `OkPacket` only keeps the first entry of the session state block
+            for info in schema_changes(payload) {
+                if let Ok(SessionStateChange::Schema(schema)) = info.decode() {
+                    self.inner.db_name = Some(String::from_utf8_lossy(&schema).into_owned());
+                }
+            }
+        }
         self.inner.status = ok_packet.status_flags();
         self.inner.last_err_packet = None;
         self.inner.last_ok_packet = Some(ok_packet);
@@ -230,6 +517,12 @@ impl Conn {
 
     /// Handles ERR packet.
     pub(crate) fn handle_err(&mut self, err_packet: ErrPacket<'static>) {
+        if self.inner.tx_status == TxStatus::InTransaction
+            && err_packet.is_error()
+            && is_implicit_rollback(err_packet.error_code(), self.inner.rollback_on_timeout)
+        {
+            self.inner.tx_status = TxStatus::RolledBackByServer;
+        }
         self.inner.status = StatusFlags::empty();
         self.inner.last_ok_packet = None;
         self.inner.last_err_packet = Some(err_packet);
@@ -243,6 +536,107 @@ impl Conn {
     /// Sets the given transaction status for this connection.
     pub(crate) fn set_tx_status(&mut self, tx_status: TxStatus) {
         self.inner.tx_status = tx_status;
+        if tx_status == TxStatus::None {
+            // statements of the transaction are closed before the next command
+            let mut dropped_stmts = self.inner.dropped_stmts.lock().unwrap();
+            while let Some(stmt) = self.inner.tx_stmt_cache.pop_lru() {
+                dropped_stmts.push(stmt.id());
+            }
+        }
+    }
+
+    /// Returns a name for a new savepoint, that is unique within this connection.
+    pub(crate) fn next_savepoint_name(&mut self) -> String {
+        self.inner.savepoints += 1;
+        format!("mysql_async_sp_{}", self.inner.savepoints)
+    }
+
+    /// Applies [`Opts::query_rewriter`] (if any) to the given query.
+    pub(crate) fn rewrite_query<'q>(&self, query: &'q str) -> Result<Cow<'q, str>> {
+        match self.inner.opts.query_rewriter() {
+            Some(rewriter) => rewriter(query).map_err(|reason| {
+                DriverError::QueryRejected {
+                    query: query.into(),
+                    reason,
+                }
+                .into()
+            }),
+            None => Ok(query.into()),
+        }
+    }
+
+    /// Passes the given query to [`Opts::slow_query_logger`] (if any),
+    /// if it took longer than [`Opts::slow_query_threshold`].
+    pub(crate) fn log_slow_query(&self, query: &str, started: Instant) {
+        if let (Some(threshold), Some(logger)) = (
+            self.inner.opts.slow_query_threshold(),
+            self.inner.opts.slow_query_logger(),
+        ) {
+            let elapsed = started.elapsed();
+            if elapsed > threshold {
+                logger(SlowQuery::new(query, self.no_backslash_escapes(), elapsed));
+            }
+        }
+    }
+
+    /// Prepends [`Opts::transaction_hint`] (if any) to the given query.
+    pub(crate) fn with_transaction_hint<'q>(&self, query: &'q str) -> Cow<'q, str> {
+        match self.inner.opts.transaction_hint() {
+            Some(hint) => {
+                let hint = hint.replace("{connection_id}", &self.id().to_string());
+                format!("{} {}", hint, query).into()
+            }
+            None => query.into(),
+        }
+    }
+
+    /// Returns the location of the caller of a [`Queryable`] method,
+    /// if [`Opts::tag_caller_location`] is `true`.
+    #[cfg(feature = "caller-location")]
+    #[track_caller]
+    pub(crate) fn caller_location(&self) -> Option<&'static Location<'static>> {
+        if self.inner.opts.tag_caller_location() {
+            Some(Location::caller())
+        } else {
+            None
+        }
+    }
+
+    /// Caller locations are only tracked with the `caller-location` feature.
+    #[cfg(not(feature = "caller-location"))]
+    pub(crate) fn caller_location(&self) -> Option<&'static Location<'static>> {
+        None
+    }
+
+    /// Remembers the given location (see [`Conn::caller_location`]).
+    ///
+    /// Must be called by the returned future (not by the method itself), so that the location
+    /// of a future, that is dropped without being polled, isn't kept.
+    ///
+    /// The outermost call wins, i.e. a location is kept until it's taken by
+    /// [`Conn::take_caller_location`], so that queries, that are issued by other methods
+    /// of this library on behalf of the caller, are tagged with the caller's location.
+    pub(crate) fn remember_caller_location(
+        &mut self,
+        location: Option<&'static Location<'static>>,
+    ) {
+        if self.inner.caller_location.is_none() {
+            self.inner.caller_location = location;
+        }
+    }
+
+    /// Takes the location remembered by [`Conn::remember_caller_location`] (if any).
+    pub(crate) fn take_caller_location(&mut self) -> Option<&'static Location<'static>> {
+        self.inner.caller_location.take()
+    }
+
+    /// Same as [`Conn::with_transaction_hint`], but only if this connection is in transaction.
+    pub(crate) fn hint_if_in_transaction<'q>(&self, query: &'q str) -> Cow<'q, str> {
+        if self.get_tx_status() == TxStatus::InTransaction {
+            self.with_transaction_hint(query)
+        } else {
+            query.into()
+        }
     }
 
     /// Returns pending result metadata, if any.
@@ -260,21 +654,212 @@ impl Conn {
         std::mem::replace(&mut self.inner.pending_result, meta)
     }
 
+    /// Returns the list, where dropped [`crate::OwnedStatement`]s put their ids.
+    pub(crate) fn dropped_stmts(&self) -> Arc<Mutex<Vec<u32>>> {
+        self.inner.dropped_stmts.clone()
+    }
+
+    /// Closes statements of dropped [`crate::OwnedStatement`]s.
+    ///
+    /// `COM_STMT_CLOSE` has no response, so it doesn't cost a round trip.
+    pub(crate) async fn close_dropped_stmts(&mut self) -> Result<()> {
+        let ids = std::mem::take(&mut *self.inner.dropped_stmts.lock().unwrap());
+        for id in ids {
+            self.stmt_cache_mut().remove(id);
+            self.reset_seq_id();
+            self.write_packet(ComStmtClose::new(id)).await?;
+            self.on_stmt_closed();
+        }
+        Ok(())
+    }
+
+    /// Marks the beginning (`true`) or the end (`false`) of a step of a result processing,
+    /// that can't be resumed if its future is dropped half way (e.g. reading of a result set
+    /// header and column definitions). Rows, on the contrary, are consumed one packet at a time,
+    /// so a dropped [`crate::QueryResult::drop_result`] is resumed by the next cleanup.
+    pub(crate) fn set_unresumable_step(&mut self, in_progress: bool) {
+        self.inner.unresumable_step = in_progress;
+    }
+
+    /// Closes the connection, if an unresumable step was interrupted
+    /// (see [`Conn::set_unresumable_step`]), because it is out of sync with the server.
+    pub(crate) fn ensure_in_sync(&mut self) -> Result<()> {
+        if self.inner.unresumable_step {
+            self.inner.stream.take();
+            self.inner.disconnected = true;
+            return Err(DriverError::ConnectionOutOfSync.into());
+        }
+        Ok(())
+    }
+
+    /// Returns the number of statements, that are currently prepared on the server side
+    /// by this connection (including cached statements).
+    pub fn prepared_stmt_count(&self) -> usize {
+        self.inner.prepared_stmt_count
+    }
+
+    /// Must be called once a statement is prepared on the server side.
+    pub(crate) fn on_stmt_prepared(&mut self) {
+        self.inner.prepared_stmt_count += 1;
+    }
+
+    /// Must be called once a statement is closed on the server side.
+    pub(crate) fn on_stmt_closed(&mut self) {
+        self.inner.prepared_stmt_count = self.inner.prepared_stmt_count.saturating_sub(1);
+    }
+
+    /// Returns `true` if `CLIENT_OPTIONAL_RESULTSET_METADATA` capability was negotiated.
+    pub(crate) fn optional_metadata(&self) -> bool {
+        self.inner.optional_metadata
+    }
+
+    /// Sets `resultset_metadata` session variable to `NONE` (if `suppressed`) or to `FULL`.
+    ///
+    /// Does nothing, if the variable already has the desired value
+    /// or if [`Opts::suppress_metadata`] isn't in effect.
+    pub(crate) async fn set_metadata_suppressed(&mut self, suppressed: bool) -> Result<()> {
+        if !self.optional_metadata() || self.inner.metadata_suppressed == suppressed {
+            return Ok(());
+        }
+        let query = if suppressed {
+            "SET resultset_metadata = NONE"
+        } else {
+            "SET resultset_metadata = FULL"
+        };
+        self.write_command_data(Command::COM_QUERY, query).await?;
+        self.read_packet().await?;
+        self.inner.metadata_suppressed = suppressed;
+        Ok(())
+    }
+
+    /// Sets columns of the statement being executed (see [`Opts::suppress_metadata`]).
+    pub(crate) fn set_expected_columns(&mut self, columns: Option<Arc<[Column]>>) {
+        self.inner.expected_columns = columns;
+    }
+
+    /// Takes columns of the executed statement (see [`Opts::suppress_metadata`]).
+    pub(crate) fn take_expected_columns(&mut self) -> Option<Arc<[Column]>> {
+        self.inner.expected_columns.take()
+    }
+
+    /// Returns `true` if there are remaining parts of a split multi-statement query.
+    pub(crate) fn has_pending_queries(&self) -> bool {
+        !self.inner.pending_queries.is_empty()
+    }
+
+    /// Sets remaining parts of a split multi-statement query.
+    pub(crate) fn set_pending_queries(&mut self, queries: VecDeque<String>) {
+        self.inner.pending_queries = queries;
+    }
+
+    /// Takes the next part of a split multi-statement query.
+    pub(crate) fn take_pending_query(&mut self) -> Option<String> {
+        self.inner.pending_queries.pop_front()
+    }
+
+    /// Returns current `max_allowed_packet` value.
+    pub(crate) fn max_allowed_packet(&self) -> usize {
+        self.inner
+            .stream
+            .as_ref()
+            .and_then(Stream::max_allowed_packet)
+            .unwrap_or(DEFAULT_MAX_ALLOWED_PACKET)
+    }
+
     /// Returns current status flags.
     pub(crate) fn status(&self) -> StatusFlags {
         self.inner.status
     }
 
+    /// Returns `true` if `NO_BACKSLASH_ESCAPES` SQL mode is enabled for this connection,
+    /// i.e. backslash is an ordinary character within string literals.
+    ///
+    /// The value is reported by the server in the `SERVER_STATUS_NO_BACKSLASH_ESCAPES` status
+    /// flag and is taken into account when a text query is parsed by the driver
+    /// (see [`Opts::split_oversized_queries`] and [`Opts::always_prepare`]).
+    pub fn no_backslash_escapes(&self) -> bool {
+        self.status()
+            .contains(StatusFlags::SERVER_STATUS_NO_BACKSLASH_ESCAPES)
+    }
+
     /// Returns server version.
     pub fn server_version(&self) -> (u16, u16, u16) {
         self.inner.version
     }
 
+    /// Returns `true` if the `CLIENT_MULTI_RESULTS` capability was negotiated
+    /// with the server, i.e. stored procedures may be called on this connection
+    /// (see [`Opts::require_multi_results`]).
+    pub fn supports_multi_results(&self) -> bool {
+        self.capabilities()
+            .contains(CapabilityFlags::CLIENT_MULTI_RESULTS)
+    }
+
+    /// Enables or disables `TCP_NODELAY` on the socket of this connection.
+    ///
+    /// Initially it's set according to [`Opts::tcp_nodelay`], and it's set that way again,
+    /// if [`Conn::reset`] needs to reconnect. Does nothing for a socket connection.
+    pub fn set_tcp_nodelay(&mut self, nodelay: bool) -> Result<()> {
+        self.stream_mut()?.set_tcp_nodelay(nodelay)?;
+        Ok(())
+    }
+
     /// Returns connection options.
     pub fn opts(&self) -> &Opts {
         &self.inner.opts
     }
 
+    /// Returns the name of the authentication plugin that authenticated this connection.
+    pub fn auth_plugin_name(&self) -> &str {
+        std::str::from_utf8(self.inner.auth_plugin.as_bytes()).unwrap_or_default()
+    }
+
+    /// Returns `true` if the server requested an authentication method switch
+    /// during the connection phase.
+    pub fn auth_switched(&self) -> bool {
+        self.inner.auth_switched
+    }
+
+    /// Returns text of the last statement executed on this connection.
+    ///
+    /// For prepared statements this is the text of the prepared query
+    /// (named parameters are replaced with `?`).
+    /// Always returns `None` unless [`Opts::retain_last_statement`] is `true`.
+    pub fn last_statement(&self) -> Option<&str> {
+        self.inner.last_statement.as_deref()
+    }
+
+    /// Retains the given statement text, if `Opts::retain_last_statement` is `true`.
+    pub(crate) fn set_last_statement<T: Into<Arc<str>>>(&mut self, statement: T) {
+        if self.inner.opts.retain_last_statement() {
+            self.inner.last_statement = Some(statement.into());
+        }
+    }
+
+    /// Returns the number of bytes sent over this connection.
+    ///
+    /// This is the number of bytes written to the socket (i.e. after compression).
+    /// For a TLS connection TLS overhead is not included.
+    pub fn bytes_sent(&self) -> u64 {
+        self.inner
+            .stream
+            .as_ref()
+            .map(Stream::bytes_sent)
+            .unwrap_or_default()
+    }
+
+    /// Returns the number of bytes received over this connection.
+    ///
+    /// This is the number of bytes read from the socket (i.e. before decompression).
+    /// For a TLS connection TLS overhead is not included.
+    pub fn bytes_received(&self) -> u64 {
+        self.inner
+            .stream
+            .as_ref()
+            .map(Stream::bytes_received)
+            .unwrap_or_default()
+    }
+
     fn take_stream(&mut self) -> Stream {
         self.inner.stream.take().unwrap()
     }
@@ -283,7 +868,9 @@ impl Conn {
     pub async fn disconnect(mut self) -> Result<()> {
         if !self.inner.disconnected {
             self.inner.disconnected = true;
-            self.write_command_data(Command::COM_QUIT, &[]).await?;
+            if self.inner.opts.send_quit_on_disconnect() {
+                self.write_command_data(Command::COM_QUIT, &[]).await?;
+            }
             let stream = self.take_stream();
             stream.close().await?;
         }
@@ -324,13 +911,33 @@ impl Conn {
         if let Some(stream) = self.inner.stream.as_mut() {
             stream.set_keepalive_ms(self.inner.opts.tcp_keepalive())?;
             stream.set_tcp_nodelay(self.inner.opts.tcp_nodelay())?;
+            stream.set_buffer_sizes(
+                self.inner.opts.read_buffer_size(),
+                self.inner.opts.write_buffer_size(),
+            );
+            stream.set_reuse_buffers(self.inner.opts.reuse_buffers());
         }
         Ok(())
     }
 
     async fn handle_handshake(&mut self) -> Result<()> {
-        let packet = self.read_packet().await?;
+        let packet = match self.inner.opts.handshake_timeout() {
+            Some(duration) => crate::runtime::timeout(duration, self.read_packet())
+                .await
+                .ok_or(DriverError::HandshakeTimeout)??,
+            None => self.read_packet().await?,
+        };
         let handshake = parse_handshake_packet(&*packet)?;
+        // pre-4.1 servers use a different handshake and a weak password scramble
+        // (`legacy-auth` supports the weak scramble of 4.1+ servers only)
+        if !handshake.capabilities().contains(
+            CapabilityFlags::CLIENT_PROTOCOL_41 | CapabilityFlags::CLIENT_SECURE_CONNECTION,
+        ) {
+            return Err(DriverError::ServerTooOld {
+                min_version: (4, 1, 1),
+            }
+            .into());
+        }
         self.inner.nonce = {
             let mut nonce = Vec::from(handshake.scramble_1_ref());
             nonce.extend_from_slice(handshake.scramble_2_ref().unwrap_or(&[][..]));
@@ -338,6 +945,11 @@ impl Conn {
         };
 
         self.inner.capabilities = handshake.capabilities() & self.inner.opts.get_capabilities();
+        if self.inner.opts.require_multi_results() && !self.supports_multi_results() {
+            return Err(DriverError::MultiResultsNotSupported.into());
+        }
+        self.inner.optional_metadata =
+            self.inner.opts.suppress_metadata() && server_supports_optional_metadata(&packet);
         self.inner.version = handshake.server_version_parsed().unwrap_or((0, 0, 0));
         self.inner.id = handshake.connection_id();
         self.inner.status = handshake.status_flags();
@@ -361,7 +973,7 @@ impl Conn {
             .contains(CapabilityFlags::CLIENT_SSL)
         {
             let ssl_request = SslRequest::new(self.inner.capabilities);
-            self.write_packet(ssl_request.as_ref()).await?;
+            self.write_client_flags_packet(ssl_request.as_ref()).await?;
             let conn = self;
             let ssl_opts = conn.opts().ssl_opts().cloned().expect("unreachable");
             let domain = conn.opts().ip_or_hostname().into();
@@ -385,31 +997,86 @@ impl Conn {
             self.inner.opts.db_name(),
             &self.inner.auth_plugin,
             self.capabilities(),
-            &Default::default(), // TODO: Add support
+            &self.connect_attrs(),
         );
 
-        self.write_packet(handshake_response.as_ref()).await?;
+        self.write_client_flags_packet(handshake_response.as_ref())
+            .await?;
         Ok(())
     }
 
+    /// Writes the given SSL request or handshake response, that starts with capability flags.
+    ///
+    /// `mysql_common` doesn't know `CLIENT_OPTIONAL_RESULTSET_METADATA`, so it's added here.
+    async fn write_client_flags_packet(&mut self, packet: &[u8]) -> Result<()> {
+        let mut packet = packet.to_vec();
+        if self.inner.optional_metadata && packet.len() >= 4 {
+            let flags = u32::from_le_bytes([packet[0], packet[1], packet[2], packet[3]])
+                | CLIENT_OPTIONAL_RESULTSET_METADATA;
+            packet[..4].copy_from_slice(&flags.to_le_bytes());
+        }
+        self.write_packet(packet).await
+    }
+
+    /// Returns connection attributes to send to the server (see [`Opts::connect_attrs`]).
+    fn connect_attrs(&self) -> HashMap<String, String> {
+        let mut attrs = HashMap::new();
+        attrs.insert("_client_name".into(), "mysql_async".into());
+        attrs.insert("_client_version".into(), env!("CARGO_PKG_VERSION").into());
+        attrs.insert("_os".into(), std::env::consts::OS.into());
+        attrs.insert("_platform".into(), std::env::consts::ARCH.into());
+        attrs.insert("_pid".into(), std::process::id().to_string());
+        attrs.extend(self.inner.opts.connect_attrs().clone());
+        attrs
+    }
+
     async fn perform_auth_switch(
         &mut self,
         auth_switch_request: AuthSwitchRequest<'_>,
     ) -> Result<()> {
-        if !self.inner.auth_switched {
-            self.inner.auth_switched = true;
-            self.inner.nonce = auth_switch_request.plugin_data().into();
-            self.inner.auth_plugin = auth_switch_request.auth_plugin().clone().into_owned();
-            let plugin_data = self
-                .inner
-                .auth_plugin
-                .gen_data(self.inner.opts.pass(), &*self.inner.nonce)
-                .unwrap_or_else(Vec::new);
-            self.write_packet(plugin_data).await?;
-            self.continue_auth().await?;
-            Ok(())
-        } else {
-            unreachable!("auth_switched flag should be checked by caller")
+        if self.inner.auth_switched {
+            if self.inner.auth_rescrambles >= MAX_AUTH_RESCRAMBLES {
+                return Err(DriverError::TooManyAuthSwitches.into());
+            }
+            self.inner.auth_rescrambles += 1;
+        }
+        self.inner.auth_switched = true;
+        self.inner.nonce = auth_switch_request.plugin_data().into();
+        self.inner.auth_plugin = auth_switch_request.auth_plugin().clone().into_owned();
+        #[cfg(feature = "legacy-auth")]
+        {
+            if self.inner.auth_plugin.as_bytes() == old_password::PLUGIN_NAME {
+                return self.perform_old_password_auth().await;
+            }
+        }
+        let plugin_data = self
+            .inner
+            .auth_plugin
+            .gen_data(self.inner.opts.pass(), &*self.inner.nonce)
+            .unwrap_or_else(Vec::new);
+        self.write_packet(plugin_data).await?;
+        self.continue_auth().await?;
+        Ok(())
+    }
+
+    /// Sends the pre-4.1 password scramble (`mysql_old_password`) and reads the server response.
+    ///
+    /// **Insecure.** Only available with the `legacy-auth` feature, otherwise fails with
+    /// [`DriverError::OldPasswordAuthRequested`].
+    async fn perform_old_password_auth(&mut self) -> Result<()> {
+        #[cfg(feature = "legacy-auth")]
+        {
+            let data = old_password::scramble_323(&self.inner.nonce, self.inner.opts.pass());
+            self.write_packet(data).await?;
+            let packet = self.read_packet().await?;
+            match packet.get(0) {
+                Some(0x00) => Ok(()),
+                _ => Err(DriverError::UnexpectedPacket { payload: packet }.into()),
+            }
+        }
+        #[cfg(not(feature = "legacy-auth"))]
+        {
+            Err(DriverError::OldPasswordAuthRequested.into())
         }
     }
 
@@ -439,8 +1106,9 @@ impl Conn {
             .contains(CapabilityFlags::CLIENT_COMPRESS)
         {
             if let Some(compression) = self.inner.opts.compression() {
+                let threshold = self.inner.opts.compression_threshold();
                 if let Some(stream) = self.inner.stream.as_mut() {
-                    stream.compress(compression);
+                    stream.compress(compression, threshold);
                 }
             }
         }
@@ -466,13 +1134,18 @@ impl Conn {
                     if self.is_secure() {
                         self.write_packet(&*pass).await?;
                     } else {
-                        self.write_packet(&[0x02][..]).await?;
-                        let packet = self.read_packet().await?;
-                        let key = &packet[1..];
+                        let key = match self.inner.opts.server_public_key() {
+                            Some(path) => read_server_public_key(path).await?,
+                            None => {
+                                self.write_packet(&[0x02][..]).await?;
+                                let packet = self.read_packet().await?;
+                                packet[1..].to_vec()
+                            }
+                        };
                         for (i, byte) in pass.iter_mut().enumerate() {
                             *byte ^= self.inner.nonce[i % self.inner.nonce.len()];
                         }
-                        let encrypted_pass = crypto::encrypt(&*pass, key);
+                        let encrypted_pass = crypto::encrypt(&*pass, &key);
                         self.write_packet(&*encrypted_pass).await?;
                     };
                     self.drop_packet().await?;
@@ -483,8 +1156,20 @@ impl Conn {
                 }
                 .into()),
             },
-            Some(0xfe) if !self.inner.auth_switched => {
+            Some(0xfe)
+                if !self
+                    .capabilities()
+                    .contains(CapabilityFlags::CLIENT_PLUGIN_AUTH) =>
+            {
+                // servers without plugin authentication (pre-5.5.7) send a single `0xfe` byte
+                // to request the old password scramble
+                self.perform_old_password_auth().await
+            }
+            Some(0xfe) => {
                 let auth_switch_request = parse_auth_switch_request(&*packet)?.into_owned();
+                if !self.inner.auth_switch_allowed(&auth_switch_request) {
+                    return Err(DriverError::UnexpectedPacket { payload: packet }.into());
+                }
                 self.perform_auth_switch(auth_switch_request).await?;
                 Ok(())
             }
@@ -499,8 +1184,20 @@ impl Conn {
         let packet = self.read_packet().await?;
         match packet.get(0) {
             Some(0x00) => Ok(()),
-            Some(0xfe) if !self.inner.auth_switched => {
+            Some(0xfe)
+                if !self
+                    .capabilities()
+                    .contains(CapabilityFlags::CLIENT_PLUGIN_AUTH) =>
+            {
+                // servers without plugin authentication (pre-5.5.7) send a single `0xfe` byte
+                // to request the old password scramble
+                self.perform_old_password_auth().await
+            }
+            Some(0xfe) => {
                 let auth_switch_request = parse_auth_switch_request(packet.as_ref())?.into_owned();
+                if !self.inner.auth_switch_allowed(&auth_switch_request) {
+                    return Err(DriverError::UnexpectedPacket { payload: packet }.into());
+                }
                 self.perform_auth_switch(auth_switch_request).await?;
                 Ok(())
             }
@@ -515,8 +1212,8 @@ impl Conn {
             OkPacketKind::Other
         };
 
-        if let Ok(ok_packet) = parse_ok_packet(&*packet, self.capabilities(), kind) {
-            self.handle_ok(ok_packet.into_owned());
+        if let Ok(ok_packet) = parse_ok(&*packet, self.capabilities(), kind) {
+            self.handle_ok(ok_packet.into_owned(), packet);
         } else if let Ok(err_packet) = parse_err_packet(&*packet, self.capabilities()) {
             self.handle_err(err_packet.clone().into_owned());
             return Err(err_packet.into()).into();
@@ -525,18 +1222,39 @@ impl Conn {
         Ok(())
     }
 
+    /// Drops the stream after an I/O error and notifies the pool (if any).
+    fn connection_lost(&mut self) {
+        self.inner.stream.take();
+        self.inner.disconnected = true;
+        if let Some(pool) = self.inner.pool.as_ref() {
+            pool.connection_lost(self.inner.pool_epoch);
+        }
+    }
+
     pub(crate) async fn read_packet(&mut self) -> Result<Vec<u8>> {
         let packet = crate::io::ReadPacket::new(&mut *self)
             .await
             .map_err(|io_err| {
-                self.inner.stream.take();
-                self.inner.disconnected = true;
+                self.connection_lost();
                 Error::from(io_err)
             })?;
+        self.capture_handshake_frame(FrameDirection::ServerToClient, &packet);
         self.handle_packet(&*packet)?;
         Ok(packet)
     }
 
+    /// Records the given packet, if the connection phase is captured
+    /// (see [`Opts::capture_handshake`]).
+    fn capture_handshake_frame(&mut self, direction: FrameDirection, payload: &[u8]) {
+        if let Some(seq_id) = self.inner.handshake_seq_id {
+            if let Some(capture) = self.inner.opts.handshake_capture() {
+                capture.push(direction, seq_id, payload);
+            }
+            // the sequence is shared by both sides and isn't reset during the connection phase
+            self.inner.handshake_seq_id = Some(seq_id.wrapping_add(1));
+        }
+    }
+
     /// Returns future that reads packets from a server.
     pub(crate) async fn read_packets(&mut self, n: usize) -> Result<Vec<Vec<u8>>> {
         let mut packets = Vec::with_capacity(n);
@@ -546,15 +1264,32 @@ impl Conn {
         Ok(packets)
     }
 
+    /// Returns future that writes a packet to a server.
+    ///
+    /// Returns `DriverError::PacketTooLarge` without writing anything,
+    /// if the packet exceeds `max_allowed_packet`.
     pub(crate) async fn write_packet<T>(&mut self, data: T) -> Result<()>
     where
         T: Into<Vec<u8>>,
     {
-        crate::io::WritePacket::new(&mut *self, data.into())
+        let data = data.into();
+        if let Some(max) = self
+            .inner
+            .stream
+            .as_ref()
+            .and_then(Stream::max_allowed_packet)
+        {
+            if data.len() > max {
+                let size = data.len();
+                return Err(DriverError::PacketTooLarge { size, max }.into());
+            }
+        }
+
+        self.capture_handshake_frame(FrameDirection::ClientToServer, &data);
+        crate::io::WritePacket::new(&mut *self, data)
             .await
             .map_err(|io_err| {
-                self.inner.stream.take();
-                self.inner.disconnected = true;
+                self.connection_lost();
                 From::from(io_err)
             })
     }
@@ -584,46 +1319,198 @@ impl Conn {
         Ok(())
     }
 
-    async fn run_init_commands(&mut self) -> Result<()> {
-        let mut init: Vec<_> = self.inner.opts.init().iter().cloned().collect();
-
-        while let Some(query) = init.pop() {
-            self.query_drop(query).await?;
+    /// Sets the session isolation level, if [`Opts::default_isolation_level`] is defined.
+    async fn set_default_isolation_level(&mut self) -> Result<()> {
+        if let Some(isolation_level) = self.inner.opts.default_isolation_level() {
+            self.query_drop(format!(
+                "SET SESSION TRANSACTION ISOLATION LEVEL {}",
+                isolation_level
+            ))
+            .await?;
         }
+        Ok(())
+    }
 
+    /// Sets the session `sql_mode`, if [`Opts::sql_mode`] is defined,
+    /// and remembers the effective value (see [`Conn::assert_sql_mode`]).
+    async fn set_sql_mode(&mut self) -> Result<()> {
+        if let Some(sql_mode) = self.inner.opts.sql_mode().map(String::from) {
+            self.exec_drop("SET SESSION sql_mode = ?", (sql_mode,))
+                .await?;
+            self.inner.sql_mode = Some(self.current_sql_mode().await?);
+        }
         Ok(())
     }
 
-    /// Returns a future that resolves to [`Conn`].
-    pub fn new<T: Into<Opts>>(opts: T) -> crate::BoxFuture<'static, Conn> {
-        let opts = opts.into();
-        let fut = Box::pin(async move {
-            let mut conn = Conn::empty(opts.clone());
+    /// Sets the session `SQL_SELECT_LIMIT` (`None` means the server default).
+    async fn set_select_limit(&mut self, limit: Option<u64>) -> Result<()> {
+        match limit {
+            Some(limit) => {
+                self.query_drop(format!("SET SESSION SQL_SELECT_LIMIT = {}", limit))
+                    .await
+            }
+            None => {
+                self.query_drop("SET SESSION SQL_SELECT_LIMIT = DEFAULT")
+                    .await
+            }
+        }
+    }
 
-            let stream = if let Some(path) = opts.socket() {
-                Stream::connect_socket(path.to_owned()).await?
-            } else {
-                Stream::connect_tcp(opts.hostport_or_url()).await?
-            };
+    /// Sets the session `SQL_SELECT_LIMIT`, if [`Opts::default_select_limit`] is defined.
+    async fn set_default_select_limit(&mut self) -> Result<()> {
+        match self.inner.opts.default_select_limit() {
+            Some(limit) => self.set_select_limit(Some(limit)).await,
+            None => Ok(()),
+        }
+    }
 
-            conn.inner.stream = Some(stream);
-            conn.setup_stream()?;
-            conn.handle_handshake().await?;
-            conn.switch_to_ssl_if_needed().await?;
-            conn.do_handshake_response().await?;
-            conn.continue_auth().await?;
-            conn.switch_to_compression()?;
-            conn.read_socket().await?;
-            conn.reconnect_via_socket_if_needed().await?;
-            conn.read_max_allowed_packet().await?;
-            conn.read_wait_timeout().await?;
-            conn.run_init_commands().await?;
+    /// Returns the session `sql_mode`.
+    async fn current_sql_mode(&mut self) -> Result<String> {
+        let sql_mode: Option<String> = self.query_first("SELECT @@SESSION.sql_mode").await?;
+        Ok(sql_mode.unwrap_or_default())
+    }
 
-            Ok(conn)
+    /// Checks, that the session `sql_mode` wasn't changed since it was set according
+    /// to [`Opts::sql_mode`] (e.g. by a stored procedure or by a `SET sql_mode` query).
+    ///
+    /// Returns [`DriverError::SqlModeDrift`] if it was changed. Does nothing,
+    /// if [`Opts::sql_mode`] isn't defined.
+    pub async fn assert_sql_mode(&mut self) -> Result<()> {
+        let expected = match self.inner.sql_mode.clone() {
+            Some(expected) => expected,
+            None => return Ok(()),
+        };
+        let actual = self.current_sql_mode().await?;
+        if actual != expected {
+            return Err(DriverError::SqlModeDrift { expected, actual }.into());
+        }
+        Ok(())
+    }
+
+    async fn run_init_commands(&mut self) -> Result<()> {
+        let mut init: Vec<_> = self.inner.opts.init().iter().cloned().collect();
+
+        self.inner.init_warnings.clear();
+        while let Some(query) = init.pop() {
+            self.query_drop(&query).await?;
+            if self.get_warnings() > 0 {
+                let warnings = if self.inner.captured_warnings.is_empty() {
+                    self.show_warnings().await?
+                } else {
+                    std::mem::take(&mut self.inner.captured_warnings)
+                };
+                if self.inner.opts.strict_init() {
+                    return Err(DriverError::InitCommandWarnings { query, warnings }.into());
+                }
+                self.inner.init_warnings.push((query, warnings));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns a future that resolves to [`Conn`].
+    pub fn new<T: Into<Opts>>(opts: T) -> crate::BoxFuture<'static, Conn> {
+        let opts = opts.into();
+        let fut = Box::pin(async move {
+            let mut conn = Conn::empty(opts.clone());
+
+            let in_tcp_phase = |err: io::Error| Error::from(err).in_phase(ConnectPhase::Tcp);
+            let stream = if let Some(path) = opts.socket() {
+                Stream::connect_socket(path.to_owned())
+                    .await
+                    .map_err(in_tcp_phase)?
+            } else {
+                let addrs = match opts.hostport_or_url().to_socket_addrs() {
+                    Ok(addrs) => addrs.collect::<Vec<_>>(),
+                    Err(err) => return Err(Error::from(err).in_phase(ConnectPhase::Resolve)),
+                };
+                if addrs.is_empty() {
+                    let err = io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "could not resolve to any address",
+                    );
+                    return Err(Error::from(err).in_phase(ConnectPhase::Resolve));
+                }
+                let mut stream = Stream::connect_tcp(&addrs[..])
+                    .await
+                    .map_err(in_tcp_phase)?;
+                if let Some(version) = opts.proxy_protocol() {
+                    stream
+                        .write_proxy_protocol_header(version, opts.proxy_protocol_addrs())
+                        .await
+                        .map_err(in_tcp_phase)?;
+                }
+                stream
+            };
+
+            conn.inner.stream = Some(stream);
+            conn.connect().await?;
+
+            Ok(conn)
+        });
+        crate::BoxFuture(fut)
+    }
+
+    /// Returns a future that resolves to a [`Conn`] over the given in-memory transport
+    /// (see [`crate::mock::MockServer`]).
+    #[cfg(feature = "mock")]
+    pub(crate) fn new_mock(
+        opts: Opts,
+        stream: tokio::io::DuplexStream,
+    ) -> crate::BoxFuture<'static, Conn> {
+        let fut = Box::pin(async move {
+            let mut conn = Conn::empty(opts);
+            conn.inner.stream = Some(Stream::connect_mock(stream));
+            conn.connect().await?;
+            Ok(conn)
         });
         crate::BoxFuture(fut)
     }
 
+    /// Performs the connection phase over the stream, that is already established.
+    async fn connect(&mut self) -> Result<()> {
+        let in_phase = |phase| move |err: Error| err.in_phase(phase);
+        self.setup_stream().map_err(in_phase(ConnectPhase::Tcp))?;
+        if self.inner.opts.handshake_capture().is_some() {
+            self.inner.handshake_seq_id = Some(0);
+        }
+        self.handle_handshake()
+            .await
+            .map_err(in_phase(ConnectPhase::Handshake))?;
+        self.switch_to_ssl_if_needed()
+            .await
+            .map_err(in_phase(ConnectPhase::Tls))?;
+        self.do_handshake_response()
+            .await
+            .map_err(in_phase(ConnectPhase::Auth))?;
+        self.continue_auth()
+            .await
+            .map_err(in_phase(ConnectPhase::Auth))?;
+        self.inner.handshake_seq_id = None;
+        self.switch_to_compression()
+            .map_err(in_phase(ConnectPhase::Handshake))?;
+        self.read_settings()
+            .await
+            .map_err(in_phase(ConnectPhase::Init))?;
+        self.reconnect_via_socket_if_needed()
+            .await
+            .map_err(in_phase(ConnectPhase::Init))?;
+        self.set_default_isolation_level()
+            .await
+            .map_err(in_phase(ConnectPhase::Init))?;
+        self.set_sql_mode()
+            .await
+            .map_err(in_phase(ConnectPhase::Init))?;
+        self.set_default_select_limit()
+            .await
+            .map_err(in_phase(ConnectPhase::Init))?;
+        self.run_init_commands()
+            .await
+            .map_err(in_phase(ConnectPhase::Init))?;
+        Ok(())
+    }
+
     /// Returns a future that resolves to [`Conn`].
     pub async fn from_url<T: AsRef<str>>(url: T) -> Result<Conn> {
         Conn::new(Opts::from_str(url.as_ref())?).await
@@ -650,32 +1537,102 @@ impl Conn {
         Ok(())
     }
 
-    /// Reads and stores socket address inside the connection.
+    /// Reads and stores `max_allowed_packet`, `wait_timeout`, `innodb_rollback_on_timeout`
+    /// and socket address (if needed) in the connection using a single query.
     ///
-    /// Do nothing if socket address is already in [`Opts`] or if `prefer_socket` is `false`.
-    async fn read_socket(&mut self) -> Result<()> {
-        if self.inner.opts.prefer_socket() && self.inner.socket.is_none() {
-            let row_opt = self.query_first("SELECT @@socket").await?;
-            self.inner.socket = row_opt.unwrap_or((None,)).0;
+    /// Socket address won't be read if it is already in [`Opts`] or if `prefer_socket` is `false`.
+    /// Defaults are used for values that are `NULL` or not convertible.
+    ///
+    /// Servers (or proxies) without InnoDB don't have `innodb_rollback_on_timeout`, so the query
+    /// is repeated without it (defaults to `false`), if the server rejects it.
+    async fn read_settings(&mut self) -> Result<()> {
+        const ROLLBACK_ON_TIMEOUT: &str = "@@innodb_rollback_on_timeout";
+
+        let read_socket = self.inner.opts.prefer_socket() && self.inner.socket.is_none();
+        let mut variables = vec![
+            "@@max_allowed_packet",
+            "@@wait_timeout",
+            ROLLBACK_ON_TIMEOUT,
+        ];
+        if read_socket {
+            variables.push("@@socket");
         }
-        Ok(())
-    }
 
-    /// Reads and stores `max_allowed_packet` in the connection.
-    async fn read_max_allowed_packet(&mut self) -> Result<()> {
-        let row_opt = self.query_first("SELECT @@max_allowed_packet").await?;
+        let query = format!("SELECT {}", variables.join(", "));
+        let mut row: Option<Row> = match self.query_first(query).await {
+            Ok(row) => row,
+            Err(Error::Server(_)) => {
+                variables.retain(|variable| *variable != ROLLBACK_ON_TIMEOUT);
+                let query = format!("SELECT {}", variables.join(", "));
+                self.query_first(query).await?
+            }
+            Err(err) => return Err(err),
+        };
+        let mut take = |variable: &str| {
+            let index = variables.iter().position(|x| *x == variable);
+            row.as_mut()
+                .zip(index)
+                .and_then(|(row, index)| row.take_opt::<Value, _>(index))
+                .and_then(|value| value.ok())
+                .unwrap_or(Value::NULL)
+        };
+
+        let max_allowed_packet = from_value_opt::<usize>(take("@@max_allowed_packet"))
+            .unwrap_or(DEFAULT_MAX_ALLOWED_PACKET);
+        let wait_timeout_secs = from_value_opt::<u64>(take("@@wait_timeout")).unwrap_or(28800);
+        let rollback_on_timeout =
+            from_value_opt::<bool>(take(ROLLBACK_ON_TIMEOUT)).unwrap_or(false);
+        let socket = if read_socket {
+            from_value_opt::<Option<String>>(take("@@socket")).unwrap_or_default()
+        } else {
+            None
+        };
+
         if let Some(stream) = self.inner.stream.as_mut() {
-            stream.set_max_allowed_packet(row_opt.unwrap_or((DEFAULT_MAX_ALLOWED_PACKET,)).0);
+            stream.set_max_allowed_packet(max_allowed_packet);
         }
+        self.inner.wait_timeout = Duration::from_secs(wait_timeout_secs);
+        self.inner.rollback_on_timeout = rollback_on_timeout;
+        if read_socket {
+            self.inner.socket = socket;
+        }
+
         Ok(())
     }
 
-    /// Reads and stores `wait_timeout` in the connection.
-    async fn read_wait_timeout(&mut self) -> Result<()> {
-        let row_opt = self.query_first("SELECT @@wait_timeout").await?;
-        let wait_timeout_secs = row_opt.unwrap_or((28800,)).0;
-        self.inner.wait_timeout = Duration::from_secs(wait_timeout_secs);
-        Ok(())
+    /// Returns the state of this connection without performing any IO.
+    ///
+    /// Unlike [`Queryable::ping`], it could be called on a connection with a pending result,
+    /// so it's possible to tell a connection, that needs a cleanup, from a dead one.
+    /// Note, that [`Health::Idle`] doesn't mean that the server is still reachable.
+    pub fn is_healthy(&self) -> Health {
+        if self.inner.stream.is_none() || self.inner.disconnected || self.inner.unresumable_step {
+            return Health::Dead;
+        }
+
+        let pending_result = self.inner.pending_result.is_some();
+        let in_transaction = self.inner.tx_status != TxStatus::None;
+        if pending_result || in_transaction {
+            Health::Busy {
+                pending_result,
+                in_transaction,
+            }
+        } else {
+            Health::Idle
+        }
+    }
+
+    /// Executes `COM_PING` (see [`Queryable::ping`]) and returns its round-trip time.
+    ///
+    /// Pending results are dropped (and pending statements or transactions are closed)
+    /// before the timer starts, so only the `COM_PING` write and the reply are timed.
+    pub async fn ping_timed(&mut self) -> Result<Duration> {
+        self.clean_dirty().await?;
+        let started = Instant::now();
+        self.reset_seq_id();
+        self.write_packet(vec![Command::COM_PING as u8]).await?;
+        self.read_packet().await?;
+        Ok(started.elapsed())
     }
 
     /// Returns true if time since last IO exceeds `wait_timeout`
@@ -697,6 +1654,11 @@ impl Conn {
     /// Executes `COM_RESET_CONNECTION` on `self`.
     ///
     /// If server version is older than 5.7.2, then it'll reconnect.
+    /// [`Opts::default_isolation_level`], [`Opts::sql_mode`] and [`Opts::default_select_limit`]
+    /// are applied again afterwards.
+    ///
+    /// The default database is restored to [`Opts::db_name`] (if defined), even if it was
+    /// changed by [`Conn::select_db`] or by a `USE` statement.
     pub async fn reset(&mut self) -> Result<()> {
         let pool = self.inner.pool.clone();
 
@@ -704,22 +1666,239 @@ impl Conn {
             self.write_command_data(Command::COM_RESET_CONNECTION, &[])
                 .await?;
             self.read_packet().await?;
+            // session isolation level, sql_mode and select limit are reset to the server defaults
+            self.set_default_isolation_level().await?;
+            self.set_sql_mode().await?;
+            self.set_default_select_limit().await?;
+            // `COM_RESET_CONNECTION` keeps the current default database
+            if let Some(db_name) = self.inner.opts.db_name().map(String::from) {
+                self.select_db(db_name).await?;
+            }
         } else {
             let opts = self.inner.opts.clone();
-            let old_conn = std::mem::replace(self, Conn::new(opts).await?);
+            let mut old_conn = std::mem::replace(self, Conn::new(opts).await?);
+            // the new connection takes over the limiter permit of the old one
+            self.inner.limiter_permit = old_conn.inner.limiter_permit.take();
             // tidy up the old connection
             old_conn.close_conn().await?;
         };
 
         self.inner.stmt_cache.clear();
+        self.inner.tx_stmt_cache.clear();
+        self.inner.dropped_stmts.lock().unwrap().clear();
+        self.inner.metadata_suppressed = false;
+        self.inner.prepared_stmt_count = 0;
+        self.inner.time_zone = None;
+        self.inner.read_only = false;
         self.inner.pool = pool;
         Ok(())
     }
 
+    /// Sets the access mode of transactions of this session
+    /// (`SET SESSION TRANSACTION READ ONLY` or `READ WRITE`).
+    ///
+    /// Writes to non-temporary tables will fail on a read-only session, so it's useful
+    /// to catch accidental writes on connections, that are meant to be read-only
+    /// (see [`Pool::read_conn`]).
+    pub async fn set_read_only(&mut self, read_only: bool) -> Result<()> {
+        let access_mode = if read_only { "READ ONLY" } else { "READ WRITE" };
+        self.query_drop(format!("SET SESSION TRANSACTION {}", access_mode))
+            .await?;
+        self.inner.read_only = read_only;
+        Ok(())
+    }
+
+    /// Returns `true` if the session access mode was set to `READ ONLY`
+    /// using [`Conn::set_read_only`].
+    pub fn is_read_only(&self) -> bool {
+        self.inner.read_only
+    }
+
+    /// Waits until the given GTID set is executed by the server this connection is connected to
+    /// (`WAIT_FOR_EXECUTED_GTID_SET`).
+    ///
+    /// Returns `true` if the GTID set was executed, and `false` if the timeout has elapsed first.
+    /// Use it to read your writes on a replica: wait for the GTID set of a transaction
+    /// executed on the primary (e.g. `@@gtid_executed`) before reading.
+    ///
+    /// Requires `gtid_mode = ON` and MySql 5.7.5 or newer. The timeout is truncated
+    /// to microseconds.
+    pub async fn wait_for_gtid<T: AsRef<str>>(
+        &mut self,
+        gtid_set: T,
+        timeout: Duration,
+    ) -> Result<bool> {
+        let timeout = format!("{}.{:06}", timeout.as_secs(), timeout.subsec_micros());
+        let result: Option<Option<u8>> = self
+            .exec_first(
+                "SELECT WAIT_FOR_EXECUTED_GTID_SET(?, CAST(? AS DECIMAL(20, 6)))",
+                (gtid_set.as_ref(), timeout),
+            )
+            .await?;
+        // `1` means that the timeout has elapsed
+        Ok(result.flatten() == Some(0))
+    }
+
+    /// Returns the default database of this connection (if any) without a round trip.
+    ///
+    /// If session state tracking is supported by the server (MySql 5.7 and newer),
+    /// then changes of the default database (e.g. via a `USE` statement) are reported
+    /// by the server, so the returned value is the same as the result of `SELECT DATABASE()`.
+    /// Otherwise it's the database, that was set on connect or via [`Conn::select_db`].
+    pub fn current_schema(&self) -> Option<&str> {
+        self.inner.db_name.as_deref().filter(|x| !x.is_empty())
+    }
+
+    /// Executes `COM_INIT_DB`, that changes the default database of this connection.
+    pub async fn select_db<T: AsRef<str>>(&mut self, db_name: T) -> Result<()> {
+        let db_name = db_name.as_ref();
+        self.write_command_data(Command::COM_INIT_DB, db_name.as_bytes())
+            .await?;
+        self.read_packet().await?;
+        self.inner.db_name = Some(db_name.into());
+        Ok(())
+    }
+
+    /// Executes `COM_FIELD_LIST` and returns definitions of columns of the given table,
+    /// whose names match the given wildcard (e.g. `"id%"`, empty wildcard matches all columns).
+    ///
+    /// This command is deprecated, so it'll return [`DriverError::FieldListNotSupported`]
+    /// if the server doesn't support it.
+    pub async fn field_list<T, U>(&mut self, table: T, wildcard: U) -> Result<Vec<Column>>
+    where
+        T: AsRef<str>,
+        U: AsRef<str>,
+    {
+        let mut body = Vec::new();
+        body.extend_from_slice(table.as_ref().as_bytes());
+        body.push(0x00);
+        body.extend_from_slice(wildcard.as_ref().as_bytes());
+        self.write_command_data(Command::COM_FIELD_LIST, body)
+            .await?;
+
+        let mut columns = Vec::new();
+        loop {
+            let packet = match self.read_packet().await {
+                Ok(packet) => packet,
+                // ER_UNKNOWN_COM_ERROR
+                Err(Error::Server(ref err)) if err.code == 1047 => {
+                    return Err(DriverError::FieldListNotSupported.into());
+                }
+                Err(err) => return Err(err),
+            };
+            if packet.first() == Some(&0xfe) {
+                // EOF packet (or OK packet if `CLIENT_DEPRECATE_EOF` is set).
+                return Ok(columns);
+            }
+            columns.push(column_from_payload(packet)?);
+        }
+    }
+
+    /// Returns the session time zone (`SYSTEM` is resolved to `@@system_time_zone`).
+    ///
+    /// The value is cached until [`Conn::reset`] or [`Conn::set_time_zone`] is called, so it
+    /// won't reflect changes made by executing `SET time_zone` directly.
+    pub async fn time_zone(&mut self) -> Result<String> {
+        if let Some(ref time_zone) = self.inner.time_zone {
+            return Ok(time_zone.clone());
+        }
+
+        let (session, system): (String, String) = self
+            .query_first("SELECT @@session.time_zone, @@system_time_zone")
+            .await?
+            .unwrap_or_default();
+
+        let time_zone = if session == "SYSTEM" { system } else { session };
+        self.inner.time_zone = Some(time_zone.clone());
+        Ok(time_zone)
+    }
+
+    /// Sets the session time zone (e.g. `+00:00` or `Europe/Moscow`).
+    pub async fn set_time_zone<T: AsRef<str>>(&mut self, time_zone: T) -> Result<()> {
+        self.inner.time_zone = None;
+        self.exec_drop("SET time_zone = ?", (time_zone.as_ref(),))
+            .await
+    }
+
+    /// Performs the given `SELECT` query with the given server-side execution timeout
+    /// (see [`Queryable::query`]).
+    ///
+    /// The timeout is applied using the `MAX_EXECUTION_TIME` optimizer hint, that is injected
+    /// right after the leading `SELECT` keyword. It is supported by MySql 5.7.8 and newer
+    /// (older servers treat it as a comment). The timeout is truncated to milliseconds
+    /// and zero means no timeout.
+    ///
+    /// Returns [`DriverError::MaxExecutionTimeRequiresSelect`] if the query isn't a `SELECT`
+    /// statement, since the hint is only applicable to `SELECT`s. Query that exceeds
+    /// the timeout fails with the `ER_QUERY_TIMEOUT` (`3024`) server error.
+    pub async fn query_with_max_execution_time<T, Q>(
+        &mut self,
+        query: Q,
+        timeout: Duration,
+    ) -> Result<Vec<T>>
+    where
+        Q: AsRef<str>,
+        T: FromRow + Send + 'static,
+    {
+        match with_max_execution_time(query.as_ref(), timeout) {
+            Some(query) => self.query(query).await,
+            None => Err(DriverError::MaxExecutionTimeRequiresSelect.into()),
+        }
+    }
+
+    /// Performs the given query with the given session `SQL_SELECT_LIMIT`
+    /// (see [`Queryable::query`]).
+    ///
+    /// `None` means no limit. [`Opts::default_select_limit`] (or the server default)
+    /// is restored afterwards, so it's the way to bypass the default limit for a single query.
+    pub async fn query_with_limit<T, Q>(&mut self, query: Q, limit: Option<u64>) -> Result<Vec<T>>
+    where
+        Q: AsRef<str>,
+        T: FromRow + Send + 'static,
+    {
+        // the maximum value means no limit
+        self.set_select_limit(Some(limit.unwrap_or(u64::MAX)))
+            .await?;
+        let result = self.query(query.as_ref()).await;
+        let restored = self
+            .set_select_limit(self.inner.opts.default_select_limit())
+            .await;
+        let rows = result?;
+        restored?;
+        Ok(rows)
+    }
+
+    /// Performs the given query followed by `SELECT FOUND_ROWS()` in a single round trip
+    /// and returns the rows along with the number of found rows.
+    ///
+    /// Use it with `SQL_CALC_FOUND_ROWS` and `LIMIT` to get the total number of rows,
+    /// e.g. for pagination or progress reporting. Note, that `SQL_CALC_FOUND_ROWS`
+    /// is deprecated since MySql 8.0.17.
+    ///
+    /// The server doesn't provide a row count estimation in result set metadata,
+    /// so this is the way to get the total number of rows in advance.
+    pub async fn query_with_found_rows<T, Q>(&mut self, query: Q) -> Result<(Vec<T>, u64)>
+    where
+        Q: AsRef<str>,
+        T: FromRow + Send + 'static,
+    {
+        let mut result = self.query_iter(with_found_rows(query.as_ref())).await?;
+        let rows = result.collect::<T>().await?;
+        if result.is_empty() {
+            return Err(DriverError::MissingFoundRows.into());
+        }
+        let found_rows = result.collect::<u64>().await?;
+        result.drop_result().await?;
+        match found_rows.into_iter().next() {
+            Some(found_rows) => Ok((rows, found_rows)),
+            None => Err(DriverError::MissingFoundRows.into()),
+        }
+    }
+
     /// Requires that `self.inner.tx_status != TxStatus::None`
     async fn rollback_transaction(&mut self) -> Result<()> {
         debug_assert_ne!(self.inner.tx_status, TxStatus::None);
-        self.inner.tx_status = TxStatus::None;
+        self.set_tx_status(TxStatus::None);
         self.query_drop("ROLLBACK").await
     }
 
@@ -744,25 +1923,40 @@ impl Conn {
                     .drop_result()
                     .await
             }
-            Some(ResultSetMeta::Error(_)) => match self.set_pending_result(None) {
-                Some(ResultSetMeta::Error(err)) => Err(err.into()),
-                _ => unreachable!(),
-            },
+            Some(ResultSetMeta::Error(_)) => {
+                // execution of a split multi-statement query stops at the first error
+                self.set_pending_queries(VecDeque::new());
+                match self.set_pending_result(None) {
+                    Some(ResultSetMeta::Error(err)) => Err(err.into()),
+                    _ => unreachable!(),
+                }
+            }
             None => Ok(()),
         }
     }
 
-    /// This function will drop pending result and rollback a transaction, if needed.
+    /// This function will drop pending result, rollback a transaction
+    /// and restore the `READ WRITE` access mode, if needed.
     ///
     /// The purpose of this function, is to cleanup the connection while returning it to a [`Pool`].
     async fn cleanup_for_pool(mut self) -> Result<Self> {
         loop {
-            let result = if self.inner.pending_result.is_some() {
-                self.drop_result().await
-            } else if self.inner.tx_status != TxStatus::None {
-                self.rollback_transaction().await
-            } else {
-                break;
+            let result = match self.is_healthy() {
+                Health::Busy {
+                    pending_result: true,
+                    ..
+                } => self.drop_result().await,
+                Health::Busy {
+                    in_transaction: true,
+                    ..
+                } => self.rollback_transaction().await,
+                _ if self.inner.read_only => {
+                    let result = self.set_read_only(false).await;
+                    // won't retry on a non-fatal error
+                    self.inner.read_only = false;
+                    result
+                }
+                _ => break,
             };
 
             // The connection was dropped and we assume that it was dropped intentionally,
@@ -780,11 +1974,159 @@ impl Conn {
     }
 }
 
+/// Maximum number of re-scrambles (auth switches to the current plugin with a new nonce),
+/// that a server may request after the first auth switch.
+const MAX_AUTH_RESCRAMBLES: u8 = 1;
+
+/// `CLIENT_OPTIONAL_RESULTSET_METADATA` capability flag (MySql 8.0.19+).
+const CLIENT_OPTIONAL_RESULTSET_METADATA: u32 = 0x0200_0000;
+
+/// Checks `CLIENT_OPTIONAL_RESULTSET_METADATA` flag of the given initial handshake packet.
+///
+/// `mysql_common` drops capability flags, that it doesn't know.
+fn server_supports_optional_metadata(handshake: &[u8]) -> bool {
+    // protocol version, NUL-terminated server version
+    let version_end = match handshake.iter().skip(1).position(|x| *x == 0) {
+        Some(pos) => 1 + pos + 1,
+        None => return false,
+    };
+    // connection id, auth-plugin-data-part-1, filler, lower flags, charset, status flags
+    let upper_flags = version_end + 4 + 8 + 1 + 2 + 1 + 2;
+    match handshake.get(upper_flags..upper_flags + 2) {
+        Some(flags) => {
+            let flags = u32::from(u16::from_le_bytes([flags[0], flags[1]])) << 16;
+            flags & CLIENT_OPTIONAL_RESULTSET_METADATA != 0
+        }
+        None => false,
+    }
+}
+
+/// Prepends the `/* file:line */` comment to the given query, if `location` is `Some`
+/// (see [`Opts::tag_caller_location`]).
+pub(crate) fn with_caller_location<'q>(
+    query: &'q str,
+    location: Option<&Location<'_>>,
+) -> Cow<'q, str> {
+    match location {
+        Some(location) => format!(
+            "/* {}:{} */ {}",
+            location.file().replace("*/", "* /"),
+            location.line(),
+            query
+        )
+        .into(),
+        None => query.into(),
+    }
+}
+
+/// Appends `SELECT FOUND_ROWS()` to the given query (see [`Conn::query_with_found_rows`]).
+///
+/// Statements are separated by a newline, so that a trailing `--` or `#` comment
+/// of the given query doesn't swallow the appended one.
+fn with_found_rows(query: &str) -> String {
+    let query = query.trim_end().trim_end_matches(';');
+    format!("{}\n; SELECT FOUND_ROWS()", query)
+}
+
+/// Parses OK packet from `payload` assuming passed client-server `capabilities`.
+///
+/// `mysql_common` expects the info string, if `CLIENT_SESSION_TRACK` capability is negotiated,
+/// but the server omits it, if it's empty and session state isn't changed.
+pub(crate) fn parse_ok(
+    payload: &[u8],
+    capabilities: CapabilityFlags,
+    kind: OkPacketKind,
+) -> std::io::Result<OkPacket<'_>> {
+    match parse_ok_packet(payload, capabilities, kind) {
+        Err(_) if capabilities.contains(CapabilityFlags::CLIENT_SESSION_TRACK) => {
+            // without this flag the rest of the packet (empty here) is read as the info string
+            parse_ok_packet(
+                payload,
+                capabilities - CapabilityFlags::CLIENT_SESSION_TRACK,
+                kind,
+            )
+        }
+        result => result,
+    }
+}
+
+/// Returns `SESSION_TRACK_SCHEMA` entries of the session state block of the given OK packet.
+///
+/// Requires `CLIENT_SESSION_TRACK` capability and `SERVER_SESSION_STATE_CHANGED` status flag.
+fn schema_changes(payload: &[u8]) -> Vec<SessionStateInfo<'_>> {
+    let mut block = session_state_block(payload).unwrap_or_default();
+    let mut changes = Vec::new();
+    while let Some((&data_type, mut rest)) = block.split_first() {
+        if read_lenenc_bytes(&mut rest).is_none() {
+            break;
+        }
+        if data_type == SessionStateType::SESSION_TRACK_SCHEMA as u8 {
+            let entry = &block[..block.len() - rest.len()];
+            changes.extend(SessionStateInfo::parse(entry).ok());
+        }
+        block = rest;
+    }
+    changes
+}
+
+/// Returns the raw session state block of the given OK packet (see [`schema_changes`]).
+fn session_state_block(mut payload: &[u8]) -> Option<&[u8]> {
+    // header
+    payload = payload.get(1..)?;
+    // affected rows, last insert id
+    payload.read_lenenc_int().ok()?;
+    payload.read_lenenc_int().ok()?;
+    // status flags, warnings
+    payload = payload.get(4..)?;
+    // info
+    read_lenenc_bytes(&mut payload)?;
+    read_lenenc_bytes(&mut payload)
+}
+
+/// Reads a length-encoded string from the given buffer.
+fn read_lenenc_bytes<'a>(buf: &mut &'a [u8]) -> Option<&'a [u8]> {
+    let len = buf.read_lenenc_int().ok()?;
+    if (buf.len() as u64) < len {
+        return None;
+    }
+    let (value, rest) = buf.split_at(len as usize);
+    *buf = rest;
+    Some(value)
+}
+
+/// Injects the `MAX_EXECUTION_TIME` optimizer hint right after the leading `SELECT` keyword.
+///
+/// Returns `None` if the given query isn't a `SELECT` statement.
+fn with_max_execution_time(query: &str, timeout: Duration) -> Option<String> {
+    const SELECT: &str = "SELECT";
+
+    let trimmed = query.trim_start();
+    let keyword = trimmed.get(..SELECT.len())?;
+    let rest = &trimmed[SELECT.len()..];
+    let is_select = keyword.eq_ignore_ascii_case(SELECT)
+        && rest
+            .chars()
+            .next()
+            .map(|c| !(c.is_alphanumeric() || c == '_' || c == '$'))
+            .unwrap_or(true);
+
+    if is_select {
+        Some(format!(
+            "{} /*+ MAX_EXECUTION_TIME({}) */{}",
+            keyword,
+            timeout.as_millis(),
+            rest
+        ))
+    } else {
+        None
+    }
+}
+
 #[cfg(test)]
 mod test {
     use crate::{
-        from_row, params, prelude::*, test_misc::get_opts, Conn, Error, OptsBuilder, TxOpts,
-        WhiteListFsLocalInfileHandler,
+        consts::ColumnType, from_row, params, prelude::*, test_misc::get_opts, Conn, DriverError,
+        Error, Health, OptsBuilder, TxOpts, WhiteListFsLocalInfileHandler,
     };
 
     #[test]
@@ -837,6 +2179,22 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn should_return_peer_and_local_addr() -> super::Result<()> {
+        let opts = OptsBuilder::from_opts(get_opts()).prefer_socket(false);
+        let mut conn = Conn::new(opts.clone()).await?;
+        let peer_addr = conn.peer_addr().unwrap();
+        let local_addr = conn.local_addr().unwrap();
+
+        let port: u16 = conn.query_first("SELECT @@port").await?.unwrap();
+        assert_eq!(peer_addr.port(), port);
+        assert_eq!(peer_addr.port(), crate::Opts::from(opts).tcp_port());
+        assert_ne!(local_addr, peer_addr);
+
+        conn.disconnect().await?;
+        Ok(())
+    }
+
     #[tokio::test]
     async fn should_connect() -> super::Result<()> {
         let mut conn: Conn = Conn::new(get_opts()).await?;
@@ -885,50 +2243,2124 @@ mod test {
                 .db_name(None::<String>);
             let result = Conn::new(opts).await;
 
-            conn.query_drop("DROP USER 'test_user'@'%'").await.unwrap();
+            conn.query_drop("DROP USER 'test_user'@'%'").await.unwrap();
+
+            let result = result?;
+            assert_eq!(result.auth_plugin_name(), plug);
+            result.disconnect().await?;
+        }
+
+        if crate::test_misc::test_compression() {
+            assert!(format!("{:?}", conn).contains("Compression"));
+        }
+
+        if crate::test_misc::test_ssl() {
+            assert!(format!("{:?}", conn).contains("Tls"));
+        }
+
+        conn.disconnect().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_not_send_packet_larger_than_max_allowed_packet() -> super::Result<()> {
+        const MAX: usize = 1024;
+
+        let mut conn = Conn::new(get_opts()).await?;
+        conn.stream_mut()?.set_max_allowed_packet(MAX);
+
+        // `COM_QUERY` byte + query text
+        let query = format!("SELECT '{}'", "a".repeat(MAX - 10));
+        assert_eq!(query.len() + 1, MAX + 1);
+
+        match conn.query_drop(query).await {
+            Err(Error::Driver(DriverError::PacketTooLarge { size, max })) => {
+                assert_eq!(size, MAX + 1);
+                assert_eq!(max, MAX);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        // connection should still be usable
+        let result: Option<u8> = conn.query_first("SELECT 1").await?;
+        assert_eq!(result, Some(1));
+
+        conn.disconnect().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_split_oversized_multi_statement_query() -> super::Result<()> {
+        const MAX: usize = 1024;
+        const COUNT: usize = 200;
+
+        let mut conn = Conn::new(get_opts().split_oversized_queries(true)).await?;
+        conn.stream_mut()?.set_max_allowed_packet(MAX);
+
+        conn.query_drop("CREATE TEMPORARY TABLE split (id INT, text TEXT)")
+            .await?;
+
+        // string literals and comments contain `;`, that must not be treated as a delimiter
+        let batch = (0..COUNT)
+            .map(|i| format!("INSERT INTO split VALUES ({}, 'foo;bar') /* ; */;\n", i))
+            .collect::<String>();
+        assert!(batch.len() > MAX);
+
+        conn.query_drop(&batch).await?;
+
+        let result: Vec<(usize, String)> =
+            conn.query("SELECT COUNT(*), MIN(text) FROM split").await?;
+        assert_eq!(result, vec![(COUNT, "foo;bar".to_string())]);
+
+        // result sets of all parts are available through a single query result
+        let batch = (0..COUNT)
+            .map(|i| format!("SELECT {};", i))
+            .collect::<String>();
+        assert!(batch.len() > MAX);
+
+        let mut result = conn.query_iter(batch).await?;
+        let mut values = Vec::new();
+        while !result.is_empty() {
+            values.extend(result.collect::<usize>().await?);
+        }
+        assert_eq!(values, (0..COUNT).collect::<Vec<_>>());
+
+        // execution stops at the first error
+        let batch = format!(
+            "{}BLABLA;{}",
+            "DO 1;".repeat(MAX / 5),
+            "INSERT INTO split VALUES (0, '');".repeat(MAX / 10)
+        );
+        assert!(conn.query_drop(batch).await.is_err());
+        let count: Option<usize> = conn.query_first("SELECT COUNT(*) FROM split").await?;
+        assert_eq!(count, Some(COUNT));
+
+        conn.disconnect().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_cache_time_zone() -> super::Result<()> {
+        let mut conn = Conn::new(get_opts()).await?;
+
+        conn.set_time_zone("+00:00").await?;
+        assert_eq!(conn.time_zone().await?, "+00:00");
+
+        conn.set_time_zone("+03:00").await?;
+        assert_eq!(conn.time_zone().await?, "+03:00");
+
+        // cached value won't reflect direct changes
+        conn.query_drop("SET time_zone = '+01:00'").await?;
+        assert_eq!(conn.time_zone().await?, "+03:00");
+
+        conn.reset().await?;
+        let expected: Option<String> = conn
+            .query_first(
+                "SELECT IF(@@session.time_zone = 'SYSTEM', @@system_time_zone, @@session.time_zone)",
+            )
+            .await?;
+        assert_eq!(Some(conn.time_zone().await?), expected);
+
+        conn.disconnect().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_respect_no_backslash_escapes() -> super::Result<()> {
+        let opts = OptsBuilder::from_opts(get_opts()).always_prepare(true);
+        let mut conn = Conn::new(opts).await?;
+        assert!(!conn.no_backslash_escapes());
+
+        conn.query_drop("SET SESSION sql_mode = 'NO_BACKSLASH_ESCAPES'")
+            .await?;
+        assert!(conn.no_backslash_escapes());
+
+        let value: Option<String> = conn.query_first(r"SELECT 'a\b\'").await?;
+        assert_eq!(value.unwrap(), r"a\b\");
+        let value: Option<String> = conn.exec_first("SELECT ?", (r"a\'b\",)).await?;
+        assert_eq!(value.unwrap(), r"a\'b\");
+        // multi-statement query is detected, so it isn't prepared
+        let values: Vec<String> = conn.query(r#"SELECT 'c\'; SELECT "\""#).await?;
+        assert_eq!(values, vec![r"c\"]);
+
+        conn.query_drop("SET SESSION sql_mode = DEFAULT").await?;
+        assert!(!conn.no_backslash_escapes());
+
+        conn.disconnect().await?;
+        Ok(())
+    }
+
+    #[test]
+    fn should_inject_max_execution_time_hint() {
+        use super::with_max_execution_time;
+        use std::time::Duration;
+
+        let timeout = Duration::from_millis(1500);
+        assert_eq!(
+            with_max_execution_time("SELECT 1", timeout).unwrap(),
+            "SELECT /*+ MAX_EXECUTION_TIME(1500) */ 1"
+        );
+        assert_eq!(
+            with_max_execution_time("  select\n*  FROM t", timeout).unwrap(),
+            "select /*+ MAX_EXECUTION_TIME(1500) */\n*  FROM t"
+        );
+        assert_eq!(
+            with_max_execution_time("SELECT*FROM t", Duration::from_micros(2500)).unwrap(),
+            "SELECT /*+ MAX_EXECUTION_TIME(2) */*FROM t"
+        );
+        assert!(with_max_execution_time("SELECTED", timeout).is_none());
+        assert!(with_max_execution_time("DO SLEEP(1)", timeout).is_none());
+        assert!(with_max_execution_time("(SELECT 1)", timeout).is_none());
+        assert!(with_max_execution_time("SEL", timeout).is_none());
+        assert!(with_max_execution_time("", timeout).is_none());
+    }
+
+    #[test]
+    fn should_append_found_rows_query() {
+        use super::with_found_rows;
+
+        assert_eq!(
+            with_found_rows("SELECT SQL_CALC_FOUND_ROWS 1 LIMIT 1"),
+            "SELECT SQL_CALC_FOUND_ROWS 1 LIMIT 1\n; SELECT FOUND_ROWS()"
+        );
+        assert_eq!(
+            with_found_rows("SELECT 1; \n"),
+            "SELECT 1\n; SELECT FOUND_ROWS()"
+        );
+        assert_eq!(
+            with_found_rows("SELECT 1 -- comment"),
+            "SELECT 1 -- comment\n; SELECT FOUND_ROWS()"
+        );
+    }
+
+    #[tokio::test]
+    async fn should_query_with_found_rows() -> super::Result<()> {
+        let mut conn = Conn::new(get_opts()).await?;
+        conn.query_drop("CREATE TEMPORARY TABLE found_rows (id INT)")
+            .await?;
+        conn.exec_batch(
+            "INSERT INTO found_rows (id) VALUES (?)",
+            (1..=10).map(|id| (id,)),
+        )
+        .await?;
+
+        let (rows, found_rows) = conn
+            .query_with_found_rows::<u32, _>(
+                "SELECT SQL_CALC_FOUND_ROWS id FROM found_rows ORDER BY id LIMIT 3",
+            )
+            .await?;
+        assert_eq!(rows, vec![1, 2, 3]);
+        assert_eq!(found_rows, 10);
+
+        // a trailing comment doesn't swallow `SELECT FOUND_ROWS()`
+        let (rows, found_rows) = conn
+            .query_with_found_rows::<u32, _>(
+                "SELECT SQL_CALC_FOUND_ROWS id FROM found_rows ORDER BY id LIMIT 2 -- first page",
+            )
+            .await?;
+        assert_eq!(rows, vec![1, 2]);
+        assert_eq!(found_rows, 10);
+        assert_eq!(conn.is_healthy(), Health::Idle);
+
+        conn.disconnect().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_query_with_max_execution_time() -> super::Result<()> {
+        use std::time::{Duration, Instant};
+
+        let mut conn = Conn::new(get_opts()).await?;
+        let timeout = Duration::from_millis(500);
+
+        let result: Vec<u8> = conn
+            .query_with_max_execution_time("SELECT 42", timeout)
+            .await?;
+        assert_eq!(result, vec![42]);
+
+        let started = Instant::now();
+        // `SLEEP` returns `1` without an error if it's the only thing, that was interrupted,
+        // so make it a part of the query.
+        let result = conn
+            .query_with_max_execution_time::<u8, _>(
+                "SELECT 1 FROM (SELECT 1 AS x) AS t WHERE SLEEP(5) = 0",
+                timeout,
+            )
+            .await;
+        match result {
+            // ER_QUERY_TIMEOUT
+            Err(Error::Server(ref err)) if err.code == 3024 => (),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        let result = conn
+            .query_with_max_execution_time::<u8, _>("DO SLEEP(5)", timeout)
+            .await;
+        assert!(matches!(
+            result,
+            Err(Error::Driver(DriverError::MaxExecutionTimeRequiresSelect))
+        ));
+
+        conn.disconnect().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_retain_last_statement() -> super::Result<()> {
+        let mut conn = Conn::new(get_opts()).await?;
+        conn.query_drop("DO 1").await?;
+        assert_eq!(conn.last_statement(), None);
+        conn.disconnect().await?;
+
+        let opts = OptsBuilder::from_opts(get_opts()).retain_last_statement(true);
+        let mut conn = Conn::new(opts).await?;
+
+        const QUERY: &str = "SELECT * FROM mysql_async_no_such_table";
+        assert!(conn.query_drop(QUERY).await.is_err());
+        assert_eq!(conn.last_statement(), Some(QUERY));
+
+        const NAMED: &str = "SELECT :foo FROM mysql_async_no_such_table";
+        assert!(conn.exec_drop(NAMED, params! { "foo" => 1 }).await.is_err());
+        assert_eq!(
+            conn.last_statement(),
+            Some("SELECT ? FROM mysql_async_no_such_table")
+        );
+
+        let stmt = conn.prep("SELECT ?").await?;
+        conn.query_drop("DO 1").await?;
+        conn.exec_drop(&stmt, (1,)).await?;
+        assert_eq!(conn.last_statement(), Some("SELECT ?"));
+
+        conn.disconnect().await?;
+        Ok(())
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn should_use_server_public_key_from_file() -> super::Result<()> {
+        use crate::mock::MockServer;
+        use std::io::Write;
+
+        const PUBLIC_KEY: &str = "\
+-----BEGIN PUBLIC KEY-----
+MIIBIjANBgkqhkiG9w0BAQEFAAOCAQ8AMIIBCgKCAQEAs1XobjQmvt01bb6oR47l
++mtNK9GueZdbkG0L4Q7x6LC7SD5eK360CSWHMtN+vD1OGq0LlS/EPPn3p2rLewCj
+kZy8L1o7S847CUvfH4XOg1O1sWwmE+rf+8KuVFHV4xeaKFl56VXnLY2uLwz2cZaZ
+I6w/zzhOUsQLra/Em/Me8THnlLdd+t68tKi8PpFKa1ImH/eiP8HUJbDO0l4vh2KJ
+0IWP8TCdLWTeROD7050nCQhvarMy3R1MmPV+UtQuyL4h/DapWThv5B3VTCgHYkMD
+oZ8pr1fIOGKxDWz7u4EZuTR6F5ljKmaWWNc/YQQZDfTq18fLi3CZ4gbzvIkcAQPk
+BwIDAQAB
+-----END PUBLIC KEY-----
+";
+
+        let mut key_file = tempfile::NamedTempFile::new()?;
+        key_file.write_all(PUBLIC_KEY.as_bytes())?;
+
+        let opts = OptsBuilder::default()
+            .user(Some("root"))
+            .pass(Some("password"))
+            .server_public_key(Some(key_file.path()));
+        let (mut server, conn) = MockServer::connect(opts);
+
+        let client = async move {
+            let conn: Conn = conn.await?;
+            assert_eq!(conn.auth_plugin_name(), "caching_sha2_password");
+            conn.disconnect().await
+        };
+
+        let server = async move {
+            server
+                .write_handshake("caching_sha2_password", &[0x2a; 20])
+                .await?;
+            server.read_handshake_response().await?;
+            // perform full authentication
+            server.write_packet(&[0x01, 0x04]).await?;
+            // password encrypted with 2048 bit key instead of the public key request (`0x02`)
+            assert_eq!(server.read_packet().await?.len(), 256);
+            server.write_ok().await?;
+            server.answer_settings_query().await?;
+            assert_eq!(server.read_command().await?, b"\x01");
+            Ok::<_, std::io::Error>(())
+        };
+
+        let (client, server) = futures_util::future::join(client, server).await;
+        server?;
+        client
+    }
+
+    #[tokio::test]
+    async fn should_list_fields() -> super::Result<()> {
+        let mut conn = Conn::new(get_opts()).await?;
+        conn.query_drop(
+            "CREATE TEMPORARY TABLE tmp_field_list (id INT, name VARCHAR(16), note TEXT)",
+        )
+        .await?;
+
+        let columns = conn.field_list("tmp_field_list", "").await?;
+        let columns = columns
+            .iter()
+            .map(|column| (column.name_str().into_owned(), column.column_type()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            columns,
+            vec![
+                ("id".into(), ColumnType::MYSQL_TYPE_LONG),
+                ("name".into(), ColumnType::MYSQL_TYPE_VAR_STRING),
+                ("note".into(), ColumnType::MYSQL_TYPE_BLOB),
+            ]
+        );
+
+        let columns = conn.field_list("tmp_field_list", "n%").await?;
+        assert_eq!(columns.len(), 2);
+
+        // connection is usable afterwards
+        assert_eq!(conn.query_first::<u8, _>("SELECT 1").await?, Some(1));
+
+        conn.disconnect().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_round_trip_big_result_with_custom_buffer_sizes() -> super::Result<()> {
+        for &(read, write) in &[(1024 * 1024, 1024 * 1024), (16, 16)] {
+            let opts = get_opts().read_buffer_size(read).write_buffer_size(write);
+            let mut conn = Conn::new(opts).await?;
+
+            let value = "A".repeat(2 * 1024 * 1024);
+            let result: Option<String> = conn.exec_first("SELECT ?", (&value,)).await?;
+            assert_eq!(result.as_ref(), Some(&value));
+
+            conn.disconnect().await?;
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn should_round_trip_big_result_with_custom_buffer_sizes_on_mock() -> super::Result<()> {
+        use crate::mock::MockServer;
+
+        let opts = OptsBuilder::default()
+            .read_buffer_size(1024 * 1024)
+            .write_buffer_size(1024 * 1024);
+        let (mut server, conn) = MockServer::connect(opts);
+        let value = "A".repeat(2 * 1024 * 1024);
+
+        let client = async move {
+            let mut conn: Conn = conn.await?;
+            let result: Vec<String> = conn.query("SELECT big").await?;
+            conn.disconnect().await?;
+            Ok::<_, Error>(result)
+        };
+
+        let server = async {
+            server.accept().await?;
+            server.read_command().await?;
+            let rows = vec![vec![Some(&*value)]; 3];
+            server.write_text_result_set(&["big"], &rows).await?;
+            assert_eq!(server.read_command().await?, b"\x01");
+            Ok::<_, std::io::Error>(())
+        };
+
+        let (client, server) = futures_util::future::join(client, server).await;
+        server?;
+        assert_eq!(client?, vec![value.clone(); 3]);
+        Ok(())
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn should_decode_rows_into_reused_buffers_on_mock() -> super::Result<()> {
+        use crate::mock::MockServer;
+
+        let (mut server, conn) = MockServer::connect(OptsBuilder::default().reuse_buffers(true));
+        let big = "B".repeat(100 * 1024);
+
+        let client = async move {
+            let mut conn: Conn = conn.await?;
+            let first: Vec<(String, Option<String>)> = conn.query("SELECT first").await?;
+
+            let mut result = conn.query_iter("SELECT second").await?;
+            let row = result.next().await?.map(crate::from_row::<String>);
+            result.drop_result().await?;
+
+            conn.query_drop("SELECT third").await?;
+            let fourth: Vec<(String, String, String)> = conn.query("SELECT fourth").await?;
+            conn.disconnect().await?;
+            Ok::<_, Error>((first, row, fourth))
+        };
+
+        let server = async {
+            server.accept().await?;
+            server.read_command().await?;
+            let rows = vec![
+                vec![Some("a"), None],
+                vec![Some(&*big), Some("b")],
+                vec![Some("c"), Some("d")],
+            ];
+            server.write_text_result_set(&["x", "y"], &rows).await?;
+            for _ in 0..2 {
+                server.read_command().await?;
+                let rows = vec![vec![Some("foo")], vec![Some(&*big)], vec![Some("bar")]];
+                server.write_text_result_set(&["x"], &rows).await?;
+            }
+            server.read_command().await?;
+            let rows = vec![vec![Some("1"), Some("22"), Some("333")]; 2];
+            server
+                .write_text_result_set(&["x", "y", "z"], &rows)
+                .await?;
+            assert_eq!(server.read_command().await?, b"\x01");
+            Ok::<_, std::io::Error>(())
+        };
+
+        let (client, server) = futures_util::future::join(client, server).await;
+        server?;
+        let (first, row, fourth) = client?;
+        assert_eq!(
+            first,
+            vec![
+                ("a".into(), None),
+                (big.clone(), Some("b".into())),
+                ("c".into(), Some("d".into())),
+            ]
+        );
+        assert_eq!(row, Some("foo".into()));
+        assert_eq!(fourth, vec![("1".into(), "22".into(), "333".into()); 2]);
+        Ok(())
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn should_report_unsupported_field_list() -> super::Result<()> {
+        use crate::mock::MockServer;
+
+        let (mut server, conn) = MockServer::connect(OptsBuilder::default());
+
+        let client = async move {
+            let mut conn: Conn = conn.await?;
+            let result = conn.field_list("foo", "").await;
+            conn.disconnect().await?;
+            Ok::<_, Error>(result)
+        };
+
+        let server = async move {
+            server.accept().await?;
+            assert_eq!(server.read_command().await?, b"\x04foo\0");
+            server.write_err(1047, "08S01", "Unknown command").await?;
+            assert_eq!(server.read_command().await?, b"\x01");
+            Ok::<_, std::io::Error>(())
+        };
+
+        let (client, server) = futures_util::future::join(client, server).await;
+        server?;
+        match client? {
+            Err(Error::Driver(DriverError::FieldListNotSupported)) => Ok(()),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn should_not_send_quit_if_disabled() -> super::Result<()> {
+        use crate::mock::MockServer;
+
+        let opts = OptsBuilder::default().send_quit_on_disconnect(false);
+        let (mut server, conn) = MockServer::connect(opts);
+
+        let client = async move {
+            let conn: Conn = conn.await?;
+            conn.disconnect().await
+        };
+
+        let server = async move {
+            server.accept().await?;
+            // connection is closed without `COM_QUIT`
+            let err = server.read_command().await.unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+            Ok::<_, std::io::Error>(())
+        };
+
+        let (client, server) = futures_util::future::join(client, server).await;
+        server?;
+        client
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn should_connect_without_plugin_auth() -> super::Result<()> {
+        use crate::{
+            consts::CapabilityFlags,
+            mock::{MockServer, DEFAULT_MOCK_CAPABILITIES},
+            ConnectPhase,
+        };
+
+        let old_capabilities = DEFAULT_MOCK_CAPABILITIES - CapabilityFlags::CLIENT_PLUGIN_AUTH;
+
+        // `mysql_native_password` scramble is used without plugin authentication
+        let (server, conn) = MockServer::connect(OptsBuilder::default());
+        let mut server = server.with_capabilities(old_capabilities);
+        let server = async move {
+            server
+                .write_handshake("caching_sha2_password", &[0x2a; 20])
+                .await?;
+            let response = server.read_handshake_response().await?;
+            assert!(!server
+                .capabilities()
+                .contains(CapabilityFlags::CLIENT_PLUGIN_AUTH));
+            // no plugin name is sent
+            assert!(!String::from_utf8_lossy(&response).contains("_password"));
+            server.write_ok().await?;
+            server.answer_settings_query().await?;
+            Ok::<_, std::io::Error>(())
+        };
+        let (client, server) = futures_util::future::join(conn, server).await;
+        server?;
+        client?;
+
+        // old password scramble is requested (see `should_perform_old_password_auth`)
+        #[cfg(not(feature = "legacy-auth"))]
+        {
+            let (server, conn) = MockServer::connect(OptsBuilder::default());
+            let mut server = server.with_capabilities(old_capabilities);
+            let server = async move {
+                server
+                    .write_handshake("mysql_native_password", &[0x2a; 20])
+                    .await?;
+                server.read_handshake_response().await?;
+                server.write_packet(&[0xfe]).await?;
+                Ok::<_, std::io::Error>(())
+            };
+            let (client, server) = futures_util::future::join(conn, server).await;
+            server?;
+            match client {
+                Err(Error::Connect(err)) => {
+                    assert_eq!(err.phase, ConnectPhase::Auth);
+                    match *err.source {
+                        Error::Driver(DriverError::OldPasswordAuthRequested) => (),
+                        ref err => panic!("unexpected error: {}", err),
+                    }
+                }
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+
+        // pre-4.1 protocol
+        let (server, conn) = MockServer::connect(OptsBuilder::default());
+        let mut server = server.with_capabilities(
+            old_capabilities
+                - CapabilityFlags::CLIENT_PROTOCOL_41
+                - CapabilityFlags::CLIENT_SECURE_CONNECTION,
+        );
+        let server = async move {
+            server
+                .write_handshake("mysql_native_password", &[0x2a; 20])
+                .await
+        };
+        let (client, server) = futures_util::future::join(conn, server).await;
+        server?;
+        match client {
+            Err(Error::Connect(err)) => {
+                assert_eq!(err.phase, ConnectPhase::Handshake);
+                match *err.source {
+                    Error::Driver(DriverError::ServerTooOld {
+                        min_version: (4, 1, 1),
+                    }) => (),
+                    ref err => panic!("unexpected error: {}", err),
+                }
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn should_require_multi_results() -> super::Result<()> {
+        use crate::{
+            consts::CapabilityFlags,
+            mock::{MockServer, DEFAULT_MOCK_CAPABILITIES},
+            ConnectPhase,
+        };
+
+        // the capability is negotiated
+        let (mut server, conn) = MockServer::connect(OptsBuilder::default());
+        let (client, server) = futures_util::future::join(conn, server.accept()).await;
+        server?;
+        let conn = client?;
+        assert!(conn.supports_multi_results());
+
+        // the capability is stripped (e.g. by a proxy)
+        let stripped = DEFAULT_MOCK_CAPABILITIES
+            - CapabilityFlags::CLIENT_MULTI_RESULTS
+            - CapabilityFlags::CLIENT_PS_MULTI_RESULTS;
+        let (server, conn) = MockServer::connect(OptsBuilder::default());
+        let mut server = server.with_capabilities(stripped);
+        let (client, server) = futures_util::future::join(conn, server.accept()).await;
+        server?;
+        assert!(!client?.supports_multi_results());
+
+        let (server, conn) =
+            MockServer::connect(OptsBuilder::default().require_multi_results(true));
+        let mut server = server.with_capabilities(stripped);
+        let server = async move {
+            server
+                .write_handshake("mysql_native_password", &[0x2a; 20])
+                .await
+        };
+        let (client, server) = futures_util::future::join(conn, server).await;
+        server?;
+        match client {
+            Err(Error::Connect(err)) => {
+                assert_eq!(err.phase, ConnectPhase::Handshake);
+                match *err.source {
+                    Error::Driver(DriverError::MultiResultsNotSupported) => (),
+                    ref err => panic!("unexpected error: {}", err),
+                }
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn should_handle_double_auth_switch() -> super::Result<()> {
+        use crate::{mock::MockServer, ConnectPhase};
+        use mysql_common::scramble::scramble_native;
+
+        async fn switch(server: &mut MockServer, nonce: &[u8; 20]) -> std::io::Result<()> {
+            let mut auth_switch_request = vec![0xfe];
+            auth_switch_request.extend_from_slice(b"mysql_native_password\0");
+            auth_switch_request.extend_from_slice(nonce);
+            auth_switch_request.push(0);
+            server.write_packet(&auth_switch_request).await?;
+            let scramble = server.read_packet().await?;
+            assert_eq!(&scramble[..], &scramble_native(nonce, b"pass").unwrap()[..]);
+            Ok(())
+        }
+
+        let opts = OptsBuilder::default().user(Some("user")).pass(Some("pass"));
+
+        // the second switch re-scrambles with a new nonce
+        let (mut server, conn) = MockServer::connect(opts.clone());
+        let server = async move {
+            server
+                .write_handshake("caching_sha2_password", &[0x2a; 20])
+                .await?;
+            server.read_handshake_response().await?;
+            switch(&mut server, &[0x01; 20]).await?;
+            switch(&mut server, &[0x02; 20]).await?;
+            server.write_ok().await?;
+            server.answer_settings_query().await?;
+            Ok::<_, std::io::Error>(())
+        };
+        let (client, server) = futures_util::future::join(conn, server).await;
+        server?;
+        let conn = client?;
+        assert!(conn.auth_switched());
+        assert_eq!(conn.auth_plugin_name(), "mysql_native_password");
+
+        // a hostile server can't keep the client re-scrambling forever
+        let (mut server, conn) = MockServer::connect(opts);
+        let server = async move {
+            server
+                .write_handshake("caching_sha2_password", &[0x2a; 20])
+                .await?;
+            server.read_handshake_response().await?;
+            switch(&mut server, &[0x01; 20]).await?;
+            switch(&mut server, &[0x02; 20]).await?;
+            let mut auth_switch_request = vec![0xfe];
+            auth_switch_request.extend_from_slice(b"mysql_native_password\0");
+            auth_switch_request.extend_from_slice(&[0x03; 20]);
+            auth_switch_request.push(0);
+            server.write_packet(&auth_switch_request).await
+        };
+        let (client, server) = futures_util::future::join(conn, server).await;
+        server?;
+        match client {
+            Err(Error::Connect(err)) => {
+                assert_eq!(err.phase, ConnectPhase::Auth);
+                match *err.source {
+                    Error::Driver(DriverError::TooManyAuthSwitches) => (),
+                    ref err => panic!("unexpected error: {}", err),
+                }
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        Ok(())
+    }
+
+    #[cfg(all(feature = "mock", feature = "legacy-auth"))]
+    #[tokio::test]
+    async fn should_perform_old_password_auth() -> super::Result<()> {
+        use crate::{
+            consts::CapabilityFlags,
+            mock::{MockServer, DEFAULT_MOCK_CAPABILITIES},
+        };
+
+        const NONCE: &[u8; 20] = b"abcdefgh0123456789ab";
+
+        // server without plugin authentication requests the old scramble
+        let opts = OptsBuilder::default()
+            .user(Some("legacy"))
+            .pass(Some("mypass"));
+        let (server, conn) = MockServer::connect(opts.clone());
+        let mut server = server
+            .with_capabilities(DEFAULT_MOCK_CAPABILITIES - CapabilityFlags::CLIENT_PLUGIN_AUTH);
+        let server = async move {
+            server
+                .write_handshake("mysql_native_password", NONCE)
+                .await?;
+            server.read_handshake_response().await?;
+            server.write_packet(&[0xfe]).await?;
+            let scramble = server.read_packet().await?;
+            assert_eq!(
+                scramble,
+                super::old_password::scramble_323(NONCE, Some("mypass"))
+            );
+            server.write_ok().await?;
+            server.answer_settings_query().await?;
+            Ok::<_, std::io::Error>(())
+        };
+        let (client, server) = futures_util::future::join(conn, server).await;
+        server?;
+        client?;
+
+        // server with plugin authentication switches to `mysql_old_password`
+        let (mut server, conn) = MockServer::connect(opts);
+        let server = async move {
+            server
+                .write_handshake("mysql_native_password", &[0x2a; 20])
+                .await?;
+            server.read_handshake_response().await?;
+            let mut auth_switch_request = vec![0xfe];
+            auth_switch_request.extend_from_slice(b"mysql_old_password\0");
+            auth_switch_request.extend_from_slice(&NONCE[..8]);
+            auth_switch_request.push(0);
+            server.write_packet(&auth_switch_request).await?;
+            let scramble = server.read_packet().await?;
+            assert_eq!(
+                scramble,
+                super::old_password::scramble_323(NONCE, Some("mypass"))
+            );
+            server.write_ok().await?;
+            server.answer_settings_query().await?;
+            Ok::<_, std::io::Error>(())
+        };
+        let (client, server) = futures_util::future::join(conn, server).await;
+        server?;
+        let conn = client?;
+        assert_eq!(conn.auth_plugin_name(), "mysql_old_password");
+
+        Ok(())
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn should_capture_handshake() -> super::Result<()> {
+        use crate::{mock::MockServer, FrameDirection};
+        use std::sync::{Arc, Mutex};
+
+        let frames = Arc::new(Mutex::new(Vec::new()));
+        let opts = OptsBuilder::default().capture_handshake(Some(frames.clone()));
+        let (mut server, conn) = MockServer::connect(opts);
+
+        let client = async move {
+            let mut conn: Conn = conn.await?;
+            conn.query_drop("DO 1").await?;
+            conn.disconnect().await
+        };
+
+        let server = async move {
+            let handshake_response = server.accept().await?;
+            server.read_command().await?;
+            server.write_ok().await?;
+            server.read_command().await?;
+            Ok::<_, std::io::Error>(handshake_response)
+        };
+
+        let (client, server) = futures_util::future::join(client, server).await;
+        let handshake_response = server?;
+        client?;
+
+        let frames = frames.lock().unwrap();
+        // handshake, handshake response and OK
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[0].direction, FrameDirection::ServerToClient);
+        assert_eq!(frames[0].seq_id, 0);
+        assert_eq!(frames[0].payload[0], 0x0a);
+        assert_eq!(frames[1].direction, FrameDirection::ClientToServer);
+        assert_eq!(frames[1].seq_id, 1);
+        assert_eq!(frames[1].payload, handshake_response);
+        assert_eq!(frames[2].direction, FrameDirection::ServerToClient);
+        assert_eq!(frames[2].seq_id, 2);
+        assert_eq!(frames[2].payload[0], 0x00);
+        Ok(())
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn should_rewrite_queries() -> super::Result<()> {
+        use crate::{consts::Command, mock::MockServer};
+        use std::borrow::Cow;
+
+        let opts = OptsBuilder::default().query_rewriter(|query: &str| {
+            if query.starts_with("SELECT @@") {
+                Ok(Cow::Borrowed(query))
+            } else if query.to_uppercase().starts_with("DROP") {
+                Err("DROP is not allowed".to_string())
+            } else {
+                Ok(Cow::Owned(format!("/* tenant:123 */ {}", query)))
+            }
+        });
+        let (mut server, conn) = MockServer::connect(opts);
+
+        let client = async move {
+            let mut conn: Conn = conn.await?;
+            conn.query_drop("DO 1").await?;
+            conn.prep("DO 2").await?;
+            // named parameters are replaced after the rewrite
+            assert!(matches!(conn.prep("DO :x").await, Err(Error::Server(_))));
+            match conn.query_drop("drop table t").await {
+                Err(Error::Driver(DriverError::QueryRejected { query, reason })) => {
+                    assert_eq!(query, "drop table t");
+                    assert_eq!(reason, "DROP is not allowed");
+                }
+                other => panic!("unexpected result: {:?}", other),
+            }
+            conn.disconnect().await
+        };
+
+        let server = async move {
+            server.accept().await?;
+
+            let command = server.read_command().await?;
+            assert_eq!(command[0], Command::COM_QUERY as u8);
+            assert_eq!(&command[1..], b"/* tenant:123 */ DO 1");
+            server.write_ok().await?;
+
+            let command = server.read_command().await?;
+            assert_eq!(command[0], Command::COM_STMT_PREPARE as u8);
+            assert_eq!(&command[1..], b"/* tenant:123 */ DO 2");
+            let mut payload = vec![0x00];
+            payload.extend_from_slice(&1_u32.to_le_bytes());
+            payload.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0]);
+            server.write_packet(&payload).await?;
+
+            let command = server.read_command().await?;
+            assert_eq!(&command[1..], b"/* tenant:123 */ DO ?");
+            server.write_err(1064, "42000", "syntax error").await?;
+
+            // rejected query isn't sent
+            assert_eq!(server.read_command().await?, vec![Command::COM_QUIT as u8]);
+            Ok::<_, std::io::Error>(())
+        };
+
+        let (client, server) = futures_util::future::join(client, server).await;
+        server?;
+        client
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn should_send_transaction_hint() -> super::Result<()> {
+        use crate::{consts::Command, mock::MockServer, TxOpts};
+
+        let opts = OptsBuilder::default().transaction_hint(Some("/* backend={connection_id} */"));
+        let (mut server, conn) = MockServer::connect(opts);
+
+        let client = async move {
+            let mut conn: Conn = conn.await?;
+            let mut tx = conn.start_transaction(TxOpts::default()).await?;
+            tx.query_drop("DO 1").await?;
+            tx.prep("DO 2").await?;
+            tx.commit().await?;
+            conn.query_drop("DO 3").await?;
+            conn.disconnect().await
+        };
+
+        let server = async move {
+            server.accept().await?;
+
+            let mut commands = Vec::new();
+            for _ in 0..5 {
+                let command = server.read_command().await?;
+                if command[0] == Command::COM_STMT_PREPARE as u8 {
+                    let mut payload = vec![0x00];
+                    payload.extend_from_slice(&1_u32.to_le_bytes());
+                    payload.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0]);
+                    server.write_packet(&payload).await?;
+                } else {
+                    server.write_ok().await?;
+                }
+                commands.push((
+                    command[0],
+                    String::from_utf8_lossy(&command[1..]).into_owned(),
+                ));
+            }
+            assert_eq!(server.read_command().await?, vec![Command::COM_QUIT as u8]);
+
+            let query = Command::COM_QUERY as u8;
+            let prepare = Command::COM_STMT_PREPARE as u8;
+            assert_eq!(
+                commands,
+                vec![
+                    (query, "/* backend=1 */ START TRANSACTION".into()),
+                    (query, "/* backend=1 */ DO 1".into()),
+                    (prepare, "/* backend=1 */ DO 2".into()),
+                    (query, "/* backend=1 */ COMMIT".into()),
+                    (query, "DO 3".into()),
+                ]
+            );
+            Ok::<_, std::io::Error>(())
+        };
+
+        let (client, server) = futures_util::future::join(client, server).await;
+        server?;
+        client
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn should_handle_prepared_stmt_limit() -> super::Result<()> {
+        use crate::{mock::MockServer, PreparedStmtLimitPolicy};
+
+        /// `COM_STMT_PREPARE` response for a statement without columns and params.
+        fn prepare_ok(id: u32) -> Vec<u8> {
+            let mut payload = vec![0x00];
+            payload.extend_from_slice(&id.to_le_bytes());
+            payload.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0]);
+            payload
+        }
+
+        const LIMIT_MESSAGE: &str = "Can't create more than max_prepared_stmt_count statements";
+
+        // evicts the cached statement and retries
+        let opts = OptsBuilder::default().on_prepared_limit(PreparedStmtLimitPolicy::EvictCached);
+        let (mut server, conn) = MockServer::connect(opts);
+
+        let client = async move {
+            let mut conn: Conn = conn.await?;
+            conn.prep("DO 1").await?;
+            assert_eq!(conn.prepared_stmt_count(), 1);
+            let stmt = conn.prep("DO 2").await?;
+            assert_eq!(stmt.id(), 2);
+            assert_eq!(conn.prepared_stmt_count(), 1);
+            assert_eq!(conn.stmt_cache_ref().len(), 1);
+            conn.disconnect().await
+        };
+
+        let server = async move {
+            server.accept().await?;
+            assert_eq!(server.read_command().await?, b"\x16DO 1");
+            server.write_packet(&prepare_ok(1)).await?;
+            assert_eq!(server.read_command().await?, b"\x16DO 2");
+            server.write_err(1461, "42000", LIMIT_MESSAGE).await?;
+            assert_eq!(server.read_command().await?, b"\x19\x01\0\0\0");
+            assert_eq!(server.read_command().await?, b"\x16DO 2");
+            server.write_packet(&prepare_ok(2)).await?;
+            assert_eq!(server.read_command().await?, b"\x01");
+            Ok::<_, std::io::Error>(())
+        };
+
+        let (client, server) = futures_util::future::join(client, server).await;
+        server?;
+        client?;
+
+        // reports the typed error and keeps the connection usable
+        let opts = OptsBuilder::default().on_prepared_limit(PreparedStmtLimitPolicy::Error);
+        let (mut server, conn) = MockServer::connect(opts);
+
+        let client = async move {
+            let mut conn: Conn = conn.await?;
+            conn.prep("DO 1").await?;
+            let result = conn.prep("DO 2").await;
+            assert_eq!(conn.prepared_stmt_count(), 1);
+            conn.disconnect().await?;
+            Ok::<_, Error>(result)
+        };
+
+        let server = async move {
+            server.accept().await?;
+            assert_eq!(server.read_command().await?, b"\x16DO 1");
+            server.write_packet(&prepare_ok(1)).await?;
+            assert_eq!(server.read_command().await?, b"\x16DO 2");
+            server.write_err(1461, "42000", LIMIT_MESSAGE).await?;
+            assert_eq!(server.read_command().await?, b"\x01");
+            Ok::<_, std::io::Error>(())
+        };
+
+        let (client, server) = futures_util::future::join(client, server).await;
+        server?;
+        match client? {
+            Err(err @ Error::Driver(DriverError::PreparedStmtLimitReached { prepared: 1 })) => {
+                assert!(!err.is_fatal());
+                Ok(())
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn should_resume_interrupted_drop_result() -> super::Result<()> {
+        use crate::mock::MockServer;
+        use std::time::Duration;
+        use tokio::{sync::oneshot, time::timeout};
+
+        const TIMEOUT: Duration = Duration::from_millis(50);
+
+        let (mut server, conn) = MockServer::connect(OptsBuilder::default());
+        let (interrupted_tx, interrupted_rx) = oneshot::channel::<()>();
+
+        let client = async move {
+            let mut conn: Conn = conn.await?;
+            let result = conn.query_iter("SELECT a").await?;
+            // the rest of the result set is not yet sent
+            assert!(timeout(TIMEOUT, result.drop_result()).await.is_err());
+            assert_eq!(
+                conn.is_healthy(),
+                Health::Busy {
+                    pending_result: true,
+                    in_transaction: false
+                }
+            );
+            interrupted_tx.send(()).unwrap();
+
+            let result: Option<String> = conn.query_first("SELECT b").await?;
+            conn.disconnect().await?;
+            Ok::<_, Error>(result)
+        };
+
+        let server = async move {
+            server.accept().await?;
+            assert_eq!(server.read_command().await?, b"\x03SELECT a");
+            server.write_columns(&["a"]).await?;
+            server.write_text_row(&[Some("1")]).await?;
+            interrupted_rx.await.unwrap();
+            server.write_text_row(&[Some("2")]).await?;
+            server.write_result_set_end().await?;
+
+            assert_eq!(server.read_command().await?, b"\x03SELECT b");
+            server
+                .write_text_result_set(&["b"], &[vec![Some("b")]])
+                .await?;
+            assert_eq!(server.read_command().await?, b"\x01");
+            Ok::<_, std::io::Error>(())
+        };
+
+        let (client, server) = futures_util::future::join(client, server).await;
+        server?;
+        assert_eq!(client?, Some("b".into()));
+
+        // interrupted reading of column definitions can't be resumed
+        let (mut server, conn) = MockServer::connect(OptsBuilder::default());
+
+        let client = async move {
+            let mut conn: Conn = conn.await?;
+            assert!(timeout(TIMEOUT, conn.query_iter("SELECT a, b"))
+                .await
+                .is_err());
+            assert_eq!(conn.is_healthy(), Health::Dead);
+            let result = conn.query_drop("SELECT c").await;
+            Ok::<_, Error>(result)
+        };
+
+        let server = async move {
+            server.accept().await?;
+            assert_eq!(server.read_command().await?, b"\x03SELECT a, b");
+            // column count without column definitions
+            server.write_packet(&[0x02]).await?;
+            // connection is closed by the client
+            let err = server.read_command().await.unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+            Ok::<_, std::io::Error>(())
+        };
+
+        let (client, server) = futures_util::future::join(client, server).await;
+        server?;
+        match client? {
+            Err(Error::Driver(DriverError::ConnectionOutOfSync)) => Ok(()),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn should_send_proxy_protocol_header() -> super::Result<()> {
+        use crate::ProxyProtocolVersion;
+        use tokio::{io::AsyncReadExt, net::TcpListener};
+
+        /// Reads the header and returns it along with the expected one.
+        async fn accept_header<F>(
+            mut listener: TcpListener,
+            expected: F,
+        ) -> std::io::Result<(Vec<u8>, Vec<u8>)>
+        where
+            F: FnOnce(std::net::SocketAddr, std::net::SocketAddr) -> Vec<u8>,
+        {
+            let (mut stream, peer_addr) = listener.accept().await?;
+            let expected = expected(peer_addr, stream.local_addr()?);
+            let mut header = vec![0_u8; expected.len()];
+            stream.read_exact(&mut header).await?;
+            Ok((header, expected))
+        }
+
+        // v1 with inferred addresses
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let opts = OptsBuilder::default()
+            .ip_or_hostname("127.0.0.1")
+            .tcp_port(listener.local_addr()?.port())
+            .proxy_protocol(ProxyProtocolVersion::V1);
+        let server = accept_header(listener, |source, destination| {
+            format!(
+                "PROXY TCP4 127.0.0.1 127.0.0.1 {} {}\r\n",
+                source.port(),
+                destination.port()
+            )
+            .into_bytes()
+        });
+        let (client, server) = futures_util::future::join(Conn::new(opts), server).await;
+        assert!(client.is_err());
+        let (header, expected) = server?;
+        assert_eq!(header, expected);
+
+        // v2 with explicit addresses
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let opts = OptsBuilder::default()
+            .ip_or_hostname("127.0.0.1")
+            .tcp_port(listener.local_addr()?.port())
+            .proxy_protocol(ProxyProtocolVersion::V2)
+            .proxy_protocol_addrs((
+                "192.168.0.1:56324".parse().unwrap(),
+                "10.0.0.2:3306".parse().unwrap(),
+            ));
+        let server = accept_header(listener, |_, _| {
+            let mut expected = b"\r\n\r\n\0\r\nQUIT\n".to_vec();
+            expected.extend_from_slice(&[0x21, 0x11, 0x00, 0x0c]);
+            expected.extend_from_slice(&[192, 168, 0, 1, 10, 0, 0, 2, 0xdc, 0x04, 0x0c, 0xea]);
+            expected
+        });
+        let (client, server) = futures_util::future::join(Conn::new(opts), server).await;
+        assert!(client.is_err());
+        let (header, expected) = server?;
+        assert_eq!(header, expected);
+
+        Ok(())
+    }
+
+    fn connect_phase<T: std::fmt::Debug>(result: super::Result<T>) -> crate::ConnectPhase {
+        match result {
+            Err(Error::Connect(err)) => err.phase,
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn should_report_resolve_and_tcp_phase() -> super::Result<()> {
+        use crate::ConnectPhase;
+        use tokio::net::TcpListener;
+
+        let opts = OptsBuilder::default().ip_or_hostname("nonexistent.invalid");
+        assert_eq!(connect_phase(Conn::new(opts).await), ConnectPhase::Resolve);
+
+        let port = TcpListener::bind("127.0.0.1:0").await?.local_addr()?.port();
+        let opts = OptsBuilder::default()
+            .ip_or_hostname("127.0.0.1")
+            .tcp_port(port);
+        assert_eq!(connect_phase(Conn::new(opts).await), ConnectPhase::Tcp);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_report_tls_phase() -> super::Result<()> {
+        use crate::{consts::CapabilityFlags, ConnectPhase, SslOpts};
+        use tokio::{
+            io::{AsyncReadExt, AsyncWriteExt},
+            net::TcpListener,
+        };
+
+        let mut listener = TcpListener::bind("127.0.0.1:0").await?;
+        let opts = OptsBuilder::default()
+            .ip_or_hostname("127.0.0.1")
+            .tcp_port(listener.local_addr()?.port())
+            .ssl_opts(SslOpts::default());
+
+        let server = async move {
+            let (mut stream, _) = listener.accept().await?;
+            let capabilities = (CapabilityFlags::CLIENT_PROTOCOL_41
+                | CapabilityFlags::CLIENT_SECURE_CONNECTION
+                | CapabilityFlags::CLIENT_PLUGIN_AUTH
+                | CapabilityFlags::CLIENT_SSL)
+                .bits()
+                .to_le_bytes();
+            let mut handshake = vec![0x0a];
+            handshake.extend_from_slice(b"8.0.0\0");
+            handshake.extend_from_slice(&[1, 0, 0, 0]);
+            handshake.extend_from_slice(&[0x2a; 8]);
+            handshake.push(0x00);
+            handshake.extend_from_slice(&capabilities[..2]);
+            handshake.extend_from_slice(&[33, 0x02, 0x00]);
+            handshake.extend_from_slice(&capabilities[2..]);
+            handshake.push(21);
+            handshake.extend_from_slice(&[0; 10]);
+            handshake.extend_from_slice(&[0x2a; 12]);
+            handshake.push(0x00);
+            handshake.extend_from_slice(b"mysql_native_password\0");
+
+            let mut packet = (handshake.len() as u32).to_le_bytes();
+            packet[3] = 0;
+            stream.write_all(&packet).await?;
+            stream.write_all(&handshake).await?;
+
+            // read the SSL request and respond with garbage instead of TLS handshake
+            let mut ssl_request = [0_u8; 4 + 32];
+            stream.read_exact(&mut ssl_request).await?;
+            stream.write_all(b"garbage").await?;
+            Ok::<_, std::io::Error>(())
+        };
+
+        let (client, server) = futures_util::future::join(Conn::new(opts), server).await;
+        server?;
+        assert_eq!(connect_phase(client), ConnectPhase::Tls);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn should_report_auth_phase() -> super::Result<()> {
+        use crate::{mock::MockServer, ConnectPhase};
+
+        let (mut server, conn) = MockServer::connect(OptsBuilder::default());
+        let server = async move {
+            server
+                .write_handshake("mysql_native_password", &[0x2a; 20])
+                .await?;
+            server.read_handshake_response().await?;
+            server
+                .write_err(1045, "28000", "Access denied for user")
+                .await?;
+            Ok::<_, std::io::Error>(())
+        };
+
+        let (client, server) = futures_util::future::join(conn, server).await;
+        server?;
+        match client {
+            Err(Error::Connect(err)) => {
+                assert_eq!(err.phase, ConnectPhase::Auth);
+                assert!(matches!(*err.source, Error::Server(ref err) if err.code == 1045));
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_interpret_upsert_outcome() -> super::Result<()> {
+        use crate::UpsertOutcome;
+
+        let mut conn = Conn::new(get_opts()).await?;
+        assert_eq!(conn.upsert_outcome(), None);
+
+        conn.query_drop("CREATE TEMPORARY TABLE upsert (id INT PRIMARY KEY, val INT)")
+            .await?;
+
+        const UPSERT: &str =
+            "INSERT INTO upsert (id, val) VALUES (?, ?) ON DUPLICATE KEY UPDATE val = VALUES(val)";
+
+        conn.exec_drop(UPSERT, (1, 1)).await?;
+        assert_eq!(conn.upsert_outcome(), Some(UpsertOutcome::Inserted));
+
+        conn.exec_drop(UPSERT, (1, 2)).await?;
+        assert_eq!(conn.upsert_outcome(), Some(UpsertOutcome::Updated));
+
+        conn.exec_drop(UPSERT, (1, 2)).await?;
+        assert_eq!(conn.upsert_outcome(), Some(UpsertOutcome::Unchanged));
+
+        conn.query_drop("INSERT INTO upsert (id, val) VALUES (2, 2), (3, 3)")
+            .await?;
+        assert_eq!(conn.upsert_outcome(), None);
+
+        conn.disconnect().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_count_bytes() -> super::Result<()> {
+        let mut conn = Conn::new(get_opts()).await?;
+        assert!(conn.bytes_sent() > 0);
+        assert!(conn.bytes_received() > 0);
+
+        let sent = conn.bytes_sent();
+        let received = conn.bytes_received();
+        let query = "SELECT REPEAT('A', 10000)";
+        let result: Option<String> = conn.query_first(query).await?;
+        assert_eq!(result.unwrap().len(), 10000);
+
+        let sent = conn.bytes_sent() - sent;
+        let received = conn.bytes_received() - received;
+        assert!(sent >= query.len() as u64);
+        if conn.opts().compression().is_some() {
+            assert!(received < 10000);
+        } else {
+            assert!(received >= 10000);
+        }
+
+        conn.disconnect().await?;
+        Ok(())
+    }
+
+    #[test]
+    fn should_allow_auth_switch_to_the_same_plugin_with_new_nonce() {
+        use mysql_common::packets::parse_auth_switch_request;
+
+        let mut inner = super::ConnInner::empty(get_opts().into());
+
+        let first = parse_auth_switch_request(b"\xfecaching_sha2_password\x00nonce1\x00").unwrap();
+        assert!(inner.auth_switch_allowed(&first));
+        inner.auth_switched = true;
+        inner.nonce = first.plugin_data().into();
+        inner.auth_plugin = first.auth_plugin().clone().into_owned();
+
+        // re-scramble
+        let second = parse_auth_switch_request(b"\xfecaching_sha2_password\x00nonce2\x00").unwrap();
+        assert!(inner.auth_switch_allowed(&second));
+
+        // same nonce
+        assert!(!inner.auth_switch_allowed(&first));
+
+        // different plugin
+        let third = parse_auth_switch_request(b"\xfemysql_native_password\x00nonce3\x00").unwrap();
+        assert!(!inner.auth_switch_allowed(&third));
+    }
+
+    #[test]
+    fn should_not_panic_if_dropped_without_tokio_runtime() {
+        let fut = Conn::new(get_opts());
+        let mut runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            fut.await.unwrap();
+        });
+        // connection will drop here
+    }
+
+    #[tokio::test]
+    async fn should_execute_init_queries_on_new_connection() -> super::Result<()> {
+        let opts = OptsBuilder::from_opts(get_opts()).init(vec!["SET @a = 42", "SET @b = 'foo'"]);
+        let mut conn = Conn::new(opts).await?;
+        let result: Vec<(u8, String)> = conn.query("SELECT @a, @b").await?;
+        conn.disconnect().await?;
+        assert_eq!(result, vec![(42, "foo".into())]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_collect_init_warnings() -> super::Result<()> {
+        const WARNS: &str = "SET @a = CAST('foo' AS SIGNED)";
+
+        let opts = OptsBuilder::from_opts(get_opts()).init(vec![WARNS, "SET @b = 1"]);
+        let conn = Conn::new(opts.clone()).await?;
+        let init_warnings = conn.init_warnings();
+        assert_eq!(init_warnings.len(), 1);
+        assert_eq!(init_warnings[0].0, WARNS);
+        assert_eq!(init_warnings[0].1.len(), 1);
+        // ER_TRUNCATED_WRONG_VALUE
+        assert_eq!(init_warnings[0].1[0].code, 1292);
+        conn.disconnect().await?;
+
+        let result = Conn::new(opts.strict_init(true)).await;
+        match result {
+            Err(Error::Connect(err)) => {
+                assert_eq!(err.phase, crate::ConnectPhase::Init);
+                match *err.source {
+                    Error::Driver(DriverError::InitCommandWarnings { query, warnings }) => {
+                        assert_eq!(query, WARNS);
+                        assert_eq!(warnings[0].code, 1292);
+                    }
+                    other => panic!("unexpected error: {:?}", other),
+                }
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let opts = OptsBuilder::from_opts(get_opts())
+            .init(vec!["SET @b = 1"])
+            .strict_init(true);
+        let conn = Conn::new(opts).await?;
+        assert!(conn.init_warnings().is_empty());
+        conn.disconnect().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_capture_warnings() -> super::Result<()> {
+        let opts = OptsBuilder::from_opts(get_opts()).capture_warnings(true);
+        let mut conn = Conn::new(opts).await?;
+
+        let mut result = conn.query_iter("SELECT CAST('foo' AS SIGNED)").await?;
+        let rows: Vec<i64> = result.collect().await?;
+        assert_eq!(rows, vec![0]);
+        assert_eq!(result.warnings(), 1);
+        let warnings = result.captured_warnings().to_vec();
+        assert_eq!(warnings.len(), 1);
+        // ER_TRUNCATED_WRONG_VALUE
+        assert_eq!(warnings[0].code, 1292);
+        assert_eq!(warnings[0].level, "Warning");
+        result.drop_result().await?;
+
+        // statement without a result set
+        conn.query_drop("SET @a = CAST('bar' AS SIGNED)").await?;
+        assert_eq!(conn.captured_warnings().len(), 1);
+        assert_eq!(conn.get_warnings(), 1);
+
+        // warnings of the previous query are cleared
+        conn.exec_drop("SELECT ?", (1,)).await?;
+        assert!(conn.captured_warnings().is_empty());
+
+        conn.disconnect().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_read_settings_in_one_round_trip() -> super::Result<()> {
+        let mut conn = Conn::new(get_opts()).await?;
+        // single settings query performed on connect plus this very query
+        let (_, questions): (String, u32) = conn
+            .query_first("SHOW SESSION STATUS LIKE 'Questions'")
+            .await?
+            .unwrap();
+        assert!(questions <= 2);
+        assert!(conn.inner.wait_timeout > std::time::Duration::from_secs(0));
+        conn.disconnect().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_toggle_tcp_nodelay() -> super::Result<()> {
+        let mut conn = Conn::new(get_opts()).await?;
+        for nodelay in vec![false, false, true, true] {
+            conn.set_tcp_nodelay(nodelay)?;
+            let result: Option<u8> = conn.query_first("SELECT 1").await?;
+            assert_eq!(result, Some(1));
+        }
+        conn.disconnect().await?;
+        Ok(())
+    }
+
+    #[test]
+    fn should_prepend_caller_location() {
+        use std::panic::Location;
+
+        use super::with_caller_location;
+
+        let location = Location::caller();
+        assert_eq!(with_caller_location("SELECT 1", None), "SELECT 1");
+        assert_eq!(
+            with_caller_location("SELECT 1", Some(location)),
+            format!("/* {}:{} */ SELECT 1", location.file(), location.line())
+        );
+    }
+
+    #[test]
+    fn should_parse_bulk_dml_info() {
+        use super::BulkDmlInfo;
+
+        let info = |records, duplicates, warnings| BulkDmlInfo {
+            records,
+            duplicates,
+            warnings,
+        };
+        assert_eq!(
+            BulkDmlInfo::parse("Records: 3  Duplicates: 1  Warnings: 2"),
+            Some(info(3, 1, 2))
+        );
+        assert_eq!(
+            BulkDmlInfo::parse("Records: 5  Deleted: 1  Skipped: 2  Warnings: 0"),
+            Some(info(5, 3, 0))
+        );
+        assert_eq!(
+            BulkDmlInfo::parse("Rows matched: 4  Changed: 2  Warnings: 0"),
+            Some(info(4, 0, 0))
+        );
+        assert_eq!(BulkDmlInfo::parse(""), None);
+        assert_eq!(
+            BulkDmlInfo::parse("Records: 3  Duplicates: x  Warnings: 0"),
+            None
+        );
+        assert_eq!(BulkDmlInfo::parse("Records: 3  Warnings: 0"), None);
+    }
+
+    #[tokio::test]
+    async fn should_parse_bulk_info_of_multi_row_insert() -> super::Result<()> {
+        let mut conn = Conn::new(get_opts()).await?;
+        conn.query_drop("CREATE TEMPORARY TABLE bulk (id INT PRIMARY KEY)")
+            .await?;
+
+        conn.query_drop("INSERT INTO bulk VALUES (1)").await?;
+        assert_eq!(conn.bulk_info(), None);
+
+        conn.query_drop("INSERT IGNORE INTO bulk VALUES (1), (2), (3)")
+            .await?;
+        let info = conn.bulk_info().unwrap();
+        assert_eq!((info.records, info.duplicates), (3, 1));
+        assert_eq!(info.warnings, u64::from(conn.get_warnings()));
+
+        conn.query_drop("UPDATE bulk SET id = id + 10 WHERE id > 1")
+            .await?;
+        let info = conn.bulk_info().unwrap();
+        assert_eq!((info.records, info.duplicates, info.warnings), (2, 0, 0));
+
+        conn.disconnect().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_measure_ping_round_trip() -> super::Result<()> {
+        use std::time::Duration;
+
+        let mut conn = Conn::new(get_opts()).await?;
+        for _ in 0..3 {
+            let elapsed = conn.ping_timed().await?;
+            assert!(elapsed > Duration::from_secs(0));
+            assert!(elapsed < Duration::from_secs(1));
+        }
+        conn.disconnect().await?;
+        Ok(())
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn should_not_time_cleanup_of_ping() -> super::Result<()> {
+        use crate::{consts::Command, mock::MockServer};
+        use std::time::Duration;
+
+        const DELAY: Duration = Duration::from_millis(300);
+
+        let (mut server, conn) = MockServer::connect(OptsBuilder::default());
+        let client = async move {
+            let mut conn: Conn = conn.await?;
+            // rows of this result are drained before the ping
+            drop(conn.query_iter("SELECT 1").await?);
+            let elapsed = conn.ping_timed().await?;
+            conn.disconnect().await?;
+            Ok::<_, Error>(elapsed)
+        };
+        let server = async {
+            server.accept().await?;
+            server.read_command().await?;
+            server.write_columns(&["1"]).await?;
+            tokio::time::delay_for(DELAY).await;
+            server.write_text_row(&[Some("1")]).await?;
+            server.write_result_set_end().await?;
+            assert_eq!(server.read_command().await?, [Command::COM_PING as u8]);
+            server.write_ok().await?;
+            assert_eq!(server.read_command().await?, [Command::COM_QUIT as u8]);
+            Ok::<_, std::io::Error>(())
+        };
+
+        let (client, server) = futures_util::future::join(client, server).await;
+        server?;
+        assert!(client? < DELAY);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_track_current_schema() -> super::Result<()> {
+        use crate::consts::CapabilityFlags;
+
+        let mut conn = Conn::new(get_opts().db_name(Some("mysql"))).await?;
+        assert_eq!(conn.current_schema(), Some("mysql"));
+
+        conn.select_db("information_schema").await?;
+        assert_eq!(conn.current_schema(), Some("information_schema"));
+
+        if conn
+            .capabilities()
+            .contains(CapabilityFlags::CLIENT_SESSION_TRACK)
+        {
+            // the change is reported by the server
+            conn.query_drop("USE mysql").await?;
+            assert_eq!(conn.current_schema(), Some("mysql"));
+        }
+
+        let db_name: Option<String> = conn.query_first("SELECT DATABASE()").await?;
+        assert_eq!(conn.current_schema(), db_name.as_deref());
+
+        conn.disconnect().await?;
+        Ok(())
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn should_parse_ok_packets_without_info_if_session_track_is_negotiated(
+    ) -> super::Result<()> {
+        use crate::{
+            consts::{CapabilityFlags, StatusFlags},
+            mock::{MockServer, DEFAULT_MOCK_CAPABILITIES},
+        };
+
+        let (server, conn) = MockServer::connect(OptsBuilder::default());
+        let mut server = server
+            .with_capabilities(DEFAULT_MOCK_CAPABILITIES | CapabilityFlags::CLIENT_SESSION_TRACK);
+        let client = async move {
+            let mut conn: Conn = conn.await?;
+            assert!(conn
+                .capabilities()
+                .contains(CapabilityFlags::CLIENT_SESSION_TRACK));
+            conn.query_drop("INSERT INTO foo VALUES (1); INSERT INTO foo VALUES (2), (3)")
+                .await?;
+            let result = (conn.affected_rows(), conn.last_insert_id());
+            conn.query_drop("DO 1").await?;
+            conn.disconnect().await?;
+            Ok::<_, Error>(result)
+        };
+        let server = async {
+            server.accept().await?;
+            server.read_command().await?;
+            // affected rows, last insert id, status flags, warnings and no info string
+            let more =
+                StatusFlags::SERVER_STATUS_AUTOCOMMIT | StatusFlags::SERVER_MORE_RESULTS_EXISTS;
+            let mut payload = vec![0x00, 0x01, 0x01];
+            payload.extend_from_slice(&more.bits().to_le_bytes());
+            payload.extend_from_slice(&[0x00, 0x00]);
+            server.write_packet(&payload).await?;
+            let mut payload = vec![0x00, 0x02, 0x02];
+            payload.extend_from_slice(&StatusFlags::SERVER_STATUS_AUTOCOMMIT.bits().to_le_bytes());
+            payload.extend_from_slice(&[0x00, 0x00]);
+            server.write_packet(&payload).await?;
+            assert_eq!(server.read_command().await?, b"\x03DO 1");
+            server.write_ok().await?;
+            server.read_command().await?;
+            Ok::<_, std::io::Error>(())
+        };
+
+        let (client, server) = futures_util::future::join(client, server).await;
+        server?;
+        assert_eq!(client?, (2, Some(2)));
+        Ok(())
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn should_track_schema_change_after_other_session_state_changes() -> super::Result<()> {
+        use crate::{
+            consts::{CapabilityFlags, StatusFlags},
+            mock::{MockServer, DEFAULT_MOCK_CAPABILITIES},
+        };
+
+        let (server, conn) = MockServer::connect(OptsBuilder::default());
+        let mut server = server
+            .with_capabilities(DEFAULT_MOCK_CAPABILITIES | CapabilityFlags::CLIENT_SESSION_TRACK);
+        let client = async move {
+            let mut conn: Conn = conn.await?;
+            conn.query_drop("SET autocommit = 1; USE foo").await?;
+            let schema = conn.current_schema().map(String::from);
+            conn.disconnect().await?;
+            Ok::<_, Error>(schema)
+        };
+        let server = async {
+            server.accept().await?;
+            server.read_command().await?;
+            let status =
+                StatusFlags::SERVER_STATUS_AUTOCOMMIT | StatusFlags::SERVER_SESSION_STATE_CHANGED;
+            // system variable change goes first, then the schema change
+            let block = b"\x00\x0e\x0aautocommit\x02ON\x01\x04\x03foo";
+            let mut payload = vec![0x00, 0x00, 0x00];
+            payload.extend_from_slice(&status.bits().to_le_bytes());
+            payload.extend_from_slice(&[0x00, 0x00, 0x00, block.len() as u8]);
+            payload.extend_from_slice(block);
+            server.write_packet(&payload).await?;
+            server.read_command().await?;
+            Ok::<_, std::io::Error>(())
+        };
+
+        let (client, server) = futures_util::future::join(client, server).await;
+        server?;
+        assert_eq!(client?.as_deref(), Some("foo"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_not_roll_back_deadlock_victim_twice() -> super::Result<()> {
+        use super::TxStatus;
+        use crate::TxOpts;
+
+        // a deadlock requires a table shared by two sessions, so it can't be temporary
+        let table = format!("mysql_async_deadlock_test_{}", std::process::id());
+        let update =
+            |val: u8, id: u8| format!("UPDATE {} SET val = {} WHERE id = {}", table, val, id);
+        let is_deadlock = |result: &super::Result<()>| match result {
+            Err(Error::Server(err)) => err.code == 1213,
+            _ => false,
+        };
+
+        let mut conn1 = Conn::new(get_opts()).await?;
+        let mut conn2 = Conn::new(get_opts()).await?;
+        conn1
+            .query_drop(format!(
+                "CREATE TABLE {} (id INT PRIMARY KEY, val INT) ENGINE=InnoDB",
+                table
+            ))
+            .await?;
+        conn1
+            .query_drop(format!("INSERT INTO {} VALUES (1, 0), (2, 0)", table))
+            .await?;
+
+        let mut tx1 = conn1.start_transaction(TxOpts::default()).await?;
+        let mut tx2 = conn2.start_transaction(TxOpts::default()).await?;
+        tx1.query_drop(update(1, 1)).await?;
+        tx2.query_drop(update(2, 2)).await?;
+
+        let (res1, res2) =
+            futures_util::future::join(tx1.query_drop(update(1, 2)), tx2.query_drop(update(2, 1)))
+                .await;
+        let deadlock = is_deadlock(&res1) != is_deadlock(&res2);
+        let (victim, survivor) = if is_deadlock(&res1) {
+            (tx1, tx2)
+        } else {
+            (tx2, tx1)
+        };
+        let survivor_commit = survivor.commit().await;
+        let victim_commit = victim.commit().await;
+
+        let mut states = Vec::new();
+        for conn in [&mut conn1, &mut conn2].iter_mut() {
+            let tx_status = conn.get_tx_status();
+            let one: super::Result<Option<u8>> = conn.query_first("SELECT 1").await;
+            states.push((tx_status, one));
+        }
+
+        // the table is dropped before the assertions
+        conn1.query_drop(format!("DROP TABLE {}", table)).await?;
+        conn1.disconnect().await?;
+        conn2.disconnect().await?;
+
+        assert!(deadlock, "expected a deadlock, got {:?}", (res1, res2));
+        survivor_commit?;
+        match victim_commit {
+            Err(Error::Driver(DriverError::TransactionRolledBack)) => (),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        for (tx_status, one) in states {
+            assert_eq!(tx_status, TxStatus::None);
+            assert_eq!(one?, Some(1));
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "mock")]
+    #[tokio::test]
+    async fn should_read_rollback_on_timeout_with_other_settings() -> super::Result<()> {
+        use crate::mock::MockServer;
+        use std::time::Duration;
+
+        const QUERY: &[u8] =
+            b"\x03SELECT @@max_allowed_packet, @@wait_timeout, @@innodb_rollback_on_timeout";
+
+        // the variable is unknown, if the answer is `None`
+        for (answer, expected) in &[(Some("1"), true), (Some("OFF"), false), (None, false)] {
+            let opts = OptsBuilder::default().prefer_socket(false);
+            let (mut server, conn) = MockServer::connect(opts);
+            let server = async {
+                server
+                    .write_handshake("mysql_native_password", &[0x2a; 20])
+                    .await?;
+                server.read_handshake_response().await?;
+                server.write_ok().await?;
+                assert_eq!(server.read_command().await?, QUERY);
+                match answer {
+                    Some(value) => {
+                        let columns = [
+                            "@@max_allowed_packet",
+                            "@@wait_timeout",
+                            "@@innodb_rollback_on_timeout",
+                        ];
+                        let row = vec![Some("4194304"), Some("28800"), Some(*value)];
+                        server.write_text_result_set(&columns, &[row]).await?;
+                    }
+                    None => {
+                        server
+                            .write_err(
+                                1193,
+                                "HY000",
+                                "Unknown system variable 'innodb_rollback_on_timeout'",
+                            )
+                            .await?;
+                        // the query is repeated without the unknown variable
+                        assert_eq!(
+                            server.read_command().await?,
+                            b"\x03SELECT @@max_allowed_packet, @@wait_timeout"
+                        );
+                        let columns = ["@@max_allowed_packet", "@@wait_timeout"];
+                        let row = vec![Some("4194304"), Some("28800")];
+                        server.write_text_result_set(&columns, &[row]).await?;
+                    }
+                }
+                Ok::<_, std::io::Error>(())
+            };
+            let (conn, server) = futures_util::future::join(conn, server).await;
+            server?;
+            let conn = conn?;
+            assert_eq!(conn.inner.rollback_on_timeout, *expected);
+            assert_eq!(conn.inner.wait_timeout, Duration::from_secs(28800));
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_restore_default_database_on_reset() -> super::Result<()> {
+        let mut conn = Conn::new(get_opts().db_name(Some("mysql"))).await?;
+        conn.select_db("information_schema").await?;
+        let db_name: Option<String> = conn.query_first("SELECT DATABASE()").await?;
+        assert_eq!(db_name.as_deref(), Some("information_schema"));
+
+        conn.reset().await?;
+        let db_name: Option<String> = conn.query_first("SELECT DATABASE()").await?;
+        assert_eq!(db_name.as_deref(), Some("mysql"));
+
+        conn.query_drop("USE information_schema").await?;
+        conn.reset().await?;
+        let db_name: Option<String> = conn.query_first("SELECT DATABASE()").await?;
+        assert_eq!(db_name.as_deref(), Some("mysql"));
+
+        conn.disconnect().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_reset_the_connection() -> super::Result<()> {
+        let mut conn = Conn::new(get_opts()).await?;
+        conn.exec_drop("SELECT ?", (1_u8,)).await?;
+        conn.reset().await?;
+        conn.exec_drop("SELECT ?", (1_u8,)).await?;
+        conn.disconnect().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_apply_default_isolation_level() -> super::Result<()> {
+        const QUERY: &str = "SELECT @@transaction_isolation";
+
+        let opts = OptsBuilder::from_opts(get_opts())
+            .default_isolation_level(crate::IsolationLevel::ReadCommitted);
+        let mut conn = Conn::new(opts).await?;
+        let level: Option<String> = conn.query_first(QUERY).await?;
+        assert_eq!(level.as_deref(), Some("READ-COMMITTED"));
+
+        conn.query_drop("SET SESSION TRANSACTION ISOLATION LEVEL SERIALIZABLE")
+            .await?;
+        conn.reset().await?;
+        let level: Option<String> = conn.query_first(QUERY).await?;
+        assert_eq!(level.as_deref(), Some("READ-COMMITTED"));
+
+        conn.disconnect().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_detect_sql_mode_drift() -> super::Result<()> {
+        let opts =
+            OptsBuilder::from_opts(get_opts()).sql_mode(Some("STRICT_ALL_TABLES,NO_ZERO_DATE"));
+        let mut conn = Conn::new(opts).await?;
+        conn.assert_sql_mode().await?;
+
+        conn.query_drop("SET SESSION sql_mode = 'ANSI_QUOTES'")
+            .await?;
+        match conn.assert_sql_mode().await {
+            Err(Error::Driver(DriverError::SqlModeDrift { expected, actual })) => {
+                assert!(expected.contains("STRICT_ALL_TABLES"));
+                assert_eq!(actual, "ANSI_QUOTES");
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        conn.reset().await?;
+        conn.assert_sql_mode().await?;
+
+        conn.disconnect().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_apply_default_select_limit() -> super::Result<()> {
+        let opts = OptsBuilder::from_opts(get_opts()).default_select_limit(10);
+        let mut conn = Conn::new(opts).await?;
+        conn.query_drop("CREATE TEMPORARY TABLE limited (id INT)")
+            .await?;
+        conn.exec_batch("INSERT INTO limited VALUES (?)", (0..100).map(|x| (x,)))
+            .await?;
+
+        let ids: Vec<u32> = conn.query("SELECT id FROM limited").await?;
+        assert_eq!(ids.len(), 10);
+        // explicit `LIMIT` takes precedence
+        let ids: Vec<u32> = conn.query("SELECT id FROM limited LIMIT 50").await?;
+        assert_eq!(ids.len(), 50);
+
+        let ids: Vec<u32> = conn
+            .query_with_limit("SELECT id FROM limited", None)
+            .await?;
+        assert_eq!(ids.len(), 100);
+        let ids: Vec<u32> = conn
+            .query_with_limit("SELECT id FROM limited", Some(20))
+            .await?;
+        assert_eq!(ids.len(), 20);
+        let ids: Vec<u32> = conn.query("SELECT id FROM limited").await?;
+        assert_eq!(ids.len(), 10);
+
+        conn.reset().await?;
+        let limit: Option<u64> = conn.query_first("SELECT @@SQL_SELECT_LIMIT").await?;
+        assert_eq!(limit, Some(10));
+
+        conn.disconnect().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_close_dropped_owned_statement() -> super::Result<()> {
+        const QUERY: &str = "SHOW SESSION STATUS LIKE 'Com_stmt_close'";
+
+        let mut conn = Conn::new(get_opts()).await?;
+        let (_, closed_before): (String, usize) = conn.query_first(QUERY).await?.unwrap();
 
-            result?.disconnect().await?;
-        }
+        let stmt = conn.prep_owned("SELECT ?").await?;
+        assert_eq!(conn.stmt_cache_ref().len(), 0);
+        let result: Vec<u8> = conn.exec(&stmt, (42,)).await?;
+        assert_eq!(result, vec![42]);
+        drop(stmt);
 
-        if crate::test_misc::test_compression() {
-            assert!(format!("{:?}", conn).contains("Compression"));
-        }
+        let (_, closed_after): (String, usize) = conn.query_first(QUERY).await?.unwrap();
+        assert_eq!(closed_after, closed_before + 1);
 
-        if crate::test_misc::test_ssl() {
-            assert!(format!("{:?}", conn).contains("Tls"));
+        conn.disconnect().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_report_statement_cache_hits() -> super::Result<()> {
+        let mut conn = Conn::new(get_opts()).await?;
+
+        for query in &["SELECT ?", "SELECT :foo"] {
+            let first = conn.prep_detailed(*query).await?;
+            assert!(!first.cache_hit);
+            let second = conn.prep_detailed(*query).await?;
+            assert!(second.cache_hit);
+            assert_eq!(first.statement, second.statement);
         }
 
         conn.disconnect().await?;
         Ok(())
     }
 
-    #[test]
-    fn should_not_panic_if_dropped_without_tokio_runtime() {
-        let fut = Conn::new(get_opts());
-        let mut runtime = tokio::runtime::Runtime::new().unwrap();
-        runtime.block_on(async {
-            fut.await.unwrap();
+    #[tokio::test]
+    async fn should_time_out_if_server_does_not_send_handshake() -> super::Result<()> {
+        use std::time::{Duration, Instant};
+
+        // accepts connections, but never sends anything
+        let mut listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+        let server = tokio::spawn(async move {
+            let (socket, _) = listener.accept().await?;
+            tokio::time::delay_for(Duration::from_secs(5)).await;
+            drop(socket);
+            Ok::<_, std::io::Error>(())
         });
-        // connection will drop here
+
+        let opts = OptsBuilder::default()
+            .ip_or_hostname("127.0.0.1")
+            .tcp_port(port)
+            .handshake_timeout(Duration::from_millis(100));
+        let start = Instant::now();
+        match Conn::new(opts).await {
+            Err(crate::Error::Connect(err)) => {
+                assert_eq!(err.phase, crate::ConnectPhase::Handshake);
+                match *err.source {
+                    crate::Error::Driver(DriverError::HandshakeTimeout) => (),
+                    ref err => panic!("unexpected error: {}", err),
+                }
+            }
+            Err(err) => panic!("unexpected error: {}", err),
+            Ok(_) => panic!("connection should fail"),
+        }
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        drop(server);
+        Ok(())
     }
 
     #[tokio::test]
-    async fn should_execute_init_queries_on_new_connection() -> super::Result<()> {
-        let opts = OptsBuilder::from_opts(get_opts()).init(vec!["SET @a = 42", "SET @b = 'foo'"]);
+    async fn should_send_connect_attrs() -> super::Result<()> {
+        let opts = OptsBuilder::from_opts(get_opts())
+            .program_name("mysql_async_test")
+            .program_version("1.2.3");
         let mut conn = Conn::new(opts).await?;
-        let result: Vec<(u8, String)> = conn.query("SELECT @a, @b").await?;
+
+        let enabled: Option<bool> = conn.query_first("SELECT @@performance_schema").await?;
+        if enabled == Some(true) {
+            let attrs: Vec<(String, String)> = conn
+                .query(
+                    "SELECT ATTR_NAME, ATTR_VALUE FROM performance_schema.session_connect_attrs \
+                     WHERE PROCESSLIST_ID = CONNECTION_ID() ORDER BY ATTR_NAME",
+                )
+                .await?;
+            let value = |name: &str| {
+                attrs
+                    .iter()
+                    .find(|(attr_name, _)| attr_name == name)
+                    .map(|(_, value)| value.as_str())
+            };
+            assert_eq!(value("program_name"), Some("mysql_async_test"));
+            assert_eq!(value("program_version"), Some("1.2.3"));
+            assert_eq!(value("_client_name"), Some("mysql_async"));
+        }
+
         conn.disconnect().await?;
-        assert_eq!(result, vec![(42, "foo".into())]);
         Ok(())
     }
 
     #[tokio::test]
-    async fn should_reset_the_connection() -> super::Result<()> {
+    async fn should_suppress_result_set_metadata() -> super::Result<()> {
+        const QUERY: &str = "SELECT ? AS first_column, ? AS second_column, ? AS third_column";
+        const ITERATIONS: u64 = 10;
+
+        async fn bytes_per_execute(conn: &mut Conn) -> super::Result<u64> {
+            let stmt = conn.prep(QUERY).await?;
+            // switch `resultset_metadata`, if necessary
+            conn.exec_drop(&stmt, (1, "foo", 1.5)).await?;
+
+            let before = conn.bytes_received();
+            for i in 0..ITERATIONS {
+                let row: crate::Row = conn.exec_first(&stmt, (i, "foo", 1.5)).await?.unwrap();
+                assert_eq!(row.columns_ref()[1].name_str(), "second_column");
+                assert_eq!(row.get::<u64, _>(0), Some(i));
+                assert_eq!(row.get::<String, _>(1).as_deref(), Some("foo"));
+                assert_eq!(row.get::<f64, _>(2), Some(1.5));
+            }
+            Ok((conn.bytes_received() - before) / ITERATIONS)
+        }
+
+        let opts = OptsBuilder::from_opts(get_opts()).suppress_metadata(true);
+        let mut conn = Conn::new(opts).await?;
+        if conn.server_version() < (8, 0, 19) || !conn.optional_metadata() {
+            return conn.disconnect().await;
+        }
+        let suppressed = bytes_per_execute(&mut conn).await?;
+
+        // text protocol always receives metadata
+        let row: crate::Row = conn.query_first("SELECT 1 AS x").await?.unwrap();
+        assert_eq!(row.columns_ref()[0].name_str(), "x");
+        conn.disconnect().await?;
+
         let mut conn = Conn::new(get_opts()).await?;
-        conn.exec_drop("SELECT ?", (1_u8,)).await?;
-        conn.reset().await?;
-        conn.exec_drop("SELECT ?", (1_u8,)).await?;
+        let full = bytes_per_execute(&mut conn).await?;
         conn.disconnect().await?;
+
+        assert!(suppressed < full, "{} >= {}", suppressed, full);
         Ok(())
     }
 
@@ -959,6 +4391,30 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn should_bypass_stmt_cache_if_disabled() -> super::Result<()> {
+        let mut conn = Conn::new(get_opts()).await?;
+        conn.exec_drop("DO 1", ()).await?;
+        conn.exec_drop("DO 2", ()).await?;
+        assert_eq!(conn.stmt_cache_ref().len(), 2);
+
+        conn.set_stmt_cache_enabled(false);
+        assert!(!conn.stmt_cache_enabled());
+        conn.exec_drop("DO 1", ()).await?;
+        conn.exec_drop("DO 3", ()).await?;
+        conn.exec_drop("DO 4", ()).await?;
+        assert_eq!(conn.stmt_cache_ref().len(), 2);
+
+        conn.set_stmt_cache_enabled(true);
+        let stmt = conn.prep("DO 1").await?;
+        let cached = conn.get_cached_stmt("DO 1").unwrap();
+        assert_eq!(stmt.id(), cached.id());
+        assert_eq!(conn.stmt_cache_ref().len(), 2);
+
+        conn.disconnect().await?;
+        Ok(())
+    }
+
     #[tokio::test]
     async fn should_hold_stmt_cache_size_bound() -> super::Result<()> {
         let opts = OptsBuilder::from_opts(get_opts()).stmt_cache_size(3);
@@ -986,6 +4442,49 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn should_warm_statements() -> super::Result<()> {
+        async fn prepared(conn: &mut Conn) -> super::Result<usize> {
+            let (_, count): (String, usize) = conn
+                .query_first("SHOW SESSION STATUS LIKE 'Com_stmt_prepare'")
+                .await?
+                .unwrap();
+            Ok(count)
+        }
+
+        let queries = ["SELECT :a", "SELECT :a, :b, :a", "SELECT CONCAT(:foo, ?)"];
+
+        let mut conn = Conn::new(get_opts()).await?;
+        let err = conn.warm_statements(&queries).await.unwrap_err();
+        assert_eq!(err.query, queries[2]);
+        assert!(matches!(
+            err.source,
+            Error::Driver(DriverError::MixedParams)
+        ));
+
+        let queries = [queries[0], queries[1], "SELECT CONCAT(:foo, :bar)"];
+        conn.warm_statements(&queries).await?;
+        assert_eq!(conn.stmt_cache_ref().len(), 3);
+        let before = prepared(&mut conn).await?;
+
+        let row: Option<(u8,)> = conn.exec_first(queries[0], params! { "a" => 1 }).await?;
+        assert_eq!(row, Some((1,)));
+        let row: Option<(u8, u8, u8)> = conn
+            .exec_first(queries[1], params! { "a" => 1, "b" => 2 })
+            .await?;
+        assert_eq!(row, Some((1, 2, 1)));
+        let row: Option<(String,)> = conn
+            .exec_first(queries[2], params! { "foo" => "foo", "bar" => "bar" })
+            .await?;
+        assert_eq!(row, Some(("foobar".into(),)));
+        conn.warm_statements(&queries).await?;
+
+        assert_eq!(prepared(&mut conn).await?, before);
+
+        conn.disconnect().await?;
+        Ok(())
+    }
+
     #[tokio::test]
     async fn should_perform_queries() -> super::Result<()> {
         let long_string = ::std::iter::repeat('A')
@@ -1417,6 +4916,168 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn should_run_statements_via_transaction_conn() -> super::Result<()> {
+        async fn insert(conn: &mut Conn, id: u32) -> super::Result<()> {
+            conn.exec_drop("INSERT INTO tmp VALUES (?)", (id,)).await
+        }
+
+        let mut conn = Conn::new(get_opts()).await?;
+        conn.query_drop("CREATE TEMPORARY TABLE tmp (id INT) ENGINE=InnoDB")
+            .await?;
+
+        let mut transaction = conn.start_transaction(Default::default()).await?;
+        transaction.query_drop("INSERT INTO tmp VALUES (1)").await?;
+        insert(transaction.conn_mut(), 2).await?;
+        assert_eq!(
+            transaction.conn_mut().get_tx_status(),
+            super::TxStatus::InTransaction
+        );
+        let count: Option<u8> = transaction.query_first("SELECT COUNT(*) FROM tmp").await?;
+        assert_eq!(count, Some(2));
+        transaction.rollback().await?;
+
+        let count: Option<u8> = conn.query_first("SELECT COUNT(*) FROM tmp").await?;
+        assert_eq!(count, Some(0));
+
+        conn.disconnect().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a server with `gtid_mode=ON`"]
+    async fn should_wait_for_gtid() -> super::Result<()> {
+        use std::time::Duration;
+
+        let mut primary = Conn::new(get_opts()).await?;
+        let gtid_mode: Option<String> = primary.query_first("SELECT @@GLOBAL.gtid_mode").await?;
+        assert_eq!(gtid_mode.as_deref(), Some("ON"));
+
+        primary
+            .query_drop("CREATE TEMPORARY TABLE gtid_test (id INT)")
+            .await?;
+        primary
+            .query_drop("INSERT INTO gtid_test VALUES (1)")
+            .await?;
+        let gtid_set: String = primary
+            .query_first("SELECT @@GLOBAL.gtid_executed")
+            .await?
+            .unwrap();
+
+        // the test server may be a replica itself (see `REPLICA_DATABASE_URL`)
+        let replica_opts = std::env::var("REPLICA_DATABASE_URL")
+            .map(|url| crate::Opts::from_url(&url).unwrap())
+            .unwrap_or_else(|_| get_opts().into());
+        let mut replica = Conn::new(replica_opts).await?;
+        assert!(
+            replica
+                .wait_for_gtid(&gtid_set, Duration::from_secs(10))
+                .await?
+        );
+
+        // this transaction is never executed
+        let future = "3E11FA47-71CA-11E1-9E33-C80AA9429562:1000000000";
+        assert!(
+            !replica
+                .wait_for_gtid(future, Duration::from_millis(100))
+                .await?
+        );
+
+        replica.disconnect().await?;
+        primary.disconnect().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_isolate_statements_of_transactions() -> super::Result<()> {
+        const QUERY: &str = "SELECT ?";
+
+        let opts = OptsBuilder::from_opts(get_opts()).isolate_tx_statements(true);
+        let mut conn = Conn::new(opts).await?;
+        let outer = conn.prep_detailed(QUERY).await?;
+        assert!(!outer.cache_hit);
+
+        let mut transaction = conn.start_transaction(Default::default()).await?;
+        // the connection-wide cache isn't used within a transaction
+        let inner = transaction.conn_mut().prep_detailed(QUERY).await?;
+        assert!(!inner.cache_hit);
+        assert_ne!(inner.statement.id(), outer.statement.id());
+        let again = transaction.conn_mut().prep_detailed(QUERY).await?;
+        assert!(again.cache_hit);
+        assert_eq!(again.statement.id(), inner.statement.id());
+        transaction.rollback().await?;
+
+        // statement of the transaction isn't reused, but the connection-wide one is
+        let after = conn.prep_detailed(QUERY).await?;
+        assert!(after.cache_hit);
+        assert_eq!(after.statement.id(), outer.statement.id());
+
+        // statement of the transaction is closed
+        let mut transaction = conn.start_transaction(Default::default()).await?;
+        let next = transaction.conn_mut().prep_detailed(QUERY).await?;
+        assert!(!next.cache_hit);
+        transaction.commit().await?;
+        let result: super::Result<Vec<u8>> = conn.exec(&inner.statement, (1,)).await;
+        assert!(matches!(result, Err(Error::Server(ref err)) if err.code == 1243));
+
+        conn.disconnect().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_roll_back_nested_scope_to_savepoint() -> super::Result<()> {
+        use futures_util::FutureExt;
+
+        let mut conn = Conn::new(get_opts()).await?;
+        conn.query_drop("CREATE TEMPORARY TABLE tmp (id INT) ENGINE=InnoDB")
+            .await?;
+
+        let mut transaction = conn.start_transaction(Default::default()).await?;
+        transaction.query_drop("INSERT INTO tmp VALUES (1)").await?;
+
+        let result = transaction
+            .nested(|tx| {
+                Box::pin(async move {
+                    tx.query_drop("INSERT INTO tmp VALUES (2)").await?;
+                    // scopes could be nested further
+                    tx.nested(|tx| Box::pin(tx.query_drop("INSERT INTO tmp VALUES (3)")))
+                        .await?;
+                    tx.query_drop("INSERT INTO missing_table VALUES (4)").await
+                })
+            })
+            .await;
+        assert!(matches!(result, Err(Error::Server(_))));
+
+        let value = transaction
+            .nested(|tx| {
+                Box::pin(async move {
+                    tx.query_drop("INSERT INTO tmp VALUES (5)").await?;
+                    Ok(5_u8)
+                })
+            })
+            .await?;
+        assert_eq!(value, 5);
+
+        // a panicking scope is rolled back as well
+        let panicked = std::panic::AssertUnwindSafe(transaction.nested::<_, ()>(|tx| {
+            Box::pin(async move {
+                tx.query_drop("INSERT INTO tmp VALUES (6)").await?;
+                panic!("scope panicked")
+            })
+        }))
+        .catch_unwind()
+        .await;
+        assert!(panicked.is_err());
+
+        transaction.commit().await?;
+
+        let ids: Vec<u32> = conn.query("SELECT id FROM tmp ORDER BY id").await?;
+        assert_eq!(ids, vec![1, 5]);
+
+        conn.disconnect().await?;
+        Ok(())
+    }
+
     #[tokio::test]
     async fn should_handle_multiresult_set_with_error() -> super::Result<()> {
         const QUERY_FIRST: &str = "SELECT * FROM tmp; SELECT 1; SELECT 2;";
@@ -1446,6 +5107,76 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn should_decode_after_dropping_differently_shaped_results() -> super::Result<()> {
+        const QUERY: &str = "SELECT 1, 'foo' UNION ALL SELECT 2, 'bar';
+            SELECT 'baz', 3.5, NULL, 4;
+            SELECT REPEAT('A', 1000) UNION ALL SELECT REPEAT('B', 1000)";
+        let mut conn = Conn::new(get_opts()).await?;
+
+        // partially consumed result
+        let mut result = conn.query_iter(QUERY).await?;
+        let row: Option<(u8, String)> = result.next().await?.map(from_row);
+        assert_eq!(row, Some((1, "foo".into())));
+        result.drop_result().await?;
+
+        let result: Vec<(String, f64, Option<u8>, u8)> =
+            conn.query("SELECT 'baz', 3.5, NULL, 4").await?;
+        assert_eq!(result, vec![("baz".into(), 3.5, None, 4)]);
+
+        // fully dropped results
+        conn.query_drop(QUERY).await?;
+        conn.exec_drop("SELECT ?, REPEAT('C', ?)", (1, 1000))
+            .await?;
+
+        let result: Vec<(u8, String)> = conn.exec("SELECT ?, ?", (5, "qux")).await?;
+        assert_eq!(result, vec![(5, "qux".into())]);
+        let result: Vec<String> = conn.query("SELECT REPEAT('D', 3)").await?;
+        assert_eq!(result, vec!["DDD".to_string()]);
+
+        conn.disconnect().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_report_connection_health() -> super::Result<()> {
+        let mut conn = Conn::new(get_opts()).await?;
+        assert_eq!(conn.is_healthy(), Health::Idle);
+
+        // leaked result
+        let result = conn.query_iter("SELECT 1 UNION ALL SELECT 2").await?;
+        drop(result);
+        assert_eq!(
+            conn.is_healthy(),
+            Health::Busy {
+                pending_result: true,
+                in_transaction: false,
+            }
+        );
+        conn.drop_result().await?;
+        assert_eq!(conn.is_healthy(), Health::Idle);
+
+        // leaked transaction
+        let tx = conn.start_transaction(TxOpts::default()).await?;
+        drop(tx);
+        assert_eq!(
+            conn.is_healthy(),
+            Health::Busy {
+                pending_result: false,
+                in_transaction: true,
+            }
+        );
+        conn.rollback_transaction().await?;
+        assert_eq!(conn.is_healthy(), Health::Idle);
+
+        conn.inner.disconnected = true;
+        assert_eq!(conn.is_healthy(), Health::Dead);
+        conn.inner.disconnected = false;
+
+        conn.disconnect().await?;
+        Ok(())
+    }
+
     #[tokio::test]
     async fn should_handle_binary_multiresult_set_with_error() -> super::Result<()> {
         const PROC_DEF_FIRST: &str =
@@ -1631,6 +5362,76 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn should_report_local_infile_progress() -> super::Result<()> {
+        use std::{
+            fs::write,
+            sync::{Arc, Mutex},
+        };
+
+        use crate::InfileHandlerFuture;
+
+        struct ProgressHandler {
+            inner: WhiteListFsLocalInfileHandler,
+            progress: Arc<Mutex<Vec<u64>>>,
+        }
+
+        impl LocalInfileHandler for ProgressHandler {
+            fn handle(&self, file_name: &[u8]) -> InfileHandlerFuture {
+                self.inner.handle(file_name)
+            }
+
+            fn progress(&self, _file_name: &[u8], bytes_sent: u64) {
+                self.progress.lock().unwrap().push(bytes_sent);
+            }
+        }
+
+        let file_path = tempfile::Builder::new().tempfile_in("").unwrap();
+        let file_path = file_path.path();
+        let file_name = file_path.file_name().unwrap();
+
+        let line = format!("{}\n", "A".repeat(99));
+        let contents = line.repeat(100);
+        write(file_name, &contents)?;
+
+        let progress = Arc::new(Mutex::new(Vec::new()));
+        let opts = get_opts().local_infile_handler(Some(ProgressHandler {
+            inner: WhiteListFsLocalInfileHandler::new(&[file_name][..]),
+            progress: progress.clone(),
+        }));
+
+        let mut conn = Conn::new(opts).await.unwrap();
+        conn.query_drop("CREATE TEMPORARY TABLE tmp (a TEXT);")
+            .await
+            .unwrap();
+
+        match conn
+            .query_drop(format!(
+                r#"LOAD DATA LOCAL INFILE "{}" INTO TABLE tmp;"#,
+                file_name.to_str().unwrap(),
+            ))
+            .await
+        {
+            Ok(_) => (),
+            Err(super::Error::Server(ref err)) if err.code == 1148 || err.code == 3948 => {
+                // LOAD DATA LOCAL is not allowed or disabled
+                return Ok(());
+            }
+            e @ Err(_) => e.unwrap(),
+        };
+
+        let progress = progress.lock().unwrap().clone();
+        assert!(progress.len() > 1);
+        assert!(progress.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(progress.last(), Some(&(contents.len() as u64)));
+
+        let count: Option<usize> = conn.query_first("SELECT COUNT(*) FROM tmp").await?;
+        assert_eq!(count, Some(100));
+
+        conn.disconnect().await?;
+        Ok(())
+    }
+
     #[cfg(feature = "nightly")]
     mod bench {
         use crate::{conn::Conn, queryable::Queryable, test_misc::get_opts};
@@ -1675,6 +5476,57 @@ mod test {
             runtime.block_on(conn.disconnect()).unwrap();
         }
 
+        #[bench]
+        fn drop_many_rows(bencher: &mut test::Bencher) {
+            let mut runtime = tokio::runtime::Runtime::new().unwrap();
+            let mut conn = runtime.block_on(Conn::new(get_opts())).unwrap();
+
+            bencher.iter(|| {
+                runtime
+                    .block_on(conn.query_drop(
+                        "SELECT REPEAT('A', 100), 42, 4.2 FROM information_schema.COLLATIONS",
+                    ))
+                    .unwrap();
+            });
+
+            runtime.block_on(conn.disconnect()).unwrap();
+        }
+
+        #[bench]
+        fn drop_many_rows_reusing_buffers(bencher: &mut test::Bencher) {
+            let mut runtime = tokio::runtime::Runtime::new().unwrap();
+            let mut conn = runtime
+                .block_on(Conn::new(get_opts().reuse_buffers(true)))
+                .unwrap();
+
+            bencher.iter(|| {
+                runtime
+                    .block_on(conn.query_drop(
+                        "SELECT REPEAT('A', 100), 42, 4.2 FROM information_schema.COLLATIONS",
+                    ))
+                    .unwrap();
+            });
+
+            runtime.block_on(conn.disconnect()).unwrap();
+        }
+
+        #[bench]
+        fn collect_many_rows(bencher: &mut test::Bencher) {
+            let mut runtime = tokio::runtime::Runtime::new().unwrap();
+            let mut conn = runtime.block_on(Conn::new(get_opts())).unwrap();
+
+            bencher.iter(|| {
+                let rows: Vec<(String, u8, f64)> = runtime
+                    .block_on(conn.query(
+                        "SELECT REPEAT('A', 100), 42, 4.2 FROM information_schema.COLLATIONS",
+                    ))
+                    .unwrap();
+                rows
+            });
+
+            runtime.block_on(conn.disconnect()).unwrap();
+        }
+
         #[bench]
         fn prepare_and_exec(bencher: &mut test::Bencher) {
             let mut runtime = tokio::runtime::Runtime::new().unwrap();
@@ -0,0 +1,140 @@
+// Copyright (c) 2020 Anatoly Ikorsky
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Pre-4.1 password scramble (`mysql_old_password`).
+//!
+//! **This algorithm is insecure.** It's only implemented to connect to legacy servers,
+//! that store old password hashes, and it's only available with the `legacy-auth` feature.
+//!
+//! Only 4.1+ servers are supported (e.g. a 5.x server with `old_passwords=1` or an account
+//! with an old password hash). Pre-4.1 (3.23/4.0) servers use a different wire protocol,
+//! so connections to them still fail with [`crate::DriverError::ServerTooOld`].
+
+/// Name of the authentication plugin, that uses the pre-4.1 password scramble.
+pub(crate) const PLUGIN_NAME: &[u8] = b"mysql_old_password";
+
+/// Length of the pre-4.1 scramble.
+const SCRAMBLE_LENGTH_323: usize = 8;
+
+/// Pre-4.1 password hash (the value of the `OLD_PASSWORD` function).
+fn hash_password(password: &[u8]) -> [u32; 2] {
+    let mut nr: u32 = 1_345_345_333;
+    let mut add: u32 = 7;
+    let mut nr2: u32 = 0x1234_5671;
+    for &byte in password {
+        if byte == b' ' || byte == b'\t' {
+            continue;
+        }
+        let tmp = u32::from(byte);
+        nr ^= (nr & 63)
+            .wrapping_add(add)
+            .wrapping_mul(tmp)
+            .wrapping_add(nr << 8);
+        nr2 = nr2.wrapping_add((nr2 << 8) ^ nr);
+        add = add.wrapping_add(tmp);
+    }
+    [nr & 0x7fff_ffff, nr2 & 0x7fff_ffff]
+}
+
+/// Random number generator of the pre-4.1 scramble.
+struct Rand {
+    seed1: u64,
+    seed2: u64,
+}
+
+impl Rand {
+    const MAX_VALUE: u64 = 0x3fff_ffff;
+
+    fn new(seed1: u32, seed2: u32) -> Self {
+        Self {
+            seed1: u64::from(seed1) % Self::MAX_VALUE,
+            seed2: u64::from(seed2) % Self::MAX_VALUE,
+        }
+    }
+
+    fn next(&mut self) -> f64 {
+        self.seed1 = (self.seed1 * 3 + self.seed2) % Self::MAX_VALUE;
+        self.seed2 = (self.seed1 + self.seed2 + 33) % Self::MAX_VALUE;
+        self.seed1 as f64 / Self::MAX_VALUE as f64
+    }
+}
+
+/// Scrambles the given password using the first 8 bytes of the given nonce.
+///
+/// Returns auth data, that is sent to the server (a NUL-terminated scramble,
+/// or a single NUL byte for an empty password).
+pub(crate) fn scramble_323(nonce: &[u8], password: Option<&str>) -> Vec<u8> {
+    let password = password.unwrap_or_default().as_bytes();
+    if password.is_empty() {
+        return vec![0];
+    }
+
+    let message = &nonce[..std::cmp::min(nonce.len(), SCRAMBLE_LENGTH_323)];
+    let hash_pass = hash_password(password);
+    let hash_message = hash_password(message);
+    let mut rand = Rand::new(
+        hash_pass[0] ^ hash_message[0],
+        hash_pass[1] ^ hash_message[1],
+    );
+
+    let mut scramble = message
+        .iter()
+        .map(|_| (rand.next() * 31.0).floor() as u8 + 64)
+        .collect::<Vec<_>>();
+    let extra = (rand.next() * 31.0).floor() as u8;
+    for byte in scramble.iter_mut() {
+        *byte ^= extra;
+    }
+    scramble.push(0);
+    scramble
+}
+
+#[cfg(test)]
+mod test {
+    use super::{hash_password, scramble_323};
+
+    #[test]
+    fn should_hash_old_password() {
+        // `SELECT OLD_PASSWORD('mypass')`
+        assert_eq!(hash_password(b"mypass"), [0x6f8c_114b, 0x58f2_ce9e]);
+        // whitespace is ignored
+        assert_eq!(hash_password(b"my pass\t"), hash_password(b"mypass"));
+    }
+
+    #[test]
+    fn should_scramble_old_password() {
+        let nonce = b"12345678abcdefghijkl";
+        let scramble = scramble_323(nonce, Some("mypass"));
+        assert_eq!(scramble.len(), 9);
+        assert_eq!(scramble[8], 0);
+        assert!(scramble[..8].iter().all(|x| (0x40..0x80).contains(x)));
+        // only the first 8 bytes of the nonce are used
+        assert_eq!(scramble_323(&nonce[..8], Some("mypass")), scramble);
+        assert_ne!(scramble_323(nonce, Some("other")), scramble);
+
+        assert_eq!(scramble_323(nonce, None), vec![0]);
+        assert_eq!(scramble_323(nonce, Some("")), vec![0]);
+    }
+
+    #[test]
+    fn should_match_libmysqlclient_scramble() {
+        // reference vectors of `scramble_323` from libmysqlclient's `password.c`
+        let nonce = [9, 8, 7, 6, 5, 4, 3, 2];
+        for (password, scramble) in &[
+            (" pass", b"\x47\x57\x5c\x5a\x43\x5b\x42\x51\x00"),
+            ("pass ", b"\x47\x57\x5c\x5a\x43\x5b\x42\x51\x00"),
+            ("123\t456", b"\x57\x5c\x47\x50\x5b\x5b\x55\x59\x00"),
+            (
+                "C0mpl!ca ted#PASS123",
+                b"\x5d\x5d\x55\x48\x49\x58\x4a\x45\x00",
+            ),
+        ] {
+            assert_eq!(scramble_323(&nonce, Some(password)), &scramble[..]);
+        }
+    }
+}
@@ -0,0 +1,144 @@
+// Copyright (c) 2020 Anatoly Ikorsky
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::sync::{atomic, Arc};
+
+use super::Pool;
+use crate::opts::Opts;
+
+/// Replicas of a pool (see [`PoolBuilder::add_replica`]).
+#[derive(Debug, Default)]
+pub(super) struct Replicas {
+    /// Replica pools along with their weights.
+    pools: Vec<(Pool, usize)>,
+    /// Number of connections taken from replicas so far (drives the weighted round-robin).
+    checkouts: atomic::AtomicUsize,
+}
+
+impl Replicas {
+    /// Returns replica pools.
+    pub(super) fn pools(&self) -> impl Iterator<Item = &Pool> {
+        self.pools.iter().map(|(pool, _)| pool)
+    }
+
+    /// Returns the replica pool for the next read connection, if any.
+    ///
+    /// Out of every `sum(weights)` calls, the replica of weight `w` is returned `w` times.
+    pub(super) fn next(&self) -> Option<&Pool> {
+        let total = self.pools.iter().map(|(_, weight)| weight).sum::<usize>();
+        if total == 0 {
+            return None;
+        }
+        let mut n = self.checkouts.fetch_add(1, atomic::Ordering::Relaxed) % total;
+        for (pool, weight) in &self.pools {
+            if n < *weight {
+                return Some(pool);
+            }
+            n -= weight;
+        }
+        None
+    }
+}
+
+/// Builder for a [`Pool`] with read replicas.
+///
+/// Each endpoint is a separate pool with its own options, so a replica may have its own
+/// credentials and [`crate::PoolOpts`] (e.g. pool constraints that match its capacity).
+///
+/// [`Pool::read_conn`] distributes connections between replicas using weighted round-robin
+/// (a replica of weight `2` gets twice as many connections as a replica of weight `1`).
+/// Everything else (e.g. [`Pool::get_conn`] and [`Pool::write_conn`]) uses the primary.
+///
+/// ```rust
+/// # use mysql_async::{OptsBuilder, PoolBuilder, PoolConstraints, PoolOpts};
+/// let replica_opts = |host: &str, max: usize| {
+///     let constraints = PoolConstraints::new(1, max).unwrap();
+///     OptsBuilder::default()
+///         .ip_or_hostname(host)
+///         .pool_opts(PoolOpts::default().with_constraints(constraints))
+/// };
+///
+/// let pool = PoolBuilder::new(OptsBuilder::default().ip_or_hostname("primary"))
+///     .add_replica(replica_opts("small-replica", 10), 1)
+///     .add_replica(replica_opts("large-replica", 30), 3)
+///     .build();
+/// ```
+#[derive(Debug, Clone)]
+pub struct PoolBuilder {
+    primary: Opts,
+    replicas: Vec<(Opts, usize)>,
+}
+
+impl PoolBuilder {
+    /// Creates a builder with the given options of the primary server.
+    pub fn new<O: Into<Opts>>(primary: O) -> Self {
+        Self {
+            primary: primary.into(),
+            replicas: Vec::new(),
+        }
+    }
+
+    /// Adds a replica with the given options and weight.
+    ///
+    /// Replica of weight `0` is never used by [`Pool::read_conn`].
+    pub fn add_replica<O: Into<Opts>>(mut self, opts: O, weight: usize) -> Self {
+        self.replicas.push((opts.into(), weight));
+        self
+    }
+
+    /// Creates the pool.
+    ///
+    /// Note that [`Pool::disconnect`] and [`Pool::abort`] also apply to replicas.
+    pub fn build(self) -> Pool {
+        let replicas = Replicas {
+            pools: self
+                .replicas
+                .into_iter()
+                .map(|(opts, weight)| (Pool::new(opts), weight))
+                .collect(),
+            checkouts: Default::default(),
+        };
+        let mut pool = Pool::new(self.primary);
+        pool.replicas = Arc::new(replicas);
+        pool
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::PoolBuilder;
+    use crate::test_misc::get_opts;
+
+    #[test]
+    fn should_distribute_by_weight() {
+        let pool = PoolBuilder::new(get_opts())
+            .add_replica(get_opts(), 1)
+            .add_replica(get_opts(), 0)
+            .add_replica(get_opts(), 3)
+            .build();
+        let replicas = pool.replicas.pools().collect::<Vec<_>>();
+
+        let mut counts = vec![0; replicas.len()];
+        for _ in 0..400 {
+            let replica = pool.replicas.next().unwrap();
+            let index = replicas
+                .iter()
+                .position(|x| Arc::ptr_eq(&x.inner, &replica.inner))
+                .unwrap();
+            counts[index] += 1;
+        }
+        assert_eq!(counts, vec![100, 0, 300]);
+
+        let pool = PoolBuilder::new(get_opts())
+            .add_replica(get_opts(), 0)
+            .build();
+        assert!(pool.replicas.next().is_none());
+    }
+}
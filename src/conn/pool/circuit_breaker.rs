@@ -0,0 +1,220 @@
+// Copyright (c) 2020 Anatoly Ikorsky
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::error::DriverError;
+
+/// Options of a pool's circuit breaker (see [`crate::PoolOpts::with_circuit_breaker`]).
+///
+/// The breaker trips open after `failure_threshold` consecutive connection failures within
+/// `failure_window`. An open breaker fails new connections with [`DriverError::CircuitOpen`]
+/// for `cooldown`, and then lets a single connection attempt through to probe the server
+/// (half-open state). The breaker closes if the probe succeeds, and opens again otherwise.
+///
+/// ```
+/// # use mysql_async::{CircuitBreakerOpts, PoolOpts};
+/// # use std::time::Duration;
+/// let breaker = CircuitBreakerOpts::new(5, Duration::from_secs(10), Duration::from_secs(30));
+/// let pool_opts = PoolOpts::default().with_circuit_breaker(breaker);
+/// ```
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct CircuitBreakerOpts {
+    failure_threshold: usize,
+    failure_window: Duration,
+    cooldown: Duration,
+}
+
+impl CircuitBreakerOpts {
+    /// Creates circuit breaker options.
+    ///
+    /// `failure_threshold` of `0` is treated as `1`.
+    pub fn new(failure_threshold: usize, failure_window: Duration, cooldown: Duration) -> Self {
+        Self {
+            failure_threshold: std::cmp::max(failure_threshold, 1),
+            failure_window,
+            cooldown,
+        }
+    }
+
+    /// Number of consecutive connection failures, that trips the breaker open.
+    pub fn failure_threshold(&self) -> usize {
+        self.failure_threshold
+    }
+
+    /// Consecutive failures are only counted if they happen within this window.
+    pub fn failure_window(&self) -> Duration {
+        self.failure_window
+    }
+
+    /// Time the breaker stays open before a connection attempt is let through.
+    pub fn cooldown(&self) -> Duration {
+        self.cooldown
+    }
+}
+
+/// State of a circuit breaker.
+#[derive(Debug, Default)]
+struct State {
+    /// Number of consecutive failures since `first_failure`.
+    failures: usize,
+    first_failure: Option<Instant>,
+    /// The breaker is open since this `Instant`.
+    opened_at: Option<Instant>,
+    /// A probe connection is in progress (the breaker is half-open).
+    probing: bool,
+}
+
+/// Circuit breaker of a pool.
+#[derive(Debug, Default)]
+pub(super) struct CircuitBreaker {
+    opts: Option<CircuitBreakerOpts>,
+    state: Mutex<State>,
+}
+
+impl CircuitBreaker {
+    pub(super) fn new(opts: Option<CircuitBreakerOpts>) -> Self {
+        Self {
+            opts,
+            state: Default::default(),
+        }
+    }
+
+    /// Returns an error if a new connection isn't allowed.
+    ///
+    /// Once the cooldown is over, it allows a single connection (the probe) until the outcome
+    /// of this connection is reported via [`CircuitBreaker::on_success`],
+    /// [`CircuitBreaker::on_failure`] or [`CircuitBreaker::on_cancel`].
+    pub(super) fn check(&self) -> Result<(), DriverError> {
+        let opts = match self.opts {
+            Some(opts) => opts,
+            None => return Ok(()),
+        };
+        let mut state = self.state.lock().unwrap();
+        match state.opened_at {
+            None => Ok(()),
+            Some(opened_at) if opened_at.elapsed() < opts.cooldown => Err(DriverError::CircuitOpen),
+            Some(_) if state.probing => Err(DriverError::CircuitOpen),
+            Some(_) => {
+                state.probing = true;
+                Ok(())
+            }
+        }
+    }
+
+    /// Closes the breaker.
+    pub(super) fn on_success(&self) {
+        if self.opts.is_some() {
+            *self.state.lock().unwrap() = State::default();
+        }
+    }
+
+    /// Counts a connection failure (trips the breaker open if the threshold is reached).
+    pub(super) fn on_failure(&self) {
+        let opts = match self.opts {
+            Some(opts) => opts,
+            None => return,
+        };
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        if state.opened_at.is_some() {
+            // the probe has failed
+            state.opened_at = Some(now);
+            state.probing = false;
+            return;
+        }
+        match state.first_failure {
+            Some(first_failure) if now.duration_since(first_failure) <= opts.failure_window => {
+                state.failures += 1;
+            }
+            _ => {
+                state.failures = 1;
+                state.first_failure = Some(now);
+            }
+        }
+        if state.failures >= opts.failure_threshold {
+            state.opened_at = Some(now);
+            state.failures = 0;
+            state.first_failure = None;
+        }
+    }
+
+    /// Lets another probe through, if the probe connection was cancelled.
+    pub(super) fn on_cancel(&self) {
+        if self.opts.is_some() {
+            self.state.lock().unwrap().probing = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::{CircuitBreaker, CircuitBreakerOpts};
+    use crate::error::DriverError;
+
+    #[test]
+    fn should_trip_and_recover() {
+        let opts = CircuitBreakerOpts::new(3, Duration::from_secs(60), Duration::from_millis(50));
+        let breaker = CircuitBreaker::new(Some(opts));
+
+        // a success resets consecutive failures
+        breaker.on_failure();
+        breaker.on_failure();
+        breaker.on_success();
+        breaker.on_failure();
+        breaker.on_failure();
+        assert_eq!(breaker.check(), Ok(()));
+
+        breaker.on_failure();
+        assert_eq!(breaker.check(), Err(DriverError::CircuitOpen));
+
+        // half-open: a single probe is allowed
+        std::thread::sleep(Duration::from_millis(60));
+        assert_eq!(breaker.check(), Ok(()));
+        assert_eq!(breaker.check(), Err(DriverError::CircuitOpen));
+
+        // failed probe opens the breaker again
+        breaker.on_failure();
+        assert_eq!(breaker.check(), Err(DriverError::CircuitOpen));
+
+        // cancelled probe lets another probe through
+        std::thread::sleep(Duration::from_millis(60));
+        assert_eq!(breaker.check(), Ok(()));
+        breaker.on_cancel();
+        assert_eq!(breaker.check(), Ok(()));
+
+        breaker.on_success();
+        assert_eq!(breaker.check(), Ok(()));
+        assert_eq!(breaker.check(), Ok(()));
+    }
+
+    #[test]
+    fn should_only_count_failures_within_window() {
+        let opts = CircuitBreakerOpts::new(2, Duration::from_millis(20), Duration::from_secs(60));
+        let breaker = CircuitBreaker::new(Some(opts));
+
+        breaker.on_failure();
+        std::thread::sleep(Duration::from_millis(30));
+        breaker.on_failure();
+        assert_eq!(breaker.check(), Ok(()));
+        breaker.on_failure();
+        assert_eq!(breaker.check(), Err(DriverError::CircuitOpen));
+
+        // no breaker by default
+        let breaker = CircuitBreaker::new(None);
+        for _ in 0..10 {
+            breaker.on_failure();
+        }
+        assert_eq!(breaker.check(), Ok(()));
+    }
+}
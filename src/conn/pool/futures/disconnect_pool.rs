@@ -27,11 +27,18 @@ use std::sync::{atomic, Arc};
 #[must_use = "futures do nothing unless you `.await` or poll them"]
 pub struct DisconnectPool {
     pool_inner: Arc<Inner>,
+    /// Disconnection of replicas of the pool (see [`crate::PoolBuilder`]).
+    replicas: Vec<DisconnectPool>,
 }
 
 impl DisconnectPool {
     pub(crate) fn new(pool: Pool) -> Self {
         Self {
+            replicas: pool
+                .replicas
+                .pools()
+                .map(|replica| replica.clone().disconnect())
+                .collect(),
             pool_inner: pool.inner,
         }
     }
@@ -40,13 +47,20 @@ impl DisconnectPool {
 impl Future for DisconnectPool {
     type Output = Result<(), Error>;
 
-    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut replicas_closed = true;
+        for replica in self.replicas.iter_mut() {
+            if Pin::new(replica).poll(cx)?.is_pending() {
+                replicas_closed = false;
+            }
+        }
+
         let mut exchange = self.pool_inner.exchange.lock().unwrap();
         exchange.spawn_futures_if_needed(&self.pool_inner);
         exchange.waiting.push_back(cx.waker().clone());
         drop(exchange);
 
-        if self.pool_inner.closed.load(atomic::Ordering::Acquire) {
+        if self.pool_inner.closed.load(atomic::Ordering::Acquire) && replicas_closed {
             Poll::Ready(Ok(()))
         } else {
             Poll::Pending
@@ -28,6 +28,8 @@ pub(crate) enum GetConnInner {
     Connecting(BoxFuture<'static, Conn>),
     /// This future will check, that idling connection is alive.
     Checking(BoxFuture<'static, Conn>),
+    /// This future will change the default database of a connection (see `GetConn::db_name`).
+    SelectingDb(BoxFuture<'static, Conn>),
 }
 
 impl GetConnInner {
@@ -35,6 +37,14 @@ impl GetConnInner {
     pub fn take(&mut self) -> GetConnInner {
         std::mem::replace(self, GetConnInner::New)
     }
+
+    /// Changes the default database of the given connection.
+    fn select_db(mut conn: Conn, db_name: String) -> GetConnInner {
+        GetConnInner::SelectingDb(BoxFuture(Box::pin(async move {
+            conn.select_db(db_name).await?;
+            Ok(conn)
+        })))
+    }
 }
 
 /// This future will take connection from a pool and resolve to [`Conn`].
@@ -43,13 +53,25 @@ impl GetConnInner {
 pub struct GetConn {
     pub(crate) pool: Option<Pool>,
     pub(crate) inner: GetConnInner,
+    /// Required default database of the resulting connection (if any).
+    pub(crate) db_name: Option<String>,
 }
 
 impl GetConn {
-    pub(crate) fn new(pool: &Pool) -> GetConn {
+    pub(crate) fn new(pool: &Pool, db_name: Option<String>) -> GetConn {
         GetConn {
             pool: Some(pool.clone()),
             inner: GetConnInner::New,
+            db_name,
+        }
+    }
+
+    /// Returns `Some(db_name)` if default database of the given connection
+    /// needs to be changed.
+    fn db_to_select(&mut self, conn: &Conn) -> Option<String> {
+        match self.db_name.take() {
+            Some(db_name) if conn.inner.db_name.as_ref() != Some(&db_name) => Some(db_name),
+            _ => None,
         }
     }
 
@@ -74,9 +96,16 @@ impl Future for GetConn {
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         loop {
             match self.inner {
-                GetConnInner::New => match ready!(Pin::new(self.pool_mut()).poll_new_conn(cx))?
-                    .inner
-                    .take()
+                GetConnInner::New => match ready!({
+                    let this = &mut *self;
+                    let pool = this
+                        .pool
+                        .as_mut()
+                        .expect("GetConn::poll polled after returning Async::Ready");
+                    Pin::new(pool).poll_new_conn(cx, this.db_name.as_deref())
+                })?
+                .inner
+                .take()
                 {
                     GetConnInner::Connecting(conn_fut) => {
                         self.inner = GetConnInner::Connecting(conn_fut);
@@ -84,7 +113,7 @@ impl Future for GetConn {
                     GetConnInner::Checking(conn_fut) => {
                         self.inner = GetConnInner::Checking(conn_fut);
                     }
-                    GetConnInner::Done => unreachable!(
+                    GetConnInner::Done | GetConnInner::SelectingDb(_) => unreachable!(
                         "Pool::poll_new_conn never gives out already-consumed GetConns"
                     ),
                     GetConnInner::New => {
@@ -102,10 +131,17 @@ impl Future for GetConn {
 
                     return match result {
                         Ok(mut c) => {
+                            pool.inner.breaker.on_success();
+                            c.inner.pool_epoch = pool.inner.epoch();
                             c.inner.pool = Some(pool);
+                            if let Some(db_name) = self.db_to_select(&c) {
+                                self.inner = GetConnInner::select_db(c, db_name);
+                                continue;
+                            }
                             Poll::Ready(Ok(c))
                         }
                         Err(e) => {
+                            pool.inner.breaker.on_failure();
                             pool.cancel_connection();
                             Poll::Ready(Err(e))
                         }
@@ -119,6 +155,10 @@ impl Future for GetConn {
 
                             let pool = self.pool_take();
                             checked_conn.inner.pool = Some(pool);
+                            if let Some(db_name) = self.db_to_select(&checked_conn) {
+                                self.inner = GetConnInner::select_db(checked_conn, db_name);
+                                continue;
+                            }
                             return Poll::Ready(Ok(checked_conn));
                         }
                         Err(_) => {
@@ -131,6 +171,13 @@ impl Future for GetConn {
                         }
                     }
                 }
+                GetConnInner::SelectingDb(ref mut f) => {
+                    // connection is already bound to the pool,
+                    // so it'll be returned there on error
+                    let result = ready!(Pin::new(f).poll(cx));
+                    self.inner = GetConnInner::Done;
+                    return Poll::Ready(result);
+                }
             }
         }
     }
@@ -142,6 +189,7 @@ impl Drop for GetConn {
         // Make sure we maintain the necessary invariants towards the pool.
         if let Some(pool) = self.pool.take() {
             if let GetConnInner::Connecting(..) = self.inner.take() {
+                pool.inner.breaker.on_cancel();
                 pool.cancel_connection();
             }
         }
@@ -0,0 +1,105 @@
+// Copyright (c) 2020 Anatoly Ikorsky
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use std::{
+    collections::HashMap,
+    fmt,
+    hash::{Hash, Hasher},
+    sync::{Arc, Mutex},
+};
+
+use crate::Opts;
+
+/// Caps the number of live connections to a server across multiple pools
+/// (see [`crate::PoolOpts::with_limiter`]).
+///
+/// Connections are counted per server endpoint (`host:port`), so the same limiter
+/// may be shared by pools to different servers. A pool acquires a permit before it creates
+/// a new connection (waiting for one, if the cap is reached), and the permit is released
+/// as soon as the connection is closed.
+///
+/// Note that connections idling in a pool are live, so they count against the limit and hold
+/// their permits until they are closed (e.g. by [`crate::PoolOpts::inactive_connection_ttl`]
+/// or by [`crate::Conn::disconnect`]). So idling connections of one pool may starve other pools,
+/// that share the limiter. Use a short `inactive_connection_ttl` and a small minimum
+/// of [`crate::PoolConstraints`] to bound the number of idling connections.
+///
+/// ```rust
+/// # use mysql_async::{ConnectionLimiter, PoolOpts};
+/// let limiter = ConnectionLimiter::new(100);
+/// let pool_opts = PoolOpts::default().with_limiter(limiter.clone());
+/// let other_pool_opts = PoolOpts::default().with_limiter(limiter);
+/// ```
+///
+/// Two limiters are equal if they are clones of each other.
+#[derive(Clone)]
+pub struct ConnectionLimiter {
+    max_connections: usize,
+    semaphores: Arc<Mutex<HashMap<String, Arc<Semaphore>>>>,
+}
+
+impl ConnectionLimiter {
+    /// Creates a limiter, that allows up to `max_connections` live connections per server.
+    pub fn new(max_connections: usize) -> Self {
+        Self {
+            max_connections,
+            semaphores: Default::default(),
+        }
+    }
+
+    /// Returns the maximum number of live connections per server.
+    pub fn max_connections(&self) -> usize {
+        self.max_connections
+    }
+
+    /// Returns the number of live connections (including idling ones) to the server
+    /// of the given options, that are counted by this limiter.
+    pub fn live_connections(&self, opts: &Opts) -> usize {
+        self.max_connections - self.semaphore(opts).available_permits()
+    }
+
+    /// Waits for a permit to connect to the server of the given options.
+    pub(crate) async fn acquire(&self, opts: &Opts) -> OwnedSemaphorePermit {
+        self.semaphore(opts).acquire_owned().await
+    }
+
+    fn semaphore(&self, opts: &Opts) -> Arc<Semaphore> {
+        let endpoint = format!("{}:{}", opts.ip_or_hostname(), opts.tcp_port());
+        let max_connections = self.max_connections;
+        self.semaphores
+            .lock()
+            .unwrap()
+            .entry(endpoint)
+            .or_insert_with(|| Arc::new(Semaphore::new(max_connections)))
+            .clone()
+    }
+}
+
+impl PartialEq for ConnectionLimiter {
+    fn eq(&self, other: &ConnectionLimiter) -> bool {
+        Arc::ptr_eq(&self.semaphores, &other.semaphores)
+    }
+}
+
+impl Eq for ConnectionLimiter {}
+
+impl Hash for ConnectionLimiter {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (&*self.semaphores as *const Mutex<_>).hash(state)
+    }
+}
+
+impl fmt::Debug for ConnectionLimiter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConnectionLimiter")
+            .field("max_connections", &self.max_connections)
+            .finish()
+    }
+}
@@ -6,10 +6,17 @@
 // option. All files in the project carrying such notice may not be copied,
 // modified, or distributed except according to those terms.
 
+use futures_util::{
+    future::FutureExt,
+    stream::{self, StreamExt},
+};
 use tokio::sync::mpsc;
 
 use std::{
+    cmp,
     collections::VecDeque,
+    future::Future,
+    panic::{self, AssertUnwindSafe},
     pin::Pin,
     str::FromStr,
     sync::{atomic, Arc, Mutex},
@@ -18,13 +25,23 @@ use std::{
 };
 
 use crate::{
-    conn::{pool::futures::*, Conn},
+    conn::{pool::futures::*, Conn, Health},
     error::*,
     opts::{Opts, PoolOpts},
-    queryable::transaction::{Transaction, TxOpts, TxStatus},
-    BoxFuture,
+    queryable::{
+        transaction::{Transaction, TxOpts},
+        Queryable,
+    },
+    runtime, BoxFuture, Params,
+};
+
+pub use self::{
+    builder::PoolBuilder, circuit_breaker::CircuitBreakerOpts, limiter::ConnectionLimiter,
 };
 
+mod builder;
+mod circuit_breaker;
+mod limiter;
 mod recycler;
 // this is a really unfortunate name for a module
 pub mod futures;
@@ -44,10 +61,10 @@ impl IdlingConn {
     fn elapsed(&self) -> Duration {
         self.since.elapsed()
     }
-}
 
-impl From<Conn> for IdlingConn {
-    fn from(conn: Conn) -> Self {
+    /// Creates an idling connection that belongs to the given generation of the pool.
+    fn new(mut conn: Conn, epoch: usize) -> Self {
+        conn.inner.pool_epoch = epoch;
         Self {
             since: Instant::now(),
             conn,
@@ -76,11 +93,14 @@ impl Exchange {
         use ttl_check_inerval::TtlCheckInterval;
         if let Some((dropped, pool_opts)) = self.recycler.take() {
             // Spawn the Recycler.
-            tokio::spawn(Recycler::new(pool_opts.clone(), inner.clone(), dropped));
+            runtime::spawn(Recycler::new(pool_opts.clone(), inner.clone(), dropped));
 
             // Spawn the ttl check interval if `inactive_connection_ttl` isn't `0`
-            if pool_opts.inactive_connection_ttl() > Duration::from_secs(0) {
-                tokio::spawn(TtlCheckInterval::new(pool_opts, inner.clone()));
+            // or if idling connections should be checked for liveness.
+            if pool_opts.inactive_connection_ttl() > Duration::from_secs(0)
+                || pool_opts.idle_liveness_check()
+            {
+                runtime::spawn(TtlCheckInterval::new(pool_opts, inner.clone()));
             }
         }
     }
@@ -91,9 +111,25 @@ impl Exchange {
 pub struct Inner {
     close: atomic::AtomicBool,
     closed: atomic::AtomicBool,
+    /// Connections are disconnected without cleanup if `true` (see [`Pool::abort`]).
+    aborted: atomic::AtomicBool,
+    /// Generation of pooled connections.
+    ///
+    /// It is bumped whenever a connection is lost, so that connections of a previous generation
+    /// are discarded on checkout without being checked one by one.
+    epoch: atomic::AtomicUsize,
+    /// Circuit breaker for new connections (see [`PoolOpts::with_circuit_breaker`]).
+    breaker: circuit_breaker::CircuitBreaker,
     exchange: Mutex<Exchange>,
 }
 
+impl Inner {
+    /// Returns the current generation of pooled connections.
+    fn epoch(&self) -> usize {
+        self.epoch.load(atomic::Ordering::Acquire)
+    }
+}
+
 #[derive(Clone)]
 /// Asynchronous pool of MySql connections.
 ///
@@ -104,6 +140,8 @@ pub struct Pool {
     opts: Opts,
     inner: Arc<Inner>,
     drop: mpsc::UnboundedSender<Option<Conn>>,
+    /// Read replicas (see [`PoolBuilder`]).
+    replicas: Arc<builder::Replicas>,
 }
 
 impl Pool {
@@ -117,6 +155,9 @@ impl Pool {
             inner: Arc::new(Inner {
                 close: false.into(),
                 closed: false.into(),
+                aborted: false.into(),
+                epoch: 0.into(),
+                breaker: circuit_breaker::CircuitBreaker::new(pool_opts.circuit_breaker()),
                 exchange: Mutex::new(Exchange {
                     available: VecDeque::with_capacity(pool_opts.constraints().max()),
                     waiting: VecDeque::new(),
@@ -125,6 +166,7 @@ impl Pool {
                 }),
             }),
             drop: tx,
+            replicas: Default::default(),
         }
     }
 
@@ -135,8 +177,56 @@ impl Pool {
     }
 
     /// Async function that resolves to `Conn`.
+    ///
+    /// If [`Opts::db_name`] is defined, then the resulting connection will have it as
+    /// the default database, even if it was changed by [`Pool::get_conn_for_db`] or
+    /// [`Conn::select_db`].
     pub fn get_conn(&self) -> GetConn {
-        GetConn::new(self)
+        GetConn::new(self, self.opts.db_name().map(Into::into))
+    }
+
+    /// Async function that resolves to `Conn` with the given default database.
+    ///
+    /// Idling connections, that already have the given default database, are preferred,
+    /// so that a connection returned to the pool will be reused for the same database without
+    /// a round trip. Otherwise the default database of a connection is changed using
+    /// `COM_INIT_DB` (see [`Conn::select_db`]).
+    ///
+    /// Note that the pool only tracks database changes made via this function
+    /// and [`Conn::select_db`], i.e. not by a `USE` statement.
+    pub fn get_conn_for_db<T: Into<String>>(&self, db_name: T) -> GetConn {
+        GetConn::new(self, Some(db_name.into()))
+    }
+
+    /// Resolves to a connection, that is meant for read-only queries.
+    ///
+    /// The connection is taken from a replica (see [`PoolBuilder`]), or from this pool
+    /// if there are no replicas.
+    ///
+    /// The session access mode of the resulting connection is `READ ONLY`
+    /// (see [`Conn::set_read_only`]), so an accidental write will fail. The access mode
+    /// is restored when the connection returns to the pool.
+    pub async fn read_conn(&self) -> Result<Conn> {
+        let mut conn = match self.replicas.next() {
+            Some(replica) => replica.get_conn().await?,
+            None => self.get_conn().await?,
+        };
+        if !conn.is_read_only() {
+            conn.set_read_only(true).await?;
+        }
+        Ok(conn)
+    }
+
+    /// Resolves to a connection, that is meant for writes.
+    ///
+    /// Same as [`Pool::get_conn`] (i.e. the connection is taken from the primary, if this pool
+    /// has replicas), but also makes sure, that the session access mode is `READ WRITE`.
+    pub async fn write_conn(&self) -> Result<Conn> {
+        let mut conn = self.get_conn().await?;
+        if conn.is_read_only() {
+            conn.set_read_only(false).await?;
+        }
+        Ok(conn)
     }
 
     /// Starts a new transaction.
@@ -145,10 +235,91 @@ impl Pool {
         Transaction::new(conn, options).await
     }
 
+    /// Runs the given closure within a transaction on a connection taken from this pool.
+    ///
+    /// The transaction is committed if the closure resolves to `Ok`, and rolled back if it
+    /// resolves to `Err` or panics (the panic is resumed after the rollback). The connection
+    /// is returned to the pool in either case.
+    ///
+    /// ```rust
+    /// # use mysql_async::{prelude::*, test_misc::get_opts, Pool, Result, TxOpts};
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<()> {
+    /// let pool = Pool::new(get_opts());
+    /// let value = pool
+    ///     .transaction(TxOpts::default(), |tx| {
+    ///         Box::pin(async move { tx.query_first::<u8, _>("SELECT 42").await })
+    ///     })
+    ///     .await?;
+    /// assert_eq!(value, Some(42));
+    /// # pool.disconnect().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn transaction<F, T>(&self, options: TxOpts, f: F) -> Result<T>
+    where
+        F: for<'t> FnOnce(
+            &'t mut Transaction<'static>,
+        ) -> Pin<Box<dyn Future<Output = Result<T>> + Send + 't>>,
+    {
+        let mut tx = self.start_transaction(options).await?;
+        let result = AssertUnwindSafe(async { f(&mut tx).await })
+            .catch_unwind()
+            .await;
+        match result {
+            Ok(Ok(value)) => {
+                tx.commit().await?;
+                Ok(value)
+            }
+            Ok(Err(err)) => {
+                // The original error is more relevant than a rollback error, and the pool
+                // will clean up the connection if the rollback fails.
+                let _ = tx.rollback().await;
+                Err(err)
+            }
+            Err(payload) => {
+                let _ = tx.rollback().await;
+                panic::resume_unwind(payload)
+            }
+        }
+    }
+
+    /// Exectues the given query for each item in the given params iterator using up to
+    /// `concurrency` connections from this pool at a time.
+    ///
+    /// Every connection is returned to the pool as soon as its item is executed. Resolves to
+    /// a vector that contains a result for each item in the order of `params_iter`.
+    ///
+    /// `concurrency` of `0` is treated as `1`.
+    pub async fn exec_drop_concurrent<Q, I, P>(
+        &self,
+        query: Q,
+        params_iter: I,
+        concurrency: usize,
+    ) -> Vec<Result<()>>
+    where
+        Q: AsRef<str>,
+        I: IntoIterator<Item = P>,
+        I::IntoIter: Send,
+        P: Into<Params> + Send,
+    {
+        let query = query.as_ref();
+        stream::iter(params_iter)
+            .map(|params| async move {
+                let mut conn = self.get_conn().await?;
+                conn.exec_drop(query, params).await
+            })
+            .buffered(cmp::max(concurrency, 1))
+            .collect()
+            .await
+    }
+
     /// Async function that disconnects this pool from the server and resolves to `()`.
     ///
     /// **Note:** This Future won't resolve until all active connections, taken from it,
     /// are dropped or disonnected. Also all pending and new `GetConn`'s will resolve to error.
+    ///
+    /// Replicas of this pool (see [`PoolBuilder`]) are disconnected as well.
     pub fn disconnect(self) -> DisconnectPool {
         let was_closed = self.inner.close.swap(true, atomic::Ordering::AcqRel);
         if !was_closed {
@@ -162,22 +333,71 @@ impl Pool {
         DisconnectPool::new(self)
     }
 
+    /// Immediately closes all idle connections of this pool without waiting for them
+    /// to be cleanly terminated.
+    ///
+    /// Connections, that are currently taken from the pool, will be closed the same way
+    /// as soon as they are returned, i.e. they won't be cleaned up or reused. Pending and
+    /// new `GetConn`'s will resolve to error.
+    ///
+    /// Unlike [`Pool::disconnect`], this function does not wait for anything, so it is suitable
+    /// for a fast shutdown. You can still await [`Pool::disconnect`] afterwards to wait for
+    /// the taken connections to be returned.
+    ///
+    /// Replicas of this pool (see [`PoolBuilder`]) are aborted as well.
+    pub fn abort(&self) {
+        for replica in self.replicas.pools() {
+            replica.abort();
+        }
+        self.inner.aborted.store(true, atomic::Ordering::Release);
+        let was_closed = self.inner.close.swap(true, atomic::Ordering::AcqRel);
+
+        // take idle connections before the Recycler does
+        let (available, waiting) = {
+            let mut exchange = self.inner.exchange.lock().unwrap();
+            let available = exchange.available.drain(..).collect::<Vec<_>>();
+            let waiting = exchange.waiting.drain(..).collect::<Vec<_>>();
+            (available, waiting)
+        };
+
+        if !was_closed {
+            // wake up the Recycler (see `Pool::disconnect`)
+            let _ = self.drop.send(None).is_ok();
+        }
+
+        for IdlingConn { mut conn, .. } in available {
+            // dropping the stream closes the socket
+            conn.inner.stream.take();
+            conn.inner.disconnected = true;
+            self.send_to_recycler(conn);
+        }
+
+        // pending `GetConn`'s will see that the pool is closed
+        for waker in waiting {
+            waker.wake();
+        }
+    }
+
     /// A way to return connection taken from a pool.
-    fn return_conn(&mut self, conn: Conn) {
+    fn return_conn(&mut self, mut conn: Conn) {
         // NOTE: we're not in async context here, so we can't block or return NotReady
         // any and all cleanup work _has_ to be done in the spawned recycler
 
+        if self.inner.aborted.load(atomic::Ordering::Acquire) {
+            conn.inner.stream.take();
+            conn.inner.disconnected = true;
+        }
+
         // fast-path for when the connection is immediately ready to be reused
-        if conn.inner.stream.is_some()
-            && !conn.inner.disconnected
+        if conn.is_healthy() == Health::Idle
             && !conn.expired()
-            && conn.inner.tx_status == TxStatus::None
-            && conn.inner.pending_result.is_none()
             && !self.inner.close.load(atomic::Ordering::Acquire)
         {
             let mut exchange = self.inner.exchange.lock().unwrap();
             if exchange.available.len() < self.opts.pool_opts().active_bound() {
-                exchange.available.push_back(conn.into());
+                exchange
+                    .available
+                    .push_back(IdlingConn::new(conn, self.inner.epoch()));
                 if let Some(w) = exchange.waiting.pop_front() {
                     w.wake();
                 }
@@ -205,6 +425,19 @@ impl Pool {
         }
     }
 
+    /// Indicate that a connection of the given generation was lost.
+    ///
+    /// Starts a new generation (unless it was already started by another lost connection),
+    /// so that all currently idling connections will be discarded on their next checkout.
+    pub(crate) fn connection_lost(&self, epoch: usize) {
+        let _ = self.inner.epoch.compare_exchange(
+            epoch,
+            epoch.wrapping_add(1),
+            atomic::Ordering::AcqRel,
+            atomic::Ordering::Acquire,
+        );
+    }
+
     /// Indicate that a connection failed to be created and release it.
     ///
     /// Decreases the exist counter since a broken or dropped connection should not count towards
@@ -219,11 +452,21 @@ impl Pool {
     }
 
     /// Poll the pool for an available connection.
-    fn poll_new_conn(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<GetConn>> {
-        self.poll_new_conn_inner(cx)
+    ///
+    /// Idling connections with the given default database are preferred.
+    fn poll_new_conn(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        db_name: Option<&str>,
+    ) -> Poll<Result<GetConn>> {
+        self.poll_new_conn_inner(cx, db_name)
     }
 
-    fn poll_new_conn_inner(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<GetConn>> {
+    fn poll_new_conn_inner(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        db_name: Option<&str>,
+    ) -> Poll<Result<GetConn>> {
         let mut exchange = self.inner.exchange.lock().unwrap();
 
         // NOTE: this load must happen while we hold the lock,
@@ -235,13 +478,34 @@ impl Pool {
 
         exchange.spawn_futures_if_needed(&self.inner);
 
+        let epoch = self.inner.epoch();
         loop {
-            if let Some(IdlingConn { mut conn, .. }) = exchange.available.pop_back() {
-                if !conn.expired() {
+            let position = db_name.and_then(|db_name| {
+                exchange
+                    .available
+                    .iter()
+                    .rposition(|idling| idling.conn.inner.db_name.as_deref() == Some(db_name))
+            });
+            let idling = match position {
+                Some(position) => exchange.available.remove(position),
+                None => exchange.available.pop_back(),
+            };
+            if let Some(IdlingConn { mut conn, .. }) = idling {
+                if conn.inner.pool_epoch != epoch {
+                    // Connection is of a previous generation, so it's probably broken.
+                    conn.inner.stream.take();
+                    conn.inner.disconnected = true;
+                    self.send_to_recycler(conn);
+                } else if !conn.expired() {
+                    let pool = self.clone();
                     return Poll::Ready(Ok(GetConn {
                         pool: Some(self.clone()),
+                        db_name: None,
                         inner: GetConnInner::Checking(BoxFuture(Box::pin(async move {
-                            conn.stream_mut()?.check().await?;
+                            if let Err(err) = conn.stream_mut()?.check().await {
+                                pool.connection_lost(epoch);
+                                return Err(err.into());
+                            }
                             Ok(conn)
                         }))),
                     }));
@@ -256,12 +520,24 @@ impl Pool {
         // we didn't _immediately_ get one -- try to make one
         // we first try to just do a load so we don't do an unnecessary add then sub
         if exchange.exist < self.opts.pool_opts().constraints().max() {
+            self.inner.breaker.check()?;
+
             // we are allowed to make a new connection, so we will!
             exchange.exist += 1;
 
+            let opts = self.opts.clone();
             return Poll::Ready(Ok(GetConn {
                 pool: Some(self.clone()),
-                inner: GetConnInner::Connecting(BoxFuture(Box::pin(Conn::new(self.opts.clone())))),
+                db_name: None,
+                inner: GetConnInner::Connecting(BoxFuture(Box::pin(async move {
+                    let permit = match opts.pool_opts().limiter() {
+                        Some(limiter) => Some(limiter.acquire(&opts).await),
+                        None => None,
+                    };
+                    let mut conn = Conn::new(opts).await?;
+                    conn.inner.limiter_permit = permit;
+                    Ok(conn)
+                }))),
             }));
         }
 
@@ -287,12 +563,25 @@ impl Drop for Conn {
 
 #[cfg(test)]
 mod test {
-    use futures_util::{future::try_join_all, stream::StreamExt, try_join};
+    use futures_util::{
+        future::{try_join_all, FutureExt},
+        stream::StreamExt,
+        try_join,
+    };
+
+    use std::{
+        panic::AssertUnwindSafe,
+        time::{Duration, Instant},
+    };
 
-    use std::time::Duration;
+    use tokio::time::timeout;
 
     use crate::{
-        conn::pool::Pool, opts::PoolOpts, prelude::*, test_misc::get_opts, PoolConstraints, TxOpts,
+        conn::pool::{CircuitBreakerOpts, ConnectionLimiter, Pool},
+        opts::{Opts, PoolOpts},
+        prelude::*,
+        test_misc::get_opts,
+        Conn, DriverError, Error, PoolConstraints, TxOpts,
     };
 
     macro_rules! conn_ex_field {
@@ -398,6 +687,79 @@ mod test {
         master.disconnect().await
     }
 
+    #[tokio::test]
+    async fn should_exec_drop_concurrently() -> super::Result<()> {
+        let pool =
+            Pool::new(get_opts().pool_opts(
+                PoolOpts::default().with_constraints(PoolConstraints::new(1, 4).unwrap()),
+            ));
+
+        let mut conn = pool.get_conn().await?;
+        conn.query_drop(
+            "CREATE TABLE IF NOT EXISTS mysql_async_concurrent_test (id INT PRIMARY KEY)",
+        )
+        .await?;
+        conn.query_drop("TRUNCATE TABLE mysql_async_concurrent_test")
+            .await?;
+        drop(conn);
+
+        // the last item is a duplicate and should fail
+        let params = (0..1000).chain(Some(0)).map(|id| (id,));
+        let results = pool
+            .exec_drop_concurrent(
+                "INSERT INTO mysql_async_concurrent_test (id) VALUES (?)",
+                params,
+                4,
+            )
+            .await;
+        assert_eq!(results.len(), 1001);
+        assert!(results[..1000].iter().all(Result::is_ok));
+        assert!(results[1000].is_err());
+        assert!(ex_field!(pool, exist) <= 4);
+
+        let mut conn = pool.get_conn().await?;
+        let count: Option<usize> = conn
+            .query_first("SELECT COUNT(*) FROM mysql_async_concurrent_test")
+            .await?;
+        assert_eq!(count, Some(1000));
+        conn.query_drop("DROP TABLE mysql_async_concurrent_test")
+            .await?;
+        drop(conn);
+
+        pool.disconnect().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_discard_previous_generation_on_connection_loss() -> super::Result<()> {
+        const NUM_CONNS: usize = 5;
+        let pool = Pool::new(get_opts());
+
+        let conns = try_join_all((0..NUM_CONNS).map(|_| pool.get_conn())).await?;
+        let ids = conns.iter().map(|conn| conn.id()).collect::<Vec<_>>();
+        drop(conns);
+        assert_eq!(ex_field!(pool, available).len(), NUM_CONNS);
+
+        // Idling connections are alive, but one of the connections taken from the pool is lost.
+        let mut master = crate::Conn::new(get_opts()).await?;
+        let mut conn = pool.get_conn().await?;
+        master.query_drop(format!("KILL {}", conn.id())).await?;
+        assert!(conn.query_drop("DO 1").await.is_err());
+        assert_eq!(pool.inner.epoch(), 1);
+        drop(conn);
+
+        // Every subsequent checkout should yield a fresh connection.
+        let conns = try_join_all((0..NUM_CONNS).map(|_| pool.get_conn())).await?;
+        for conn in conns.iter() {
+            assert!(!ids.contains(&conn.id()));
+        }
+        drop(conns);
+
+        master.disconnect().await?;
+        pool.disconnect().await?;
+        Ok(())
+    }
+
     #[tokio::test]
     #[ignore]
     async fn can_handle_the_pressure() {
@@ -419,6 +781,271 @@ mod test {
         drop(pool);
     }
 
+    #[tokio::test]
+    async fn should_get_conn_for_db() -> super::Result<()> {
+        async fn database(conn: &mut Conn) -> super::Result<String> {
+            Ok(conn.query_first("SELECT DATABASE()").await?.unwrap())
+        }
+
+        async fn init_db_count(conn: &mut Conn) -> super::Result<usize> {
+            let (_, count): (String, usize) = conn
+                .query_first("SHOW SESSION STATUS LIKE 'Com_init_db'")
+                .await?
+                .unwrap();
+            Ok(count)
+        }
+
+        let pool = Pool::new(get_opts());
+        let mut conn_a = pool.get_conn_for_db("mysql").await?;
+        let mut conn_b = pool.get_conn_for_db("information_schema").await?;
+        assert_eq!(database(&mut conn_a).await?, "mysql");
+        assert_eq!(database(&mut conn_b).await?, "information_schema");
+        let (id_a, id_b) = (conn_a.id(), conn_b.id());
+        let init_db_a = init_db_count(&mut conn_a).await?;
+        let init_db_b = init_db_count(&mut conn_b).await?;
+        drop(conn_a);
+        drop(conn_b);
+        assert_eq!(ex_field!(pool, available).len(), 2);
+
+        // idling connections are reused for the same database without `COM_INIT_DB`
+        for _ in 0..2 {
+            let mut conn_a = pool.get_conn_for_db("mysql").await?;
+            let mut conn_b = pool.get_conn_for_db("information_schema").await?;
+            assert_eq!(conn_a.id(), id_a);
+            assert_eq!(conn_b.id(), id_b);
+            assert_eq!(database(&mut conn_a).await?, "mysql");
+            assert_eq!(database(&mut conn_b).await?, "information_schema");
+            assert_eq!(init_db_count(&mut conn_a).await?, init_db_a);
+            assert_eq!(init_db_count(&mut conn_b).await?, init_db_b);
+        }
+
+        // otherwise the default database is changed
+        let conn = pool.get_conn_for_db("mysql").await?;
+        let mut other = pool.get_conn_for_db("mysql").await?;
+        assert_eq!(conn.id(), id_a);
+        assert_eq!(other.id(), id_b);
+        assert_eq!(database(&mut other).await?, "mysql");
+        assert_eq!(init_db_count(&mut other).await?, init_db_b + 1);
+        drop(conn);
+        drop(other);
+
+        // unknown database
+        assert!(pool.get_conn_for_db("nonexistent_db").await.is_err());
+        assert_eq!(ex_field!(pool, exist), 2);
+
+        pool.disconnect().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_reject_writes_on_read_conn() -> super::Result<()> {
+        let constraints = PoolConstraints::new(1, 1).unwrap();
+        let opts = get_opts().pool_opts(PoolOpts::default().with_constraints(constraints));
+        let pool = Pool::new(opts);
+
+        // temporary tables are writable in read-only sessions,
+        // so the test uses a uniquely named table, that is dropped afterwards
+        let table = format!("mysql_async_read_conn_{}", std::process::id());
+        pool.write_conn()
+            .await?
+            .query_drop(format!("CREATE TABLE {} (id INT)", table))
+            .await?;
+
+        let mut conn = pool.read_conn().await?;
+        let read_only = conn.is_read_only();
+        let count: Option<u64> = conn
+            .query_first(format!("SELECT COUNT(*) FROM {}", table))
+            .await?;
+        let read_only_insert = conn
+            .query_drop(format!("INSERT INTO {} VALUES (1)", table))
+            .await;
+        drop(conn);
+
+        // access mode is restored when the connection returns to the pool
+        let mut conn = pool.get_conn().await?;
+        let restored = !conn.is_read_only();
+        let insert = conn
+            .query_drop(format!("INSERT INTO {} VALUES (1)", table))
+            .await;
+        conn.query_drop(format!("DROP TABLE {}", table)).await?;
+        drop(conn);
+
+        assert!(read_only);
+        assert_eq!(count, Some(0));
+        match read_only_insert {
+            // ER_CANT_EXECUTE_IN_READ_ONLY_TRANSACTION
+            Err(Error::Server(ref err)) if err.code == 1792 => (),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(restored);
+        insert?;
+
+        pool.disconnect().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_discard_idling_connections_closed_by_server() -> super::Result<()> {
+        let pool_opts = PoolOpts::default()
+            .with_constraints(PoolConstraints::new(1, 1).unwrap())
+            .with_ttl_check_interval(Duration::from_secs(1))
+            .with_idle_liveness_check(true);
+        let pool = Pool::new(get_opts().pool_opts(pool_opts));
+
+        let mut conn = pool.get_conn().await?;
+        conn.query_drop("DO 1").await?;
+        let id = conn.id();
+        drop(conn);
+        while ex_field!(pool, available).len() != 1 {
+            tokio::time::delay_for(Duration::from_millis(10)).await;
+        }
+
+        let mut master = Conn::new(get_opts()).await?;
+        master.exec_drop("KILL ?", (id,)).await?;
+        master.disconnect().await?;
+
+        // the connection is discarded by the check without being checked out
+        tokio::time::delay_for(Duration::from_millis(1500)).await;
+        assert_eq!(ex_field!(pool, available).len(), 0);
+        assert_eq!(ex_field!(pool, exist), 0);
+
+        let conn = pool.get_conn().await?;
+        assert_ne!(conn.id(), id);
+        drop(conn);
+        pool.disconnect().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_trip_circuit_breaker() -> super::Result<()> {
+        use tokio::{
+            io::split,
+            net::{TcpListener, TcpStream},
+        };
+
+        let backend = Opts::from(get_opts());
+        let backend_addr = format!("{}:{}", backend.ip_or_hostname(), backend.tcp_port());
+        // the port refuses connections until the proxy to the backend is started
+        let port = TcpListener::bind("127.0.0.1:0").await?.local_addr()?.port();
+
+        let breaker =
+            CircuitBreakerOpts::new(3, Duration::from_secs(60), Duration::from_millis(500));
+        let pool_opts = PoolOpts::default()
+            .with_constraints(PoolConstraints::new(0, 10).unwrap())
+            .with_circuit_breaker(breaker);
+        let pool = Pool::new(
+            get_opts()
+                .ip_or_hostname("127.0.0.1")
+                .tcp_port(port)
+                .prefer_socket(false)
+                .pool_opts(pool_opts),
+        );
+
+        for _ in 0..3 {
+            match pool.get_conn().await {
+                Err(Error::Driver(DriverError::CircuitOpen)) | Ok(_) => {
+                    panic!("expected a connection failure")
+                }
+                Err(_) => (),
+            }
+        }
+
+        // the breaker is open, so connection attempts fail fast
+        let started = Instant::now();
+        for _ in 0..10 {
+            match pool.get_conn().await {
+                Err(Error::Driver(DriverError::CircuitOpen)) => (),
+                _ => panic!("expected DriverError::CircuitOpen"),
+            }
+        }
+        assert!(started.elapsed() < Duration::from_millis(100));
+
+        // the backend comes back
+        let mut listener = TcpListener::bind(format!("127.0.0.1:{}", port)).await?;
+        tokio::spawn(async move {
+            while let Ok((client, _)) = listener.accept().await {
+                let backend_addr = backend_addr.clone();
+                tokio::spawn(async move {
+                    let server = TcpStream::connect(backend_addr).await?;
+                    let (mut client_read, mut client_write) = split(client);
+                    let (mut server_read, mut server_write) = split(server);
+                    futures_util::future::try_join(
+                        tokio::io::copy(&mut client_read, &mut server_write),
+                        tokio::io::copy(&mut server_read, &mut client_write),
+                    )
+                    .await?;
+                    Ok::<_, std::io::Error>(())
+                });
+            }
+        });
+
+        // still open until the cooldown is over
+        match pool.get_conn().await {
+            Err(Error::Driver(DriverError::CircuitOpen)) => (),
+            _ => panic!("expected DriverError::CircuitOpen"),
+        }
+
+        tokio::time::delay_for(Duration::from_millis(600)).await;
+        let mut conn1 = pool.get_conn().await?;
+        conn1.query_drop("DO 1").await?;
+        // the probe has succeeded, so the breaker is closed
+        let mut conn2 = pool.get_conn().await?;
+        conn2.query_drop("DO 1").await?;
+
+        drop(conn1);
+        drop(conn2);
+        pool.disconnect().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_share_connection_limiter_between_pools() -> super::Result<()> {
+        let limiter = ConnectionLimiter::new(2);
+        let pool_opts = PoolOpts::default()
+            .with_constraints(PoolConstraints::new(0, 4).unwrap())
+            .with_limiter(limiter.clone());
+        let opts = Opts::from(get_opts().pool_opts(pool_opts));
+        let pool1 = Pool::new(opts.clone());
+        let pool2 = Pool::new(opts.clone());
+
+        let conn1 = pool1.get_conn().await?;
+        let conn2 = pool2.get_conn().await?;
+        assert_eq!(limiter.live_connections(&opts), 2);
+
+        // the cap is reached, so neither pool can create a new connection
+        for pool in &[&pool1, &pool2] {
+            let result = timeout(Duration::from_millis(500), pool.get_conn()).await;
+            assert!(result.is_err());
+        }
+
+        // closed connection releases its permit
+        conn1.disconnect().await?;
+        let conn3 = pool2.get_conn().await?;
+        assert_eq!(limiter.live_connections(&opts), 2);
+
+        // idling connection holds its permit
+        drop(conn2);
+        drop(conn3);
+        let (limiter, opts) = (&limiter, &opts);
+        let conns = try_join_all((0..10).map(|i| {
+            let pool = if i % 2 == 0 { &pool1 } else { &pool2 };
+            async move {
+                let mut conn = pool.get_conn().await?;
+                assert!(limiter.live_connections(opts) <= 2);
+                conn.query_drop("DO 1").await?;
+                conn.disconnect().await
+            }
+        }));
+        // pool1 has no idling connections, so it waits for a permit released by pool2
+        conns.await?;
+        assert!(limiter.live_connections(opts) <= 2);
+
+        pool1.disconnect().await?;
+        pool2.disconnect().await?;
+        assert_eq!(limiter.live_connections(opts), 0);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn should_start_transaction() -> super::Result<()> {
         let constraints = PoolConstraints::new(1, 1).unwrap();
@@ -444,6 +1071,59 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn should_run_scoped_transaction() -> super::Result<()> {
+        let constraints = PoolConstraints::new(1, 1).unwrap();
+        let opts = get_opts().pool_opts(PoolOpts::default().with_constraints(constraints));
+        let pool = Pool::new(opts);
+
+        "CREATE TEMPORARY TABLE tmp(id int)".ignore(&pool).await?;
+
+        // commit
+        let value = pool
+            .transaction(TxOpts::default(), |tx| {
+                Box::pin(async move {
+                    tx.exec_drop("INSERT INTO tmp (id) VALUES (?)", (1_u8,))
+                        .await?;
+                    Ok(42)
+                })
+            })
+            .await?;
+        assert_eq!(value, 42);
+
+        // error rollback
+        let result = pool
+            .transaction(TxOpts::default(), |tx| {
+                Box::pin(async move {
+                    tx.exec_drop("INSERT INTO tmp (id) VALUES (?)", (2_u8,))
+                        .await?;
+                    tx.query_drop("SELECT * FROM non_existing_table").await
+                })
+            })
+            .await;
+        assert!(result.is_err());
+
+        // panic rollback
+        let result = AssertUnwindSafe(pool.transaction::<_, ()>(TxOpts::default(), |tx| {
+            Box::pin(async move {
+                tx.exec_drop("INSERT INTO tmp (id) VALUES (?)", (3_u8,))
+                    .await?;
+                panic!("foo");
+            })
+        }))
+        .catch_unwind()
+        .await;
+        assert!(result.is_err());
+
+        let ids: Vec<u8> = pool.get_conn().await?.query("SELECT id FROM tmp").await?;
+        assert_eq!(ids, vec![1]);
+        assert_eq!(ex_field!(pool, exist), 1);
+
+        pool.get_conn().await?.query_drop("DROP TABLE tmp").await?;
+        pool.disconnect().await?;
+        Ok(())
+    }
+
     #[tokio::test]
     async fn should_check_inactive_connection_ttl() -> super::Result<()> {
         const POOL_MIN: usize = 5;
@@ -622,6 +1302,46 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn should_abort_pool() -> super::Result<()> {
+        async fn is_alive(conn: &mut Conn, id: u32) -> super::Result<bool> {
+            let row: Option<u32> = conn
+                .exec_first(
+                    "SELECT ID FROM information_schema.PROCESSLIST WHERE ID = ?",
+                    (id,),
+                )
+                .await?;
+            Ok(row.is_some())
+        }
+
+        let mut conn = Conn::new(get_opts()).await?;
+        let pool = Pool::new(get_opts());
+        let idle = pool.get_conn().await?;
+        let taken = pool.get_conn().await?;
+        let (idle_id, taken_id) = (idle.id(), taken.id());
+        drop(idle);
+        assert_eq!(ex_field!(pool, available).len(), 1);
+
+        let started = Instant::now();
+        pool.abort();
+        assert_eq!(ex_field!(pool, available).len(), 0);
+        assert!(matches!(
+            pool.get_conn().await,
+            Err(Error::Driver(DriverError::PoolDisconnected))
+        ));
+
+        // taken connection is closed as soon as it's returned
+        drop(taken);
+        while is_alive(&mut conn, idle_id).await? || is_alive(&mut conn, taken_id).await? {
+            assert!(started.elapsed() < Duration::from_secs(1));
+            tokio::time::delay_for(Duration::from_millis(10)).await;
+        }
+
+        pool.disconnect().await?;
+        conn.disconnect().await?;
+        Ok(())
+    }
+
     #[test]
     fn drop_impl_for_conn_should_not_panic_within_unwind() {
         use tokio::runtime;
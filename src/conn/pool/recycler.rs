@@ -18,7 +18,7 @@ use std::{
 };
 
 use super::{IdlingConn, Inner};
-use crate::{queryable::transaction::TxStatus, BoxFuture, Conn, PoolOpts};
+use crate::{BoxFuture, Conn, Health, PoolOpts};
 use tokio::sync::mpsc::UnboundedReceiver;
 
 #[derive(Debug)]
@@ -62,14 +62,12 @@ impl Future for Recycler {
 
         macro_rules! conn_decision {
             ($self:ident, $conn:ident) => {
-                if $conn.inner.stream.is_none() || $conn.inner.disconnected {
+                if $conn.is_healthy() == Health::Dead {
                     // drop unestablished connection
                     $self
                         .discard
                         .push(BoxFuture(Box::pin(::futures_util::future::ok(()))));
-                } else if $conn.inner.tx_status != TxStatus::None
-                    || $conn.inner.pending_result.is_some()
-                {
+                } else if $conn.is_healthy() != Health::Idle {
                     $self
                         .cleaning
                         .push(BoxFuture(Box::pin($conn.cleanup_for_pool())));
@@ -81,7 +79,8 @@ impl Future for Recycler {
                         drop(exchange);
                         $self.discard.push(BoxFuture(Box::pin($conn.close_conn())));
                     } else {
-                        exchange.available.push_back($conn.into());
+                        let epoch = $self.inner.epoch();
+                        exchange.available.push_back(IdlingConn::new($conn, epoch));
                         if let Some(w) = exchange.waiting.pop_front() {
                             w.wake();
                         }
@@ -11,15 +11,19 @@ use futures_util::{
     stream::{StreamExt, StreamFuture},
 };
 use pin_project::pin_project;
-use tokio::time::{self, Interval};
 
 use std::{
     future::Future,
+    mem,
     sync::{atomic::Ordering, Arc},
+    time::Duration,
 };
 
-use super::Inner;
-use crate::PoolOpts;
+use super::{Exchange, Inner};
+use crate::{
+    runtime::{self, Interval},
+    PoolOpts,
+};
 use futures_core::task::{Context, Poll};
 use std::pin::Pin;
 
@@ -28,6 +32,8 @@ use std::pin::Pin;
 /// The purpose of this interval is to remove idling connections that both:
 /// * overflows min bound of the pool;
 /// * idles longer then `inactive_connection_ttl`.
+///
+/// It also removes idling connections closed by the server, if `idle_liveness_check` is `true`.
 #[pin_project]
 pub(crate) struct TtlCheckInterval {
     inner: Arc<Inner>,
@@ -39,7 +45,7 @@ pub(crate) struct TtlCheckInterval {
 impl TtlCheckInterval {
     /// Creates new `TtlCheckInterval`.
     pub fn new(pool_opts: PoolOpts, inner: Arc<Inner>) -> Self {
-        let interval = time::interval(pool_opts.ttl_check_interval()).into_future();
+        let interval = runtime::interval(pool_opts.ttl_check_interval()).into_future();
         Self {
             inner,
             interval,
@@ -51,6 +57,14 @@ impl TtlCheckInterval {
     pub fn check_ttl(&self) {
         let mut exchange = self.inner.exchange.lock().unwrap();
 
+        if self.pool_opts.idle_liveness_check() {
+            Self::discard_closed(&mut exchange);
+        }
+
+        if self.pool_opts.inactive_connection_ttl() == Duration::from_secs(0) {
+            return;
+        }
+
         let num_idling = exchange.available.len();
         let num_to_drop = num_idling.saturating_sub(self.pool_opts.constraints().min());
 
@@ -59,7 +73,7 @@ impl TtlCheckInterval {
             if idling_conn.elapsed() > self.pool_opts.inactive_connection_ttl() {
                 assert!(idling_conn.conn.inner.pool.is_none());
                 let inner = self.inner.clone();
-                tokio::spawn(idling_conn.conn.disconnect().then(move |_| {
+                runtime::spawn(idling_conn.conn.disconnect().then(move |_| {
                     let mut exchange = inner.exchange.lock().unwrap();
                     exchange.exist -= 1;
                     ok::<_, ()>(())
@@ -69,6 +83,34 @@ impl TtlCheckInterval {
             }
         }
     }
+
+    /// Discards idling connections, that are closed by the server.
+    ///
+    /// The check of a connection completes immediately, so it's performed under the lock.
+    fn discard_closed(exchange: &mut Exchange) {
+        let available = mem::take(&mut exchange.available);
+        for mut idling_conn in available {
+            let alive = match idling_conn.conn.inner.stream.as_mut() {
+                Some(stream) => stream
+                    .check()
+                    .now_or_never()
+                    .map(|result| result.is_ok())
+                    .unwrap_or(true),
+                None => false,
+            };
+            if alive {
+                exchange.available.push_back(idling_conn);
+            } else {
+                // there is no one to send `COM_QUIT` to
+                idling_conn.conn.inner.stream.take();
+                idling_conn.conn.inner.disconnected = true;
+                exchange.exist -= 1;
+                if let Some(w) = exchange.waiting.pop_front() {
+                    w.wake();
+                }
+            }
+        }
+    }
 }
 
 impl Future for TtlCheckInterval {
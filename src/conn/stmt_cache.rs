@@ -16,7 +16,10 @@ use std::{
     sync::Arc,
 };
 
-use crate::queryable::stmt::StmtInner;
+use crate::{
+    error::StmtWarmupError,
+    queryable::{stmt::StmtInner, transaction::TxStatus},
+};
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct QueryString(pub Arc<str>);
@@ -36,6 +39,8 @@ impl PartialEq<str> for QueryString {
 pub struct Entry {
     pub stmt: Arc<StmtInner>,
     pub query: QueryString,
+    /// Queries with named parameters, that were translated to `query`.
+    pub named_queries: Vec<QueryString>,
 }
 
 #[derive(Debug)]
@@ -43,6 +48,9 @@ pub struct StmtCache {
     cap: usize,
     cache: LruCache<u32, Entry>,
     query_map: HashMap<QueryString, u32, BuildHasherDefault<XxHash>>,
+    /// Maps a query with named parameters to the statement id and parameter names,
+    /// so that named parameters are parsed once per cached statement.
+    named_map: HashMap<QueryString, (u32, Vec<String>), BuildHasherDefault<XxHash>>,
 }
 
 impl StmtCache {
@@ -51,6 +59,7 @@ impl StmtCache {
             cap,
             cache: LruCache::unbounded(),
             query_map: Default::default(),
+            named_map: Default::default(),
         }
     }
 
@@ -68,6 +77,26 @@ impl StmtCache {
         }
     }
 
+    /// Returns the statement and parameter names for the given query with named parameters.
+    pub fn by_named_query(&mut self, query: &str) -> Option<(&Entry, &[String])> {
+        let (id, named_params) = self.named_map.get(query)?;
+        let entry = self.cache.get(id)?;
+        Some((entry, &named_params[..]))
+    }
+
+    /// Associates the given query with named parameters with the cached statement `id`.
+    ///
+    /// Does nothing if there is no such statement in the cache.
+    pub fn put_named(&mut self, query: Arc<str>, id: u32, named_params: Vec<String>) {
+        if let Some(entry) = self.cache.peek_mut(&id) {
+            let query = QueryString(query);
+            if !entry.named_queries.contains(&query) {
+                entry.named_queries.push(query.clone());
+            }
+            self.named_map.insert(query, (id, named_params));
+        }
+    }
+
     pub fn put(&mut self, query: Arc<str>, stmt: Arc<StmtInner>) -> Option<Arc<StmtInner>> {
         if self.cap == 0 {
             return None;
@@ -76,11 +105,22 @@ impl StmtCache {
         let query = QueryString(query);
 
         self.query_map.insert(query.clone(), stmt.id());
-        self.cache.put(stmt.id(), Entry { stmt, query });
+        let named_queries = match self.cache.pop(&stmt.id()) {
+            Some(old_entry) => old_entry.named_queries,
+            None => Vec::new(),
+        };
+        self.cache.put(
+            stmt.id(),
+            Entry {
+                stmt,
+                query,
+                named_queries,
+            },
+        );
 
         if self.cache.len() > self.cap {
             if let Some((_, entry)) = self.cache.pop_lru() {
-                self.query_map.remove(&*entry.query.0.as_ref());
+                self.forget(&entry);
                 return Some(entry.stmt);
             }
         }
@@ -88,14 +128,30 @@ impl StmtCache {
         None
     }
 
+    /// Removes and returns the least recently used statement.
+    pub fn pop_lru(&mut self) -> Option<Arc<StmtInner>> {
+        let (_, entry) = self.cache.pop_lru()?;
+        self.forget(&entry);
+        Some(entry.stmt)
+    }
+
     pub fn clear(&mut self) {
         self.query_map.clear();
+        self.named_map.clear();
         self.cache.clear();
     }
 
     pub fn remove(&mut self, id: u32) {
         if let Some(entry) = self.cache.pop(&id) {
-            self.query_map.remove::<str>(entry.query.borrow());
+            self.forget(&entry);
+        }
+    }
+
+    /// Removes queries, that refer to the given (already removed) entry.
+    fn forget(&mut self, entry: &Entry) {
+        self.query_map.remove::<str>(entry.query.borrow());
+        for named_query in &entry.named_queries {
+            self.named_map.remove::<str>(named_query.borrow());
         }
     }
 
@@ -111,13 +167,90 @@ impl StmtCache {
 }
 
 impl super::Conn {
+    /// Returns `true` if the transaction-scoped statement cache is in use
+    /// (see [`crate::Opts::isolate_tx_statements`]).
+    fn uses_tx_stmt_cache(&self) -> bool {
+        self.inner.opts.isolate_tx_statements() && self.inner.tx_status != TxStatus::None
+    }
+
     #[cfg(test)]
     pub(crate) fn stmt_cache_ref(&self) -> &StmtCache {
-        &self.inner.stmt_cache
+        if self.uses_tx_stmt_cache() {
+            &self.inner.tx_stmt_cache
+        } else {
+            &self.inner.stmt_cache
+        }
     }
 
     pub(crate) fn stmt_cache_mut(&mut self) -> &mut StmtCache {
-        &mut self.inner.stmt_cache
+        if self.uses_tx_stmt_cache() {
+            &mut self.inner.tx_stmt_cache
+        } else {
+            &mut self.inner.stmt_cache
+        }
+    }
+
+    /// Returns `true` if statement cache is enabled for this connection.
+    ///
+    /// Statement cache is enabled by default (see [`Conn::set_stmt_cache_enabled`]).
+    pub fn stmt_cache_enabled(&self) -> bool {
+        self.inner.stmt_cache_enabled
+    }
+
+    /// Enables or disables statement cache for this connection.
+    ///
+    /// While disabled, statements are prepared without caching, and already cached statements
+    /// are neither used nor evicted. They will be reused as soon as the cache is enabled again.
+    ///
+    /// Unlike [`stmt_cache_size`], this does not resize the cache.
+    ///
+    /// [`stmt_cache_size`]: crate::Opts::stmt_cache_size
+    pub fn set_stmt_cache_enabled(&mut self, enabled: bool) {
+        self.inner.stmt_cache_enabled = enabled;
+    }
+
+    /// Prepares the given queries and puts them into the statement cache.
+    ///
+    /// Use it to pre-warm the cache with a fixed set of queries, so that later calls to `exec*`
+    /// methods don't need to prepare them. Named parameters are parsed once, so later calls
+    /// reuse the positional form of a query.
+    ///
+    /// Stops at the first query, that can't be prepared, and returns it along with the error.
+    /// Note, that statements won't be cached if statement cache is disabled or
+    /// its [`stmt_cache_size`] is less than the number of queries.
+    ///
+    /// ```rust
+    /// # use mysql_async::{prelude::*, test_misc::get_opts, Conn};
+    /// # #[tokio::main]
+    /// # async fn main() -> mysql_async::Result<()> {
+    /// let mut conn = Conn::new(get_opts()).await?;
+    /// conn.warm_statements(&["SELECT :foo", "SELECT :foo, :bar"]).await?;
+    ///
+    /// let err = conn.warm_statements(&["SELECT 1", "SELEC 2"]).await.unwrap_err();
+    /// assert_eq!(err.query, "SELEC 2");
+    /// # conn.disconnect().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`stmt_cache_size`]: crate::Opts::stmt_cache_size
+    pub async fn warm_statements<Q>(
+        &mut self,
+        queries: &[Q],
+    ) -> std::result::Result<(), StmtWarmupError>
+    where
+        Q: AsRef<str>,
+    {
+        for query in queries {
+            let query = query.as_ref();
+            if let Err(source) = self.get_statement(query).await {
+                return Err(StmtWarmupError {
+                    query: query.into(),
+                    source,
+                });
+            }
+        }
+        Ok(())
     }
 
     /// Caches the given statement.
@@ -125,7 +258,7 @@ impl super::Conn {
     /// Returns LRU statement on cache capacity overflow.
     pub(crate) fn cache_stmt(&mut self, stmt: &Arc<StmtInner>) -> Option<Arc<StmtInner>> {
         let query = stmt.raw_query.clone();
-        if self.inner.opts.stmt_cache_size() > 0 {
+        if self.inner.stmt_cache_enabled && self.inner.opts.stmt_cache_size() > 0 {
             self.stmt_cache_mut().put(query, stmt.clone())
         } else {
             None
@@ -136,8 +269,46 @@ impl super::Conn {
     ///
     /// `raw_query` is the query with `?` placeholders (not with `:<name>` placeholders).
     pub(crate) fn get_cached_stmt(&mut self, raw_query: &str) -> Option<Arc<StmtInner>> {
+        if !self.inner.stmt_cache_enabled {
+            return None;
+        }
         self.stmt_cache_mut()
             .by_query(raw_query)
             .map(|entry| entry.stmt.clone())
     }
+
+    /// Returns statement and its named parameters, if cached.
+    ///
+    /// Unlike [`Conn::get_cached_stmt`], `query` is the query as given by the user, so named
+    /// parameters are not parsed again for a statement, that is already in the cache.
+    pub(crate) fn get_cached_named_stmt(
+        &mut self,
+        query: &str,
+    ) -> Option<(Arc<StmtInner>, Option<Vec<String>>)> {
+        if !self.inner.stmt_cache_enabled {
+            return None;
+        }
+        let cache = self.stmt_cache_mut();
+        if let Some(entry) = cache.by_query(query) {
+            // query without named parameters
+            return Some((entry.stmt.clone(), None));
+        }
+        cache
+            .by_named_query(query)
+            .map(|(entry, named_params)| (entry.stmt.clone(), Some(named_params.to_vec())))
+    }
+
+    /// Remembers, that the given query with named parameters was translated to the given
+    /// cached statement.
+    pub(crate) fn cache_named_stmt(
+        &mut self,
+        query: &str,
+        stmt: &StmtInner,
+        named_params: Vec<String>,
+    ) {
+        if self.inner.stmt_cache_enabled {
+            self.stmt_cache_mut()
+                .put_named(query.into(), stmt.id(), named_params);
+        }
+    }
 }
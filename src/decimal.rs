@@ -0,0 +1,201 @@
+// Copyright (c) 2020 Anatoly Ikorsky
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use mysql_common::{
+    rust_decimal::Decimal,
+    value::{
+        convert::{ConvIr, FromValue, FromValueError},
+        Value,
+    },
+};
+
+use std::str::from_utf8;
+
+/// Wrapper that converts a MySQL `DECIMAL` value to `rust_decimal::Decimal` without rounding.
+///
+/// `Decimal` itself rounds a value, that has more significant digits than it can represent
+/// (about 28), while conversion to `ExactDecimal` will fail in this case. Use
+/// `bigdecimal::BigDecimal` if you need the full range of the MySQL `DECIMAL` type.
+///
+/// ```rust
+/// # use mysql_async::{from_value_opt, rust_decimal::Decimal, ExactDecimal, Value};
+/// # use std::str::FromStr;
+/// let value = Value::from("1234567890.0123456789");
+/// let exact = from_value_opt::<ExactDecimal>(value).unwrap();
+/// assert_eq!(exact.into_inner(), Decimal::from_str("1234567890.0123456789").unwrap());
+///
+/// let value = Value::from("12345678901234567890.0123456789");
+/// assert!(from_value_opt::<ExactDecimal>(value).is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ExactDecimal(Decimal);
+
+impl ExactDecimal {
+    /// Returns the wrapped value.
+    pub fn into_inner(self) -> Decimal {
+        self.0
+    }
+}
+
+impl AsRef<Decimal> for ExactDecimal {
+    fn as_ref(&self) -> &Decimal {
+        &self.0
+    }
+}
+
+impl From<Decimal> for ExactDecimal {
+    fn from(decimal: Decimal) -> Self {
+        ExactDecimal(decimal)
+    }
+}
+
+impl From<ExactDecimal> for Value {
+    fn from(ExactDecimal(decimal): ExactDecimal) -> Value {
+        // `Decimal`'s string representation is exact.
+        Value::from(decimal)
+    }
+}
+
+/// Intermediate result of a `Value`-to-`ExactDecimal` conversion.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExactDecimalIr {
+    value: Value,
+    output: Decimal,
+}
+
+impl ConvIr<ExactDecimal> for ExactDecimalIr {
+    fn new(v: Value) -> Result<Self, FromValueError> {
+        let output = match v {
+            Value::Int(x) => Decimal::from(x),
+            Value::UInt(x) => Decimal::from(x),
+            Value::Bytes(ref bytes) => match from_utf8(bytes).map(Decimal::from_str_exact) {
+                Ok(Ok(x)) => x,
+                _ => return Err(FromValueError(v)),
+            },
+            // floating point values are inexact by definition
+            v => return Err(FromValueError(v)),
+        };
+        Ok(ExactDecimalIr { value: v, output })
+    }
+
+    fn commit(self) -> ExactDecimal {
+        ExactDecimal(self.output)
+    }
+
+    fn rollback(self) -> Value {
+        self.value
+    }
+}
+
+impl FromValue for ExactDecimal {
+    type Intermediate = ExactDecimalIr;
+}
+
+#[cfg(test)]
+mod test {
+    use mysql_common::{
+        bigdecimal::BigDecimal,
+        rust_decimal::Decimal,
+        value::{convert::from_value_opt, Value},
+    };
+
+    use std::str::FromStr;
+
+    use super::ExactDecimal;
+    use crate::{prelude::*, test_misc::get_opts, Conn};
+
+    #[test]
+    fn should_convert_exact_decimal() {
+        for x in &[
+            "0",
+            "-1.5",
+            "1234567890123456789.0123456789",
+            "0.0000000001",
+        ] {
+            let exact = from_value_opt::<ExactDecimal>(Value::from(*x)).unwrap();
+            assert_eq!(exact.into_inner(), Decimal::from_str(x).unwrap());
+            assert_eq!(Value::from(exact), Value::from(*x));
+        }
+
+        let exact = from_value_opt::<ExactDecimal>(Value::Int(-42)).unwrap();
+        assert_eq!(exact.into_inner(), Decimal::from(-42));
+        let exact = from_value_opt::<ExactDecimal>(Value::UInt(u64::MAX)).unwrap();
+        assert_eq!(exact.into_inner(), Decimal::from(u64::MAX));
+
+        // too many significant digits
+        let value = Value::from("12345678901234567890.0123456789");
+        assert!(from_value_opt::<ExactDecimal>(value.clone()).is_err());
+        // while the full range is supported by `BigDecimal`
+        let big = from_value_opt::<BigDecimal>(value.clone()).unwrap();
+        assert_eq!(Value::from(big), value);
+
+        // overflow
+        let value = Value::from("99999999999999999999999999999999999.5");
+        assert!(from_value_opt::<ExactDecimal>(value).is_err());
+
+        assert!(from_value_opt::<ExactDecimal>(Value::Double(1.5)).is_err());
+        assert!(from_value_opt::<ExactDecimal>(Value::from("foo")).is_err());
+    }
+
+    #[tokio::test]
+    async fn should_round_trip_decimals() -> super::super::Result<()> {
+        let mut conn = Conn::new(get_opts()).await?;
+        conn.query_drop("CREATE TEMPORARY TABLE decimals (id INT, value DECIMAL(30,10))")
+            .await?;
+
+        // values, that would lose precision as `f64`
+        let values = [
+            "1234567890123456789.0123456789",
+            "-0.1000000001",
+            "9007199254740993.0000000001",
+            "0.0000000000",
+        ];
+        assert_ne!(values[2].parse::<f64>().unwrap().to_string(), values[2]);
+
+        for (id, value) in values.iter().enumerate() {
+            let decimal = ExactDecimal::from(Decimal::from_str(value).unwrap());
+            conn.exec_drop(
+                "INSERT INTO decimals (id, value) VALUES (?, ?)",
+                (id, decimal),
+            )
+            .await?;
+        }
+
+        let text: Vec<(usize, ExactDecimal)> = conn
+            .query("SELECT id, value FROM decimals ORDER BY id")
+            .await?;
+        let binary: Vec<(usize, ExactDecimal)> = conn
+            .exec("SELECT id, value FROM decimals ORDER BY id", ())
+            .await?;
+        for rows in &[text, binary] {
+            for (id, decimal) in rows {
+                assert_eq!(
+                    decimal.into_inner(),
+                    Decimal::from_str(values[*id]).unwrap()
+                );
+            }
+        }
+
+        // the full range of `DECIMAL(30,10)` requires `BigDecimal`
+        let max = "99999999999999999999.9999999999";
+        conn.exec_drop(
+            "INSERT INTO decimals (id, value) VALUES (?, ?)",
+            (values.len(), BigDecimal::from_str(max).unwrap()),
+        )
+        .await?;
+        let row: crate::Row = conn
+            .exec_first("SELECT value FROM decimals WHERE id = ?", (values.len(),))
+            .await?
+            .unwrap();
+        assert_eq!(row.get::<BigDecimal, _>(0).unwrap().to_string(), max);
+        assert!(row.get_opt::<ExactDecimal, _>(0).unwrap().is_err());
+
+        conn.disconnect().await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,301 @@
+// Copyright (c) 2020 Anatoly Ikorsky
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use mysql_common::value::{
+    convert::{ConvIr, FromValue, FromValueError},
+    Value,
+};
+
+use std::{collections::HashSet, hash::Hash, str::from_utf8};
+
+/// Rust enum, that maps to a MySQL `ENUM` column (or to a member of a `SET` column).
+///
+/// Use [`mysql_enum!`] to implement it along with `FromValue` and `Into<Value>`.
+pub trait MysqlEnum: Sized {
+    /// Returns the variant for the given string representation.
+    fn from_mysql_str(value: &str) -> Option<Self>;
+
+    /// Returns the string representation of this variant.
+    fn as_mysql_str(&self) -> &'static str;
+}
+
+/// Defines a Rust enum, that maps to a MySQL `ENUM` column.
+///
+/// Every variant is mapped to its string representation. The enum implements [`MysqlEnum`],
+/// `FromValue` and `Into<Value>`, so it could be used as a parameter and taken from a row.
+/// Unknown strings won't convert (`FromValueError` will contain the value).
+///
+/// ```rust
+/// # use mysql_async::{from_value_opt, mysql_enum, Value};
+/// mysql_enum! {
+///     #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+///     pub enum Size {
+///         Small => "small",
+///         Large => "large",
+///     }
+/// }
+///
+/// assert_eq!(from_value_opt::<Size>(Value::from("large")).unwrap(), Size::Large);
+/// assert_eq!(Value::from(Size::Small), Value::from("small"));
+/// assert!(from_value_opt::<Size>(Value::from("medium")).is_err());
+/// ```
+#[macro_export]
+macro_rules! mysql_enum {
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident {
+            $($(#[$variant_meta:meta])* $variant:ident => $value:literal),+ $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis enum $name {
+            $($(#[$variant_meta])* $variant,)+
+        }
+
+        impl $crate::MysqlEnum for $name {
+            fn from_mysql_str(value: &str) -> ::std::option::Option<Self> {
+                match value {
+                    $($value => ::std::option::Option::Some($name::$variant),)+
+                    _ => ::std::option::Option::None,
+                }
+            }
+
+            fn as_mysql_str(&self) -> &'static str {
+                match *self {
+                    $($name::$variant => $value,)+
+                }
+            }
+        }
+
+        impl $crate::prelude::FromValue for $name {
+            type Intermediate = $crate::EnumIr<$name>;
+        }
+
+        impl ::std::convert::From<$name> for $crate::Value {
+            fn from(x: $name) -> $crate::Value {
+                $crate::Value::from($crate::MysqlEnum::as_mysql_str(&x))
+            }
+        }
+    };
+}
+
+/// Returns the string representation of the given `ENUM` or `SET` value.
+fn value_to_str(v: &Value) -> Option<&str> {
+    match *v {
+        Value::Bytes(ref bytes) => from_utf8(bytes).ok(),
+        _ => None,
+    }
+}
+
+/// Intermediate result of a `Value`-to-`T` conversion, where `T` is a [`MysqlEnum`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumIr<T> {
+    value: Value,
+    output: T,
+}
+
+impl<T: MysqlEnum> ConvIr<T> for EnumIr<T> {
+    fn new(v: Value) -> Result<Self, FromValueError> {
+        match value_to_str(&v).and_then(T::from_mysql_str) {
+            Some(output) => Ok(EnumIr { value: v, output }),
+            None => Err(FromValueError(v)),
+        }
+    }
+
+    fn commit(self) -> T {
+        self.output
+    }
+
+    fn rollback(self) -> Value {
+        self.value
+    }
+}
+
+/// Wrapper for a MySQL `SET` value, where members are [`MysqlEnum`] variants.
+///
+/// MySQL returns `SET` values as a comma-separated list of members.
+///
+/// ```rust
+/// # use mysql_async::{from_value_opt, mysql_enum, Set, Value};
+/// mysql_enum! {
+///     #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+///     enum Flag {
+///         X => "x",
+///         Y => "y",
+///     }
+/// }
+///
+/// let flags = from_value_opt::<Set<Flag>>(Value::from("x,y")).unwrap();
+/// assert!(flags.contains(&Flag::X) && flags.contains(&Flag::Y));
+/// assert!(from_value_opt::<Set<Flag>>(Value::from("")).unwrap().is_empty());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Set<T: Eq + Hash>(pub HashSet<T>);
+
+impl<T: Eq + Hash> Default for Set<T> {
+    fn default() -> Self {
+        Set(HashSet::new())
+    }
+}
+
+impl<T: Eq + Hash> Set<T> {
+    /// Returns the set of members.
+    pub fn into_inner(self) -> HashSet<T> {
+        self.0
+    }
+}
+
+impl<T: Eq + Hash> std::ops::Deref for Set<T> {
+    type Target = HashSet<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T: Eq + Hash> std::ops::DerefMut for Set<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<T: Eq + Hash> std::iter::FromIterator<T> for Set<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Set(iter.into_iter().collect())
+    }
+}
+
+impl<T: MysqlEnum + Eq + Hash> From<Set<T>> for Value {
+    fn from(Set(members): Set<T>) -> Value {
+        let members = members
+            .iter()
+            .map(MysqlEnum::as_mysql_str)
+            .collect::<Vec<_>>();
+        Value::from(members.join(","))
+    }
+}
+
+/// Intermediate result of a `Value`-to-`Set<T>` conversion.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetIr<T: Eq + Hash> {
+    value: Value,
+    output: Set<T>,
+}
+
+impl<T: MysqlEnum + Eq + Hash> ConvIr<Set<T>> for SetIr<T> {
+    fn new(v: Value) -> Result<Self, FromValueError> {
+        let output = match value_to_str(&v) {
+            Some("") => Some(Set::default()),
+            Some(members) => members.split(',').map(T::from_mysql_str).collect(),
+            None => None,
+        };
+        match output {
+            Some(output) => Ok(SetIr { value: v, output }),
+            None => Err(FromValueError(v)),
+        }
+    }
+
+    fn commit(self) -> Set<T> {
+        self.output
+    }
+
+    fn rollback(self) -> Value {
+        self.value
+    }
+}
+
+impl<T: MysqlEnum + Eq + Hash> FromValue for Set<T> {
+    type Intermediate = SetIr<T>;
+}
+
+#[cfg(test)]
+mod test {
+    use mysql_common::value::{convert::from_value_opt, Value};
+
+    use super::Set;
+    use crate::{prelude::*, test_misc::get_opts, Conn, Row};
+
+    crate::mysql_enum! {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        enum Letter {
+            A => "a",
+            B => "b",
+        }
+    }
+
+    crate::mysql_enum! {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        enum Flag {
+            X => "x",
+            Y => "y",
+            Z => "z",
+        }
+    }
+
+    #[test]
+    fn should_convert_enum_and_set() {
+        assert_eq!(
+            from_value_opt::<Letter>(Value::from("a")).unwrap(),
+            Letter::A
+        );
+        assert_eq!(Value::from(Letter::B), Value::from("b"));
+        let err = from_value_opt::<Letter>(Value::from("c")).unwrap_err();
+        assert_eq!(err.0, Value::from("c"));
+        assert!(from_value_opt::<Letter>(Value::Int(1)).is_err());
+
+        let set = from_value_opt::<Set<Flag>>(Value::from("x,z")).unwrap();
+        assert_eq!(set, vec![Flag::X, Flag::Z].into_iter().collect());
+        assert!(from_value_opt::<Set<Flag>>(Value::from(""))
+            .unwrap()
+            .is_empty());
+        assert!(from_value_opt::<Set<Flag>>(Value::from("x,w")).is_err());
+        assert!(from_value_opt::<Set<Flag>>(Value::from("x,")).is_err());
+
+        let set = vec![Flag::Y].into_iter().collect::<Set<_>>();
+        assert_eq!(Value::from(set), Value::from("y"));
+    }
+
+    #[tokio::test]
+    async fn should_decode_enum_and_set_columns() -> super::super::Result<()> {
+        let mut conn = Conn::new(get_opts()).await?;
+        conn.query_drop(
+            "CREATE TEMPORARY TABLE enums (id INT, e ENUM('a','b'), s SET('x','y','z'))",
+        )
+        .await?;
+        conn.exec_drop(
+            "INSERT INTO enums (id, e, s) VALUES (?, ?, ?), (?, ?, ?)",
+            (
+                1,
+                Letter::A,
+                vec![Flag::X, Flag::Z].into_iter().collect::<Set<_>>(),
+                2,
+                Letter::B,
+                Set::<Flag>::default(),
+            ),
+        )
+        .await?;
+
+        let expected = vec![
+            (Letter::A, vec![Flag::X, Flag::Z].into_iter().collect()),
+            (Letter::B, Set::default()),
+        ];
+        let query = "SELECT e, s FROM enums ORDER BY id";
+        let text: Vec<(Letter, Set<Flag>)> = conn.query(query).await?;
+        assert_eq!(text, expected);
+        let binary: Vec<(Letter, Set<Flag>)> = conn.exec(query, ()).await?;
+        assert_eq!(binary, expected);
+
+        // unknown variant is reported
+        let row: Row = conn.query_first("SELECT 'c'").await?.unwrap();
+        let err = row.get_opt::<Letter, _>(0).unwrap().unwrap_err();
+        assert_eq!(err.0, Value::from("c"));
+
+        conn.disconnect().await?;
+        Ok(())
+    }
+}
@@ -14,7 +14,7 @@ use mysql_common::{
 };
 use thiserror::Error;
 
-use std::{borrow::Cow, io, result};
+use std::{borrow::Cow, fmt, io, result};
 
 /// Result type alias for this library.
 pub type Result<T> = result::Result<T, Error>;
@@ -36,16 +36,123 @@ pub enum Error {
 
     #[error("URL error: `{}'", _0)]
     Url(#[source] UrlError),
+
+    #[error("Connection error: {}", _0)]
+    Connect(#[source] ConnectPhaseError),
 }
 
 impl Error {
     /// Returns true if the error means that connection is broken.
     pub fn is_fatal(&self) -> bool {
         match self {
-            Error::Driver(_) | Error::Io(_) | Error::Other(_) | Error::Url(_) => true,
+            Error::Driver(DriverError::PreparedStmtLimitReached { .. })
+            | Error::Driver(DriverError::TransactionRolledBack) => false,
+            Error::Driver(_)
+            | Error::Io(_)
+            | Error::Other(_)
+            | Error::Url(_)
+            | Error::Connect(_) => true,
             Error::Server(_) => false,
         }
     }
+
+    /// Wraps this error into [`ConnectPhaseError`] with the given phase,
+    /// unless it is already wrapped.
+    pub(crate) fn in_phase(self, phase: ConnectPhase) -> Error {
+        match self {
+            err @ Error::Connect(_) => err,
+            err => Error::Connect(ConnectPhaseError {
+                phase,
+                source: Box::new(err),
+            }),
+        }
+    }
+}
+
+/// Phase of the connection establishment (see [`ConnectPhaseError`]).
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum ConnectPhase {
+    /// Resolution of the server address.
+    Resolve,
+    /// Establishment of a TCP connection (or a connection via socket).
+    Tcp,
+    /// TLS handshake.
+    Tls,
+    /// Initial handshake.
+    Handshake,
+    /// Authentication.
+    Auth,
+    /// Initialization of the connection (i.e. reading server settings and running `init` queries).
+    Init,
+}
+
+impl fmt::Display for ConnectPhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let phase = match self {
+            ConnectPhase::Resolve => "resolve",
+            ConnectPhase::Tcp => "TCP",
+            ConnectPhase::Tls => "TLS",
+            ConnectPhase::Handshake => "handshake",
+            ConnectPhase::Auth => "auth",
+            ConnectPhase::Init => "init",
+        };
+        f.write_str(phase)
+    }
+}
+
+/// Error, that occurred during the given phase of the connection establishment.
+#[derive(Debug, Error)]
+#[error("{} phase failed: {}", phase, source)]
+pub struct ConnectPhaseError {
+    pub phase: ConnectPhase,
+    #[source]
+    pub source: Box<Error>,
+}
+
+/// Error, that occurred while preparing one of the statements passed to
+/// [`Conn::warm_statements`](crate::Conn::warm_statements).
+#[derive(Debug, Error)]
+#[error("Can't prepare `{}': {}", query, source)]
+pub struct StmtWarmupError {
+    /// The offending query.
+    pub query: String,
+    #[source]
+    pub source: Error,
+}
+
+impl From<StmtWarmupError> for Error {
+    fn from(err: StmtWarmupError) -> Self {
+        err.source
+    }
+}
+
+/// Error, that occurred while reading one of the result sets of
+/// [`Conn::query_all_sets`](crate::Conn::query_all_sets).
+#[derive(Error)]
+#[error("Can't read all result sets: {}", source)]
+pub struct ResultSetsError<T> {
+    /// Result sets, that were read before the error.
+    pub completed: Vec<Vec<T>>,
+    #[source]
+    pub source: Error,
+}
+
+impl<T> fmt::Debug for ResultSetsError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResultSetsError")
+            .field(
+                "completed",
+                &format_args!("[{} result sets]", self.completed.len()),
+            )
+            .field("source", &self.source)
+            .finish()
+    }
+}
+
+impl<T> From<ResultSetsError<T>> for Error {
+    fn from(err: ResultSetsError<T>) -> Self {
+        err.source
+    }
 }
 
 /// This type enumerates IO errors.
@@ -67,6 +174,41 @@ pub struct ServerError {
     pub state: String,
 }
 
+impl ServerError {
+    /// Parses the message of a duplicate-key error (`ER_DUP_ENTRY`, `1062`).
+    ///
+    /// Returns `None` for other errors or if the message has an unexpected format
+    /// (e.g. if the server error messages are localized).
+    pub fn duplicate_key_info(&self) -> Option<DuplicateKeyInfo> {
+        const PREFIX: &str = "Duplicate entry '";
+        const INFIX: &str = "' for key '";
+
+        if self.code != 1062 {
+            return None;
+        }
+        let rest = self.message.strip_prefix(PREFIX)?;
+        // the value itself may contain the infix
+        let infix_pos = rest.rfind(INFIX)?;
+        let key = rest[infix_pos + INFIX.len()..].strip_suffix('\'')?;
+        // MySql 8.0.19+ qualifies the key name with the table name
+        let key_name = key.rsplit('.').next().unwrap_or(key);
+        Some(DuplicateKeyInfo {
+            key_name: key_name.into(),
+            value: rest[..infix_pos].into(),
+        })
+    }
+}
+
+/// Details of a duplicate-key error (see [`ServerError::duplicate_key_info`]).
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct DuplicateKeyInfo {
+    /// Name of the violated key (e.g. `PRIMARY`), without the table name.
+    pub key_name: String,
+    /// Conflicting value as reported by the server (parts of a composite key are joined
+    /// with `-`, and the value may be truncated).
+    pub value: String,
+}
+
 /// This type enumerates connection URL errors.
 #[derive(Debug, Error, Clone, Eq, PartialEq)]
 pub enum UrlError {
@@ -79,6 +221,9 @@ pub enum UrlError {
     #[error("Invalid value `{}' for connection URL parameter `{}'", value, param)]
     InvalidParamValue { param: String, value: String },
 
+    #[error("Invalid port `{}' in connection URL", port)]
+    InvalidPort { port: String },
+
     #[error("Invalid pool constraints: pool_min ({}) > pool_max ({}).", min, max)]
     InvalidPoolConstraints { min: usize, max: usize },
 
@@ -107,6 +252,12 @@ pub enum DriverError {
     #[error("Error converting from mysql row.")]
     FromRow { row: Row },
 
+    #[error("Value {} is out of range of `{}'.", value, target)]
+    IntegerOverflow { value: String, target: &'static str },
+
+    #[error("Result set of `SELECT FOUND_ROWS()` is missing.")]
+    MissingFoundRows,
+
     #[error("Missing named parameter `{}'.", name)]
     MissingNamedParam { name: String },
 
@@ -128,9 +279,44 @@ pub enum DriverError {
     #[error("Pool was disconnected.")]
     PoolDisconnected,
 
+    #[error(
+        "Connection is refused by the circuit breaker of the pool, \
+         because of recent connection failures."
+    )]
+    CircuitOpen,
+
+    #[error("Query `{}' is rejected: {}", query, reason)]
+    QueryRejected { query: String, reason: String },
+
     #[error("`SET TRANSACTION READ (ONLY|WRITE)' is not supported in your MySQL version.")]
     ReadOnlyTransNotSupported,
 
+    #[error(
+        "Transaction can't be committed, because it was rolled back by the server \
+         (e.g. it was chosen as a deadlock victim)."
+    )]
+    TransactionRolledBack,
+
+    #[error(
+        "Server doesn't support multiple result sets (`CLIENT_MULTI_RESULTS` capability), \
+         so stored procedures can't be called."
+    )]
+    MultiResultsNotSupported,
+
+    #[error(
+        "Server protocol is too old. Minimum supported server version is {}.{}.{}.",
+        min_version.0,
+        min_version.1,
+        min_version.2
+    )]
+    ServerTooOld { min_version: (u16, u16, u16) },
+
+    #[error(
+        "Server requested the pre-4.1 password authentication, which requires \
+         the `legacy-auth' feature."
+    )]
+    OldPasswordAuthRequested,
+
     #[error(
         "Statement takes {} parameters but {} was supplied.",
         required,
@@ -138,17 +324,66 @@ pub enum DriverError {
     )]
     StmtParamsMismatch { required: u16, supplied: u16 },
 
+    #[error("Duration is out of the range of MySql `TIME` type.")]
+    TimeOutOfRange,
+
+    #[error("Timestamp is out of the range supported by MySql.")]
+    TimestampOutOfRange,
+
+    #[error("Server requested too many authentication method switches.")]
+    TooManyAuthSwitches,
+
     #[error("Unexpected packet.")]
     UnexpectedPacket { payload: Vec<u8> },
 
     #[error("Unknown authentication plugin `{}'.", name)]
     UnknownAuthPlugin { name: String },
 
-    #[error("Packet too large.")]
-    PacketTooLarge,
+    #[error(
+        "Packet of {} bytes exceeds max_allowed_packet of {} bytes.",
+        size,
+        max
+    )]
+    PacketTooLarge { size: usize, max: usize },
 
     #[error("Bad compressed packet header.")]
     BadCompressedPacketHeader,
+
+    #[error("`COM_FIELD_LIST' is not supported by the server.")]
+    FieldListNotSupported,
+
+    #[error("Init command `{}' produced {} warning(s).", query, warnings.len())]
+    InitCommandWarnings {
+        query: String,
+        warnings: Vec<crate::Warning>,
+    },
+
+    #[error("`MAX_EXECUTION_TIME' hint is only applicable to `SELECT' statements.")]
+    MaxExecutionTimeRequiresSelect,
+
+    #[error("Server didn't send the initial handshake packet in time.")]
+    HandshakeTimeout,
+
+    #[error(
+        "Server-side limit of prepared statements (`max_prepared_stmt_count') is reached \
+         ({} prepared by this connection). Close unused statements, decrease `stmt_cache_size' \
+         or use `on_prepared_limit=evict_cached'.",
+        prepared
+    )]
+    PreparedStmtLimitReached { prepared: usize },
+
+    #[error(
+        "Connection is out of sync with the server, because processing of a result \
+         was interrupted (i.e. its future was dropped)."
+    )]
+    ConnectionOutOfSync,
+
+    #[error(
+        "Session sql_mode `{}' differs from the expected `{}'.",
+        actual,
+        expected
+    )]
+    SqlModeDrift { expected: String, actual: String },
 }
 
 impl From<DriverError> for Error {
@@ -286,3 +521,76 @@ impl From<PacketCodecError> for Error {
         Error::Io(err.into())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{DuplicateKeyInfo, Error, ServerError};
+    use crate::{prelude::*, test_misc::get_opts, Conn};
+
+    fn dup_entry(message: &str) -> ServerError {
+        ServerError {
+            code: 1062,
+            message: message.into(),
+            state: "23000".into(),
+        }
+    }
+
+    #[test]
+    fn should_parse_duplicate_key_info() {
+        let info = |key_name: &str, value: &str| {
+            Some(DuplicateKeyInfo {
+                key_name: key_name.into(),
+                value: value.into(),
+            })
+        };
+
+        assert_eq!(
+            dup_entry("Duplicate entry '1' for key 'PRIMARY'").duplicate_key_info(),
+            info("PRIMARY", "1")
+        );
+        assert_eq!(
+            dup_entry("Duplicate entry 'foo-42' for key 'users.uniq_name_age'")
+                .duplicate_key_info(),
+            info("uniq_name_age", "foo-42")
+        );
+        assert_eq!(
+            dup_entry("Duplicate entry 'x' for key 'y' for key 't.k'").duplicate_key_info(),
+            info("k", "x' for key 'y")
+        );
+        assert_eq!(dup_entry("Doppelter Eintrag").duplicate_key_info(), None);
+
+        let mut other = dup_entry("Duplicate entry '1' for key 'PRIMARY'");
+        other.code = 1064;
+        assert_eq!(other.duplicate_key_info(), None);
+    }
+
+    #[tokio::test]
+    async fn should_report_duplicate_key_info() -> super::Result<()> {
+        let mut conn = Conn::new(get_opts()).await?;
+        conn.query_drop(
+            "CREATE TEMPORARY TABLE dup_keys (
+                id INT PRIMARY KEY,
+                email VARCHAR(255),
+                UNIQUE KEY uniq_email (email)
+            )",
+        )
+        .await?;
+        conn.query_drop("INSERT INTO dup_keys VALUES (1, 'foo@example.com')")
+            .await?;
+
+        match conn
+            .query_drop("INSERT INTO dup_keys VALUES (2, 'foo@example.com')")
+            .await
+        {
+            Err(Error::Server(err)) => {
+                let info = err.duplicate_key_info().unwrap();
+                assert_eq!(info.key_name, "uniq_email");
+                assert_eq!(info.value, "foo@example.com");
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        conn.disconnect().await?;
+        Ok(())
+    }
+}
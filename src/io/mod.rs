@@ -11,7 +11,10 @@ pub use self::{read_packet::ReadPacket, write_packet::WritePacket};
 use bytes::{BufMut, BytesMut};
 use futures_core::{ready, stream};
 use futures_util::stream::{FuturesUnordered, StreamExt};
-use mysql_common::proto::codec::PacketCodec as PacketCodecInner;
+use mysql_common::proto::codec::{
+    error::PacketCodecError, packet_to_chunks, ChunkDecoder, ChunkInfo,
+    PacketCodec as PacketCodecInner,
+};
 use native_tls::{Certificate, Identity, TlsConnector};
 use pin_project::pin_project;
 use tokio::{io::ErrorKind::Interrupted, net::TcpStream, prelude::*};
@@ -26,8 +29,8 @@ use std::{
         ErrorKind::{NotConnected, Other, UnexpectedEof},
         Read,
     },
-    mem::MaybeUninit,
-    net::ToSocketAddrs,
+    mem::{self, MaybeUninit},
+    net::{SocketAddr, ToSocketAddrs},
     ops::{Deref, DerefMut},
     path::Path,
     pin::Pin,
@@ -35,7 +38,11 @@ use std::{
     time::Duration,
 };
 
-use crate::{error::IoError, io::socket::Socket, opts::SslOpts};
+use crate::{
+    error::IoError,
+    io::socket::Socket,
+    opts::{ProxyProtocolVersion, SslOpts},
+};
 
 macro_rules! with_interrupted {
     ($e:expr) => {
@@ -48,24 +55,137 @@ macro_rules! with_interrupted {
     };
 }
 
+mod proxy_protocol;
 mod read_packet;
 mod socket;
 mod write_packet;
 
+/// Packet buffers with a larger capacity aren't kept for reuse.
+const MAX_REUSED_BUFFER_CAPACITY: usize = 64 * 1024;
+
+#[derive(Debug, Default)]
+pub struct PacketCodec {
+    inner: PacketCodecInner,
+    /// Compression level and threshold, if compression is enabled.
+    compression: Option<(crate::Compression, usize)>,
+    /// Plain protocol codec, that reuses packet buffers (see `Opts::reuse_buffers`).
+    plain: Option<PlainCodec>,
+}
+
+/// Plain protocol codec, that decodes packets into reused buffers.
+///
+/// `mysql_common`'s codec allocates a new buffer for every packet.
 #[derive(Debug, Default)]
-pub struct PacketCodec(PacketCodecInner);
+struct PlainCodec {
+    seq_id: u8,
+    chunk_decoder: ChunkDecoder,
+    /// Buffer for a packet being decoded.
+    buffer: Vec<u8>,
+}
+
+impl PlainCodec {
+    fn decode(
+        &mut self,
+        src: &mut BytesMut,
+        max_allowed_packet: usize,
+    ) -> std::result::Result<Option<Vec<u8>>, PacketCodecError> {
+        loop {
+            match self
+                .chunk_decoder
+                .decode(src, &mut self.buffer, max_allowed_packet)?
+            {
+                Some(ChunkInfo::Middle(seq_id)) | Some(ChunkInfo::Last(seq_id))
+                    if seq_id != self.seq_id =>
+                {
+                    return Err(PacketCodecError::PacketsOutOfSync);
+                }
+                Some(ChunkInfo::Middle(_)) => {
+                    self.seq_id = self.seq_id.wrapping_add(1);
+                    if src.is_empty() {
+                        return Ok(None);
+                    }
+                }
+                Some(ChunkInfo::Last(_)) => {
+                    self.seq_id = self.seq_id.wrapping_add(1);
+                    return Ok(Some(mem::take(&mut self.buffer)));
+                }
+                None => return Ok(None),
+            }
+        }
+    }
+
+    fn encode(
+        &mut self,
+        item: Vec<u8>,
+        dst: &mut BytesMut,
+        max_allowed_packet: usize,
+    ) -> std::result::Result<(), PacketCodecError> {
+        if item.len() > max_allowed_packet {
+            return Err(PacketCodecError::PacketTooLarge);
+        }
+        self.seq_id = packet_to_chunks(self.seq_id, &item, dst);
+        Ok(())
+    }
+}
+
+impl PacketCodec {
+    /// Turns compression on.
+    ///
+    /// Packets shorter than `threshold` will be sent uncompressed.
+    pub fn set_compression(&mut self, level: crate::Compression, threshold: usize) {
+        self.compression = Some((level, threshold));
+        self.inner.compress(level);
+        // packet buffers are reused only by the plain protocol codec
+        self.plain = None;
+    }
+
+    /// Enables or disables reuse of packet buffers (see [`PacketCodec::reuse`]).
+    ///
+    /// Must be called before any packet is decoded or encoded. It's a no-op,
+    /// if compression is enabled.
+    pub fn set_reuse_buffers(&mut self, reuse_buffers: bool) {
+        self.plain = if reuse_buffers && self.compression.is_none() {
+            Some(PlainCodec::default())
+        } else {
+            None
+        };
+    }
+
+    /// Returns the given packet buffer to this codec, so that the next packet
+    /// is decoded into it (if buffers are reused).
+    pub fn reuse(&mut self, mut buffer: Vec<u8>) {
+        if let Some(plain) = self.plain.as_mut() {
+            // chunk data is only written to the buffer, once the whole chunk is received
+            if plain.buffer.is_empty()
+                && buffer.capacity() <= MAX_REUSED_BUFFER_CAPACITY
+                && buffer.capacity() > plain.buffer.capacity()
+            {
+                buffer.clear();
+                plain.buffer = buffer;
+            }
+        }
+    }
+
+    /// Sets sequence id to `0`.
+    pub fn reset_seq_id(&mut self) {
+        if let Some(plain) = self.plain.as_mut() {
+            plain.seq_id = 0;
+        }
+        self.inner.reset_seq_id();
+    }
+}
 
 impl Deref for PacketCodec {
     type Target = PacketCodecInner;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.inner
     }
 }
 
 impl DerefMut for PacketCodec {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+        &mut self.inner
     }
 }
 
@@ -74,7 +194,10 @@ impl Decoder for PacketCodec {
     type Error = IoError;
 
     fn decode(&mut self, src: &mut BytesMut) -> std::result::Result<Option<Self::Item>, IoError> {
-        Ok(self.0.decode(src)?)
+        match self.plain.as_mut() {
+            Some(plain) => Ok(plain.decode(src, self.inner.max_allowed_packet)?),
+            None => Ok(self.inner.decode(src)?),
+        }
     }
 }
 
@@ -82,7 +205,18 @@ impl Encoder<Vec<u8>> for PacketCodec {
     type Error = IoError;
 
     fn encode(&mut self, item: Vec<u8>, dst: &mut BytesMut) -> std::result::Result<(), IoError> {
-        Ok(self.0.encode(item, dst)?)
+        if let Some(plain) = self.plain.as_mut() {
+            return Ok(plain.encode(item, dst, self.inner.max_allowed_packet)?);
+        }
+        if let Some((level, threshold)) = self.compression {
+            // Compressed codec will use the "stored" mode for the level `none`.
+            if item.len() < threshold {
+                self.inner.compress(crate::Compression::none());
+            } else {
+                self.inner.compress(level);
+            }
+        }
+        Ok(self.inner.encode(item, dst)?)
     }
 }
 
@@ -92,6 +226,8 @@ pub(crate) enum Endpoint {
     Plain(Option<TcpStream>),
     Secure(#[pin] tokio_tls::TlsStream<TcpStream>),
     Socket(#[pin] Socket),
+    #[cfg(feature = "mock")]
+    Mock(#[pin] tokio::io::DuplexStream),
 }
 
 /// This future will check that TcpStream is live.
@@ -129,6 +265,8 @@ impl Endpoint {
                 socket.write(&[]).await?;
                 Ok(())
             }
+            #[cfg(feature = "mock")]
+            Endpoint::Mock(_) => Ok(()),
             Endpoint::Plain(None) => unreachable!(),
         }
     }
@@ -141,6 +279,30 @@ impl Endpoint {
         }
     }
 
+    /// Returns the remote address of a TCP connection.
+    pub fn peer_addr(&self) -> Option<SocketAddr> {
+        match *self {
+            Endpoint::Plain(Some(ref stream)) => stream.peer_addr().ok(),
+            Endpoint::Plain(None) => unreachable!(),
+            Endpoint::Secure(ref stream) => stream.get_ref().peer_addr().ok(),
+            Endpoint::Socket(_) => None,
+            #[cfg(feature = "mock")]
+            Endpoint::Mock(_) => None,
+        }
+    }
+
+    /// Returns the local address of a TCP connection.
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        match *self {
+            Endpoint::Plain(Some(ref stream)) => stream.local_addr().ok(),
+            Endpoint::Plain(None) => unreachable!(),
+            Endpoint::Secure(ref stream) => stream.get_ref().local_addr().ok(),
+            Endpoint::Socket(_) => None,
+            #[cfg(feature = "mock")]
+            Endpoint::Mock(_) => None,
+        }
+    }
+
     pub fn set_keepalive_ms(&self, ms: Option<u32>) -> io::Result<()> {
         let ms = ms.map(|val| Duration::from_millis(u64::from(val)));
         match *self {
@@ -148,6 +310,8 @@ impl Endpoint {
             Endpoint::Plain(None) => unreachable!(),
             Endpoint::Secure(ref stream) => stream.get_ref().set_keepalive(ms)?,
             Endpoint::Socket(_) => (/* inapplicable */),
+            #[cfg(feature = "mock")]
+            Endpoint::Mock(_) => (/* inapplicable */),
         }
         Ok(())
     }
@@ -158,6 +322,8 @@ impl Endpoint {
             Endpoint::Plain(None) => unreachable!(),
             Endpoint::Secure(ref stream) => stream.get_ref().set_nodelay(val)?,
             Endpoint::Socket(_) => (/* inapplicable */),
+            #[cfg(feature = "mock")]
+            Endpoint::Mock(_) => (/* inapplicable */),
         }
         Ok(())
     }
@@ -171,6 +337,11 @@ impl Endpoint {
             // inapplicable
             return Ok(());
         }
+        #[cfg(feature = "mock")]
+        if let Endpoint::Mock(_) = self {
+            // inapplicable
+            return Ok(());
+        }
 
         let mut builder = TlsConnector::builder();
         match ssl_opts.root_cert_path() {
@@ -184,9 +355,9 @@ impl Endpoint {
             }
             None => (),
         }
-        if let Some(pkcs12_path) = ssl_opts.pkcs12_path() {
-            let der = std::fs::read(pkcs12_path)?;
-            let identity = Identity::from_pkcs12(&*der, ssl_opts.password().unwrap_or(""))?;
+        if let Some(identity) = ssl_opts.client_identity(&domain)? {
+            let identity =
+                Identity::from_pkcs12(identity.pkcs12_der(), identity.password().unwrap_or(""))?;
             builder.identity(identity);
         }
         builder.danger_accept_invalid_hostnames(ssl_opts.skip_domain_validation());
@@ -199,7 +370,7 @@ impl Endpoint {
                 let tls_stream = tls_connector.connect(&*domain, stream).await?;
                 Endpoint::Secure(tls_stream)
             }
-            Endpoint::Secure(_) | Endpoint::Socket(_) => unreachable!(),
+            _ => unreachable!(),
         };
 
         Ok(())
@@ -218,6 +389,13 @@ impl From<Socket> for Endpoint {
     }
 }
 
+#[cfg(feature = "mock")]
+impl From<tokio::io::DuplexStream> for Endpoint {
+    fn from(stream: tokio::io::DuplexStream) -> Self {
+        Endpoint::Mock(stream)
+    }
+}
+
 impl From<tokio_tls::TlsStream<TcpStream>> for Endpoint {
     fn from(stream: tokio_tls::TlsStream<TcpStream>) -> Self {
         Endpoint::Secure(stream)
@@ -237,6 +415,8 @@ impl AsyncRead for Endpoint {
             }
             EndpointProj::Secure(ref mut stream) => stream.as_mut().poll_read(cx, buf),
             EndpointProj::Socket(ref mut stream) => stream.as_mut().poll_read(cx, buf),
+            #[cfg(feature = "mock")]
+            EndpointProj::Mock(ref mut stream) => stream.as_mut().poll_read(cx, buf),
         })
     }
 
@@ -246,6 +426,8 @@ impl AsyncRead for Endpoint {
             Endpoint::Plain(None) => unreachable!(),
             Endpoint::Secure(stream) => stream.prepare_uninitialized_buffer(buf),
             Endpoint::Socket(stream) => stream.prepare_uninitialized_buffer(buf),
+            #[cfg(feature = "mock")]
+            Endpoint::Mock(stream) => stream.prepare_uninitialized_buffer(buf),
         }
     }
 
@@ -264,6 +446,8 @@ impl AsyncRead for Endpoint {
             }
             EndpointProj::Secure(ref mut stream) => stream.as_mut().poll_read_buf(cx, buf),
             EndpointProj::Socket(ref mut stream) => stream.as_mut().poll_read_buf(cx, buf),
+            #[cfg(feature = "mock")]
+            EndpointProj::Mock(ref mut stream) => stream.as_mut().poll_read_buf(cx, buf),
         })
     }
 }
@@ -281,6 +465,8 @@ impl AsyncWrite for Endpoint {
             }
             EndpointProj::Secure(ref mut stream) => stream.as_mut().poll_write(cx, buf),
             EndpointProj::Socket(ref mut stream) => stream.as_mut().poll_write(cx, buf),
+            #[cfg(feature = "mock")]
+            EndpointProj::Mock(ref mut stream) => stream.as_mut().poll_write(cx, buf),
         })
     }
 
@@ -295,6 +481,8 @@ impl AsyncWrite for Endpoint {
             }
             EndpointProj::Secure(ref mut stream) => stream.as_mut().poll_flush(cx),
             EndpointProj::Socket(ref mut stream) => stream.as_mut().poll_flush(cx),
+            #[cfg(feature = "mock")]
+            EndpointProj::Mock(ref mut stream) => stream.as_mut().poll_flush(cx),
         })
     }
 
@@ -309,14 +497,108 @@ impl AsyncWrite for Endpoint {
             }
             EndpointProj::Secure(ref mut stream) => stream.as_mut().poll_shutdown(cx),
             EndpointProj::Socket(ref mut stream) => stream.as_mut().poll_shutdown(cx),
+            #[cfg(feature = "mock")]
+            EndpointProj::Mock(ref mut stream) => stream.as_mut().poll_shutdown(cx),
         })
     }
 }
 
+/// Wrapper, that counts bytes, that was read from and written to the underlying endpoint.
+#[pin_project]
+#[derive(Debug)]
+pub(crate) struct Counted<T> {
+    #[pin]
+    inner: T,
+    bytes_sent: u64,
+    bytes_received: u64,
+}
+
+impl<T> Counted<T> {
+    fn new(inner: T) -> Self {
+        Self {
+            inner,
+            bytes_sent: 0,
+            bytes_received: 0,
+        }
+    }
+}
+
+impl<T> Deref for Counted<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl<T> DerefMut for Counted<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner
+    }
+}
+
+impl<T: AsyncRead> AsyncRead for Counted<T> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+        buf: &mut [u8],
+    ) -> Poll<std::result::Result<usize, tokio::io::Error>> {
+        let this = self.project();
+        let n = ready!(this.inner.poll_read(cx, buf))?;
+        *this.bytes_received += n as u64;
+        Poll::Ready(Ok(n))
+    }
+
+    unsafe fn prepare_uninitialized_buffer(&self, buf: &mut [MaybeUninit<u8>]) -> bool {
+        self.inner.prepare_uninitialized_buffer(buf)
+    }
+
+    fn poll_read_buf<B>(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+        buf: &mut B,
+    ) -> Poll<std::result::Result<usize, tokio::io::Error>>
+    where
+        B: BufMut,
+    {
+        let this = self.project();
+        let n = ready!(this.inner.poll_read_buf(cx, buf))?;
+        *this.bytes_received += n as u64;
+        Poll::Ready(Ok(n))
+    }
+}
+
+impl<T: AsyncWrite> AsyncWrite for Counted<T> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+        buf: &[u8],
+    ) -> Poll<std::result::Result<usize, tokio::io::Error>> {
+        let this = self.project();
+        let n = ready!(this.inner.poll_write(cx, buf))?;
+        *this.bytes_sent += n as u64;
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<std::result::Result<(), tokio::io::Error>> {
+        self.project().inner.poll_flush(cx)
+    }
+
+    fn poll_shutdown(
+        self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<std::result::Result<(), tokio::io::Error>> {
+        self.project().inner.poll_shutdown(cx)
+    }
+}
+
 /// A Stream, connected to MySql server.
 pub struct Stream {
     closed: bool,
-    pub(crate) codec: Option<Box<Framed<Endpoint, PacketCodec>>>,
+    pub(crate) codec: Option<Box<Framed<Counted<Endpoint>, PacketCodec>>>,
 }
 
 impl fmt::Debug for Stream {
@@ -335,7 +617,7 @@ impl Stream {
 
         Self {
             closed: false,
-            codec: Box::new(Framed::new(endpoint, PacketCodec::default())).into(),
+            codec: Box::new(Framed::new(Counted::new(endpoint), PacketCodec::default())).into(),
         }
     }
 
@@ -358,11 +640,7 @@ impl Stream {
                             err = Some(e);
                         }
                         Ok(stream) => {
-                            return Ok(Stream {
-                                closed: false,
-                                codec: Box::new(Framed::new(stream.into(), PacketCodec::default()))
-                                    .into(),
-                            });
+                            return Ok(Stream::new(stream));
                         }
                     }
                 }
@@ -381,10 +659,35 @@ impl Stream {
         }
     }
 
+    /// Returns a stream over the given in-memory transport.
+    #[cfg(feature = "mock")]
+    pub(crate) fn connect_mock(stream: tokio::io::DuplexStream) -> Stream {
+        Stream::new(stream)
+    }
+
     pub(crate) async fn connect_socket<P: AsRef<Path>>(path: P) -> io::Result<Stream> {
         Ok(Stream::new(Socket::new(path).await?))
     }
 
+    /// Writes PROXY protocol header, if this stream is a plain TCP stream.
+    ///
+    /// Local and peer addresses of the stream will be used if `addrs` is `None`.
+    pub(crate) async fn write_proxy_protocol_header(
+        &mut self,
+        version: ProxyProtocolVersion,
+        addrs: Option<(SocketAddr, SocketAddr)>,
+    ) -> io::Result<()> {
+        let endpoint = self.codec.as_mut().unwrap().get_mut();
+        let (source, destination) = match (&**endpoint, addrs) {
+            (Endpoint::Plain(Some(_)), Some(addrs)) => addrs,
+            (Endpoint::Plain(Some(stream)), None) => (stream.local_addr()?, stream.peer_addr()?),
+            _ => return Ok(()),
+        };
+        let header = proxy_protocol::proxy_protocol_header(version, source, destination);
+        endpoint.write_all(&header).await?;
+        endpoint.flush().await
+    }
+
     pub(crate) fn set_keepalive_ms(&self, ms: Option<u32>) -> io::Result<()> {
         self.codec.as_ref().unwrap().get_ref().set_keepalive_ms(ms)
     }
@@ -393,16 +696,36 @@ impl Stream {
         self.codec.as_ref().unwrap().get_ref().set_tcp_nodelay(val)
     }
 
+    /// Ensures that read and write buffers have at least the given capacity.
+    pub(crate) fn set_buffer_sizes(&mut self, read_buffer_size: usize, write_buffer_size: usize) {
+        let codec = self.codec.take().unwrap();
+        let mut parts = codec.into_parts();
+        parts.read_buf.reserve(read_buffer_size);
+        parts.write_buf.reserve(write_buffer_size);
+        self.codec = Some(Box::new(Framed::from_parts(parts)));
+    }
+
     pub(crate) async fn make_secure(
         &mut self,
         domain: String,
         ssl_opts: SslOpts,
     ) -> crate::error::Result<()> {
         let codec = self.codec.take().unwrap();
-        let FramedParts { mut io, codec, .. } = codec.into_parts();
+        let FramedParts {
+            mut io,
+            codec,
+            mut read_buf,
+            mut write_buf,
+            ..
+        } = codec.into_parts();
         io.make_secure(domain, ssl_opts).await?;
-        let codec = Framed::new(io, codec);
-        self.codec = Some(Box::new(codec));
+        // keep buffers to preserve their capacity
+        read_buf.clear();
+        write_buf.clear();
+        let mut parts = FramedParts::new(io, codec);
+        parts.read_buf = read_buf;
+        parts.write_buf = write_buf;
+        self.codec = Some(Box::new(Framed::from_parts(parts)));
         Ok(())
     }
 
@@ -410,6 +733,14 @@ impl Stream {
         self.codec.as_ref().unwrap().get_ref().is_secure()
     }
 
+    pub(crate) fn peer_addr(&self) -> Option<SocketAddr> {
+        self.codec.as_ref().unwrap().get_ref().peer_addr()
+    }
+
+    pub(crate) fn local_addr(&self) -> Option<SocketAddr> {
+        self.codec.as_ref().unwrap().get_ref().local_addr()
+    }
+
     pub(crate) fn reset_seq_id(&mut self) {
         if let Some(codec) = self.codec.as_mut() {
             codec.codec_mut().reset_seq_id();
@@ -422,15 +753,55 @@ impl Stream {
         }
     }
 
+    pub(crate) fn max_allowed_packet(&self) -> Option<usize> {
+        self.codec
+            .as_ref()
+            .map(|codec| codec.codec().max_allowed_packet)
+    }
+
     pub(crate) fn set_max_allowed_packet(&mut self, max_allowed_packet: usize) {
         if let Some(codec) = self.codec.as_mut() {
             codec.codec_mut().max_allowed_packet = max_allowed_packet;
         }
     }
 
-    pub(crate) fn compress(&mut self, level: crate::Compression) {
+    /// Returns the number of bytes written to the underlying endpoint.
+    ///
+    /// Note that for a secure connection this is the number of bytes passed to the TLS layer.
+    pub(crate) fn bytes_sent(&self) -> u64 {
+        self.codec
+            .as_ref()
+            .map(|codec| codec.get_ref().bytes_sent)
+            .unwrap_or_default()
+    }
+
+    /// Returns the number of bytes read from the underlying endpoint.
+    ///
+    /// Note that for a secure connection this is the number of bytes returned by the TLS layer.
+    pub(crate) fn bytes_received(&self) -> u64 {
+        self.codec
+            .as_ref()
+            .map(|codec| codec.get_ref().bytes_received)
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn compress(&mut self, level: crate::Compression, threshold: usize) {
+        if let Some(codec) = self.codec.as_mut() {
+            codec.codec_mut().set_compression(level, threshold);
+        }
+    }
+
+    /// See [`PacketCodec::set_reuse_buffers`].
+    pub(crate) fn set_reuse_buffers(&mut self, reuse_buffers: bool) {
+        if let Some(codec) = self.codec.as_mut() {
+            codec.codec_mut().set_reuse_buffers(reuse_buffers);
+        }
+    }
+
+    /// See [`PacketCodec::reuse`].
+    pub(crate) fn reuse_buffer(&mut self, buffer: Vec<u8>) {
         if let Some(codec) = self.codec.as_mut() {
-            codec.codec_mut().compress(level);
+            codec.codec_mut().reuse(buffer);
         }
     }
 
@@ -470,3 +841,106 @@ impl stream::Stream for Stream {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use bytes::BytesMut;
+    use tokio_util::codec::{Decoder, Encoder};
+
+    use super::PacketCodec;
+
+    /// Returns the uncompressed length from the header of a compressed packet
+    /// (it is `0` if the payload is stored uncompressed).
+    fn uncompressed_len(packet: &[u8]) -> usize {
+        packet[4] as usize | (packet[5] as usize) << 8 | (packet[6] as usize) << 16
+    }
+
+    #[test]
+    fn should_decode_into_reused_buffers() {
+        let mut codec = PacketCodec::default();
+        codec.set_reuse_buffers(true);
+
+        let mut src = BytesMut::new();
+        for (seq_id, payload) in [&b"foo"[..], &b"barbaz"[..], &b"qux"[..]]
+            .iter()
+            .enumerate()
+        {
+            src.extend_from_slice(&[payload.len() as u8, 0, 0, seq_id as u8]);
+            src.extend_from_slice(payload);
+        }
+
+        let first = codec.decode(&mut src).unwrap().unwrap();
+        assert_eq!(first, b"foo");
+        let ptr = first.as_ptr();
+        codec.reuse(first);
+
+        // the next packet is decoded into the buffer of the previous one
+        let second = codec.decode(&mut src).unwrap().unwrap();
+        assert_eq!(second, b"barbaz");
+        assert_eq!(second.as_ptr(), ptr);
+
+        // too large buffers aren't kept
+        codec.reuse(Vec::with_capacity(super::MAX_REUSED_BUFFER_CAPACITY + 1));
+        let third = codec.decode(&mut src).unwrap().unwrap();
+        assert_eq!(third, b"qux");
+        assert!(third.capacity() <= super::MAX_REUSED_BUFFER_CAPACITY);
+        assert_eq!(codec.decode(&mut src).unwrap(), None);
+
+        // sequence ids are shared by both directions
+        let mut dst = BytesMut::new();
+        codec.encode(b"quux".to_vec(), &mut dst).unwrap();
+        assert_eq!(&dst[..], &b"\x04\x00\x00\x03quux"[..]);
+
+        codec.reset_seq_id();
+        let mut src = BytesMut::from(&b"\x01\x00\x00\x01x"[..]);
+        assert!(codec.decode(&mut src).is_err());
+    }
+
+    #[test]
+    fn should_decode_multi_chunk_packet_into_reused_buffer() {
+        const MAX_PAYLOAD_LEN: usize = 0x00ff_ffff;
+
+        let mut codec = PacketCodec::default();
+        codec.set_reuse_buffers(true);
+        codec.max_allowed_packet = 2 * MAX_PAYLOAD_LEN;
+        let payload = vec![0x2a; MAX_PAYLOAD_LEN + 10];
+
+        let mut src = BytesMut::new();
+        codec.encode(payload.clone(), &mut src).unwrap();
+        codec.reset_seq_id();
+
+        // input is fed in parts, that don't match chunk boundaries
+        let mut input = BytesMut::new();
+        let (head, tail) = src.split_at(src.len() - 3);
+        for part in head.chunks(4093) {
+            input.extend_from_slice(part);
+            assert_eq!(codec.decode(&mut input).unwrap(), None);
+        }
+        input.extend_from_slice(tail);
+        assert_eq!(codec.decode(&mut input).unwrap(), Some(payload));
+    }
+
+    #[test]
+    fn should_honor_compression_threshold() {
+        let mut codec = PacketCodec::default();
+        codec.set_compression(crate::Compression::default(), 100);
+
+        let mut dst = BytesMut::new();
+        codec.encode(vec![0x03; 60], &mut dst).unwrap();
+        assert_eq!(uncompressed_len(&dst), 0);
+        assert_eq!(dst.len(), 7 + 4 + 60);
+
+        codec.reset_seq_id();
+        let mut dst = BytesMut::new();
+        codec.encode(vec![0x03; 1000], &mut dst).unwrap();
+        assert_eq!(uncompressed_len(&dst), 4 + 1000);
+        assert!(dst.len() < 7 + 4 + 1000);
+
+        // packets at or above the default threshold are compressed
+        let mut codec = PacketCodec::default();
+        codec.set_compression(crate::Compression::default(), 50);
+        let mut dst = BytesMut::new();
+        codec.encode(vec![0x03; 60], &mut dst).unwrap();
+        assert_eq!(uncompressed_len(&dst), 4 + 60);
+    }
+}
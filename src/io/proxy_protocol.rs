@@ -0,0 +1,136 @@
+// Copyright (c) 2020 Anatoly Ikorsky
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::net::{IpAddr, SocketAddr};
+
+use crate::opts::ProxyProtocolVersion;
+
+/// Signature of the PROXY protocol v2 header.
+const V2_SIGNATURE: &[u8; 12] = b"\r\n\r\n\0\r\nQUIT\n";
+
+/// Returns both addresses in the same family.
+///
+/// IPv4 address is mapped to IPv6, if the other one is IPv6.
+fn same_family(source: SocketAddr, destination: SocketAddr) -> (SocketAddr, SocketAddr) {
+    let to_v6 = |addr: SocketAddr| match addr.ip() {
+        IpAddr::V4(ip) => SocketAddr::new(IpAddr::V6(ip.to_ipv6_mapped()), addr.port()),
+        IpAddr::V6(_) => addr,
+    };
+    match (source, destination) {
+        (SocketAddr::V4(_), SocketAddr::V4(_)) | (SocketAddr::V6(_), SocketAddr::V6(_)) => {
+            (source, destination)
+        }
+        _ => (to_v6(source), to_v6(destination)),
+    }
+}
+
+/// Returns PROXY protocol header for a TCP connection from `source` to `destination`.
+pub(crate) fn proxy_protocol_header(
+    version: ProxyProtocolVersion,
+    source: SocketAddr,
+    destination: SocketAddr,
+) -> Vec<u8> {
+    let (source, destination) = same_family(source, destination);
+    match version {
+        ProxyProtocolVersion::V1 => {
+            let protocol = if source.is_ipv4() { "TCP4" } else { "TCP6" };
+            format!(
+                "PROXY {} {} {} {} {}\r\n",
+                protocol,
+                source.ip(),
+                destination.ip(),
+                source.port(),
+                destination.port(),
+            )
+            .into_bytes()
+        }
+        ProxyProtocolVersion::V2 => {
+            let mut header = Vec::with_capacity(52);
+            header.extend_from_slice(V2_SIGNATURE);
+            // version 2, PROXY command
+            header.push(0x21);
+            match (source.ip(), destination.ip()) {
+                (IpAddr::V4(src), IpAddr::V4(dst)) => {
+                    // AF_INET, STREAM
+                    header.push(0x11);
+                    header.extend_from_slice(&12_u16.to_be_bytes());
+                    header.extend_from_slice(&src.octets());
+                    header.extend_from_slice(&dst.octets());
+                }
+                (IpAddr::V6(src), IpAddr::V6(dst)) => {
+                    // AF_INET6, STREAM
+                    header.push(0x21);
+                    header.extend_from_slice(&36_u16.to_be_bytes());
+                    header.extend_from_slice(&src.octets());
+                    header.extend_from_slice(&dst.octets());
+                }
+                _ => unreachable!("addresses are of the same family"),
+            }
+            header.extend_from_slice(&source.port().to_be_bytes());
+            header.extend_from_slice(&destination.port().to_be_bytes());
+            header
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::proxy_protocol_header;
+    use crate::ProxyProtocolVersion;
+
+    #[test]
+    fn should_build_v1_header() {
+        let header = proxy_protocol_header(
+            ProxyProtocolVersion::V1,
+            "192.168.0.1:56324".parse().unwrap(),
+            "10.0.0.2:3306".parse().unwrap(),
+        );
+        assert_eq!(header, b"PROXY TCP4 192.168.0.1 10.0.0.2 56324 3306\r\n");
+
+        let header = proxy_protocol_header(
+            ProxyProtocolVersion::V1,
+            "192.168.0.1:56324".parse().unwrap(),
+            "[::1]:3306".parse().unwrap(),
+        );
+        assert_eq!(
+            header,
+            &b"PROXY TCP6 ::ffff:192.168.0.1 ::1 56324 3306\r\n"[..]
+        );
+    }
+
+    #[test]
+    fn should_build_v2_header() {
+        let header = proxy_protocol_header(
+            ProxyProtocolVersion::V2,
+            "192.168.0.1:56324".parse().unwrap(),
+            "10.0.0.2:3306".parse().unwrap(),
+        );
+        assert_eq!(
+            header,
+            &[
+                0x0d, 0x0a, 0x0d, 0x0a, 0x00, 0x0d, 0x0a, 0x51, 0x55, 0x49, 0x54, 0x0a, // sig
+                0x21, 0x11, 0x00, 0x0c, // ver/cmd, fam, len
+                192, 168, 0, 1, // src addr
+                10, 0, 0, 2, // dst addr
+                0xdc, 0x04, // src port
+                0x0c, 0xea, // dst port
+            ][..]
+        );
+
+        let header = proxy_protocol_header(
+            ProxyProtocolVersion::V2,
+            "[::1]:1".parse().unwrap(),
+            "[::2]:2".parse().unwrap(),
+        );
+        assert_eq!(header.len(), 16 + 36);
+        assert_eq!(&header[12..16], &[0x21, 0x21, 0x00, 0x24]);
+        assert_eq!(header[16 + 15], 1);
+        assert_eq!(header[32 + 15], 2);
+        assert_eq!(&header[48..], &[0, 1, 0, 2]);
+    }
+}
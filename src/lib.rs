@@ -98,21 +98,37 @@
 #[cfg(feature = "nightly")]
 extern crate test;
 
-pub use mysql_common::{chrono, constants as consts, params, time, uuid};
+pub use mysql_common::{bigdecimal, chrono, constants as consts, params, rust_decimal, time, uuid};
 
 use std::{future::Future, pin::Pin};
 
 #[macro_use]
 mod macros;
+mod bit;
 mod conn;
 mod connection_like;
+mod decimal;
+mod enums;
 /// Errors used in this crate
 mod error;
 mod io;
 mod local_infile_handler;
+#[cfg(feature = "mock")]
+pub mod mock;
 mod opts;
+mod params_builder;
 mod query;
 mod queryable;
+mod row;
+mod runtime;
+mod slow_query;
+mod time_span;
+mod timestamp;
+mod value_ext;
+mod vector;
+mod warning;
+mod year;
+mod zero_date;
 
 #[must_use = "futures do nothing unless you `.await` or poll them"]
 pub struct BoxFuture<'a, T>(Pin<Box<dyn Future<Output = Result<T>> + Send + 'a>>);
@@ -140,13 +156,26 @@ impl<'a, T> std::fmt::Debug for BoxFuture<'a, T> {
 }
 
 #[doc(inline)]
-pub use self::conn::Conn;
+pub use self::bit::Bit;
 
 #[doc(inline)]
-pub use self::conn::pool::Pool;
+pub use self::conn::{
+    abort_handle::ConnAbortHandle,
+    handshake_capture::{FrameDirection, HandshakeFrame},
+    BulkDmlInfo, Conn, Health, UpsertOutcome,
+};
+
+#[doc(inline)]
+pub use self::enums::{EnumIr, MysqlEnum, Set, SetIr};
 
 #[doc(inline)]
-pub use self::error::{DriverError, Error, IoError, ParseError, Result, ServerError, UrlError};
+pub use self::conn::pool::{CircuitBreakerOpts, ConnectionLimiter, Pool, PoolBuilder};
+
+#[doc(inline)]
+pub use self::error::{
+    ConnectPhase, ConnectPhaseError, DriverError, DuplicateKeyInfo, Error, IoError, ParseError,
+    Result, ResultSetsError, ServerError, StmtWarmupError, UrlError,
+};
 
 #[doc(inline)]
 pub use self::query::QueryWithParams;
@@ -156,8 +185,10 @@ pub use self::queryable::transaction::IsolationLevel;
 
 #[doc(inline)]
 pub use self::opts::{
-    Opts, OptsBuilder, PoolConstraints, PoolOpts, SslOpts, DEFAULT_INACTIVE_CONNECTION_TTL,
-    DEFAULT_POOL_CONSTRAINTS, DEFAULT_STMT_CACHE_SIZE, DEFAULT_TTL_CHECK_INTERVAL,
+    ClientCertResolver, ClientIdentity, Opts, OptsBuilder, PoolConstraints, PoolOpts,
+    PreparedStmtLimitPolicy, ProxyProtocolVersion, QueryRewriter, SlowQueryLogger, SslOpts,
+    DEFAULT_BUFFER_SIZE, DEFAULT_INACTIVE_CONNECTION_TTL, DEFAULT_POOL_CONSTRAINTS,
+    DEFAULT_STMT_CACHE_SIZE, DEFAULT_TTL_CHECK_INTERVAL,
 };
 
 #[doc(inline)]
@@ -175,6 +206,9 @@ pub use mysql_common::row::Row;
 #[doc(inline)]
 pub use mysql_common::params::Params;
 
+#[doc(inline)]
+pub use self::params_builder::ParamsBuilder;
+
 #[doc(inline)]
 pub use mysql_common::value::Value;
 
@@ -188,7 +222,7 @@ pub use mysql_common::value::convert::{from_value, from_value_opt, FromValueErro
 pub use mysql_common::value::json::{Deserialized, Serialized};
 
 #[doc(inline)]
-pub use self::queryable::query_result::QueryResult;
+pub use self::queryable::query_result::{BufferedResult, BufferedResultSet, QueryResult};
 
 #[doc(inline)]
 pub use self::queryable::transaction::{Transaction, TxOpts};
@@ -197,7 +231,34 @@ pub use self::queryable::transaction::{Transaction, TxOpts};
 pub use self::queryable::{BinaryProtocol, TextProtocol};
 
 #[doc(inline)]
-pub use self::queryable::stmt::Statement;
+pub use self::queryable::stmt::{OwnedStatement, Prepared, Statement};
+
+#[doc(inline)]
+pub use self::row::RawRow;
+
+#[doc(inline)]
+pub use self::timestamp::Timestamp;
+
+#[doc(inline)]
+pub use self::time_span::{TimeSpan, TimeSpanIr};
+
+#[doc(inline)]
+pub use self::decimal::ExactDecimal;
+
+#[doc(inline)]
+pub use self::vector::{Vector, VectorIr};
+
+#[doc(inline)]
+pub use self::zero_date::{MaybeZeroDate, MaybeZeroDateIr};
+
+#[doc(inline)]
+pub use self::year::{Year, YearIr};
+
+#[doc(inline)]
+pub use self::slow_query::SlowQuery;
+
+#[doc(inline)]
+pub use self::warning::Warning;
 
 /// Futures used in this crate
 pub mod futures {
@@ -213,6 +274,10 @@ pub mod prelude {
     #[doc(inline)]
     pub use crate::queryable::Queryable;
     #[doc(inline)]
+    pub use crate::row::RowExt;
+    #[doc(inline)]
+    pub use crate::value_ext::ValueExt;
+    #[doc(inline)]
     pub use mysql_common::row::convert::FromRow;
     #[doc(inline)]
     pub use mysql_common::value::convert::{ConvIr, FromValue, ToValue};
@@ -221,6 +286,7 @@ pub mod prelude {
     pub trait StatementLike: crate::queryable::stmt::StatementLike {}
     impl StatementLike for str {}
     impl StatementLike for crate::Statement {}
+    impl StatementLike for crate::OwnedStatement {}
 
     /// Everything that is a connection.
     pub trait ToConnection<'a, 't: 'a>: crate::connection_like::ToConnection<'a, 't> {}
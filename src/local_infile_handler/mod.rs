@@ -76,6 +76,12 @@ pub trait LocalInfileHandler: Sync + Send {
     /// `file_name` is the file name in `LOAD DATA LOCAL INFILE '<file name>' INTO TABLE ...;`
     /// query.
     fn handle(&self, file_name: &[u8]) -> InfileHandlerFuture;
+
+    /// Called every time a chunk of the file is written to the server.
+    ///
+    /// `bytes_sent` is the total number of bytes of `file_name` sent so far. It is called on
+    /// the connection task, so it should return quickly. Does nothing by default.
+    fn progress(&self, _file_name: &[u8], _bytes_sent: u64) {}
 }
 
 pub type InfileHandlerFuture = Pin<
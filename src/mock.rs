@@ -0,0 +1,466 @@
+// Copyright (c) 2020 Anatoly Ikorsky
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! In-memory mock of a MySql server (requires `mock` feature).
+//!
+//! [`MockServer`] is connected to a [`Conn`] via an in-memory transport, so tests
+//! can script server responses and assert packets sent by the driver without a real server.
+//!
+//! ```
+//! # use mysql_async::{mock::MockServer, prelude::*, OptsBuilder, Result};
+//! # #[tokio::main]
+//! # async fn main() -> Result<()> {
+//! let opts = OptsBuilder::default().user(Some("root"));
+//! let (mut server, conn) = MockServer::connect(opts);
+//!
+//! let client = async move {
+//!     let mut conn = conn.await?;
+//!     let result: Option<String> = conn.query_first("SELECT 'foo'").await?;
+//!     conn.disconnect().await?;
+//!     Ok::<_, mysql_async::Error>(result)
+//! };
+//!
+//! let server = async move {
+//!     server.accept().await?;
+//!     // assert the exact packet sent by the driver
+//!     assert_eq!(server.read_command().await?, b"\x03SELECT 'foo'");
+//!     server
+//!         .write_text_result_set(&["foo"], &[vec![Some("foo")]])
+//!         .await?;
+//!     // COM_QUIT
+//!     assert_eq!(server.read_command().await?, b"\x01");
+//!     Ok::<_, std::io::Error>(())
+//! };
+//!
+//! let (result, server) = futures_util::future::join(client, server).await;
+//! server?;
+//! assert_eq!(result?, Some("foo".into()));
+//! # Ok(())
+//! # }
+//! ```
+
+use tokio::io::{duplex, AsyncReadExt, AsyncWriteExt, DuplexStream};
+
+use std::io;
+
+use crate::{
+    consts::{CapabilityFlags, ColumnType, StatusFlags},
+    BoxFuture, Conn, Opts,
+};
+
+/// Size of the in-memory transport buffer.
+const BUFFER_SIZE: usize = 1024 * 1024;
+
+/// Maximum payload length of a single chunk of a packet.
+const MAX_PAYLOAD_LEN: usize = 0x00ff_ffff;
+
+/// `utf8_general_ci`
+const UTF8_GENERAL_CI: u8 = 33;
+
+/// Capabilities, that [`MockServer`] advertises by default.
+pub const DEFAULT_MOCK_CAPABILITIES: CapabilityFlags = CapabilityFlags::from_bits_truncate(
+    CapabilityFlags::CLIENT_LONG_PASSWORD.bits()
+        | CapabilityFlags::CLIENT_CONNECT_WITH_DB.bits()
+        | CapabilityFlags::CLIENT_PROTOCOL_41.bits()
+        | CapabilityFlags::CLIENT_TRANSACTIONS.bits()
+        | CapabilityFlags::CLIENT_SECURE_CONNECTION.bits()
+        | CapabilityFlags::CLIENT_MULTI_STATEMENTS.bits()
+        | CapabilityFlags::CLIENT_MULTI_RESULTS.bits()
+        | CapabilityFlags::CLIENT_PS_MULTI_RESULTS.bits()
+        | CapabilityFlags::CLIENT_PLUGIN_AUTH.bits()
+        | CapabilityFlags::CLIENT_DEPRECATE_EOF.bits(),
+);
+
+/// In-memory mock of a MySql server.
+///
+/// Server side of the protocol is driven manually by a test, e.g. via [`MockServer::accept`],
+/// [`MockServer::read_command`] and `write_*` methods.
+#[derive(Debug)]
+pub struct MockServer {
+    stream: DuplexStream,
+    seq_id: u8,
+    capabilities: CapabilityFlags,
+    status: StatusFlags,
+}
+
+impl MockServer {
+    /// Returns a mock server along with a future, that connects to it.
+    ///
+    /// The future won't resolve unless the server side of the connection phase
+    /// is driven concurrently (see [`MockServer::accept`]).
+    pub fn connect<T: Into<Opts>>(opts: T) -> (MockServer, BoxFuture<'static, Conn>) {
+        let (client, server) = duplex(BUFFER_SIZE);
+        let server = MockServer {
+            stream: server,
+            seq_id: 0,
+            capabilities: DEFAULT_MOCK_CAPABILITIES,
+            status: StatusFlags::SERVER_STATUS_AUTOCOMMIT,
+        };
+        (server, Conn::new_mock(opts.into(), client))
+    }
+
+    /// Defines capabilities, that server will advertise in the initial handshake
+    /// (defaults to [`DEFAULT_MOCK_CAPABILITIES`]).
+    pub fn with_capabilities(mut self, capabilities: CapabilityFlags) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    /// Returns server capabilities.
+    ///
+    /// After [`MockServer::read_handshake_response`] this is the set of negotiated capabilities.
+    pub fn capabilities(&self) -> CapabilityFlags {
+        self.capabilities
+    }
+
+    /// Reads the next packet sent by the driver and returns its payload.
+    ///
+    /// Returns an error if the packet is out of sync.
+    pub async fn read_packet(&mut self) -> io::Result<Vec<u8>> {
+        let mut payload = Vec::new();
+        loop {
+            let mut header = [0_u8; 4];
+            self.stream.read_exact(&mut header).await?;
+            let len = u32::from_le_bytes([header[0], header[1], header[2], 0]) as usize;
+            if header[3] != self.seq_id {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "packets out of sync (expected seq_id {}, got {})",
+                        self.seq_id, header[3]
+                    ),
+                ));
+            }
+            self.seq_id = self.seq_id.wrapping_add(1);
+
+            let offset = payload.len();
+            payload.resize(offset + len, 0);
+            self.stream.read_exact(&mut payload[offset..]).await?;
+
+            if len < MAX_PAYLOAD_LEN {
+                return Ok(payload);
+            }
+        }
+    }
+
+    /// Reads the next command sent by the driver and returns its payload
+    /// (i.e. command byte followed by command data).
+    pub async fn read_command(&mut self) -> io::Result<Vec<u8>> {
+        self.seq_id = 0;
+        self.read_packet().await
+    }
+
+    /// Writes a packet with the given payload.
+    pub async fn write_packet(&mut self, payload: &[u8]) -> io::Result<()> {
+        let mut last_chunk_len = 0;
+        for chunk in payload.chunks(MAX_PAYLOAD_LEN) {
+            self.write_chunk(chunk).await?;
+            last_chunk_len = chunk.len();
+        }
+        // empty chunk terminates a packet, which length is a multiple of `MAX_PAYLOAD_LEN`
+        if last_chunk_len == 0 || last_chunk_len == MAX_PAYLOAD_LEN {
+            self.write_chunk(&[]).await?;
+        }
+        self.stream.flush().await
+    }
+
+    async fn write_chunk(&mut self, chunk: &[u8]) -> io::Result<()> {
+        let len = (chunk.len() as u32).to_le_bytes();
+        let header = [len[0], len[1], len[2], self.seq_id];
+        self.seq_id = self.seq_id.wrapping_add(1);
+        self.stream.write_all(&header).await?;
+        self.stream.write_all(chunk).await
+    }
+
+    /// Writes the initial handshake packet (protocol version 10).
+    pub async fn write_handshake(&mut self, auth_plugin: &str, nonce: &[u8; 20]) -> io::Result<()> {
+        self.seq_id = 0;
+
+        let capabilities = self.capabilities.bits().to_le_bytes();
+        let mut payload = vec![0x0a];
+        payload.extend_from_slice(b"8.0.0-mock\0");
+        // connection id
+        payload.extend_from_slice(&1_u32.to_le_bytes());
+        payload.extend_from_slice(&nonce[..8]);
+        payload.push(0x00);
+        payload.extend_from_slice(&capabilities[..2]);
+        payload.push(UTF8_GENERAL_CI);
+        payload.extend_from_slice(&self.status.bits().to_le_bytes());
+        payload.extend_from_slice(&capabilities[2..]);
+        payload.push(nonce.len() as u8 + 1);
+        payload.extend_from_slice(&[0_u8; 10]);
+        payload.extend_from_slice(&nonce[8..]);
+        payload.push(0x00);
+        payload.extend_from_slice(auth_plugin.as_bytes());
+        payload.push(0x00);
+
+        self.write_packet(&payload).await
+    }
+
+    /// Reads the handshake response packet and returns its payload.
+    ///
+    /// Server capabilities will be narrowed down to the negotiated set.
+    pub async fn read_handshake_response(&mut self) -> io::Result<Vec<u8>> {
+        let payload = self.read_packet().await?;
+        if payload.len() < 4 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "invalid handshake response",
+            ));
+        }
+        let client_capabilities = CapabilityFlags::from_bits_truncate(u32::from_le_bytes([
+            payload[0], payload[1], payload[2], payload[3],
+        ]));
+        self.capabilities &= client_capabilities;
+        Ok(payload)
+    }
+
+    /// Performs the server side of the connection phase using `mysql_native_password`
+    /// authentication plugin, that accepts any credentials.
+    ///
+    /// Also answers the query, that the driver performs right after the connection phase
+    /// (see [`MockServer::answer_settings_query`]).
+    ///
+    /// Returns payload of the handshake response packet.
+    pub async fn accept(&mut self) -> io::Result<Vec<u8>> {
+        self.write_handshake("mysql_native_password", &[0x2a; 20])
+            .await?;
+        let handshake_response = self.read_handshake_response().await?;
+        self.write_ok().await?;
+        self.answer_settings_query().await?;
+        Ok(handshake_response)
+    }
+
+    /// Answers the query, that the driver performs right after the connection phase
+    /// to read server settings (e.g. `max_allowed_packet`).
+    pub async fn answer_settings_query(&mut self) -> io::Result<()> {
+        let query = self.read_command().await?;
+        let query = String::from_utf8_lossy(&query[1..]);
+        let columns = query
+            .split(',')
+            .map(|column| column.trim().trim_start_matches("SELECT "))
+            .collect::<Vec<_>>();
+        let row = columns
+            .iter()
+            .map(|column| match *column {
+                "@@max_allowed_packet" => Some("4194304"),
+                "@@wait_timeout" => Some("28800"),
+                "@@innodb_rollback_on_timeout" => Some("0"),
+                _ => None,
+            })
+            .collect();
+        self.write_text_result_set(&columns, &[row]).await
+    }
+
+    /// Writes an OK packet.
+    pub async fn write_ok(&mut self) -> io::Result<()> {
+        let mut payload = vec![0x00];
+        self.write_ok_body(&mut payload);
+        self.write_packet(&payload).await
+    }
+
+    /// Writes an ERR packet.
+    pub async fn write_err(&mut self, code: u16, state: &str, message: &str) -> io::Result<()> {
+        let mut payload = vec![0xff];
+        payload.extend_from_slice(&code.to_le_bytes());
+        payload.push(b'#');
+        payload.extend_from_slice(state.as_bytes());
+        payload.extend_from_slice(message.as_bytes());
+        self.write_packet(&payload).await
+    }
+
+    /// Writes a text result set with the given columns and rows (`None` stands for `NULL`).
+    ///
+    /// All columns are of `VARCHAR` type.
+    pub async fn write_text_result_set(
+        &mut self,
+        columns: &[&str],
+        rows: &[Vec<Option<&str>>],
+    ) -> io::Result<()> {
+        self.write_columns(columns).await?;
+        for row in rows {
+            self.write_text_row(row).await?;
+        }
+        self.write_result_set_end().await
+    }
+
+    /// Writes the beginning of a text result set, i.e. column count and column definitions
+    /// (see [`MockServer::write_text_result_set`]).
+    pub async fn write_columns(&mut self, columns: &[&str]) -> io::Result<()> {
+        let mut payload = Vec::new();
+        put_lenenc_int(&mut payload, columns.len() as u64);
+        self.write_packet(&payload).await?;
+
+        for column in columns {
+            let mut payload = Vec::new();
+            put_lenenc_str(&mut payload, b"def");
+            put_lenenc_str(&mut payload, b"");
+            put_lenenc_str(&mut payload, b"");
+            put_lenenc_str(&mut payload, b"");
+            put_lenenc_str(&mut payload, column.as_bytes());
+            put_lenenc_str(&mut payload, column.as_bytes());
+            payload.push(0x0c);
+            payload.extend_from_slice(&u16::from(UTF8_GENERAL_CI).to_le_bytes());
+            payload.extend_from_slice(&255_u32.to_le_bytes());
+            payload.push(ColumnType::MYSQL_TYPE_VAR_STRING as u8);
+            // flags, decimals, filler
+            payload.extend_from_slice(&[0x00, 0x00, 0x00, 0x00, 0x00]);
+            self.write_packet(&payload).await?;
+        }
+
+        if !self.deprecate_eof() {
+            self.write_eof().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes a row of a text result set (see [`MockServer::write_text_result_set`]).
+    pub async fn write_text_row(&mut self, row: &[Option<&str>]) -> io::Result<()> {
+        let mut payload = Vec::new();
+        for value in row {
+            match value {
+                Some(value) => put_lenenc_str(&mut payload, value.as_bytes()),
+                None => payload.push(0xfb),
+            }
+        }
+        self.write_packet(&payload).await
+    }
+
+    /// Writes the result set terminator (see [`MockServer::write_text_result_set`]).
+    pub async fn write_result_set_end(&mut self) -> io::Result<()> {
+        if self.deprecate_eof() {
+            let mut payload = vec![0xfe];
+            self.write_ok_body(&mut payload);
+            self.write_packet(&payload).await
+        } else {
+            self.write_eof().await
+        }
+    }
+
+    fn deprecate_eof(&self) -> bool {
+        self.capabilities
+            .contains(CapabilityFlags::CLIENT_DEPRECATE_EOF)
+    }
+
+    async fn write_eof(&mut self) -> io::Result<()> {
+        let mut payload = vec![0xfe];
+        // warnings
+        payload.extend_from_slice(&[0x00, 0x00]);
+        payload.extend_from_slice(&self.status.bits().to_le_bytes());
+        self.write_packet(&payload).await
+    }
+
+    fn write_ok_body(&self, payload: &mut Vec<u8>) {
+        // affected rows, last insert id
+        payload.extend_from_slice(&[0x00, 0x00]);
+        payload.extend_from_slice(&self.status.bits().to_le_bytes());
+        // warnings
+        payload.extend_from_slice(&[0x00, 0x00]);
+    }
+}
+
+fn put_lenenc_int(buf: &mut Vec<u8>, x: u64) {
+    if x < 251 {
+        buf.push(x as u8);
+    } else if x < 0x1_0000 {
+        buf.push(0xfc);
+        buf.extend_from_slice(&(x as u16).to_le_bytes());
+    } else if x < 0x100_0000 {
+        buf.push(0xfd);
+        buf.extend_from_slice(&(x as u32).to_le_bytes()[..3]);
+    } else {
+        buf.push(0xfe);
+        buf.extend_from_slice(&x.to_le_bytes());
+    }
+}
+
+fn put_lenenc_str(buf: &mut Vec<u8>, x: &[u8]) {
+    put_lenenc_int(buf, x.len() as u64);
+    buf.extend_from_slice(x);
+}
+
+#[cfg(test)]
+mod test {
+    use super::MockServer;
+    use crate::{consts::CapabilityFlags, prelude::*, Conn, OptsBuilder};
+
+    #[tokio::test]
+    async fn should_connect_to_mock_server() -> crate::Result<()> {
+        let opts = OptsBuilder::default()
+            .user(Some("root"))
+            .init(vec!["SET NAMES utf8"]);
+        let (mut server, conn) = MockServer::connect(opts);
+
+        let client = async move {
+            let mut conn: Conn = conn.await?;
+            assert_eq!(conn.server_version(), (8, 0, 0));
+            assert_eq!(conn.auth_plugin_name(), "mysql_native_password");
+            let result: Vec<(String, Option<String>)> = conn.query("SELECT 'foo', NULL").await?;
+            conn.disconnect().await?;
+            Ok::<_, crate::Error>(result)
+        };
+
+        let server = async move {
+            let handshake_response = server.accept().await?;
+            assert!(server
+                .capabilities()
+                .contains(CapabilityFlags::CLIENT_DEPRECATE_EOF));
+            // user name follows capabilities, max packet size, collation and filler
+            assert_eq!(&handshake_response[32..37], b"root\0");
+
+            assert_eq!(server.read_command().await?, b"\x03SET NAMES utf8");
+            server.write_ok().await?;
+
+            assert_eq!(server.read_command().await?, &b"\x03SELECT 'foo', NULL"[..]);
+            server
+                .write_text_result_set(&["foo", "NULL"], &[vec![Some("foo"), None]])
+                .await?;
+
+            assert_eq!(server.read_command().await?, b"\x01");
+            Ok::<_, std::io::Error>(())
+        };
+
+        let (result, server) = futures_util::future::join(client, server).await;
+        server?;
+        assert_eq!(result?, vec![("foo".into(), None)]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_connect_to_mock_server_without_deprecate_eof() -> crate::Result<()> {
+        let (server, conn) = MockServer::connect(OptsBuilder::default());
+        let mut server = server.with_capabilities(
+            super::DEFAULT_MOCK_CAPABILITIES - CapabilityFlags::CLIENT_DEPRECATE_EOF,
+        );
+
+        let client = async move {
+            let mut conn: Conn = conn.await?;
+            let result: Option<u8> = conn.query_first("SELECT 42").await?;
+            conn.disconnect().await?;
+            Ok::<_, crate::Error>(result)
+        };
+
+        let server = async move {
+            server.accept().await?;
+            server.read_command().await?;
+            server
+                .write_text_result_set(&["42"], &[vec![Some("42")]])
+                .await?;
+            assert_eq!(server.read_command().await?, b"\x01");
+            Ok::<_, std::io::Error>(())
+        };
+
+        let (result, server) = futures_util::future::join(client, server).await;
+        server?;
+        assert_eq!(result?, Some(42));
+
+        Ok(())
+    }
+}
@@ -11,21 +11,73 @@ use url::{Host, Url};
 
 use std::{
     borrow::Cow,
-    io,
+    collections::{BTreeMap, HashMap},
+    ffi::OsString,
+    fmt, io,
     net::{Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs},
-    path::Path,
+    path::{Path, PathBuf},
     str::FromStr,
-    sync::Arc,
+    sync::{Arc, Mutex},
     time::Duration,
     vec,
 };
 
 use crate::{
+    conn::{
+        handshake_capture::{HandshakeCapture, HandshakeFrame},
+        pool::{CircuitBreakerOpts, ConnectionLimiter},
+    },
     consts::CapabilityFlags,
     error::*,
     local_infile_handler::{LocalInfileHandler, LocalInfileHandlerObject},
+    IsolationLevel, SlowQuery,
 };
 
+/// Function, that rewrites or rejects a query (see [`Opts::query_rewriter`]).
+///
+/// Returned `Err` is the reason of the rejection.
+pub type QueryRewriter =
+    dyn for<'a> Fn(&'a str) -> std::result::Result<Cow<'a, str>, String> + Send + Sync;
+
+/// Query rewriter, that is compared by address.
+#[derive(Clone)]
+pub(crate) struct QueryRewriterObject(Arc<QueryRewriter>);
+
+impl PartialEq for QueryRewriterObject {
+    fn eq(&self, other: &QueryRewriterObject) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for QueryRewriterObject {}
+
+impl fmt::Debug for QueryRewriterObject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Query rewriter object")
+    }
+}
+
+/// Function, that receives slow queries (see [`Opts::slow_query_logger`]).
+pub type SlowQueryLogger = dyn Fn(SlowQuery) + Send + Sync;
+
+/// Slow query logger, that is compared by address.
+#[derive(Clone)]
+pub(crate) struct SlowQueryLoggerObject(Arc<SlowQueryLogger>);
+
+impl PartialEq for SlowQueryLoggerObject {
+    fn eq(&self, other: &SlowQueryLoggerObject) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for SlowQueryLoggerObject {}
+
+impl fmt::Debug for SlowQueryLoggerObject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Slow query logger object")
+    }
+}
+
 /// Default pool constraints.
 pub const DEFAULT_POOL_CONSTRAINTS: PoolConstraints = PoolConstraints { min: 10, max: 100 };
 
@@ -38,9 +90,16 @@ const_assert!(
 /// Each connection will cache up to this number of statements by default.
 pub const DEFAULT_STMT_CACHE_SIZE: usize = 32;
 
+/// Default initial capacity of read and write buffers of a connection.
+pub const DEFAULT_BUFFER_SIZE: usize = 8 * 1024;
+
 /// Default server port.
 const DEFAULT_PORT: u16 = 3306;
 
+/// Prefix of environment variables, that define connection URL parameters
+/// (see [`Opts::from_env`]).
+const ENV_PARAM_PREFIX: &str = "MYSQL_OPT_";
+
 /// Default `inactive_connection_ttl` of a pool.
 ///
 /// `0` value means, that connection will be dropped immediately
@@ -117,6 +176,79 @@ impl HostPortOrUrl {
     }
 }
 
+/// Client certificate of a TLS connection (see [`SslOpts::with_client_cert_resolver`]).
+#[derive(Clone, Eq, PartialEq, Hash)]
+pub struct ClientIdentity {
+    pkcs12_der: Arc<[u8]>,
+    password: Option<Cow<'static, str>>,
+}
+
+impl ClientIdentity {
+    /// Creates an identity from the given pkcs12 archive (in `der` format).
+    pub fn new<T: Into<Vec<u8>>>(pkcs12_der: T) -> Self {
+        Self {
+            pkcs12_der: pkcs12_der.into().into(),
+            password: None,
+        }
+    }
+
+    /// Sets the password for the pkcs12 archive (defaults to `None`).
+    pub fn with_password<T: Into<Cow<'static, str>>>(mut self, password: Option<T>) -> Self {
+        self.password = password.map(Into::into);
+        self
+    }
+
+    /// Returns the pkcs12 archive (in `der` format).
+    pub fn pkcs12_der(&self) -> &[u8] {
+        &self.pkcs12_der
+    }
+
+    /// Returns the password for the pkcs12 archive.
+    pub fn password(&self) -> Option<&str> {
+        self.password.as_ref().map(AsRef::as_ref)
+    }
+}
+
+impl fmt::Debug for ClientIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClientIdentity")
+            .field(
+                "pkcs12_der",
+                &format_args!("[{} bytes]", self.pkcs12_der.len()),
+            )
+            .field("password", &self.password.as_ref().map(|_| "******"))
+            .finish()
+    }
+}
+
+/// Function, that chooses a client certificate for the given target hostname
+/// (see [`SslOpts::with_client_cert_resolver`]).
+pub type ClientCertResolver = dyn Fn(&str) -> Option<ClientIdentity> + Send + Sync;
+
+/// Client certificate resolver, that is compared and hashed by address.
+#[derive(Clone)]
+pub(crate) struct ClientCertResolverObject(Arc<ClientCertResolver>);
+
+impl PartialEq for ClientCertResolverObject {
+    fn eq(&self, other: &ClientCertResolverObject) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for ClientCertResolverObject {}
+
+impl std::hash::Hash for ClientCertResolverObject {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        (Arc::as_ptr(&self.0) as *const () as usize).hash(state)
+    }
+}
+
+impl fmt::Debug for ClientCertResolverObject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Client certificate resolver object")
+    }
+}
+
 /// Ssl Options.
 ///
 /// ```
@@ -133,6 +265,7 @@ pub struct SslOpts {
     root_cert_path: Option<Cow<'static, Path>>,
     skip_domain_validation: bool,
     accept_invalid_certs: bool,
+    client_cert_resolver: Option<ClientCertResolverObject>,
 }
 
 impl SslOpts {
@@ -171,6 +304,47 @@ impl SslOpts {
         self
     }
 
+    /// Sets the function, that chooses a client certificate for the target hostname
+    /// (defaults to `None`).
+    ///
+    /// The resolver is called for every TLS connection. It takes precedence over
+    /// [`SslOpts::with_pkcs12_path`], so the connection is established without a client
+    /// certificate if the resolver returns `None`.
+    ///
+    /// ```
+    /// # use mysql_async::{ClientIdentity, SslOpts};
+    /// # use std::sync::Arc;
+    /// let analytics = ClientIdentity::new(std::fs::read("test/client.p12").unwrap());
+    /// let ssl_opts = SslOpts::default().with_client_cert_resolver(Arc::new(move |host: &str| {
+    ///     if host.ends_with(".analytics.local") {
+    ///         Some(analytics.clone())
+    ///     } else {
+    ///         None
+    ///     }
+    /// }));
+    /// ```
+    pub fn with_client_cert_resolver(mut self, resolver: Arc<ClientCertResolver>) -> Self {
+        self.client_cert_resolver = Some(ClientCertResolverObject(resolver));
+        self
+    }
+
+    /// Returns the client certificate for the given target hostname (if any).
+    ///
+    /// It's either chosen by the client certificate resolver,
+    /// or read from [`SslOpts::pkcs12_path`].
+    pub(crate) fn client_identity(&self, domain: &str) -> io::Result<Option<ClientIdentity>> {
+        if let Some(ClientCertResolverObject(ref resolver)) = self.client_cert_resolver {
+            return Ok(resolver(domain));
+        }
+        match self.pkcs12_path() {
+            Some(pkcs12_path) => {
+                let identity = ClientIdentity::new(std::fs::read(pkcs12_path)?);
+                Ok(Some(identity.with_password(self.password.clone())))
+            }
+            None => Ok(None),
+        }
+    }
+
     pub fn pkcs12_path(&self) -> Option<&Path> {
         self.pkcs12_path.as_ref().map(|x| x.as_ref())
     }
@@ -192,6 +366,26 @@ impl SslOpts {
     }
 }
 
+/// Version of the PROXY protocol header (see [`OptsBuilder::proxy_protocol`]).
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum ProxyProtocolVersion {
+    /// Human-readable header (version 1).
+    V1,
+    /// Binary header (version 2).
+    V2,
+}
+
+/// What to do if the server-side limit of prepared statements (`max_prepared_stmt_count`)
+/// is reached (see [`Opts::on_prepared_limit`]).
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum PreparedStmtLimitPolicy {
+    /// Return [`DriverError::PreparedStmtLimitReached`].
+    Error,
+    /// Close least recently used statements of the connection's statement cache
+    /// and retry, until the statement is prepared or the cache is empty.
+    EvictCached,
+}
+
 /// Connection pool options.
 ///
 /// ```
@@ -206,6 +400,9 @@ pub struct PoolOpts {
     constraints: PoolConstraints,
     inactive_connection_ttl: Duration,
     ttl_check_interval: Duration,
+    limiter: Option<ConnectionLimiter>,
+    circuit_breaker: Option<CircuitBreakerOpts>,
+    idle_liveness_check: bool,
 }
 
 impl PoolOpts {
@@ -279,6 +476,66 @@ impl PoolOpts {
         self.ttl_check_interval
     }
 
+    /// Pool will check idling connections for liveness every [`PoolOpts::ttl_check_interval`]
+    /// and discard connections closed by the server (defaults to `false`).
+    ///
+    /// The check doesn't consume any data: a TCP connection is considered closed if it's readable
+    /// (i.e. the server has sent EOF, RST or anything else while the connection is idling).
+    /// It allows the pool to discard connections, that were killed or timed out
+    /// (see `wait_timeout`) on the server side, before they are checked out.
+    ///
+    /// # Connection URL
+    ///
+    /// You can use `idle_liveness_check` URL parameter to set this value. E.g.
+    ///
+    /// ```
+    /// # use mysql_async::*;
+    /// # fn main() -> Result<()> {
+    /// let opts = Opts::from_url("mysql://localhost/db?idle_liveness_check=true")?;
+    /// assert!(opts.pool_opts().idle_liveness_check());
+    /// # Ok(()) }
+    /// ```
+    pub fn with_idle_liveness_check(mut self, idle_liveness_check: bool) -> Self {
+        self.idle_liveness_check = idle_liveness_check;
+        self
+    }
+
+    /// Returns a `idle_liveness_check` value.
+    pub fn idle_liveness_check(&self) -> bool {
+        self.idle_liveness_check
+    }
+
+    /// Pool will acquire a permit from the given limiter before it creates a new connection
+    /// (defaults to `None`).
+    ///
+    /// Share the same limiter between pools to cap the total number of connections
+    /// these pools open to a server (see [`ConnectionLimiter`]). Connections idling in the pool
+    /// count against the limit, until they are closed.
+    pub fn with_limiter(mut self, limiter: ConnectionLimiter) -> Self {
+        self.limiter = Some(limiter);
+        self
+    }
+
+    /// Returns the connection limiter, if any.
+    pub fn limiter(&self) -> Option<&ConnectionLimiter> {
+        self.limiter.as_ref()
+    }
+
+    /// Pool will stop creating new connections for a while after consecutive connection
+    /// failures (defaults to `None`), so that [`crate::Pool::get_conn`] fails fast with
+    /// [`DriverError::CircuitOpen`] while the server is down (see [`CircuitBreakerOpts`]).
+    ///
+    /// Note that idling connections are still given out while the breaker is open.
+    pub fn with_circuit_breaker(mut self, circuit_breaker: CircuitBreakerOpts) -> Self {
+        self.circuit_breaker = Some(circuit_breaker);
+        self
+    }
+
+    /// Returns circuit breaker options, if any.
+    pub fn circuit_breaker(&self) -> Option<CircuitBreakerOpts> {
+        self.circuit_breaker
+    }
+
     /// Returns active bound for this `PoolOpts`.
     ///
     /// This value controls how many connections will be returned to an idle queue of a pool.
@@ -306,6 +563,9 @@ impl Default for PoolOpts {
             constraints: DEFAULT_POOL_CONSTRAINTS,
             inactive_connection_ttl: DEFAULT_INACTIVE_CONNECTION_TTL,
             ttl_check_interval: DEFAULT_TTL_CHECK_INTERVAL,
+            limiter: None,
+            circuit_breaker: None,
+            idle_liveness_check: false,
         }
     }
 }
@@ -352,6 +612,15 @@ pub(crate) struct MysqlOpts {
     /// Commands to execute on each new database connection.
     init: Vec<String>,
 
+    /// Connection attributes, that are sent to the server.
+    connect_attrs: HashMap<String, String>,
+
+    /// Buffer for packets of the connection phase (defaults to `None`).
+    capture_handshake: Option<HandshakeCapture>,
+
+    /// Function, that rewrites or rejects queries (defaults to `None`).
+    query_rewriter: Option<QueryRewriterObject>,
+
     /// Number of prepared statements cached on the client side (per connection). Defaults to `10`.
     stmt_cache_size: usize,
 
@@ -385,6 +654,95 @@ pub(crate) struct MysqlOpts {
     ///
     /// Note that compression level defined here will affect only outgoing packets.
     compression: Option<crate::Compression>,
+
+    /// Outgoing packets shorter than this number of bytes are sent uncompressed
+    /// (defaults to `50`).
+    ///
+    /// Only makes sense if `compression` is enabled.
+    compression_threshold: usize,
+
+    /// Split text queries that exceed `max_allowed_packet` at statement boundaries
+    /// (defaults to `false`).
+    split_oversized_queries: bool,
+
+    /// Retain text of the last executed statement (defaults to `false`).
+    retain_last_statement: bool,
+
+    /// Version of the PROXY protocol header to send on a new TCP connection (defaults to `None`).
+    proxy_protocol: Option<ProxyProtocolVersion>,
+
+    /// Source and destination addresses for the PROXY protocol header (defaults to `None`).
+    ///
+    /// Local and peer addresses of a TCP connection will be used if `None`.
+    proxy_protocol_addrs: Option<(SocketAddr, SocketAddr)>,
+
+    /// Path to a PEM file with the server RSA public key (defaults to `None`).
+    server_public_key: Option<PathBuf>,
+
+    /// Initial capacity of the read buffer of a connection (defaults to [`DEFAULT_BUFFER_SIZE`]).
+    read_buffer_size: usize,
+
+    /// Initial capacity of the write buffer of a connection
+    /// (defaults to [`DEFAULT_BUFFER_SIZE`]).
+    write_buffer_size: usize,
+
+    /// Whether to reuse packet buffers of result set rows (defaults to `false`).
+    reuse_buffers: bool,
+
+    /// Whether to execute text queries using the binary protocol (defaults to `false`).
+    always_prepare: bool,
+
+    /// Whether to fail the connection if an init command produces warnings
+    /// (defaults to `false`).
+    strict_init: bool,
+
+    /// Whether to read warnings of a query using `SHOW WARNINGS` as soon as the query result
+    /// is consumed (defaults to `false`).
+    capture_warnings: bool,
+
+    /// Isolation level of transactions for every session (defaults to `None`).
+    default_isolation_level: Option<IsolationLevel>,
+
+    /// Session `sql_mode` to set on each new connection (defaults to `None`).
+    sql_mode: Option<String>,
+
+    /// Session `SQL_SELECT_LIMIT` to set on each new connection (defaults to `None`).
+    default_select_limit: Option<u64>,
+
+    /// Whether to cache statements prepared within a transaction separately,
+    /// until the transaction ends (defaults to `false`).
+    isolate_tx_statements: bool,
+
+    /// Whether to fail the connection, if the server doesn't grant `CLIENT_MULTI_RESULTS`
+    /// (defaults to `false`).
+    require_multi_results: bool,
+
+    /// Whether to skip result set metadata for prepared statements, if supported
+    /// (defaults to `false`).
+    suppress_metadata: bool,
+
+    /// Timeout for the initial handshake packet (defaults to `None`).
+    handshake_timeout: Option<Duration>,
+
+    /// What to do if the server-side limit of prepared statements is reached
+    /// (defaults to [`PreparedStmtLimitPolicy::Error`]).
+    on_prepared_limit: PreparedStmtLimitPolicy,
+
+    /// Whether to send `COM_QUIT` before closing a connection (defaults to `true`).
+    send_quit_on_disconnect: bool,
+
+    /// Comment to prepend to statements issued within a transaction (defaults to `None`).
+    transaction_hint: Option<String>,
+
+    /// Queries, that take longer, are passed to `slow_query_logger` (defaults to `None`).
+    slow_query_threshold: Option<Duration>,
+
+    /// Function, that receives slow queries (defaults to `None`).
+    slow_query_logger: Option<SlowQueryLoggerObject>,
+
+    /// Whether to prepend the caller location to queries (defaults to `false`).
+    #[cfg(feature = "caller-location")]
+    tag_caller_location: bool,
 }
 
 /// Mysql connection options.
@@ -402,7 +760,12 @@ impl Opts {
     }
 
     pub fn from_url(url: &str) -> std::result::Result<Opts, UrlError> {
-        let mut url = Url::parse(url)?;
+        let mut url = Url::parse(url).map_err(|err| match err {
+            ParseError::InvalidPort => UrlError::InvalidPort {
+                port: get_raw_port_from_url(url).into(),
+            },
+            err => UrlError::Parse(err),
+        })?;
 
         // We use the URL for socket address resolution later, so make
         // sure it has a port set.
@@ -424,6 +787,54 @@ impl Opts {
         })
     }
 
+    /// Creates options from environment variables.
+    ///
+    /// If `DATABASE_URL` is set, then it's parsed as a connection URL and other variables
+    /// are ignored. Otherwise options are composed from the following variables:
+    ///
+    /// * `MYSQL_HOST` – see [`Opts::ip_or_hostname`] (defaults to `127.0.0.1`);
+    /// * `MYSQL_PORT` – see [`Opts::tcp_port`] (defaults to `3306`);
+    /// * `MYSQL_USER`, `MYSQL_PASSWORD`, `MYSQL_DATABASE` – see [`Opts::user`], [`Opts::pass`]
+    ///   and [`Opts::db_name`];
+    /// * `MYSQL_OPT_<PARAM>` – connection URL parameter `<param>`, e.g. `MYSQL_OPT_POOL_MAX=20`
+    ///   is the same as `?pool_max=20`, so values are parsed exactly as URL parameters.
+    ///
+    /// Malformed values are reported as [`UrlError::InvalidParamValue`]
+    /// (or [`UrlError::UnknownParameter`]) with the variable name as `param`.
+    ///
+    /// See [`Opts::from_env_vars`] to compose options from the given variables.
+    pub fn from_env() -> std::result::Result<Opts, UrlError> {
+        Opts::from_env_vars(std::env::vars_os())
+    }
+
+    /// Creates options from the given `(name, value)` pairs of environment variables
+    /// (see [`Opts::from_env`]).
+    ///
+    /// ```
+    /// # use mysql_async::*;
+    /// # fn main() -> Result<()> {
+    /// let opts = Opts::from_env_vars(vec![
+    ///     ("MYSQL_HOST", "db.example.com"),
+    ///     ("MYSQL_PORT", "3307"),
+    ///     ("MYSQL_OPT_POOL_MAX", "20"),
+    /// ])?;
+    /// assert_eq!(opts.ip_or_hostname(), "db.example.com");
+    /// assert_eq!(opts.tcp_port(), 3307);
+    /// assert_eq!(opts.pool_opts().constraints().max(), 20);
+    /// # Ok(()) }
+    /// ```
+    pub fn from_env_vars<I, K, V>(vars: I) -> std::result::Result<Opts, UrlError>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<OsString>,
+        V: Into<OsString>,
+    {
+        opts_from_env(
+            vars.into_iter()
+                .map(|(name, value)| (name.into(), value.into())),
+        )
+    }
+
     /// Address of mysql server (defaults to `127.0.0.1`). Hostnames should also work.
     pub fn ip_or_hostname(&self) -> &str {
         self.inner.address.get_ip_or_hostname()
@@ -494,6 +905,62 @@ impl Opts {
         self.inner.mysql_opts.init.as_ref()
     }
 
+    /// Connection attributes, that are sent to the server (defaults to an empty map).
+    ///
+    /// The server exposes them in `performance_schema.session_connect_attrs`.
+    /// The driver also sends `_client_name`, `_client_version`, `_os`, `_platform`
+    /// and `_pid` attributes.
+    pub fn connect_attrs(&self) -> &HashMap<String, String> {
+        &self.inner.mysql_opts.connect_attrs
+    }
+
+    /// Buffer, that receives every packet of the connection phase, i.e. the initial handshake,
+    /// the handshake response and the authentication exchange (defaults to `None`).
+    ///
+    /// It is meant for debugging connection problems with unusual servers. Packets are appended
+    /// by every connection established using these options and nothing is captured after
+    /// the authentication is completed. Note, that captured packets contain the nonce and
+    /// the password-derived authentication data.
+    pub fn capture_handshake(&self) -> Option<&Arc<Mutex<Vec<HandshakeFrame>>>> {
+        self.inner
+            .mysql_opts
+            .capture_handshake
+            .as_ref()
+            .map(|capture| &capture.0)
+    }
+
+    pub(crate) fn handshake_capture(&self) -> Option<&HandshakeCapture> {
+        self.inner.mysql_opts.capture_handshake.as_ref()
+    }
+
+    /// Function, that is applied to every query before it is sent to the server
+    /// (defaults to `None`).
+    ///
+    /// It receives the query text as given (i.e. before named parameters are replaced)
+    /// and returns the query to send. Returned `Err` rejects the query with
+    /// [`DriverError::QueryRejected`]. Note, that queries issued by the driver itself
+    /// (e.g. [`Opts::init`] commands) are passed through it as well.
+    ///
+    /// ```
+    /// # use mysql_async::*;
+    /// # use std::borrow::Cow;
+    /// let opts = Opts::from(OptsBuilder::default().query_rewriter(|query: &str| {
+    ///     if query.trim_start().to_uppercase().starts_with("DROP") {
+    ///         Err("DROP is not allowed".to_string())
+    ///     } else {
+    ///         Ok(Cow::Owned(format!("/* tenant:123 */ {}", query)))
+    ///     }
+    /// }));
+    /// assert!(opts.query_rewriter().is_some());
+    /// ```
+    pub fn query_rewriter(&self) -> Option<&Arc<QueryRewriter>> {
+        self.inner
+            .mysql_opts
+            .query_rewriter
+            .as_ref()
+            .map(|rewriter| &rewriter.0)
+    }
+
     /// TCP keep alive timeout in milliseconds (defaults to `None`).
     ///
     /// # Connection URL
@@ -655,82 +1122,720 @@ impl Opts {
         self.inner.mysql_opts.compression
     }
 
-    pub(crate) fn get_capabilities(&self) -> CapabilityFlags {
-        let mut out = CapabilityFlags::CLIENT_PROTOCOL_41
-            | CapabilityFlags::CLIENT_SECURE_CONNECTION
-            | CapabilityFlags::CLIENT_LONG_PASSWORD
-            | CapabilityFlags::CLIENT_TRANSACTIONS
-            | CapabilityFlags::CLIENT_LOCAL_FILES
-            | CapabilityFlags::CLIENT_MULTI_STATEMENTS
-            | CapabilityFlags::CLIENT_MULTI_RESULTS
-            | CapabilityFlags::CLIENT_PS_MULTI_RESULTS
-            | CapabilityFlags::CLIENT_DEPRECATE_EOF
-            | CapabilityFlags::CLIENT_PLUGIN_AUTH;
-
-        if self.inner.mysql_opts.db_name.is_some() {
-            out |= CapabilityFlags::CLIENT_CONNECT_WITH_DB;
-        }
-        if self.inner.mysql_opts.ssl_opts.is_some() {
-            out |= CapabilityFlags::CLIENT_SSL;
-        }
-        if self.inner.mysql_opts.compression.is_some() {
-            out |= CapabilityFlags::CLIENT_COMPRESS;
-        }
-
-        out
-    }
-}
-
-impl Default for MysqlOpts {
-    fn default() -> MysqlOpts {
-        MysqlOpts {
-            user: None,
-            pass: None,
-            db_name: None,
-            init: vec![],
-            tcp_keepalive: None,
-            tcp_nodelay: true,
-            local_infile_handler: None,
-            pool_opts: Default::default(),
-            conn_ttl: None,
-            stmt_cache_size: DEFAULT_STMT_CACHE_SIZE,
-            ssl_opts: None,
-            prefer_socket: true,
-            socket: None,
-            compression: None,
-        }
+    /// Outgoing packets shorter than this number of bytes are sent uncompressed
+    /// (defaults to `50`).
+    ///
+    /// Compressing tiny packets wastes CPU and could even make them larger.
+    /// Only makes sense if [`Opts::compression`] is enabled. Note, that packets shorter
+    /// than `50` bytes are never compressed, so smaller values have no effect.
+    ///
+    /// # Connection URL
+    ///
+    /// You can use `compression_threshold` URL parameter to set this value. E.g.
+    ///
+    /// ```
+    /// # use mysql_async::*;
+    /// # fn main() -> Result<()> {
+    /// let opts = Opts::from_url("mysql://localhost/db?compression=fast&compression_threshold=1024")?;
+    /// assert_eq!(opts.compression_threshold(), 1024);
+    /// # Ok(()) }
+    /// ```
+    pub fn compression_threshold(&self) -> usize {
+        self.inner.mysql_opts.compression_threshold
     }
-}
-
-/// Connection pool constraints.
-///
-/// This type stores `min` and `max` constraints for [`crate::Pool`] and ensures that `min <= max`.
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
-pub struct PoolConstraints {
-    min: usize,
-    max: usize,
-}
 
-impl PoolConstraints {
-    /// Creates new [`PoolConstraints`] if constraints are valid (`min <= max`).
+    /// Split text queries that exceed `max_allowed_packet` at statement boundaries
+    /// (defaults to `false`).
+    ///
+    /// If `true`, then a multi-statement text query, that doesn't fit into `max_allowed_packet`,
+    /// will be split into a number of multi-statement queries, that will be executed sequentially.
+    /// Result sets of these queries are available through a single [`crate::QueryResult`].
+    /// Execution stops at the first error.
+    ///
+    /// Statements are never split, so a single statement larger than `max_allowed_packet`
+    /// will still cause `DriverError::PacketTooLarge`.
+    ///
+    /// # Caveats
+    ///
+    /// Query is split on `;` outside of string literals, quoted identifiers and comments,
+    /// so client-side `DELIMITER` commands are not supported. Backslash is treated
+    /// as an escape character within string literals, unless `NO_BACKSLASH_ESCAPES` SQL mode
+    /// is enabled (see [`Conn::no_backslash_escapes`]).
     ///
     /// # Connection URL
     ///
-    /// You can use `pool_min` and `pool_max` URL parameters to define pool constraints.
+    /// You can use `split_oversized_queries` URL parameter to set this value. E.g.
     ///
     /// ```
     /// # use mysql_async::*;
     /// # fn main() -> Result<()> {
-    /// let opts = Opts::from_url("mysql://localhost/db?pool_min=0&pool_max=151")?;
-    /// assert_eq!(opts.pool_opts().constraints(), PoolConstraints::new(0, 151).unwrap());
+    /// let opts = Opts::from_url("mysql://localhost/db?split_oversized_queries=true")?;
+    /// assert_eq!(opts.split_oversized_queries(), true);
     /// # Ok(()) }
     /// ```
-    pub fn new(min: usize, max: usize) -> Option<PoolConstraints> {
-        if min <= max {
-            Some(PoolConstraints { min, max })
-        } else {
-            None
-        }
+    ///
+    /// [`Conn::no_backslash_escapes`]: crate::Conn::no_backslash_escapes
+    pub fn split_oversized_queries(&self) -> bool {
+        self.inner.mysql_opts.split_oversized_queries
+    }
+
+    /// If `true`, then connection will retain text of the last executed statement
+    /// (defaults to `false`). See [`Conn::last_statement`].
+    ///
+    /// Disabled by default because statement text may contain sensitive data.
+    ///
+    /// # Connection URL
+    ///
+    /// You can use `retain_last_statement` URL parameter to set this value. E.g.
+    ///
+    /// ```
+    /// # use mysql_async::*;
+    /// # fn main() -> Result<()> {
+    /// let opts = Opts::from_url("mysql://localhost/db?retain_last_statement=true")?;
+    /// assert_eq!(opts.retain_last_statement(), true);
+    /// # Ok(()) }
+    /// ```
+    ///
+    /// [`Conn::last_statement`]: crate::Conn::last_statement
+    pub fn retain_last_statement(&self) -> bool {
+        self.inner.mysql_opts.retain_last_statement
+    }
+
+    /// Version of the PROXY protocol header, that will be sent on a newly established
+    /// TCP connection before the MySql handshake (defaults to `None`).
+    ///
+    /// Useful if server is behind a load balancer (e.g. HAProxy), that expects
+    /// the PROXY protocol header. Not applicable to socket connections.
+    ///
+    /// # Connection URL
+    ///
+    /// You can use `proxy_protocol` URL parameter to set this value (`v1` or `v2`). E.g.
+    ///
+    /// ```
+    /// # use mysql_async::*;
+    /// # fn main() -> Result<()> {
+    /// let opts = Opts::from_url("mysql://localhost/db?proxy_protocol=v2")?;
+    /// assert_eq!(opts.proxy_protocol(), Some(ProxyProtocolVersion::V2));
+    /// # Ok(()) }
+    /// ```
+    pub fn proxy_protocol(&self) -> Option<ProxyProtocolVersion> {
+        self.inner.mysql_opts.proxy_protocol
+    }
+
+    /// Source and destination addresses of the PROXY protocol header (defaults to `None`).
+    ///
+    /// Local and peer addresses of the TCP connection will be used if `None`.
+    pub fn proxy_protocol_addrs(&self) -> Option<(SocketAddr, SocketAddr)> {
+        self.inner.mysql_opts.proxy_protocol_addrs
+    }
+
+    /// Path to a PEM file with the server RSA public key (defaults to `None`).
+    ///
+    /// `caching_sha2_password` authentication plugin will use this key to encrypt
+    /// the password over an insecure connection instead of requesting the key from the server.
+    /// The key is requested from the server if `None`.
+    ///
+    /// # Connection URL
+    ///
+    /// You can use `server_public_key` URL parameter to set this value. E.g.
+    ///
+    /// ```
+    /// # use mysql_async::*;
+    /// # use std::path::Path;
+    /// # fn main() -> Result<()> {
+    /// let opts = Opts::from_url("mysql://localhost/db?server_public_key=%2Fpath%2Fto%2Fkey.pem")?;
+    /// assert_eq!(opts.server_public_key(), Some(Path::new("/path/to/key.pem")));
+    /// # Ok(()) }
+    /// ```
+    pub fn server_public_key(&self) -> Option<&Path> {
+        self.inner.mysql_opts.server_public_key.as_deref()
+    }
+
+    /// Initial capacity of the read buffer of a connection (defaults to [`DEFAULT_BUFFER_SIZE`]).
+    ///
+    /// Larger buffer means less reads from the socket for result sets with many small rows.
+    /// Buffer will grow as needed to fit a packet in either case.
+    ///
+    /// # Connection URL
+    ///
+    /// You can use `read_buffer_size` URL parameter to set this value. E.g.
+    ///
+    /// ```
+    /// # use mysql_async::*;
+    /// # fn main() -> Result<()> {
+    /// let opts = Opts::from_url("mysql://localhost/db?read_buffer_size=65536")?;
+    /// assert_eq!(opts.read_buffer_size(), 65536);
+    /// # Ok(()) }
+    /// ```
+    pub fn read_buffer_size(&self) -> usize {
+        self.inner.mysql_opts.read_buffer_size
+    }
+
+    /// Initial capacity of the write buffer of a connection
+    /// (defaults to [`DEFAULT_BUFFER_SIZE`]).
+    ///
+    /// Buffer will grow as needed to fit a packet.
+    ///
+    /// # Connection URL
+    ///
+    /// You can use `write_buffer_size` URL parameter to set this value. E.g.
+    ///
+    /// ```
+    /// # use mysql_async::*;
+    /// # fn main() -> Result<()> {
+    /// let opts = Opts::from_url("mysql://localhost/db?write_buffer_size=65536")?;
+    /// assert_eq!(opts.write_buffer_size(), 65536);
+    /// # Ok(()) }
+    /// ```
+    pub fn write_buffer_size(&self) -> usize {
+        self.inner.mysql_opts.write_buffer_size
+    }
+
+    /// If `true`, then packet buffers of result set rows are reused (defaults to `false`).
+    ///
+    /// A packet of a row is decoded into the buffer of a previous row, if that row was decoded
+    /// into a [`Row`] (see [`QueryResult::next`]) or dropped (e.g. by
+    /// [`QueryResult::drop_result`]), which saves an allocation per row.
+    /// Rows, that keep their packet (see [`QueryResult::next_raw`]), aren't affected.
+    ///
+    /// Ignored, if compression is enabled (see [`Opts::compression`]).
+    ///
+    /// # Connection URL
+    ///
+    /// You can use `reuse_buffers` URL parameter to set this value. E.g.
+    ///
+    /// ```
+    /// # use mysql_async::*;
+    /// # fn main() -> Result<()> {
+    /// let opts = Opts::from_url("mysql://localhost/db?reuse_buffers=true")?;
+    /// assert!(opts.reuse_buffers());
+    /// # Ok(()) }
+    /// ```
+    ///
+    /// [`Row`]: crate::Row
+    /// [`QueryResult::next`]: crate::QueryResult::next
+    /// [`QueryResult::next_raw`]: crate::QueryResult::next_raw
+    /// [`QueryResult::drop_result`]: crate::QueryResult::drop_result
+    pub fn reuse_buffers(&self) -> bool {
+        self.inner.mysql_opts.reuse_buffers
+    }
+
+    /// If `true`, then text queries will be executed using the binary protocol, i.e. prepared
+    /// (using the statement cache) and executed without parameters (defaults to `false`).
+    ///
+    /// Binary protocol represents floating point and temporal values more consistently across
+    /// server versions.
+    ///
+    /// Applies to [`Queryable::query_iter`], [`Queryable::query`], [`Queryable::query_first`],
+    /// [`Queryable::query_map`], [`Queryable::query_fold`] and [`Queryable::query_drop`],
+    /// with the following exceptions, that are still executed using the text protocol:
+    ///
+    /// *   multi-statement queries, since these can't be prepared;
+    /// *   statements, that aren't supported by the prepared statement protocol
+    ///     (the server returns `ER_UNSUPPORTED_PS` for these).
+    ///
+    /// Note that `?` in a prepared query is a parameter placeholder.
+    ///
+    /// # Connection URL
+    ///
+    /// You can use `always_prepare` URL parameter to set this value. E.g.
+    ///
+    /// ```
+    /// # use mysql_async::*;
+    /// # fn main() -> Result<()> {
+    /// let opts = Opts::from_url("mysql://localhost/db?always_prepare=true")?;
+    /// assert_eq!(opts.always_prepare(), true);
+    /// # Ok(()) }
+    /// ```
+    ///
+    /// [`Queryable::query`]: crate::prelude::Queryable::query
+    /// [`Queryable::query_first`]: crate::prelude::Queryable::query_first
+    /// [`Queryable::query_map`]: crate::prelude::Queryable::query_map
+    /// [`Queryable::query_fold`]: crate::prelude::Queryable::query_fold
+    /// [`Queryable::query_drop`]: crate::prelude::Queryable::query_drop
+    /// [`Queryable::query_iter`]: crate::prelude::Queryable::query_iter
+    pub fn always_prepare(&self) -> bool {
+        self.inner.mysql_opts.always_prepare
+    }
+
+    /// If `true`, then a connection will fail with [`DriverError::InitCommandWarnings`]
+    /// if any of [`Opts::init`] commands produces warnings (defaults to `false`).
+    ///
+    /// Otherwise warnings are only collected (see [`Conn::init_warnings`]).
+    ///
+    /// # Connection URL
+    ///
+    /// You can use `strict_init` URL parameter to set this value. E.g.
+    ///
+    /// ```
+    /// # use mysql_async::*;
+    /// # fn main() -> Result<()> {
+    /// let opts = Opts::from_url("mysql://localhost/db?strict_init=true")?;
+    /// assert_eq!(opts.strict_init(), true);
+    /// # Ok(()) }
+    /// ```
+    ///
+    /// [`DriverError::InitCommandWarnings`]: crate::DriverError::InitCommandWarnings
+    /// [`Conn::init_warnings`]: crate::Conn::init_warnings
+    pub fn strict_init(&self) -> bool {
+        self.inner.mysql_opts.strict_init
+    }
+
+    /// If `true`, then warnings of a query are read using `SHOW WARNINGS` as soon as
+    /// the query result is consumed (defaults to `false`).
+    ///
+    /// It costs an additional round trip, that only happens if the server reports warnings.
+    /// Warnings are available via [`QueryResult::captured_warnings`] and
+    /// [`Conn::captured_warnings`]. For a multi-statement query only warnings
+    /// of the last statement are captured.
+    ///
+    /// # Connection URL
+    ///
+    /// You can use `capture_warnings` URL parameter to set this value. E.g.
+    ///
+    /// ```
+    /// # use mysql_async::*;
+    /// # fn main() -> Result<()> {
+    /// let opts = Opts::from_url("mysql://localhost/db?capture_warnings=true")?;
+    /// assert_eq!(opts.capture_warnings(), true);
+    /// # Ok(()) }
+    /// ```
+    ///
+    /// [`QueryResult::captured_warnings`]: crate::QueryResult::captured_warnings
+    /// [`Conn::captured_warnings`]: crate::Conn::captured_warnings
+    pub fn capture_warnings(&self) -> bool {
+        self.inner.mysql_opts.capture_warnings
+    }
+
+    /// If not `None`, then the session isolation level is set to this value on connect
+    /// and after [`Conn::reset`] (defaults to `None`, i.e. the server default is used).
+    ///
+    /// Isolation level, defined via [`TxOpts::with_isolation_level`], still takes precedence
+    /// for a single transaction.
+    ///
+    /// # Connection URL
+    ///
+    /// You can use `default_isolation_level` URL parameter to set this value. Possible values
+    /// are `read_uncommitted`, `read_committed`, `repeatable_read` and `serializable`. E.g.
+    ///
+    /// ```
+    /// # use mysql_async::*;
+    /// # fn main() -> Result<()> {
+    /// let opts = Opts::from_url("mysql://localhost/db?default_isolation_level=read_committed")?;
+    /// assert_eq!(opts.default_isolation_level(), Some(IsolationLevel::ReadCommitted));
+    /// # Ok(()) }
+    /// ```
+    ///
+    /// [`Conn::reset`]: crate::Conn::reset
+    /// [`TxOpts::with_isolation_level`]: crate::TxOpts::with_isolation_level
+    pub fn default_isolation_level(&self) -> Option<IsolationLevel> {
+        self.inner.mysql_opts.default_isolation_level
+    }
+
+    /// Session `sql_mode` to set on each new connection and after [`Conn::reset`]
+    /// (defaults to `None`, i.e. the server default is used).
+    ///
+    /// Use [`Conn::assert_sql_mode`] to detect, that the mode was changed afterwards
+    /// (e.g. by a stored procedure).
+    ///
+    /// # Connection URL
+    ///
+    /// You can use `sql_mode` URL parameter to set this value. E.g.
+    ///
+    /// ```
+    /// # use mysql_async::*;
+    /// # fn main() -> Result<()> {
+    /// let opts = Opts::from_url("mysql://localhost/db?sql_mode=STRICT_ALL_TABLES,NO_ZERO_DATE")?;
+    /// assert_eq!(opts.sql_mode(), Some("STRICT_ALL_TABLES,NO_ZERO_DATE"));
+    /// # Ok(()) }
+    /// ```
+    ///
+    /// [`Conn::reset`]: crate::Conn::reset
+    /// [`Conn::assert_sql_mode`]: crate::Conn::assert_sql_mode
+    pub fn sql_mode(&self) -> Option<&str> {
+        self.inner.mysql_opts.sql_mode.as_deref()
+    }
+
+    /// If not `None`, then the session `SQL_SELECT_LIMIT` is set to this value on connect
+    /// and after [`Conn::reset`] (defaults to `None`, i.e. the server default is used).
+    ///
+    /// It caps the number of rows returned by `SELECT` statements without a `LIMIT` clause,
+    /// so it's a safety net against runaway queries. Use [`Conn::query_with_limit`]
+    /// to run a single query with another limit.
+    ///
+    /// # Connection URL
+    ///
+    /// You can use `default_select_limit` URL parameter to set this value. E.g.
+    ///
+    /// ```
+    /// # use mysql_async::*;
+    /// # fn main() -> Result<()> {
+    /// let opts = Opts::from_url("mysql://localhost/db?default_select_limit=1000")?;
+    /// assert_eq!(opts.default_select_limit(), Some(1000));
+    /// # Ok(()) }
+    /// ```
+    ///
+    /// [`Conn::reset`]: crate::Conn::reset
+    /// [`Conn::query_with_limit`]: crate::Conn::query_with_limit
+    pub fn default_select_limit(&self) -> Option<u64> {
+        self.inner.mysql_opts.default_select_limit
+    }
+
+    /// If `true`, then statements prepared within a transaction are cached in a separate,
+    /// transaction-scoped statement cache (defaults to `false`).
+    ///
+    /// Statements of the transaction-scoped cache are closed as soon as the transaction ends
+    /// (i.e. on commit or rollback), and statements of the connection-wide cache aren't used
+    /// within a transaction. So no statement is shared between a transaction and the rest
+    /// of the session (e.g. if its plan depends on temporary tables of the transaction).
+    ///
+    /// Note, that a [`Statement`] prepared within a transaction mustn't be executed after
+    /// the transaction ends.
+    ///
+    /// # Connection URL
+    ///
+    /// You can use `isolate_tx_statements` URL parameter to set this value. E.g.
+    ///
+    /// ```
+    /// # use mysql_async::*;
+    /// # fn main() -> Result<()> {
+    /// let opts = Opts::from_url("mysql://localhost/db?isolate_tx_statements=true")?;
+    /// assert!(opts.isolate_tx_statements());
+    /// # Ok(()) }
+    /// ```
+    ///
+    /// [`Statement`]: crate::Statement
+    pub fn isolate_tx_statements(&self) -> bool {
+        self.inner.mysql_opts.isolate_tx_statements
+    }
+
+    /// If `true`, then the connection fails with [`DriverError::MultiResultsNotSupported`],
+    /// if the server doesn't grant the `CLIENT_MULTI_RESULTS` capability (defaults to `false`).
+    ///
+    /// The capability is always requested, but may be stripped by a proxy. Stored procedures
+    /// can't be called without it, so set this option, if your code calls procedures,
+    /// to fail early with a clear error (see [`Conn::supports_multi_results`]).
+    ///
+    /// # Connection URL
+    ///
+    /// You can use `require_multi_results` URL parameter to set this value. E.g.
+    ///
+    /// ```
+    /// # use mysql_async::*;
+    /// # fn main() -> Result<()> {
+    /// let opts = Opts::from_url("mysql://localhost/db?require_multi_results=true")?;
+    /// assert!(opts.require_multi_results());
+    /// # Ok(()) }
+    /// ```
+    ///
+    /// [`DriverError::MultiResultsNotSupported`]: crate::DriverError::MultiResultsNotSupported
+    /// [`Conn::supports_multi_results`]: crate::Conn::supports_multi_results
+    pub fn require_multi_results(&self) -> bool {
+        self.inner.mysql_opts.require_multi_results
+    }
+
+    /// If `true`, then the server is asked not to send result set metadata
+    /// for prepared statements (defaults to `false`).
+    ///
+    /// Requires MySql 8.0.19 or newer (`CLIENT_OPTIONAL_RESULTSET_METADATA` capability),
+    /// otherwise it is ignored. Rows are decoded using column definitions, that were
+    /// received when the statement was prepared.
+    ///
+    /// It is done via the `resultset_metadata` session variable, so switching between
+    /// text queries and statement executions costs an additional round trip. Use it for tight
+    /// loops of prepared statement executions.
+    ///
+    /// # Connection URL
+    ///
+    /// You can use `suppress_metadata` URL parameter to set this value. E.g.
+    ///
+    /// ```
+    /// # use mysql_async::*;
+    /// # fn main() -> Result<()> {
+    /// let opts = Opts::from_url("mysql://localhost/db?suppress_metadata=true")?;
+    /// assert_eq!(opts.suppress_metadata(), true);
+    /// # Ok(()) }
+    /// ```
+    pub fn suppress_metadata(&self) -> bool {
+        self.inner.mysql_opts.suppress_metadata
+    }
+
+    /// Maximum time to wait for the initial handshake packet of a new connection
+    /// (defaults to `None`, i.e. wait forever).
+    ///
+    /// Protects against servers, that accept a TCP connection but never send the handshake.
+    /// [`DriverError::HandshakeTimeout`] is returned if the timeout is exceeded.
+    ///
+    /// # Connection URL
+    ///
+    /// You can use `handshake_timeout` URL parameter to set this value (in milliseconds). E.g.
+    ///
+    /// ```
+    /// # use mysql_async::*;
+    /// # use std::time::Duration;
+    /// # fn main() -> Result<()> {
+    /// let opts = Opts::from_url("mysql://localhost/db?handshake_timeout=5000")?;
+    /// assert_eq!(opts.handshake_timeout(), Some(Duration::from_secs(5)));
+    /// # Ok(()) }
+    /// ```
+    pub fn handshake_timeout(&self) -> Option<Duration> {
+        self.inner.mysql_opts.handshake_timeout
+    }
+
+    /// What to do if the server-side limit of prepared statements (`max_prepared_stmt_count`)
+    /// is reached, i.e. if the server responds to `COM_STMT_PREPARE` with the error `1461`
+    /// (defaults to [`PreparedStmtLimitPolicy::Error`]).
+    ///
+    /// The limit is shared by all connections to the server, so a large pool with per-connection
+    /// statement caches may exhaust it.
+    ///
+    /// # Connection URL
+    ///
+    /// You can use `on_prepared_limit` URL parameter to set this value
+    /// (`error` or `evict_cached`). E.g.
+    ///
+    /// ```
+    /// # use mysql_async::*;
+    /// # fn main() -> Result<()> {
+    /// let opts = Opts::from_url("mysql://localhost/db?on_prepared_limit=evict_cached")?;
+    /// assert_eq!(opts.on_prepared_limit(), PreparedStmtLimitPolicy::EvictCached);
+    /// # Ok(()) }
+    /// ```
+    pub fn on_prepared_limit(&self) -> PreparedStmtLimitPolicy {
+        self.inner.mysql_opts.on_prepared_limit
+    }
+
+    /// If `false`, then [`Conn::disconnect`] won't send `COM_QUIT` and will just close
+    /// the socket (defaults to `true`).
+    ///
+    /// It saves a write per closed connection, but the server will report the connection
+    /// as aborted (see `Aborted_clients` status variable).
+    ///
+    /// # Connection URL
+    ///
+    /// You can use `send_quit_on_disconnect` URL parameter to set this value. E.g.
+    ///
+    /// ```
+    /// # use mysql_async::*;
+    /// # fn main() -> Result<()> {
+    /// let opts = Opts::from_url("mysql://localhost/db?send_quit_on_disconnect=false")?;
+    /// assert_eq!(opts.send_quit_on_disconnect(), false);
+    /// # Ok(()) }
+    /// ```
+    ///
+    /// [`Conn::disconnect`]: crate::Conn::disconnect
+    pub fn send_quit_on_disconnect(&self) -> bool {
+        self.inner.mysql_opts.send_quit_on_disconnect
+    }
+
+    /// Comment, that will be prepended to `START TRANSACTION` and to every statement issued
+    /// within a transaction (defaults to `None`).
+    ///
+    /// It is meant for proxies (e.g. ProxySQL), that route statements by query comments,
+    /// so that every statement of a transaction reaches the same backend. `{connection_id}`
+    /// placeholder is replaced with [`Conn::id`]. Note, that already prepared statements are
+    /// executed without a query text, so only their preparation is hinted.
+    ///
+    /// # Connection URL
+    ///
+    /// You can use `transaction_hint` URL parameter to set this value. E.g.
+    ///
+    /// ```
+    /// # use mysql_async::*;
+    /// # fn main() -> Result<()> {
+    /// let opts = Opts::from_url("mysql://localhost/db?transaction_hint=/*%20hostgroup=1%20*/")?;
+    /// assert_eq!(opts.transaction_hint(), Some("/* hostgroup=1 */"));
+    /// # Ok(()) }
+    /// ```
+    ///
+    /// [`Conn::id`]: crate::Conn::id
+    pub fn transaction_hint(&self) -> Option<&str> {
+        self.inner.mysql_opts.transaction_hint.as_deref()
+    }
+
+    /// Queries, that take longer than this threshold, are passed to
+    /// [`Opts::slow_query_logger`] (defaults to `None`, i.e. queries aren't logged).
+    ///
+    /// Applies to text queries and statement executions (i.e. to [`crate::prelude::Queryable`]
+    /// methods). Results are read lazily, so the execution time is measured from sending
+    /// the command to receiving the first result set header, i.e. the time of reading rows
+    /// isn't included.
+    ///
+    /// # Connection URL
+    ///
+    /// You can use `slow_query_threshold` URL parameter to set this value (in milliseconds). E.g.
+    ///
+    /// ```
+    /// # use mysql_async::*;
+    /// # use std::time::Duration;
+    /// # fn main() -> Result<()> {
+    /// let opts = Opts::from_url("mysql://localhost/db?slow_query_threshold=500")?;
+    /// assert_eq!(opts.slow_query_threshold(), Some(Duration::from_millis(500)));
+    /// # Ok(()) }
+    /// ```
+    pub fn slow_query_threshold(&self) -> Option<Duration> {
+        self.inner.mysql_opts.slow_query_threshold
+    }
+
+    /// Function, that receives queries, that took longer than [`Opts::slow_query_threshold`]
+    /// (defaults to `None`).
+    ///
+    /// [`SlowQuery`] contains the digest of the query (i.e. values are redacted)
+    /// and its execution time. The logger is called on the connection task,
+    /// so it should return quickly.
+    ///
+    /// ```
+    /// # use mysql_async::*;
+    /// # use std::time::Duration;
+    /// let opts = Opts::from(
+    ///     OptsBuilder::default()
+    ///         .slow_query_threshold(Some(Duration::from_millis(500)))
+    ///         .slow_query_logger(|query: SlowQuery| {
+    ///             eprintln!("slow query ({:?}): {}", query.elapsed(), query.digest())
+    ///         }),
+    /// );
+    /// assert!(opts.slow_query_logger().is_some());
+    /// ```
+    pub fn slow_query_logger(&self) -> Option<&Arc<SlowQueryLogger>> {
+        self.inner
+            .mysql_opts
+            .slow_query_logger
+            .as_ref()
+            .map(|logger| &logger.0)
+    }
+
+    /// Whether to prepend a `/* file:line */` comment with the source location of the caller
+    /// to queries (defaults to `false`). Requires the `caller-location` feature.
+    ///
+    /// The location is that of the call of a [`crate::prelude::Queryable`] method
+    /// (e.g. `conn.query_drop(..)`), so it shows up in the server's slow query log,
+    /// the process list and `performance_schema`. Note, that a prepared statement
+    /// is cached by its original text, so executions of a cached statement
+    /// are attributed to the location, where it was prepared.
+    ///
+    /// # Connection URL
+    ///
+    /// You can use `tag_caller_location` URL parameter to set this value. E.g.
+    ///
+    /// ```
+    /// # use mysql_async::*;
+    /// # fn main() -> Result<()> {
+    /// let opts = Opts::from_url("mysql://localhost/db?tag_caller_location=true")?;
+    /// assert!(opts.tag_caller_location());
+    /// # Ok(()) }
+    /// ```
+    #[cfg(feature = "caller-location")]
+    pub fn tag_caller_location(&self) -> bool {
+        self.inner.mysql_opts.tag_caller_location
+    }
+
+    pub(crate) fn get_capabilities(&self) -> CapabilityFlags {
+        let mut out = CapabilityFlags::CLIENT_PROTOCOL_41
+            | CapabilityFlags::CLIENT_SECURE_CONNECTION
+            | CapabilityFlags::CLIENT_LONG_PASSWORD
+            | CapabilityFlags::CLIENT_TRANSACTIONS
+            | CapabilityFlags::CLIENT_LOCAL_FILES
+            | CapabilityFlags::CLIENT_MULTI_STATEMENTS
+            | CapabilityFlags::CLIENT_MULTI_RESULTS
+            | CapabilityFlags::CLIENT_PS_MULTI_RESULTS
+            | CapabilityFlags::CLIENT_DEPRECATE_EOF
+            | CapabilityFlags::CLIENT_PLUGIN_AUTH
+            | CapabilityFlags::CLIENT_CONNECT_ATTRS
+            | CapabilityFlags::CLIENT_SESSION_TRACK;
+
+        if self.inner.mysql_opts.db_name.is_some() {
+            out |= CapabilityFlags::CLIENT_CONNECT_WITH_DB;
+        }
+        if self.inner.mysql_opts.ssl_opts.is_some() {
+            out |= CapabilityFlags::CLIENT_SSL;
+        }
+        if self.inner.mysql_opts.compression.is_some() {
+            out |= CapabilityFlags::CLIENT_COMPRESS;
+        }
+
+        out
+    }
+}
+
+impl Default for MysqlOpts {
+    fn default() -> MysqlOpts {
+        MysqlOpts {
+            user: None,
+            pass: None,
+            db_name: None,
+            init: vec![],
+            connect_attrs: HashMap::new(),
+            capture_handshake: None,
+            query_rewriter: None,
+            tcp_keepalive: None,
+            tcp_nodelay: true,
+            local_infile_handler: None,
+            pool_opts: Default::default(),
+            conn_ttl: None,
+            stmt_cache_size: DEFAULT_STMT_CACHE_SIZE,
+            ssl_opts: None,
+            prefer_socket: true,
+            socket: None,
+            compression: None,
+            compression_threshold: 50,
+            split_oversized_queries: false,
+            retain_last_statement: false,
+            proxy_protocol: None,
+            proxy_protocol_addrs: None,
+            server_public_key: None,
+            read_buffer_size: DEFAULT_BUFFER_SIZE,
+            write_buffer_size: DEFAULT_BUFFER_SIZE,
+            reuse_buffers: false,
+            always_prepare: false,
+            strict_init: false,
+            capture_warnings: false,
+            default_isolation_level: None,
+            sql_mode: None,
+            default_select_limit: None,
+            isolate_tx_statements: false,
+            require_multi_results: false,
+            suppress_metadata: false,
+            handshake_timeout: None,
+            on_prepared_limit: PreparedStmtLimitPolicy::Error,
+            send_quit_on_disconnect: true,
+            transaction_hint: None,
+            slow_query_threshold: None,
+            slow_query_logger: None,
+            #[cfg(feature = "caller-location")]
+            tag_caller_location: false,
+        }
+    }
+}
+
+/// Connection pool constraints.
+///
+/// This type stores `min` and `max` constraints for [`crate::Pool`] and ensures that `min <= max`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct PoolConstraints {
+    min: usize,
+    max: usize,
+}
+
+impl PoolConstraints {
+    /// Creates new [`PoolConstraints`] if constraints are valid (`min <= max`).
+    ///
+    /// # Connection URL
+    ///
+    /// You can use `pool_min` and `pool_max` URL parameters to define pool constraints.
+    ///
+    /// ```
+    /// # use mysql_async::*;
+    /// # fn main() -> Result<()> {
+    /// let opts = Opts::from_url("mysql://localhost/db?pool_min=0&pool_max=151")?;
+    /// assert_eq!(opts.pool_opts().constraints(), PoolConstraints::new(0, 151).unwrap());
+    /// # Ok(()) }
+    /// ```
+    pub fn new(min: usize, max: usize) -> Option<PoolConstraints> {
+        if min <= max {
+            Some(PoolConstraints { min, max })
+        } else {
+            None
+        }
     }
 
     /// Lower bound of this pool constraints.
@@ -841,6 +1946,53 @@ impl OptsBuilder {
         self
     }
 
+    /// Defines connection attributes. See [`Opts::connect_attrs`].
+    ///
+    /// Replaces previously defined attributes (including `program_name` and `program_version`).
+    pub fn connect_attrs<K, V, I>(mut self, connect_attrs: I) -> Self
+    where
+        K: Into<String>,
+        V: Into<String>,
+        I: IntoIterator<Item = (K, V)>,
+    {
+        self.opts.connect_attrs = connect_attrs
+            .into_iter()
+            .map(|(name, value)| (name.into(), value.into()))
+            .collect();
+        self
+    }
+
+    /// Defines `capture_handshake` option. See [`Opts::capture_handshake`].
+    pub fn capture_handshake(mut self, frames: Option<Arc<Mutex<Vec<HandshakeFrame>>>>) -> Self {
+        self.opts.capture_handshake = frames.map(HandshakeCapture);
+        self
+    }
+
+    /// Defines `query_rewriter` option. See [`Opts::query_rewriter`].
+    pub fn query_rewriter<F>(mut self, rewriter: F) -> Self
+    where
+        F: for<'a> Fn(&'a str) -> std::result::Result<Cow<'a, str>, String> + Send + Sync + 'static,
+    {
+        self.opts.query_rewriter = Some(QueryRewriterObject(Arc::new(rewriter)));
+        self
+    }
+
+    /// Defines `program_name` connection attribute. See [`Opts::connect_attrs`].
+    pub fn program_name<T: Into<String>>(mut self, program_name: T) -> Self {
+        self.opts
+            .connect_attrs
+            .insert("program_name".into(), program_name.into());
+        self
+    }
+
+    /// Defines `program_version` connection attribute. See [`Opts::connect_attrs`].
+    pub fn program_version<T: Into<String>>(mut self, program_version: T) -> Self {
+        self.opts
+            .connect_attrs
+            .insert("program_version".into(), program_version.into());
+        self
+    }
+
     /// Defines `tcp_keepalive` option. See [`Opts::tcp_keepalive`].
     pub fn tcp_keepalive<T: Into<u32>>(mut self, tcp_keepalive: Option<T>) -> Self {
         self.opts.tcp_keepalive = tcp_keepalive.map(Into::into);
@@ -901,9 +2053,173 @@ impl OptsBuilder {
         self
     }
 
-    /// Defines compression. See [`Opts::compression`].
-    pub fn compression<T: Into<Option<crate::Compression>>>(mut self, compression: T) -> Self {
-        self.opts.compression = compression.into();
+    /// Defines compression. See [`Opts::compression`].
+    pub fn compression<T: Into<Option<crate::Compression>>>(mut self, compression: T) -> Self {
+        self.opts.compression = compression.into();
+        self
+    }
+
+    /// Defines `compression_threshold` option. See [`Opts::compression_threshold`].
+    pub fn compression_threshold(mut self, compression_threshold: usize) -> Self {
+        self.opts.compression_threshold = compression_threshold;
+        self
+    }
+
+    /// Defines `split_oversized_queries` option. See [`Opts::split_oversized_queries`].
+    pub fn split_oversized_queries(mut self, split_oversized_queries: bool) -> Self {
+        self.opts.split_oversized_queries = split_oversized_queries;
+        self
+    }
+
+    /// Defines `retain_last_statement` option. See [`Opts::retain_last_statement`].
+    pub fn retain_last_statement(mut self, retain_last_statement: bool) -> Self {
+        self.opts.retain_last_statement = retain_last_statement;
+        self
+    }
+
+    /// Defines `proxy_protocol` option. See [`Opts::proxy_protocol`].
+    pub fn proxy_protocol<T: Into<Option<ProxyProtocolVersion>>>(
+        mut self,
+        proxy_protocol: T,
+    ) -> Self {
+        self.opts.proxy_protocol = proxy_protocol.into();
+        self
+    }
+
+    /// Defines `proxy_protocol_addrs` option as `(source, destination)` pair.
+    /// See [`Opts::proxy_protocol_addrs`].
+    pub fn proxy_protocol_addrs<T: Into<Option<(SocketAddr, SocketAddr)>>>(
+        mut self,
+        proxy_protocol_addrs: T,
+    ) -> Self {
+        self.opts.proxy_protocol_addrs = proxy_protocol_addrs.into();
+        self
+    }
+
+    /// Defines path to the server RSA public key. See [`Opts::server_public_key`].
+    pub fn server_public_key<T: Into<PathBuf>>(mut self, server_public_key: Option<T>) -> Self {
+        self.opts.server_public_key = server_public_key.map(Into::into);
+        self
+    }
+
+    /// Defines initial capacity of the read buffer. See [`Opts::read_buffer_size`].
+    pub fn read_buffer_size(mut self, read_buffer_size: usize) -> Self {
+        self.opts.read_buffer_size = read_buffer_size;
+        self
+    }
+
+    /// Defines initial capacity of the write buffer. See [`Opts::write_buffer_size`].
+    pub fn write_buffer_size(mut self, write_buffer_size: usize) -> Self {
+        self.opts.write_buffer_size = write_buffer_size;
+        self
+    }
+
+    /// Defines `reuse_buffers` option. See [`Opts::reuse_buffers`].
+    pub fn reuse_buffers(mut self, reuse_buffers: bool) -> Self {
+        self.opts.reuse_buffers = reuse_buffers;
+        self
+    }
+
+    /// Defines `always_prepare` option. See [`Opts::always_prepare`].
+    pub fn always_prepare(mut self, always_prepare: bool) -> Self {
+        self.opts.always_prepare = always_prepare;
+        self
+    }
+
+    /// Defines `strict_init` option. See [`Opts::strict_init`].
+    pub fn strict_init(mut self, strict_init: bool) -> Self {
+        self.opts.strict_init = strict_init;
+        self
+    }
+
+    /// Defines `capture_warnings` option. See [`Opts::capture_warnings`].
+    pub fn capture_warnings(mut self, capture_warnings: bool) -> Self {
+        self.opts.capture_warnings = capture_warnings;
+        self
+    }
+
+    /// Defines `default_isolation_level` option. See [`Opts::default_isolation_level`].
+    pub fn default_isolation_level<T: Into<Option<IsolationLevel>>>(
+        mut self,
+        default_isolation_level: T,
+    ) -> Self {
+        self.opts.default_isolation_level = default_isolation_level.into();
+        self
+    }
+
+    /// Defines `sql_mode` option. See [`Opts::sql_mode`].
+    pub fn sql_mode<T: Into<String>>(mut self, sql_mode: Option<T>) -> Self {
+        self.opts.sql_mode = sql_mode.map(Into::into);
+        self
+    }
+
+    /// Defines `default_select_limit` option. See [`Opts::default_select_limit`].
+    pub fn default_select_limit<T: Into<Option<u64>>>(mut self, default_select_limit: T) -> Self {
+        self.opts.default_select_limit = default_select_limit.into();
+        self
+    }
+
+    /// Defines `isolate_tx_statements` option. See [`Opts::isolate_tx_statements`].
+    pub fn isolate_tx_statements(mut self, isolate_tx_statements: bool) -> Self {
+        self.opts.isolate_tx_statements = isolate_tx_statements;
+        self
+    }
+
+    /// Defines `require_multi_results` option. See [`Opts::require_multi_results`].
+    pub fn require_multi_results(mut self, require_multi_results: bool) -> Self {
+        self.opts.require_multi_results = require_multi_results;
+        self
+    }
+
+    /// Defines `suppress_metadata` option. See [`Opts::suppress_metadata`].
+    pub fn suppress_metadata(mut self, suppress_metadata: bool) -> Self {
+        self.opts.suppress_metadata = suppress_metadata;
+        self
+    }
+
+    /// Defines `handshake_timeout` option. See [`Opts::handshake_timeout`].
+    pub fn handshake_timeout<T: Into<Option<Duration>>>(mut self, handshake_timeout: T) -> Self {
+        self.opts.handshake_timeout = handshake_timeout.into();
+        self
+    }
+
+    /// Defines `on_prepared_limit` option. See [`Opts::on_prepared_limit`].
+    pub fn on_prepared_limit(mut self, on_prepared_limit: PreparedStmtLimitPolicy) -> Self {
+        self.opts.on_prepared_limit = on_prepared_limit;
+        self
+    }
+
+    /// Defines `send_quit_on_disconnect` option. See [`Opts::send_quit_on_disconnect`].
+    pub fn send_quit_on_disconnect(mut self, send_quit_on_disconnect: bool) -> Self {
+        self.opts.send_quit_on_disconnect = send_quit_on_disconnect;
+        self
+    }
+
+    /// Defines `transaction_hint` option. See [`Opts::transaction_hint`].
+    pub fn transaction_hint<T: Into<String>>(mut self, transaction_hint: Option<T>) -> Self {
+        self.opts.transaction_hint = transaction_hint.map(Into::into);
+        self
+    }
+
+    /// Defines `slow_query_threshold` option. See [`Opts::slow_query_threshold`].
+    pub fn slow_query_threshold<T: Into<Option<Duration>>>(mut self, threshold: T) -> Self {
+        self.opts.slow_query_threshold = threshold.into();
+        self
+    }
+
+    /// Defines `tag_caller_location` option. See [`Opts::tag_caller_location`].
+    #[cfg(feature = "caller-location")]
+    pub fn tag_caller_location(mut self, tag_caller_location: bool) -> Self {
+        self.opts.tag_caller_location = tag_caller_location;
+        self
+    }
+
+    /// Defines `slow_query_logger` option. See [`Opts::slow_query_logger`].
+    pub fn slow_query_logger<F>(mut self, logger: F) -> Self
+    where
+        F: Fn(SlowQuery) + Send + Sync + 'static,
+    {
+        self.opts.slow_query_logger = Some(SlowQueryLoggerObject(Arc::new(logger)));
         self
     }
 }
@@ -922,6 +2238,101 @@ impl From<OptsBuilder> for Opts {
     }
 }
 
+/// Composes options from the given environment variables (see [`Opts::from_env`]).
+fn opts_from_env<I>(vars: I) -> std::result::Result<Opts, UrlError>
+where
+    I: IntoIterator<Item = (OsString, OsString)>,
+{
+    let mut env = BTreeMap::new();
+    for (name, value) in vars {
+        let name = match name.into_string() {
+            Ok(name) if name == "DATABASE_URL" || name.starts_with("MYSQL_") => name,
+            _ => continue,
+        };
+        match value.into_string() {
+            Ok(value) => {
+                env.insert(name, value);
+            }
+            Err(value) => {
+                return Err(UrlError::InvalidParamValue {
+                    param: name,
+                    value: value.to_string_lossy().into_owned(),
+                })
+            }
+        }
+    }
+
+    if let Some(url) = env.get("DATABASE_URL") {
+        return Opts::from_url(url);
+    }
+
+    let invalid = |name: &str, value: &str| UrlError::InvalidParamValue {
+        param: name.into(),
+        value: value.into(),
+    };
+
+    let host = env
+        .get("MYSQL_HOST")
+        .map(String::as_str)
+        .unwrap_or("127.0.0.1");
+    let url = if host.contains(':') {
+        format!("mysql://[{}]", host)
+    } else {
+        format!("mysql://{}", host)
+    };
+    let mut url = Url::parse(&url).map_err(|_| invalid("MYSQL_HOST", host))?;
+    if let Some(port) = env.get("MYSQL_PORT") {
+        let port = u16::from_str(port).map_err(|_| invalid("MYSQL_PORT", port))?;
+        url.set_port(Some(port)).map_err(|_| UrlError::Invalid)?;
+    }
+    if let Some(user) = env.get("MYSQL_USER") {
+        url.set_username(user)
+            .map_err(|_| invalid("MYSQL_USER", user))?;
+    }
+    if let Some(pass) = env.get("MYSQL_PASSWORD") {
+        url.set_password(Some(pass))
+            .map_err(|_| invalid("MYSQL_PASSWORD", pass))?;
+    }
+    if let Some(db_name) = env.get("MYSQL_DATABASE") {
+        url.path_segments_mut()
+            .map_err(|_| UrlError::Invalid)?
+            .push(db_name);
+    }
+    for (name, value) in &env {
+        if let Some(param) = name.strip_prefix(ENV_PARAM_PREFIX) {
+            url.query_pairs_mut()
+                .append_pair(&param.to_lowercase(), value);
+        }
+    }
+
+    let env_name = |param: String| format!("{}{}", ENV_PARAM_PREFIX, param.to_uppercase());
+    Opts::from_url(url.as_str()).map_err(|err| match err {
+        UrlError::InvalidParamValue { param, value } => UrlError::InvalidParamValue {
+            param: env_name(param),
+            value,
+        },
+        UrlError::UnknownParameter { param } => UrlError::UnknownParameter {
+            param: env_name(param),
+        },
+        err => err,
+    })
+}
+
+/// Extracts the port part of the authority of a raw (possibly malformed) connection URL.
+///
+/// Returns an empty string if there is no port.
+fn get_raw_port_from_url(url: &str) -> &str {
+    let authority = url.split_once("://").map(|(_, rest)| rest).unwrap_or(url);
+    let authority = authority.split(['/', '?', '#']).next().unwrap_or_default();
+    let host_port = authority.rsplit('@').next().unwrap_or_default();
+    // skip the IPv6 address, if any
+    let port_part = host_port.rsplit(']').next().unwrap_or_default();
+    port_part
+        .split_once(':')
+        .map(|(_, port)| port)
+        .unwrap_or_default()
+}
+
 fn get_opts_user_from_url(url: &Url) -> Option<String> {
     let user = url.username();
     if user != "" {
@@ -1038,6 +2449,18 @@ fn mysqlopts_from_url(url: &Url) -> std::result::Result<MysqlOpts, UrlError> {
                     });
                 }
             }
+        } else if key == "idle_liveness_check" {
+            match bool::from_str(&*value) {
+                Ok(value) => {
+                    opts.pool_opts = opts.pool_opts.clone().with_idle_liveness_check(value)
+                }
+                _ => {
+                    return Err(UrlError::InvalidParamValue {
+                        param: "idle_liveness_check".into(),
+                        value,
+                    });
+                }
+            }
         } else if key == "conn_ttl" {
             match u64::from_str(&*value) {
                 Ok(value) => opts.conn_ttl = Some(Duration::from_secs(value)),
@@ -1053,7 +2476,7 @@ fn mysqlopts_from_url(url: &Url) -> std::result::Result<MysqlOpts, UrlError> {
                 Ok(value) => opts.tcp_keepalive = Some(value),
                 _ => {
                     return Err(UrlError::InvalidParamValue {
-                        param: "tcp_keepalive_ms".into(),
+                        param: "tcp_keepalive".into(),
                         value,
                     });
                 }
@@ -1092,8 +2515,231 @@ fn mysqlopts_from_url(url: &Url) -> std::result::Result<MysqlOpts, UrlError> {
                     });
                 }
             }
+        } else if key == "split_oversized_queries" {
+            match bool::from_str(&value) {
+                Ok(split_oversized_queries) => {
+                    opts.split_oversized_queries = split_oversized_queries;
+                }
+                _ => {
+                    return Err(UrlError::InvalidParamValue {
+                        param: "split_oversized_queries".into(),
+                        value,
+                    });
+                }
+            }
+        } else if key == "retain_last_statement" {
+            match bool::from_str(&value) {
+                Ok(retain_last_statement) => {
+                    opts.retain_last_statement = retain_last_statement;
+                }
+                _ => {
+                    return Err(UrlError::InvalidParamValue {
+                        param: "retain_last_statement".into(),
+                        value,
+                    });
+                }
+            }
+        } else if key == "proxy_protocol" {
+            match value.as_str() {
+                "v1" | "1" => opts.proxy_protocol = Some(ProxyProtocolVersion::V1),
+                "v2" | "2" => opts.proxy_protocol = Some(ProxyProtocolVersion::V2),
+                _ => {
+                    return Err(UrlError::InvalidParamValue {
+                        param: "proxy_protocol".into(),
+                        value,
+                    });
+                }
+            }
         } else if key == "socket" {
             opts.socket = Some(value)
+        } else if key == "server_public_key" {
+            opts.server_public_key = Some(value.into())
+        } else if key == "read_buffer_size" {
+            match usize::from_str(&value) {
+                Ok(read_buffer_size) => opts.read_buffer_size = read_buffer_size,
+                _ => {
+                    return Err(UrlError::InvalidParamValue {
+                        param: "read_buffer_size".into(),
+                        value,
+                    });
+                }
+            }
+        } else if key == "always_prepare" {
+            match bool::from_str(&value) {
+                Ok(always_prepare) => opts.always_prepare = always_prepare,
+                _ => {
+                    return Err(UrlError::InvalidParamValue {
+                        param: "always_prepare".into(),
+                        value,
+                    });
+                }
+            }
+        } else if key == "strict_init" {
+            match bool::from_str(&value) {
+                Ok(strict_init) => opts.strict_init = strict_init,
+                _ => {
+                    return Err(UrlError::InvalidParamValue {
+                        param: "strict_init".into(),
+                        value,
+                    });
+                }
+            }
+        } else if key == "capture_warnings" {
+            match bool::from_str(&value) {
+                Ok(capture_warnings) => opts.capture_warnings = capture_warnings,
+                _ => {
+                    return Err(UrlError::InvalidParamValue {
+                        param: "capture_warnings".into(),
+                        value,
+                    });
+                }
+            }
+        } else if key == "transaction_hint" {
+            opts.transaction_hint = Some(value);
+        } else if key == "send_quit_on_disconnect" {
+            match bool::from_str(&value) {
+                Ok(send_quit_on_disconnect) => {
+                    opts.send_quit_on_disconnect = send_quit_on_disconnect
+                }
+                _ => {
+                    return Err(UrlError::InvalidParamValue {
+                        param: "send_quit_on_disconnect".into(),
+                        value,
+                    });
+                }
+            }
+        } else if key == "on_prepared_limit" {
+            match value.as_str() {
+                "error" => opts.on_prepared_limit = PreparedStmtLimitPolicy::Error,
+                "evict_cached" => opts.on_prepared_limit = PreparedStmtLimitPolicy::EvictCached,
+                _ => {
+                    return Err(UrlError::InvalidParamValue {
+                        param: "on_prepared_limit".into(),
+                        value,
+                    });
+                }
+            }
+        } else if key == "handshake_timeout" {
+            match u64::from_str(&value) {
+                Ok(value) => opts.handshake_timeout = Some(Duration::from_millis(value)),
+                _ => {
+                    return Err(UrlError::InvalidParamValue {
+                        param: "handshake_timeout".into(),
+                        value,
+                    });
+                }
+            }
+        } else if key == "slow_query_threshold" {
+            match u64::from_str(&value) {
+                Ok(value) => opts.slow_query_threshold = Some(Duration::from_millis(value)),
+                _ => {
+                    return Err(UrlError::InvalidParamValue {
+                        param: "slow_query_threshold".into(),
+                        value,
+                    });
+                }
+            }
+        } else if key == "tag_caller_location" {
+            #[cfg(not(feature = "caller-location"))]
+            return Err(UrlError::FeatureRequired {
+                feature: "caller-location".into(),
+                param: key,
+            });
+            #[cfg(feature = "caller-location")]
+            match bool::from_str(&value) {
+                Ok(tag_caller_location) => opts.tag_caller_location = tag_caller_location,
+                _ => {
+                    return Err(UrlError::InvalidParamValue {
+                        param: "tag_caller_location".into(),
+                        value,
+                    });
+                }
+            }
+        } else if key == "suppress_metadata" {
+            match bool::from_str(&value) {
+                Ok(suppress_metadata) => opts.suppress_metadata = suppress_metadata,
+                _ => {
+                    return Err(UrlError::InvalidParamValue {
+                        param: "suppress_metadata".into(),
+                        value,
+                    });
+                }
+            }
+        } else if key == "sql_mode" {
+            opts.sql_mode = Some(value);
+        } else if key == "default_select_limit" {
+            match u64::from_str(&value) {
+                Ok(default_select_limit) => opts.default_select_limit = Some(default_select_limit),
+                _ => {
+                    return Err(UrlError::InvalidParamValue {
+                        param: "default_select_limit".into(),
+                        value,
+                    });
+                }
+            }
+        } else if key == "isolate_tx_statements" {
+            match bool::from_str(&value) {
+                Ok(isolate_tx_statements) => opts.isolate_tx_statements = isolate_tx_statements,
+                _ => {
+                    return Err(UrlError::InvalidParamValue {
+                        param: "isolate_tx_statements".into(),
+                        value,
+                    });
+                }
+            }
+        } else if key == "require_multi_results" {
+            match bool::from_str(&value) {
+                Ok(require_multi_results) => opts.require_multi_results = require_multi_results,
+                _ => {
+                    return Err(UrlError::InvalidParamValue {
+                        param: "require_multi_results".into(),
+                        value,
+                    });
+                }
+            }
+        } else if key == "default_isolation_level" {
+            opts.default_isolation_level = match &*value {
+                "read_uncommitted" => Some(IsolationLevel::ReadUncommitted),
+                "read_committed" => Some(IsolationLevel::ReadCommitted),
+                "repeatable_read" => Some(IsolationLevel::RepeatableRead),
+                "serializable" => Some(IsolationLevel::Serializable),
+                _ => {
+                    return Err(UrlError::InvalidParamValue {
+                        param: "default_isolation_level".into(),
+                        value,
+                    });
+                }
+            };
+        } else if key == "write_buffer_size" {
+            match usize::from_str(&value) {
+                Ok(write_buffer_size) => opts.write_buffer_size = write_buffer_size,
+                _ => {
+                    return Err(UrlError::InvalidParamValue {
+                        param: "write_buffer_size".into(),
+                        value,
+                    });
+                }
+            }
+        } else if key == "reuse_buffers" {
+            match bool::from_str(&value) {
+                Ok(reuse_buffers) => opts.reuse_buffers = reuse_buffers,
+                _ => {
+                    return Err(UrlError::InvalidParamValue {
+                        param: "reuse_buffers".into(),
+                        value,
+                    });
+                }
+            }
+        } else if key == "compression_threshold" {
+            match usize::from_str(&value) {
+                Ok(compression_threshold) => opts.compression_threshold = compression_threshold,
+                _ => {
+                    return Err(UrlError::InvalidParamValue {
+                        param: "compression_threshold".into(),
+                        value,
+                    });
+                }
+            }
         } else if key == "compression" {
             if value == "fast" {
                 opts.compression = Some(crate::Compression::fast());
@@ -1144,10 +2790,46 @@ impl<T: AsRef<str> + Sized> From<T> for Opts {
 
 #[cfg(test)]
 mod test {
-    use super::{HostPortOrUrl, MysqlOpts, Opts, Url};
-    use crate::error::UrlError::InvalidParamValue;
+    use super::{ClientIdentity, HostPortOrUrl, MysqlOpts, Opts, SslOpts, Url};
+    use crate::error::UrlError::{
+        InvalidParamValue, InvalidPort, UnknownParameter, UnsupportedScheme,
+    };
+
+    use std::{path::Path, str::FromStr, sync::Arc};
 
-    use std::str::FromStr;
+    #[test]
+    fn should_resolve_client_identity_by_host() {
+        let identity = ClientIdentity::new(vec![1, 2, 3]).with_password(Some("secret"));
+        let identity_clone = identity.clone();
+        let resolver = Arc::new(move |host: &str| {
+            if host == "db1.example.com" {
+                Some(identity_clone.clone())
+            } else {
+                None
+            }
+        });
+        let ssl_opts = SslOpts::default()
+            .with_pkcs12_path(Some(Path::new("does-not-exist.p12")))
+            .with_client_cert_resolver(resolver.clone());
+
+        assert_eq!(
+            ssl_opts.client_identity("db1.example.com").unwrap(),
+            Some(identity)
+        );
+        // the resolver takes precedence over the pkcs12 path
+        assert_eq!(ssl_opts.client_identity("db2.example.com").unwrap(), None);
+
+        assert_eq!(
+            ssl_opts,
+            SslOpts::default()
+                .with_pkcs12_path(Some(Path::new("does-not-exist.p12")))
+                .with_client_cert_resolver(resolver)
+        );
+        assert!(SslOpts::default()
+            .with_pkcs12_path(Some(Path::new("does-not-exist.p12")))
+            .client_identity("db1.example.com")
+            .is_err());
+    }
 
     #[test]
     fn test_builder_eq_url() {
@@ -1176,6 +2858,78 @@ mod test {
         assert_eq!(url_opts.prefer_socket(), builder_opts.prefer_socket());
         assert_eq!(url_opts.socket(), builder_opts.socket());
         assert_eq!(url_opts.compression(), builder_opts.compression());
+        assert_eq!(
+            url_opts.compression_threshold(),
+            builder_opts.compression_threshold()
+        );
+        assert_eq!(
+            url_opts.split_oversized_queries(),
+            builder_opts.split_oversized_queries()
+        );
+        assert_eq!(
+            url_opts.retain_last_statement(),
+            builder_opts.retain_last_statement()
+        );
+        assert_eq!(url_opts.proxy_protocol(), builder_opts.proxy_protocol());
+        assert_eq!(
+            url_opts.server_public_key(),
+            builder_opts.server_public_key()
+        );
+        assert_eq!(url_opts.read_buffer_size(), builder_opts.read_buffer_size());
+        assert_eq!(
+            url_opts.write_buffer_size(),
+            builder_opts.write_buffer_size()
+        );
+        assert_eq!(url_opts.reuse_buffers(), builder_opts.reuse_buffers());
+        assert_eq!(url_opts.strict_init(), builder_opts.strict_init());
+        assert_eq!(url_opts.capture_warnings(), builder_opts.capture_warnings());
+        assert_eq!(
+            url_opts.default_isolation_level(),
+            builder_opts.default_isolation_level()
+        );
+        assert_eq!(url_opts.sql_mode(), builder_opts.sql_mode());
+        assert_eq!(
+            url_opts.default_select_limit(),
+            builder_opts.default_select_limit()
+        );
+        assert_eq!(
+            url_opts.isolate_tx_statements(),
+            builder_opts.isolate_tx_statements()
+        );
+        assert_eq!(
+            url_opts.require_multi_results(),
+            builder_opts.require_multi_results()
+        );
+        assert_eq!(
+            url_opts.suppress_metadata(),
+            builder_opts.suppress_metadata()
+        );
+        assert_eq!(
+            url_opts.handshake_timeout(),
+            builder_opts.handshake_timeout()
+        );
+        assert_eq!(
+            url_opts.on_prepared_limit(),
+            builder_opts.on_prepared_limit()
+        );
+        assert_eq!(
+            url_opts.send_quit_on_disconnect(),
+            builder_opts.send_quit_on_disconnect()
+        );
+        assert_eq!(url_opts.transaction_hint(), builder_opts.transaction_hint());
+        assert_eq!(
+            url_opts.slow_query_threshold(),
+            builder_opts.slow_query_threshold()
+        );
+        #[cfg(feature = "caller-location")]
+        assert_eq!(
+            url_opts.tag_caller_location(),
+            builder_opts.tag_caller_location()
+        );
+        assert_eq!(
+            url_opts.proxy_protocol_addrs(),
+            builder_opts.proxy_protocol_addrs()
+        );
         assert_eq!(
             url_opts.hostport_or_url().get_ip_or_hostname(),
             builder_opts.hostport_or_url().get_ip_or_hostname()
@@ -1235,6 +2989,124 @@ mod test {
         let _: Opts = opts.into();
     }
 
+    #[test]
+    fn should_report_invalid_port() {
+        let err = Opts::from_url("mysql://usr:pw@localhost:33o6/foo").unwrap_err();
+        assert_eq!(
+            err,
+            InvalidPort {
+                port: "33o6".into()
+            }
+        );
+
+        let err = Opts::from_url("mysql://[::1]:99999/foo").unwrap_err();
+        assert_eq!(
+            err,
+            InvalidPort {
+                port: "99999".into()
+            }
+        );
+    }
+
+    #[test]
+    fn should_report_unknown_query_param() {
+        let err = Opts::from_url("mysql://localhost/foo?foo=bar").unwrap_err();
+        assert_eq!(
+            err,
+            UnknownParameter {
+                param: "foo".into()
+            }
+        );
+    }
+
+    #[test]
+    fn should_compose_opts_from_env() {
+        use std::time::Duration;
+
+        let opts = Opts::from_env_vars(vec![
+            ("MYSQL_HOST", "db.example.com"),
+            ("MYSQL_PORT", "3307"),
+            ("MYSQL_USER", "app"),
+            ("MYSQL_PASSWORD", "p@ss:word"),
+            ("MYSQL_DATABASE", "app_db"),
+            ("MYSQL_OPT_POOL_MAX", "20"),
+            ("MYSQL_OPT_TCP_NODELAY", "false"),
+            ("MYSQL_OPT_CONN_TTL", "60"),
+            ("PATH", "/usr/bin"),
+        ])
+        .unwrap();
+        assert_eq!(opts.ip_or_hostname(), "db.example.com");
+        assert_eq!(opts.tcp_port(), 3307);
+        assert_eq!(opts.user(), Some("app"));
+        assert_eq!(opts.pass(), Some("p@ss:word"));
+        assert_eq!(opts.db_name(), Some("app_db"));
+        assert_eq!(opts.pool_opts().constraints().max(), 20);
+        assert_eq!(opts.tcp_nodelay(), false);
+        assert_eq!(opts.conn_ttl(), Some(Duration::from_secs(60)));
+
+        let opts = Opts::from_env_vars(Vec::<(&str, &str)>::new()).unwrap();
+        assert_eq!(opts.ip_or_hostname(), "127.0.0.1");
+        assert_eq!(opts.tcp_port(), 3306);
+        assert_eq!(opts.user(), None);
+
+        let opts = Opts::from_env_vars(vec![
+            ("DATABASE_URL", "mysql://root@localhost/foo"),
+            ("MYSQL_USER", "app"),
+        ])
+        .unwrap();
+        assert_eq!(opts.user(), Some("root"));
+
+        let opts = Opts::from_env_vars(vec![("MYSQL_HOST", "::1")]).unwrap();
+        assert_eq!(opts.ip_or_hostname(), "[::1]");
+
+        let err = Opts::from_env_vars(vec![("MYSQL_PORT", "33o6")]).unwrap_err();
+        assert_eq!(
+            err,
+            InvalidParamValue {
+                param: "MYSQL_PORT".into(),
+                value: "33o6".into()
+            }
+        );
+        let err = Opts::from_env_vars(vec![("MYSQL_OPT_TCP_NODELAY", "yes")]).unwrap_err();
+        assert_eq!(
+            err,
+            InvalidParamValue {
+                param: "MYSQL_OPT_TCP_NODELAY".into(),
+                value: "yes".into()
+            }
+        );
+        let err = Opts::from_env_vars(vec![("MYSQL_OPT_FOO", "bar")]).unwrap_err();
+        assert_eq!(
+            err,
+            UnknownParameter {
+                param: "MYSQL_OPT_FOO".into()
+            }
+        );
+    }
+
+    #[test]
+    fn should_report_unsupported_scheme() {
+        let err = Opts::from_url("postgres://localhost/foo").unwrap_err();
+        assert_eq!(
+            err,
+            UnsupportedScheme {
+                scheme: "postgres".into()
+            }
+        );
+    }
+
+    #[test]
+    fn should_report_invalid_bool_param() {
+        let err = Opts::from_url("mysql://localhost/foo?tcp_nodelay=yes").unwrap_err();
+        assert_eq!(
+            err,
+            InvalidParamValue {
+                param: "tcp_nodelay".into(),
+                value: "yes".into()
+            }
+        );
+    }
+
     #[test]
     fn should_parse_compression() {
         let err = Opts::from_url("mysql://localhost/foo?compression=").unwrap_err();
@@ -0,0 +1,149 @@
+// Copyright (c) 2020 Anatoly Ikorsky
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use mysql_common::{params::Params, value::Value};
+
+use crate::error::{DriverError, Result};
+
+/// Builder for statement parameters, that are only known at runtime
+/// (e.g. a variable-length `WHERE` clause).
+///
+/// Parameters are either positional or named. Mixing them is an error reported by
+/// [`ParamsBuilder::build`]. The number of parameters is validated against the statement
+/// before it is executed ([`DriverError::StmtParamsMismatch`]).
+///
+/// ```rust
+/// # use mysql_async::{ParamsBuilder, Params, Value};
+/// # fn main() -> mysql_async::Result<()> {
+/// let ids = vec![1_u32, 2, 3];
+///
+/// let mut builder = ParamsBuilder::new();
+/// for id in &ids {
+///     builder.push(*id);
+/// }
+/// let query = format!("SELECT * FROM t WHERE id IN ({})", vec!["?"; ids.len()].join(", "));
+///
+/// assert_eq!(query, "SELECT * FROM t WHERE id IN (?, ?, ?)");
+/// assert_eq!(
+///     builder.build()?,
+///     Params::Positional(vec![Value::UInt(1), Value::UInt(2), Value::UInt(3)]),
+/// );
+/// # Ok(()) }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParamsBuilder {
+    positional: Vec<Value>,
+    named: Vec<(String, Value)>,
+}
+
+impl ParamsBuilder {
+    /// Creates an empty builder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a positional parameter.
+    pub fn push<T: Into<Value>>(&mut self, value: T) -> &mut Self {
+        self.positional.push(value.into());
+        self
+    }
+
+    /// Sets a named parameter. Overrides the previous value of the same parameter, if any.
+    pub fn named<N: Into<String>, T: Into<Value>>(&mut self, name: N, value: T) -> &mut Self {
+        let name = name.into();
+        let value = value.into();
+        match self.named.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, v)) => *v = value,
+            None => self.named.push((name, value)),
+        }
+        self
+    }
+
+    /// Returns the number of parameters.
+    pub fn len(&self) -> usize {
+        self.positional.len() + self.named.len()
+    }
+
+    /// Returns `true` if there are no parameters.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Builds [`Params`].
+    ///
+    /// Returns [`DriverError::MixedParams`] if both positional and named parameters were given.
+    pub fn build(self) -> Result<Params> {
+        match (self.positional.is_empty(), self.named.is_empty()) {
+            (true, true) => Ok(Params::Empty),
+            (false, true) => Ok(Params::Positional(self.positional)),
+            (true, false) => Ok(Params::from(self.named)),
+            (false, false) => Err(DriverError::MixedParams.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use mysql_common::{params::Params, value::Value};
+
+    use super::ParamsBuilder;
+    use crate::{error::DriverError, prelude::*, test_misc::get_opts, Conn, Error};
+
+    #[test]
+    fn should_build_params() -> super::Result<()> {
+        assert_eq!(ParamsBuilder::new().build()?, Params::Empty);
+
+        let mut builder = ParamsBuilder::new();
+        builder.push(1_u8).push("foo").push(None::<i64>);
+        assert_eq!(builder.len(), 3);
+        assert_eq!(
+            builder.build()?,
+            Params::Positional(vec![Value::UInt(1), Value::from("foo"), Value::NULL])
+        );
+
+        let mut builder = ParamsBuilder::new();
+        builder.named("a", 1_u8).named("b", 2_u8).named("a", 3_u8);
+        assert_eq!(builder.len(), 2);
+        assert_eq!(
+            builder.build()?,
+            Params::from(vec![("a", Value::UInt(3)), ("b", Value::UInt(2))])
+        );
+
+        let mut builder = ParamsBuilder::new();
+        builder.push(1_u8).named("a", 1_u8);
+        match builder.build() {
+            Err(Error::Driver(DriverError::MixedParams)) => (),
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_validate_number_of_params() -> super::Result<()> {
+        let mut conn = Conn::new(get_opts()).await?;
+        let stmt = conn.prep("SELECT ?, ?").await?;
+
+        let mut builder = ParamsBuilder::new();
+        builder.push(1_u8);
+        match conn.exec_drop(&stmt, builder.clone().build()?).await {
+            Err(Error::Driver(DriverError::StmtParamsMismatch {
+                required: 2,
+                supplied: 1,
+            })) => (),
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        builder.push(2_u8);
+        let row: Option<(u8, u8)> = conn.exec_first(&stmt, builder.build()?).await?;
+        assert_eq!(row, Some((1, 2)));
+
+        conn.disconnect().await?;
+        Ok(())
+    }
+}
@@ -6,13 +6,15 @@
 // option. All files in the project carrying such notice may not be copied,
 // modified, or distributed except according to those terms.
 
+use futures_core::Stream;
+use futures_util::StreamExt;
 use mysql_common::{
-    packets::{parse_ok_packet, OkPacketKind},
+    packets::OkPacketKind,
     row::new_row,
     value::{read_bin_values, read_text_values, ServerSide},
 };
 
-use std::{fmt, sync::Arc};
+use std::{fmt, sync::Arc, time::Instant};
 
 use self::{
     query_result::QueryResult,
@@ -21,14 +23,16 @@ use self::{
 };
 
 use crate::{
+    conn::{parse_ok, with_caller_location},
     consts::{CapabilityFlags, Command},
     error::*,
     prelude::{FromRow, StatementLike},
     queryable::query_result::ResultSetMeta,
-    BoxFuture, Column, Conn, Params, Row,
+    BoxFuture, Column, Conn, Params, RawRow, Row,
 };
 
 pub mod query_result;
+mod split;
 pub mod stmt;
 pub mod transaction;
 
@@ -36,8 +40,10 @@ pub trait Protocol: fmt::Debug + Send + Sync + 'static {
     /// Returns `ResultSetMeta`, that corresponds to the current protocol.
     fn result_set_meta(columns: Arc<[Column]>) -> ResultSetMeta;
     fn read_result_set_row(packet: &[u8], columns: Arc<[Column]>) -> Result<Row>;
+    /// Returns a row, that decodes its values lazily (see [`RawRow`]).
+    fn read_raw_result_set_row(packet: Vec<u8>, columns: Arc<[Column]>) -> Result<RawRow>;
     fn is_last_result_set_packet(capabilities: CapabilityFlags, packet: &[u8]) -> bool {
-        parse_ok_packet(packet, capabilities, OkPacketKind::ResultSetTerminator).is_ok()
+        parse_ok(packet, capabilities, OkPacketKind::ResultSetTerminator).is_ok()
     }
 }
 
@@ -59,6 +65,10 @@ impl Protocol for TextProtocol {
             .map(|values| new_row(values, columns))
             .map_err(Into::into)
     }
+
+    fn read_raw_result_set_row(packet: Vec<u8>, columns: Arc<[Column]>) -> Result<RawRow> {
+        RawRow::text(packet, columns).map_err(Into::into)
+    }
 }
 
 impl Protocol for BinaryProtocol {
@@ -71,6 +81,10 @@ impl Protocol for BinaryProtocol {
             .map(|values| new_row(values, columns))
             .map_err(Into::into)
     }
+
+    fn read_raw_result_set_row(packet: Vec<u8>, columns: Arc<[Column]>) -> Result<RawRow> {
+        RawRow::binary(packet, columns).map_err(Into::into)
+    }
 }
 
 impl Conn {
@@ -82,7 +96,9 @@ impl Conn {
     /// won't rollback existing transaction. Another difference, is that this function
     /// won't ignore non-fatal errors.
     pub(crate) async fn clean_dirty(&mut self) -> Result<()> {
+        self.ensure_in_sync()?;
         self.drop_result().await?;
+        self.close_dropped_stmts().await?;
         if self.get_tx_status() == TxStatus::RequiresRollback {
             self.set_tx_status(TxStatus::None);
             self.exec_drop("ROLLBACK", ()).await?;
@@ -91,17 +107,139 @@ impl Conn {
     }
 
     /// Low level function that performs a text query.
+    ///
+    /// It'll split the query if it doesn't fit into `max_allowed_packet`
+    /// and `Opts::split_oversized_queries` is `true`.
     pub(crate) async fn raw_query<'a, Q>(&'a mut self, query: Q) -> Result<()>
     where
         Q: AsRef<str> + Send + Sync + 'a,
     {
-        self.write_command_data(Command::COM_QUERY, query.as_ref().as_bytes())
+        let location = self.take_caller_location();
+        let query = self.rewrite_query(query.as_ref())?;
+        let query = query.as_ref();
+        self.set_last_statement(query);
+        self.set_metadata_suppressed(false).await?;
+        let query = with_caller_location(query, location);
+        let query = self.hint_if_in_transaction(&query);
+        let query = query.as_ref();
+
+        // `COM_QUERY` byte + query text
+        let max_len = self.max_allowed_packet().saturating_sub(1);
+        let mut chunks = if self.opts().split_oversized_queries() && query.len() > max_len {
+            split::split_statements(query, max_len, self.no_backslash_escapes()).into_iter()
+        } else {
+            vec![query].into_iter()
+        };
+
+        let started = Instant::now();
+        let first = chunks.next().unwrap_or_default();
+        self.write_command_data(Command::COM_QUERY, first.as_bytes())
             .await?;
-        self.read_result_set::<TextProtocol>(true).await?;
+        self.set_pending_queries(chunks.map(String::from).collect());
+
+        if let Err(err) = self.read_result_set::<TextProtocol>(true).await {
+            self.set_pending_queries(Default::default());
+            return Err(err);
+        }
+        self.log_slow_query(query, started);
+
         Ok(())
     }
 }
 
+impl Conn {
+    /// Prepares the given text query, if it should be executed using the binary protocol
+    /// (see [`crate::Opts::always_prepare`]).
+    ///
+    /// Returns `None` if the query should be executed using the text protocol.
+    async fn prepare_text_query(&mut self, query: &str) -> Result<Option<Statement>> {
+        if !self.opts().always_prepare()
+            || split::is_multi_statement(query, self.no_backslash_escapes())
+        {
+            return Ok(None);
+        }
+        match self.get_statement(query).await {
+            Ok(statement) => Ok(Some(statement)),
+            // ER_UNSUPPORTED_PS
+            Err(Error::Server(ref err)) if err.code == 1295 => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Same as [`Queryable::exec_batch`], but params are taken from the given stream.
+    ///
+    /// It'll prepare `stmt` (once), if necessary. The next item is polled only after
+    /// the previous execution is completed, so a slow server will slow down the stream
+    /// consumption. The first error stops the batch (items executed so far stay executed).
+    pub async fn exec_batch_stream<S, P, T>(&mut self, stmt: &S, params_stream: T) -> Result<()>
+    where
+        S: StatementLike + ?Sized,
+        T: Stream<Item = P> + Send,
+        P: Into<Params> + Send,
+    {
+        let statement = self.get_statement(stmt).await?;
+        futures_util::pin_mut!(params_stream);
+        while let Some(params) = params_stream.next().await {
+            self.execute_statement(&statement, params).await?;
+            QueryResult::<BinaryProtocol>::new(&mut *self)
+                .drop_result()
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Performs the given (possibly multi-statement) query and collects every result set.
+    ///
+    /// Returns a `Vec` per result set, that is meant to contain rows (even if it's empty).
+    /// Result sets of statements, that don't return rows (e.g. `INSERT` or `SET`), are omitted.
+    ///
+    /// The first error stops the query, and the returned [`ResultSetsError`]
+    /// holds the result sets, that were read before the error.
+    ///
+    /// ```rust
+    /// # use mysql_async::{test_misc::get_opts, Conn};
+    /// # #[tokio::main]
+    /// # async fn main() -> mysql_async::Result<()> {
+    /// let mut conn = Conn::new(get_opts()).await?;
+    /// let sets: Vec<Vec<u8>> = conn
+    ///     .query_all_sets("SELECT 1; DO 2; SELECT 3 UNION SELECT 4")
+    ///     .await?;
+    /// assert_eq!(sets, vec![vec![1], vec![3, 4]]);
+    /// # conn.disconnect().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Panic
+    ///
+    /// It'll panic if any row isn't convertible to `T` (see [`QueryResult::collect`]).
+    pub async fn query_all_sets<T>(
+        &mut self,
+        query: &str,
+    ) -> std::result::Result<Vec<Vec<T>>, ResultSetsError<T>>
+    where
+        T: FromRow + Send + 'static,
+    {
+        let mut completed = Vec::new();
+        let result = async {
+            let mut result = self.query_iter(query).await?;
+            while result.next_result_set().await?.is_some() {
+                if result.columns_ref().is_empty() {
+                    continue;
+                }
+                let set = result.collect::<T>().await?;
+                completed.push(set);
+            }
+            Ok(())
+        }
+        .await;
+        match result {
+            Ok(()) => Ok(completed),
+            Err(source) => Err(ResultSetsError { completed, source }),
+        }
+    }
+}
+
 /// Methods of this trait are used to execute database queries.
 ///
 /// `Conn` is a `Queryable` as well as `Transaction`.
@@ -110,6 +248,7 @@ pub trait Queryable: Send {
     fn ping(&mut self) -> BoxFuture<'_, ()>;
 
     /// Performs the given query and returns the result.
+    #[cfg_attr(feature = "caller-location", track_caller)]
     fn query_iter<'a, Q>(
         &'a mut self,
         query: Q,
@@ -123,6 +262,7 @@ pub trait Queryable: Send {
     ///
     /// Also note, that this call may close the least recently used statement
     /// if statement cache is at its capacity (see. [`stmt_cache_size`]).
+    #[cfg_attr(feature = "caller-location", track_caller)]
     fn prep<'a, Q>(&'a mut self, query: Q) -> BoxFuture<'a, Statement>
     where
         Q: AsRef<str> + Sync + Send + 'a;
@@ -135,6 +275,7 @@ pub trait Queryable: Send {
     /// Executes the given statement with given params.
     ///
     /// It'll prepare `stmt`, if necessary.
+    #[cfg_attr(feature = "caller-location", track_caller)]
     fn exec_iter<'a: 's, 's, Q, P>(
         &'a mut self,
         stmt: &'s Q,
@@ -145,18 +286,21 @@ pub trait Queryable: Send {
         P: Into<Params>;
 
     /// Performs the given query and collects the first result set.
+    #[cfg_attr(feature = "caller-location", track_caller)]
     fn query<'a, T, Q>(&'a mut self, query: Q) -> BoxFuture<'a, Vec<T>>
     where
         Q: AsRef<str> + Send + Sync + 'a,
         T: FromRow + Send + 'static;
 
     /// Performs the given query and returns the first row of the first result set.
+    #[cfg_attr(feature = "caller-location", track_caller)]
     fn query_first<'a, T, Q>(&'a mut self, query: Q) -> BoxFuture<'a, Option<T>>
     where
         Q: AsRef<str> + Send + Sync + 'a,
         T: FromRow + Send + 'static;
 
     /// Performs the given query and returns the first row of the first result set.
+    #[cfg_attr(feature = "caller-location", track_caller)]
     fn query_map<'a, T, F, Q, U>(&'a mut self, query: Q, f: F) -> BoxFuture<'a, Vec<U>>
     where
         Q: AsRef<str> + Send + Sync + 'a,
@@ -165,6 +309,7 @@ pub trait Queryable: Send {
         U: Send;
 
     /// Performs the given query and folds the first result set to a single value.
+    #[cfg_attr(feature = "caller-location", track_caller)]
     fn query_fold<'a, T, F, Q, U>(&'a mut self, query: Q, init: U, f: F) -> BoxFuture<'a, U>
     where
         Q: AsRef<str> + Send + Sync + 'a,
@@ -173,6 +318,7 @@ pub trait Queryable: Send {
         U: Send + 'a;
 
     /// Performs the given query and drops the query result.
+    #[cfg_attr(feature = "caller-location", track_caller)]
     fn query_drop<'a, Q>(&'a mut self, query: Q) -> BoxFuture<'a, ()>
     where
         Q: AsRef<str> + Send + Sync + 'a;
@@ -180,6 +326,10 @@ pub trait Queryable: Send {
     /// Exectues the given statement for each item in the given params iterator.
     ///
     /// It'll prepare `stmt` (once), if necessary.
+    ///
+    /// Parameter types are sent on every execution, so `Option<T>` parameters may be `NULL`
+    /// for some items and non-`NULL` for others.
+    #[cfg_attr(feature = "caller-location", track_caller)]
     fn exec_batch<'a: 'b, 'b, S, P, I>(
         &'a mut self,
         stmt: &'b S,
@@ -194,6 +344,7 @@ pub trait Queryable: Send {
     /// Exectues the given statement and collects the first result set.
     ///
     /// It'll prepare `stmt`, if necessary.
+    #[cfg_attr(feature = "caller-location", track_caller)]
     fn exec<'a: 'b, 'b, T, S, P>(&'a mut self, stmt: &'b S, params: P) -> BoxFuture<'b, Vec<T>>
     where
         S: StatementLike + ?Sized + 'b,
@@ -203,6 +354,7 @@ pub trait Queryable: Send {
     /// Exectues the given statement and returns the first row of the first result set.
     ///
     /// It'll prepare `stmt`, if necessary.
+    #[cfg_attr(feature = "caller-location", track_caller)]
     fn exec_first<'a: 'b, 'b, T, S, P>(
         &'a mut self,
         stmt: &'b S,
@@ -216,6 +368,7 @@ pub trait Queryable: Send {
     /// Exectues the given stmt and folds the first result set to a signel value.
     ///
     /// It'll prepare `stmt`, if necessary.
+    #[cfg_attr(feature = "caller-location", track_caller)]
     fn exec_map<'a: 'b, 'b, T, S, P, U, F>(
         &'a mut self,
         stmt: &'b S,
@@ -232,6 +385,7 @@ pub trait Queryable: Send {
     /// Exectues the given stmt and folds the first result set to a signel value.
     ///
     /// It'll prepare `stmt`, if necessary.
+    #[cfg_attr(feature = "caller-location", track_caller)]
     fn exec_fold<'a: 'b, 'b, T, S, P, U, F>(
         &'a mut self,
         stmt: &'b S,
@@ -247,6 +401,7 @@ pub trait Queryable: Send {
         U: Send + 'a;
 
     /// Exectues the given statement and drops the result.
+    #[cfg_attr(feature = "caller-location", track_caller)]
     fn exec_drop<'a: 'b, 'b, S, P>(&'a mut self, stmt: &'b S, params: P) -> BoxFuture<'b, ()>
     where
         S: StatementLike + ?Sized + 'b,
@@ -270,8 +425,14 @@ impl Queryable for Conn {
     where
         Q: AsRef<str> + Send + Sync + 'a,
     {
+        let location = self.caller_location();
         BoxFuture(Box::pin(async move {
-            self.raw_query(query).await?;
+            self.remember_caller_location(location);
+
+            match self.prepare_text_query(query.as_ref()).await? {
+                Some(statement) => self.execute_statement(&statement, ()).await?,
+                None => self.raw_query(query).await?,
+            }
             Ok(QueryResult::new(self))
         }))
     }
@@ -280,9 +441,11 @@ impl Queryable for Conn {
     where
         Q: AsRef<str> + Sync + Send + 'a,
     {
-        BoxFuture(Box::pin(
-            async move { self.get_statement(query.as_ref()).await },
-        ))
+        let location = self.caller_location();
+        BoxFuture(Box::pin(async move {
+            self.remember_caller_location(location);
+            self.get_statement(query.as_ref()).await
+        }))
     }
 
     fn close(&mut self, stmt: Statement) -> BoxFuture<'_, ()> {
@@ -301,8 +464,11 @@ impl Queryable for Conn {
         Q: StatementLike + ?Sized + 'a,
         P: Into<Params>,
     {
+        let location = self.caller_location();
         let params = params.into();
         BoxFuture(Box::pin(async move {
+            self.remember_caller_location(location);
+
             let statement = self.get_statement(stmt).await?;
             self.execute_statement(&statement, params).await?;
             Ok(QueryResult::new(self))
@@ -314,7 +480,13 @@ impl Queryable for Conn {
         Q: AsRef<str> + Send + Sync + 'a,
         T: FromRow + Send + 'static,
     {
+        let location = self.caller_location();
         BoxFuture(Box::pin(async move {
+            self.remember_caller_location(location);
+
+            if let Some(statement) = self.prepare_text_query(query.as_ref()).await? {
+                return self.exec(&statement, ()).await;
+            }
             self.query_iter(query).await?.collect_and_drop::<T>().await
         }))
     }
@@ -324,7 +496,13 @@ impl Queryable for Conn {
         Q: AsRef<str> + Send + Sync + 'a,
         T: FromRow + Send + 'static,
     {
+        let location = self.caller_location();
         BoxFuture(Box::pin(async move {
+            self.remember_caller_location(location);
+
+            if let Some(statement) = self.prepare_text_query(query.as_ref()).await? {
+                return self.exec_first(&statement, ()).await;
+            }
             let mut result = self.query_iter(query).await?;
             let output = if result.is_empty() {
                 None
@@ -343,7 +521,10 @@ impl Queryable for Conn {
         F: FnMut(T) -> U + Send + 'a,
         U: Send,
     {
+        let location = self.caller_location();
         BoxFuture(Box::pin(async move {
+            self.remember_caller_location(location);
+
             self.query_fold(query, Vec::new(), |mut acc, row| {
                 acc.push(f(crate::from_row(row)));
                 acc
@@ -359,7 +540,13 @@ impl Queryable for Conn {
         F: FnMut(U, T) -> U + Send + 'a,
         U: Send + 'a,
     {
+        let location = self.caller_location();
         BoxFuture(Box::pin(async move {
+            self.remember_caller_location(location);
+
+            if let Some(statement) = self.prepare_text_query(query.as_ref()).await? {
+                return self.exec_fold(&statement, (), init, f).await;
+            }
             self.query_iter(query)
                 .await?
                 .reduce_and_drop(init, |acc, row| f(acc, crate::from_row(row)))
@@ -371,7 +558,13 @@ impl Queryable for Conn {
     where
         Q: AsRef<str> + Send + Sync + 'a,
     {
+        let location = self.caller_location();
         BoxFuture(Box::pin(async move {
+            self.remember_caller_location(location);
+
+            if let Some(statement) = self.prepare_text_query(query.as_ref()).await? {
+                return self.exec_drop(&statement, ()).await;
+            }
             self.query_iter(query).await?.drop_result().await
         }))
     }
@@ -387,7 +580,10 @@ impl Queryable for Conn {
         I::IntoIter: Send,
         P: Into<Params> + Send,
     {
+        let location = self.caller_location();
         BoxFuture(Box::pin(async move {
+            self.remember_caller_location(location);
+
             let statement = self.get_statement(stmt).await?;
             for params in params_iter {
                 self.execute_statement(&statement, params).await?;
@@ -405,7 +601,10 @@ impl Queryable for Conn {
         P: Into<Params> + Send + 'b,
         T: FromRow + Send + 'static,
     {
+        let location = self.caller_location();
         BoxFuture(Box::pin(async move {
+            self.remember_caller_location(location);
+
             self.exec_iter(stmt, params)
                 .await?
                 .collect_and_drop::<T>()
@@ -423,7 +622,10 @@ impl Queryable for Conn {
         P: Into<Params> + Send + 'b,
         T: FromRow + Send + 'static,
     {
+        let location = self.caller_location();
         BoxFuture(Box::pin(async move {
+            self.remember_caller_location(location);
+
             let mut result = self.exec_iter(stmt, params).await?;
             let row = if result.is_empty() {
                 None
@@ -448,7 +650,10 @@ impl Queryable for Conn {
         F: FnMut(T) -> U + Send + 'a,
         U: Send + 'a,
     {
+        let location = self.caller_location();
         BoxFuture(Box::pin(async move {
+            self.remember_caller_location(location);
+
             self.exec_fold(stmt, params, Vec::new(), |mut acc, row| {
                 acc.push(f(crate::from_row(row)));
                 acc
@@ -471,7 +676,10 @@ impl Queryable for Conn {
         F: FnMut(U, T) -> U + Send + 'a,
         U: Send + 'a,
     {
+        let location = self.caller_location();
         BoxFuture(Box::pin(async move {
+            self.remember_caller_location(location);
+
             self.exec_iter(stmt, params)
                 .await?
                 .reduce_and_drop(init, |acc, row| f(acc, crate::from_row(row)))
@@ -484,7 +692,10 @@ impl Queryable for Conn {
         S: StatementLike + ?Sized + 'b,
         P: Into<Params> + Send + 'b,
     {
+        let location = self.caller_location();
         BoxFuture(Box::pin(async move {
+            self.remember_caller_location(location);
+
             self.exec_iter(stmt, params).await?.drop_result().await
         }))
     }
@@ -676,4 +887,477 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn should_report_number_of_params_and_columns() -> Result<()> {
+        let mut conn = Conn::new(get_opts()).await?;
+        conn.query_drop("CREATE TEMPORARY TABLE arity (a INT, b INT, c INT)")
+            .await?;
+
+        let stmt = conn.prep("INSERT INTO arity VALUES (?, ?, ?)").await?;
+        assert_eq!(stmt.num_params(), 3);
+        assert_eq!(stmt.num_columns(), 0);
+
+        let stmt = conn
+            .prep("SELECT a, b FROM arity WHERE c = :c OR a = :c")
+            .await?;
+        assert_eq!(stmt.num_params(), 2);
+        assert_eq!(stmt.num_columns(), 2);
+
+        conn.disconnect().await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_sum_affected_rows_of_multi_statement_query() -> Result<()> {
+        let mut conn = Conn::new(get_opts()).await?;
+        conn.query_drop("CREATE TEMPORARY TABLE totals (id INT)")
+            .await?;
+        conn.query_drop("INSERT INTO totals VALUES (1), (2), (3), (4), (5)")
+            .await?;
+
+        let mut result = conn
+            .query_iter(
+                "UPDATE totals SET id = id + 10 WHERE id <= 3;
+                SELECT id FROM totals;
+                DELETE FROM totals WHERE id >= 4;",
+            )
+            .await?;
+        while !result.is_empty() {
+            result.collect::<crate::Row>().await?;
+        }
+        assert_eq!(result.affected_rows_total(), 3 + 5);
+        result.drop_result().await?;
+
+        conn.disconnect().await?;
+
+        Ok(())
+    }
+
+    #[cfg(feature = "caller-location")]
+    #[tokio::test]
+    async fn should_tag_queries_with_caller_location() -> Result<()> {
+        use crate::OptsBuilder;
+
+        const INFO: &str =
+            "SELECT INFO FROM information_schema.PROCESSLIST WHERE ID = CONNECTION_ID()";
+
+        let opts = OptsBuilder::from_opts(get_opts()).tag_caller_location(true);
+        let mut conn = Conn::new(opts).await?;
+
+        let line = line!() + 1;
+        let info: Option<String> = conn.query_first(INFO).await?;
+        assert_eq!(
+            info.unwrap(),
+            format!("/* {}:{} */ {}", file!(), line, INFO)
+        );
+
+        let line = line!() + 1;
+        let info: Option<String> = conn.exec_first(INFO, ()).await?;
+        assert!(info
+            .unwrap()
+            .starts_with(&format!("/* {}:{} */", file!(), line)));
+
+        // location of a future, that is dropped without being polled, isn't kept
+        drop(conn.query_drop("DO 1"));
+        let line = line!() + 1;
+        let info: Option<String> = conn.query_first(INFO).await?;
+        assert_eq!(
+            info.unwrap(),
+            format!("/* {}:{} */ {}", file!(), line, INFO)
+        );
+
+        let mut tx = conn.start_transaction(Default::default()).await?;
+        let line = line!() + 1;
+        let info: Option<String> = tx.query_first(INFO).await?;
+        assert!(info.unwrap().contains(&format!("{}:{}", file!(), line)));
+        tx.rollback().await?;
+
+        conn.disconnect().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_walk_result_sets_explicitly() -> Result<()> {
+        let mut conn = Conn::new(get_opts()).await?;
+
+        let mut result = conn
+            .query_iter("SELECT 1; SELECT 2, 3 UNION ALL SELECT 4, 5; DO 6; SELECT 'foo'")
+            .await?;
+        let mut sets = Vec::new();
+        while result.next_result_set().await?.is_some() {
+            let columns = result.columns_ref().len();
+            // the second result set is left unconsumed
+            let rows = if sets.len() == 1 {
+                Vec::new()
+            } else {
+                result.collect::<crate::Row>().await?
+            };
+            sets.push((columns, rows.len()));
+        }
+        assert_eq!(sets, vec![(1, 1), (2, 0), (0, 0), (1, 1)]);
+        assert!(result.is_empty());
+        assert_eq!(result.next_result_set().await?, None);
+        drop(result);
+
+        // rows, that were read by `next`, belong to the current result set
+        let mut result = conn.query_iter("SELECT 1; SELECT 2").await?;
+        let first: Option<u8> = result.next().await?.map(crate::from_row);
+        assert_eq!(first, Some(1));
+        assert_eq!(result.next_result_set().await?, Some(()));
+        let second: Vec<u8> = result.collect().await?;
+        assert_eq!(second, vec![2]);
+        assert_eq!(result.next_result_set().await?, None);
+        drop(result);
+
+        conn.disconnect().await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_bind_json_arrays() -> Result<()> {
+        use crate::Serialized;
+
+        let mut conn = Conn::new(get_opts()).await?;
+        if conn.server_version() < (8, 0, 17) {
+            // `MEMBER OF` isn't supported
+            conn.disconnect().await?;
+            return Ok(());
+        }
+
+        let array = Serialized(vec![1, 5, 9]);
+        let is_member: Option<bool> = conn
+            .exec_first("SELECT 5 MEMBER OF (CAST(? AS JSON))", (array.clone(),))
+            .await?;
+        assert_eq!(is_member, Some(true));
+        let is_member: Option<bool> = conn
+            .exec_first("SELECT 6 MEMBER OF (CAST(? AS JSON))", (array,))
+            .await?;
+        assert_eq!(is_member, Some(false));
+
+        // multi-valued index
+        conn.query_drop(
+            "CREATE TEMPORARY TABLE tagged (id INT, tags JSON,
+                INDEX tags ((CAST(tags AS UNSIGNED ARRAY))))",
+        )
+        .await?;
+        conn.exec_batch(
+            "INSERT INTO tagged VALUES (?, ?)",
+            vec![
+                (1, Serialized(vec![1_u32, 2])),
+                (2, Serialized(vec![5, 9])),
+                (3, Serialized(vec![])),
+            ],
+        )
+        .await?;
+        let ids: Vec<u32> = conn
+            .exec(
+                "SELECT id FROM tagged WHERE JSON_OVERLAPS(tags, CAST(? AS JSON)) ORDER BY id",
+                (Serialized(vec![2, 9]),),
+            )
+            .await?;
+        assert_eq!(ids, vec![1, 2]);
+
+        conn.disconnect().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_round_trip_values_rendered_as_sql() -> Result<()> {
+        use crate::Value;
+
+        let injection = "'; DROP TABLE users; -- \\' \" \\";
+        let cases: Vec<(Value, Option<&[u8]>)> = vec![
+            (Value::NULL, None),
+            (Value::from(injection), Some(injection.as_bytes())),
+            (Value::from("it's"), Some(b"it's")),
+            (Value::from("\0\n\r\x1a\\"), Some(b"\0\n\r\x1a\\")),
+            (Value::from(""), Some(b"")),
+            (Value::Int(-42), Some(b"-42")),
+            (Value::UInt(u64::MAX), Some(b"18446744073709551615")),
+            (Value::Double(1.5), Some(b"1.5")),
+            (
+                Value::Bytes(vec![0xff, 0x00, b'\'']),
+                Some(&[0xff, 0x00, b'\'']),
+            ),
+        ];
+
+        assert_eq!(Value::from("it's").as_sql(false), r"'it\'s'");
+        assert_eq!(Value::from("it's").as_sql(true), "'it''s'");
+        assert_eq!(Value::Bytes(vec![0xff, 0x00]).as_sql(false), "0xFF00");
+        assert_eq!(Value::NULL.as_sql(false), "NULL");
+
+        let mut conn = Conn::new(get_opts()).await?;
+        for sql_mode in &["", "NO_BACKSLASH_ESCAPES"] {
+            conn.exec_drop("SET SESSION sql_mode = ?", (*sql_mode,))
+                .await?;
+            for (value, expected) in &cases {
+                let query = format!("SELECT {}", value.as_sql(conn.no_backslash_escapes()));
+                let actual: Option<Option<Vec<u8>>> = conn.query_first(query).await?;
+                assert_eq!(actual.unwrap().as_deref(), *expected);
+            }
+        }
+
+        conn.disconnect().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_query_all_result_sets() -> Result<()> {
+        let mut conn = Conn::new(get_opts()).await?;
+
+        let sets: Vec<Vec<u8>> = conn
+            .query_all_sets("SELECT 1; SELECT 2 UNION SELECT 3;")
+            .await?;
+        assert_eq!(sets, vec![vec![1], vec![2, 3]]);
+
+        // empty result sets are kept, result sets without columns are omitted
+        let sets: Vec<Vec<(u8, String)>> = conn
+            .query_all_sets("SET @a = 1; SELECT 1, 'foo' FROM DUAL WHERE FALSE; SELECT 2, 'bar'")
+            .await?;
+        assert_eq!(sets, vec![vec![], vec![(2, "bar".to_owned())]]);
+
+        let err = conn
+            .query_all_sets::<u8>("SELECT 1; SELECT * FROM mysql_async_missing_table; SELECT 3")
+            .await
+            .unwrap_err();
+        assert_eq!(err.completed, vec![vec![1]]);
+        match err.source {
+            crate::Error::Server(ref err) if err.code == 1146 => (),
+            ref err => panic!("unexpected error: {}", err),
+        }
+
+        // the connection is still usable
+        let one: Option<u8> = conn.query_first("SELECT 1").await?;
+        assert_eq!(one, Some(1));
+
+        conn.disconnect().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_buffer_all_result_sets() -> Result<()> {
+        let constraints = crate::PoolConstraints::new(1, 1).unwrap();
+        let opts = get_opts().pool_opts(crate::PoolOpts::default().with_constraints(constraints));
+        let pool = crate::Pool::new(opts);
+
+        let mut conn = pool.get_conn().await?;
+        conn.query_drop("CREATE TEMPORARY TABLE buffered (id INT)")
+            .await?;
+        let result = conn
+            .query_iter(
+                "INSERT INTO buffered VALUES (1), (2);
+                SELECT id FROM buffered ORDER BY id;
+                SELECT 'foo', 'bar'",
+            )
+            .await?
+            .buffer_all()
+            .await?;
+        drop(conn);
+
+        // the only connection of the pool was released
+        let mut conn = pool.get_conn().await?;
+        let count: Option<u8> = conn.query_first("SELECT COUNT(*) FROM buffered").await?;
+        assert_eq!(count, Some(2));
+        drop(conn);
+
+        assert_eq!(result.affected_rows_total(), 2);
+        let result_sets = result.into_result_sets();
+        assert_eq!(result_sets.len(), 3);
+        assert!(result_sets[0].columns().is_empty());
+        assert_eq!(result_sets[0].affected_rows(), 2);
+        assert_eq!(result_sets[1].columns().len(), 1);
+        assert_eq!(result_sets[2].rows().len(), 1);
+        let ids: Vec<u8> = result_sets[1].clone().collect();
+        assert_eq!(ids, vec![1, 2]);
+        let strings: Vec<(String, String)> = result_sets[2].clone().collect();
+        assert_eq!(strings, vec![("foo".into(), "bar".into())]);
+
+        pool.disconnect().await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_report_columns_before_rows() -> Result<()> {
+        let mut conn = Conn::new(get_opts()).await?;
+        let mut events = Vec::new();
+
+        let mut result = conn
+            .query_iter("SELECT 1 AS id, 'foo' AS name UNION ALL SELECT 2, 'bar'; SELECT 3 AS x")
+            .await?;
+        while result.next_result_set().await?.is_some() {
+            result.with_columns(|columns| {
+                let names = columns
+                    .iter()
+                    .map(|column| column.name_str().into_owned())
+                    .collect::<Vec<_>>();
+                events.push(names.join(","));
+            });
+            while let Some(row) = result.next().await? {
+                events.push(format!("row of {}", row.len()));
+            }
+        }
+        drop(result);
+
+        assert_eq!(
+            events,
+            vec!["id,name", "row of 2", "row of 2", "x", "row of 1"]
+        );
+
+        let mut columns = 0;
+        conn.exec_iter("SELECT ?, ?", (1, 2))
+            .await?
+            .with_columns(|cols| columns = cols.len())
+            .for_each(|_| assert_eq!(columns, 2))
+            .await?;
+        assert_eq!(columns, 2);
+
+        conn.disconnect().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_always_prepare_text_queries() -> Result<()> {
+        async fn stmt_executions(conn: &mut Conn) -> Result<u64> {
+            let (_, count): (String, u64) = conn
+                .exec_first("SHOW SESSION STATUS LIKE 'Com_stmt_execute'", ())
+                .await?
+                .unwrap();
+            Ok(count)
+        }
+
+        const QUERY: &str = "SELECT 1.1e0, 0.1e0 + 0.2e0";
+
+        let mut text_conn = Conn::new(get_opts()).await?;
+        let mut conn = Conn::new(get_opts().always_prepare(true)).await?;
+
+        // `DOUBLE` decodes the same way as in the binary protocol
+        let (x, y): (f64, f64) = conn.query_first(QUERY).await?.unwrap();
+        let (bin_x, bin_y): (f64, f64) = text_conn.exec_first(QUERY, ()).await?.unwrap();
+        assert_eq!((x, y), (bin_x, bin_y));
+        let row: crate::Row = conn.query_first("SELECT 1.1e0, NOW(6)").await?.unwrap();
+        assert!(matches!(row.as_ref(0), Some(crate::Value::Double(_))));
+        assert!(matches!(row.as_ref(1), Some(crate::Value::Date(..))));
+
+        let before = stmt_executions(&mut conn).await?;
+        conn.query_drop("DO 1").await?;
+        let _: Vec<u8> = conn.query("SELECT 1").await?;
+        let _: Vec<u8> = conn.query_map("SELECT 1", |x| x).await?;
+        // the status query above is executed as well
+        assert_eq!(stmt_executions(&mut conn).await?, before + 4);
+
+        // streamed rows are decoded using the binary protocol
+        let before = stmt_executions(&mut conn).await?;
+        let mut result = conn.query_iter(QUERY).await?;
+        let row = result.next().await?.unwrap();
+        assert!(result.next().await?.is_none());
+        drop(result);
+        assert_eq!(crate::from_row::<(f64, f64)>(row), (bin_x, bin_y));
+        assert_eq!(stmt_executions(&mut conn).await?, before + 2);
+
+        // multi-statement queries use the text protocol
+        let before = stmt_executions(&mut conn).await?;
+        let _: Vec<u8> = conn.query("SELECT 1; SELECT 2").await?;
+        assert_eq!(stmt_executions(&mut conn).await?, before + 1);
+
+        conn.disconnect().await?;
+        text_conn.disconnect().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_exec_batch_with_mixed_nulls() -> Result<()> {
+        let mut conn = Conn::new(get_opts()).await?;
+        conn.query_drop("CREATE TEMPORARY TABLE mixed_nulls (a BIGINT, b TEXT, c DOUBLE)")
+            .await?;
+
+        let rows = vec![
+            (Some(1_i64), None, Some(1.5_f64)),
+            (None, Some("foo"), None),
+            (Some(3), Some("bar"), Some(3.5)),
+            (None, None, None),
+        ];
+
+        conn.exec_batch(
+            "INSERT INTO mixed_nulls (a, b, c) VALUES (?, ?, ?)",
+            rows.iter(),
+        )
+        .await?;
+        conn.exec_batch(
+            "INSERT INTO mixed_nulls (a, b, c) VALUES (:a, :b, :c)",
+            rows.iter()
+                .map(|(a, b, c)| params! { "a" => a, "b" => b, "c" => c }),
+        )
+        .await?;
+        conn.exec_batch(
+            "INSERT INTO mixed_nulls (a) VALUES (?)",
+            vec![vec![Some(5_i64)], vec![None], vec![Some(7)]],
+        )
+        .await?;
+
+        let result: Vec<(Option<i64>, Option<String>, Option<f64>)> =
+            conn.query("SELECT a, b, c FROM mixed_nulls").await?;
+        let expected = rows
+            .iter()
+            .chain(rows.iter())
+            .map(|(a, b, c)| (*a, b.map(String::from), *c))
+            .chain(vec![
+                (Some(5), None, None),
+                (None, None, None),
+                (Some(7), None, None),
+            ])
+            .collect::<Vec<_>>();
+        assert_eq!(result, expected);
+
+        conn.disconnect().await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_exec_batch_stream() -> Result<()> {
+        let mut conn = Conn::new(get_opts()).await?;
+        conn.query_drop("CREATE TEMPORARY TABLE streamed (id INT, name TEXT)")
+            .await?;
+
+        let rows = (0..10_u32)
+            .map(|id| (id, format!("name{}", id)))
+            .collect::<Vec<_>>();
+        conn.exec_batch_stream(
+            "INSERT INTO streamed (id, name) VALUES (?, ?)",
+            futures_util::stream::iter(rows.clone()),
+        )
+        .await?;
+
+        let result: Vec<(u32, String)> = conn
+            .query("SELECT id, name FROM streamed ORDER BY id")
+            .await?;
+        assert_eq!(result, rows);
+
+        // the first error stops the batch
+        let err = conn
+            .exec_batch_stream(
+                "INSERT INTO streamed (id, name) VALUES (?, ?)",
+                futures_util::stream::iter(vec![
+                    crate::Params::from((10_u32, "name10")),
+                    crate::Params::from((11_u32,)),
+                    crate::Params::from((12_u32, "name12")),
+                ]),
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            crate::Error::Driver(crate::DriverError::StmtParamsMismatch { .. })
+        ));
+        let count: Option<u32> = conn.query_first("SELECT COUNT(*) FROM streamed").await?;
+        assert_eq!(count, Some(11));
+
+        conn.disconnect().await?;
+
+        Ok(())
+    }
 }
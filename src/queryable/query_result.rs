@@ -14,9 +14,11 @@ use std::{borrow::Cow, marker::PhantomData, result::Result as StdResult, sync::A
 
 use crate::{
     connection_like::Connection,
+    consts::Command,
     error::*,
     prelude::{FromRow, Protocol},
-    Column, Row,
+    queryable::Protocol as _,
+    BinaryProtocol, Column, RawRow, Row,
 };
 
 /// Result set metadata.
@@ -72,6 +74,13 @@ impl ResultSetMeta {
 #[derive(Debug)]
 pub struct QueryResult<'a, 't: 'a, P> {
     conn: Connection<'a, 't>,
+    affected_rows_total: u64,
+    /// `true` if the current result set was already returned by
+    /// [`QueryResult::next_result_set`].
+    result_set_entered: bool,
+    /// `true` if rows are encoded using the binary protocol regardless of `P`
+    /// (i.e. a text query was executed as a prepared statement, see `Opts::always_prepare`).
+    binary: bool,
     __phantom: PhantomData<P>,
 }
 
@@ -80,8 +89,13 @@ where
     P: Protocol,
 {
     pub fn new<T: Into<Connection<'a, 't>>>(conn: T) -> Self {
+        let conn = conn.into();
+        let binary = matches!(conn.get_pending_result(), Some(ResultSetMeta::Binary(_)));
         QueryResult {
-            conn: conn.into(),
+            conn,
+            affected_rows_total: 0,
+            result_set_entered: false,
+            binary,
             __phantom: PhantomData,
         }
     }
@@ -98,10 +112,47 @@ where
 
     /// `true` if there are no more rows nor result sets in this query.
     pub fn is_empty(&self) -> bool {
-        !self.has_rows() && !self.conn.more_results_exists()
+        !self.has_rows() && !self.conn.more_results_exists() && !self.conn.has_pending_queries()
     }
 
     pub async fn next(&mut self) -> Result<Option<Row>> {
+        let (packet, columns) = match self.next_row_packet().await? {
+            Some(x) => x,
+            None => return Ok(None),
+        };
+        let row = if self.binary {
+            BinaryProtocol::read_result_set_row(&packet, columns)?
+        } else {
+            P::read_result_set_row(&packet, columns)?
+        };
+        self.conn.reuse_buffer(packet);
+        Ok(Some(row))
+    }
+
+    /// Same as [`QueryResult::next`], but returns a [`RawRow`], that decodes its values lazily.
+    ///
+    /// Useful if only a few columns of a wide row are needed.
+    pub async fn next_raw(&mut self) -> Result<Option<RawRow>> {
+        match self.next_row_packet().await? {
+            Some((packet, columns)) if self.binary => Ok(Some(
+                BinaryProtocol::read_raw_result_set_row(packet, columns)?,
+            )),
+            Some((packet, columns)) => Ok(Some(P::read_raw_result_set_row(packet, columns)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Captures warnings (see [`crate::Opts::capture_warnings`]), if the consumed result set
+    /// was the last one.
+    async fn on_result_set_end(&mut self) -> Result<()> {
+        if !self.conn.more_results_exists() && !self.conn.has_pending_queries() {
+            self.conn.capture_warnings().await?;
+        }
+        Ok(())
+    }
+
+    /// Returns the next row packet of the current result set along with its columns.
+    async fn next_row_packet(&mut self) -> Result<Option<(Vec<u8>, Arc<[Column]>)>> {
         loop {
             let columns = match self.conn.get_pending_result() {
                 Some(ResultSetMeta::Text(cols)) | Some(ResultSetMeta::Binary(cols)) => {
@@ -115,7 +166,9 @@ where
                 Ok(Some(columns)) => {
                     if columns.is_empty() {
                         // Empty, but not yet consumed result set.
+                        self.affected_rows_total += self.conn.affected_rows();
                         self.conn.set_pending_result(None);
+                        self.on_result_set_end().await?;
                     } else {
                         // Not yet consumed non-empty result set.
                         let packet = match self.conn.read_packet().await {
@@ -123,6 +176,7 @@ where
                             Err(err) => {
                                 // Next row contained an error. No more data will follow.
                                 self.conn.set_pending_result(None);
+                                self.conn.set_pending_queries(Default::default());
                                 return Err(err);
                             }
                         };
@@ -130,9 +184,11 @@ where
                         if P::is_last_result_set_packet(self.conn.capabilities(), &packet) {
                             // `packet` is a result set terminator.
                             self.conn.set_pending_result(None);
+                            self.on_result_set_end().await?;
                         } else {
                             // `packet` is a result set row.
-                            return Ok(Some(P::read_result_set_row(&packet, columns)?));
+                            self.result_set_entered = true;
+                            return Ok(Some((packet, columns)));
                         }
                     }
                 }
@@ -141,7 +197,32 @@ where
                     if self.conn.more_results_exists() {
                         // More data will follow.
                         self.conn.sync_seq_id();
-                        self.conn.read_result_set::<P>(false).await?;
+                        if self.binary {
+                            self.conn.read_result_set::<BinaryProtocol>(false).await?;
+                        } else {
+                            self.conn.read_result_set::<P>(false).await?;
+                        }
+                        self.result_set_entered = false;
+                        return Ok(None);
+                    } else if let Some(query) = self.conn.take_pending_query() {
+                        // Next part of a split multi-statement query.
+                        let mut body = Vec::with_capacity(1 + query.len());
+                        body.push(Command::COM_QUERY as u8);
+                        body.extend_from_slice(query.as_bytes());
+
+                        self.conn.reset_seq_id();
+                        self.conn.set_unresumable_step(true);
+                        let result = match self.conn.write_packet(body).await {
+                            Ok(()) => self.conn.read_result_set::<P>(true).await,
+                            Err(err) => Err(err),
+                        };
+                        self.conn.set_unresumable_step(false);
+
+                        if let Err(err) = result {
+                            self.conn.set_pending_queries(Default::default());
+                            return Err(err);
+                        }
+                        self.result_set_entered = false;
                         return Ok(None);
                     } else {
                         // The end of a query result.
@@ -151,12 +232,54 @@ where
                 Err(err) => {
                     // Error result set. No more data will follow.
                     self.conn.set_pending_result(None);
+                    self.conn.set_pending_queries(Default::default());
                     return Err(err);
                 }
             }
         }
     }
 
+    /// Advances to the next result set of this query result.
+    ///
+    /// The first call positions this query result at its first result set, so `None` means
+    /// that there are no more result sets. Unconsumed rows of the current result set are
+    /// read from the stream, but never decoded.
+    ///
+    /// ```rust
+    /// # use mysql_async::test_misc::get_opts;
+    /// # #[tokio::main]
+    /// # async fn main() -> mysql_async::Result<()> {
+    /// use mysql_async::*;
+    /// use mysql_async::prelude::*;
+    /// let mut conn = Conn::new(get_opts()).await?;
+    ///
+    /// let mut result = conn.query_iter("SELECT 1; SELECT 2, 3").await?;
+    /// let mut widths = Vec::new();
+    /// while result.next_result_set().await?.is_some() {
+    ///     widths.push(result.columns_ref().len());
+    /// }
+    /// assert_eq!(widths, vec![1, 2]);
+    ///
+    /// # drop(result); conn.disconnect().await }
+    /// ```
+    pub async fn next_result_set(&mut self) -> Result<Option<()>> {
+        loop {
+            if self.conn.get_pending_result().is_some() {
+                if !self.result_set_entered {
+                    self.result_set_entered = true;
+                    return Ok(Some(()));
+                }
+                // skip the rest of the current result set
+                while self.next_row_packet().await?.is_some() {}
+            } else {
+                self.next_row_packet().await?;
+                if self.conn.get_pending_result().is_none() {
+                    return Ok(None);
+                }
+            }
+        }
+    }
+
     /// Last insert id, if any.
     pub fn last_insert_id(&self) -> Option<u64> {
         self.conn.last_insert_id()
@@ -167,6 +290,14 @@ where
         self.conn.affected_rows()
     }
 
+    /// Sum of affected rows of result sets of this query result, that are consumed so far.
+    ///
+    /// Only result sets without rows (e.g. results of `UPDATE` or `DELETE` statements)
+    /// are taken into account.
+    pub fn affected_rows_total(&self) -> u64 {
+        self.affected_rows_total
+    }
+
     /// Text information as reported by the server, or an empty string.
     pub fn info(&self) -> Cow<'_, str> {
         self.conn.info()
@@ -177,6 +308,14 @@ where
         self.conn.get_warnings()
     }
 
+    /// Warnings of this query, if [`crate::Opts::capture_warnings`] is `true`.
+    ///
+    /// Warnings are captured as soon as the last result set is consumed,
+    /// so it's empty until then.
+    pub fn captured_warnings(&self) -> &[crate::Warning] {
+        self.conn.captured_warnings()
+    }
+
     /// Collects the current result set of this query result.
     ///
     /// It is parametrized by `R` and internally calls `R::from_row(Row)` on each row.
@@ -323,15 +462,70 @@ where
     }
 
     /// Drops this query result.
+    ///
+    /// Remaining rows are read from the stream, but never decoded
+    /// (their buffers are reused, see [`crate::Opts::reuse_buffers`]).
     pub async fn drop_result(mut self) -> Result<()> {
         loop {
-            while let Some(_) = self.next().await? {}
+            while let Some((packet, _)) = self.next_row_packet().await? {
+                self.conn.reuse_buffer(packet);
+            }
             if self.conn.get_pending_result().is_none() {
                 break Ok(());
             }
         }
     }
 
+    /// Reads all result sets of this query result into memory.
+    ///
+    /// Unlike [`QueryResult::collect`], that only collects the current result set,
+    /// the resulting [`BufferedResult`] holds every remaining result set and doesn't borrow
+    /// the connection, so the connection may be dropped (e.g. returned to a pool) while
+    /// the rows are processed.
+    ///
+    /// ```rust
+    /// # use mysql_async::test_misc::get_opts;
+    /// # #[tokio::main]
+    /// # async fn main() -> mysql_async::Result<()> {
+    /// use mysql_async::*;
+    /// use mysql_async::prelude::*;
+    /// let pool = Pool::new(get_opts());
+    ///
+    /// let mut conn = pool.get_conn().await?;
+    /// let result = conn.query_iter("SELECT 1; SELECT 2, 3").await?.buffer_all().await?;
+    /// drop(conn);
+    ///
+    /// assert_eq!(result.result_sets().len(), 2);
+    /// assert_eq!(result.result_sets()[1].columns().len(), 2);
+    ///
+    /// # pool.disconnect().await }
+    /// ```
+    pub async fn buffer_all(mut self) -> Result<BufferedResult> {
+        let mut result_sets = Vec::new();
+        while self.next_result_set().await?.is_some() {
+            let columns = self
+                .columns()
+                .unwrap_or_else(|| Arc::from(Vec::new().into_boxed_slice()));
+            // OK packet of a result set with rows is overwritten by the next result set
+            let (affected_rows, last_insert_id) = if columns.is_empty() {
+                (self.affected_rows(), self.last_insert_id())
+            } else {
+                (0, None)
+            };
+            let mut rows = Vec::new();
+            while let Some(row) = self.next().await? {
+                rows.push(row);
+            }
+            result_sets.push(BufferedResultSet {
+                columns,
+                rows,
+                affected_rows,
+                last_insert_id,
+            });
+        }
+        Ok(BufferedResult { result_sets })
+    }
+
     /// Returns a reference to a columns list of this query result.
     ///
     /// Empty list means that this result set was never meant to contain rows.
@@ -348,6 +542,119 @@ where
             .get_pending_result()
             .and_then(|meta| meta.columns().map(|columns| columns.clone()).ok())
     }
+
+    /// Calls `fun` with the columns of the current result set and returns `self`,
+    /// so that rows could be streamed afterwards.
+    ///
+    /// Columns are parsed before the first row of a result set, so `fun` always observes
+    /// the schema before any row is read (useful to write a CSV header or to build a schema
+    /// of an export format). Empty list means that this result set was never meant to contain
+    /// rows (see [`QueryResult::columns_ref`]).
+    ///
+    /// ```rust
+    /// # use mysql_async::test_misc::get_opts;
+    /// # #[tokio::main]
+    /// # async fn main() -> mysql_async::Result<()> {
+    /// use mysql_async::*;
+    /// use mysql_async::prelude::*;
+    /// let mut conn = Conn::new(get_opts()).await?;
+    ///
+    /// let mut header = Vec::new();
+    /// let mut result = conn.query_iter("SELECT 1 AS a, 2 AS b").await?;
+    /// result.with_columns(|columns| {
+    ///     header.extend(columns.iter().map(|column| column.name_str().into_owned()));
+    /// });
+    /// assert_eq!(header, vec!["a", "b"]);
+    ///
+    /// while let Some(row) = result.next().await? {
+    ///     assert_eq!(from_row::<(u8, u8)>(row), (1, 2));
+    /// }
+    ///
+    /// # drop(result); conn.disconnect().await }
+    /// ```
+    pub fn with_columns<F>(&mut self, fun: F) -> &mut Self
+    where
+        F: FnOnce(&[Column]),
+    {
+        fun(self.columns_ref());
+        self
+    }
+}
+
+/// Query result, that is fully read into memory (see [`QueryResult::buffer_all`]).
+#[derive(Debug, Clone, PartialEq)]
+pub struct BufferedResult {
+    result_sets: Vec<BufferedResultSet>,
+}
+
+impl BufferedResult {
+    /// Returns result sets of this query result.
+    pub fn result_sets(&self) -> &[BufferedResultSet] {
+        &self.result_sets
+    }
+
+    /// Returns result sets of this query result.
+    pub fn into_result_sets(self) -> Vec<BufferedResultSet> {
+        self.result_sets
+    }
+
+    /// Sum of affected rows of result sets of this query result
+    /// (see [`QueryResult::affected_rows_total`]).
+    pub fn affected_rows_total(&self) -> u64 {
+        self.result_sets.iter().map(|set| set.affected_rows).sum()
+    }
+}
+
+/// Result set, that is fully read into memory (see [`QueryResult::buffer_all`]).
+#[derive(Debug, Clone, PartialEq)]
+pub struct BufferedResultSet {
+    columns: Arc<[Column]>,
+    rows: Vec<Row>,
+    affected_rows: u64,
+    last_insert_id: Option<u64>,
+}
+
+impl BufferedResultSet {
+    /// Returns columns of this result set.
+    ///
+    /// Empty list means that this result set was never meant to contain rows.
+    pub fn columns(&self) -> &[Column] {
+        &self.columns
+    }
+
+    /// Returns rows of this result set.
+    pub fn rows(&self) -> &[Row] {
+        &self.rows
+    }
+
+    /// Returns rows of this result set.
+    pub fn into_rows(self) -> Vec<Row> {
+        self.rows
+    }
+
+    /// Converts rows of this result set to `R` (see [`QueryResult::collect`]).
+    ///
+    /// # Panic
+    ///
+    /// It'll panic if any row isn't convertible to `R`.
+    pub fn collect<R: FromRow>(self) -> Vec<R> {
+        self.rows.into_iter().map(FromRow::from_row).collect()
+    }
+
+    /// Number of affected rows as reported by the server, or `0`.
+    ///
+    /// Only result sets without rows (e.g. results of `UPDATE` or `DELETE` statements)
+    /// report affected rows.
+    pub fn affected_rows(&self) -> u64 {
+        self.affected_rows
+    }
+
+    /// Last insert id, if any.
+    ///
+    /// Only result sets without rows report last insert id.
+    pub fn last_insert_id(&self) -> Option<u64> {
+        self.last_insert_id
+    }
 }
 
 impl crate::Conn {
@@ -356,6 +663,22 @@ impl crate::Conn {
     where
         P: Protocol,
     {
+        // result set header and column definitions must be read at once
+        self.set_unresumable_step(true);
+        let result = self.read_result_set_header::<P>(is_first_result_set).await;
+        self.set_unresumable_step(false);
+        result
+    }
+
+    /// Helper, that reads result set header and column definitions (see `read_result_set`).
+    async fn read_result_set_header<P>(&mut self, is_first_result_set: bool) -> Result<()>
+    where
+        P: Protocol,
+    {
+        if is_first_result_set {
+            self.clear_captured_warnings();
+        }
+
         let packet = match self.read_packet().await {
             Ok(packet) => packet,
             Err(err @ Error::Server(_)) if is_first_result_set => {
@@ -399,6 +722,7 @@ impl crate::Conn {
         let mut reader = handler.handle(local_infile.file_name_ref()).await?;
 
         let mut buf = [0; 4096];
+        let mut bytes_sent = 0_u64;
         loop {
             let read = reader.read(&mut buf[..]).await?;
             self.write_packet(&buf[..read]).await?;
@@ -406,6 +730,9 @@ impl crate::Conn {
             if read == 0 {
                 break;
             }
+
+            bytes_sent += read as u64;
+            handler.progress(local_infile.file_name_ref(), bytes_sent);
         }
 
         self.read_packet().await?;
@@ -423,8 +750,23 @@ impl crate::Conn {
         P: Protocol,
     {
         let column_count = packet.read_lenenc_int()?;
-        let columns = self.read_column_defs(column_count as usize).await?;
-        let meta = P::result_set_meta(Arc::from(columns.into_boxed_slice()));
+        let expected_columns = self.take_expected_columns();
+        // `resultset_metadata` flag follows the column count
+        let metadata_follows = !self.optional_metadata() || packet.first() != Some(&0);
+        let columns = match expected_columns {
+            Some(columns) if !metadata_follows && columns.len() == column_count as usize => columns,
+            _ if !metadata_follows => {
+                return Err(DriverError::UnexpectedPacket {
+                    payload: packet.to_vec(),
+                }
+                .into())
+            }
+            _ => {
+                let columns = self.read_column_defs(column_count as usize).await?;
+                Arc::from(columns.into_boxed_slice())
+            }
+        };
+        let meta = P::result_set_meta(columns);
         self.set_pending_result(Some(meta));
         Ok(())
     }
@@ -0,0 +1,211 @@
+// Copyright (c) 2020 Anatoly Ikorsky
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+/// Returns `(start, end)` offsets of statements of the given multi-statement query.
+///
+/// Statement includes preceding whitespaces and comments, and the terminating `;` (if any).
+/// Empty statements (i.e. containing only whitespaces and comments) are skipped.
+///
+/// Backslash is an escape character within string literals,
+/// unless `no_backslash_escapes` is `true` (see `NO_BACKSLASH_ESCAPES` SQL mode).
+fn statements(query: &str, no_backslash_escapes: bool) -> Vec<(usize, usize)> {
+    let bytes = query.as_bytes();
+    let len = bytes.len();
+
+    let mut output = Vec::new();
+    let mut start = 0;
+    let mut empty = true;
+    let mut i = 0;
+
+    let skip_line = |i: usize| {
+        bytes[i..]
+            .iter()
+            .position(|x| *x == b'\n')
+            .map(|pos| i + pos + 1)
+            .unwrap_or(len)
+    };
+
+    while i < len {
+        match bytes[i] {
+            quote @ b'\'' | quote @ b'"' | quote @ b'`' => {
+                empty = false;
+                i += 1;
+                while i < len {
+                    if bytes[i] == b'\\' && quote != b'`' && !no_backslash_escapes {
+                        i += 2;
+                    } else if bytes[i] == quote {
+                        if bytes.get(i + 1) == Some(&quote) {
+                            i += 2;
+                        } else {
+                            break;
+                        }
+                    } else {
+                        i += 1;
+                    }
+                }
+                i += 1;
+            }
+            b'#' => i = skip_line(i),
+            b'-' if bytes.get(i + 1) == Some(&b'-')
+                && bytes
+                    .get(i + 2)
+                    .map(|x| x.is_ascii_whitespace())
+                    .unwrap_or(true) =>
+            {
+                i = skip_line(i)
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                // executable comments and optimizer hints are not empty
+                if let Some(b'!') | Some(b'+') = bytes.get(i + 2) {
+                    empty = false;
+                }
+                i = bytes[i + 2..]
+                    .windows(2)
+                    .position(|x| x == b"*/")
+                    .map(|pos| i + 2 + pos + 2)
+                    .unwrap_or(len);
+            }
+            b';' => {
+                i += 1;
+                if !empty {
+                    output.push((start, i));
+                }
+                start = i;
+                empty = true;
+            }
+            x if x.is_ascii_whitespace() => i += 1,
+            _ => {
+                empty = false;
+                i += 1;
+            }
+        }
+    }
+
+    if !empty {
+        output.push((start, len));
+    }
+
+    output
+}
+
+/// Returns `true` if the given query contains more than one statement.
+pub(crate) fn is_multi_statement(query: &str, no_backslash_escapes: bool) -> bool {
+    statements(query, no_backslash_escapes).len() > 1
+}
+
+/// Splits the given multi-statement query at statement boundaries into chunks,
+/// that are at most `max_len` bytes long.
+///
+/// Statements are never split, so a statement longer than `max_len` will occupy its own chunk.
+pub(crate) fn split_statements(
+    query: &str,
+    max_len: usize,
+    no_backslash_escapes: bool,
+) -> Vec<&str> {
+    let statements = statements(query, no_backslash_escapes);
+
+    let (mut chunk_start, mut chunk_end) = match statements.first() {
+        Some(first) => *first,
+        None => return vec![query],
+    };
+
+    let mut output = Vec::new();
+    for (start, end) in statements.into_iter().skip(1) {
+        if end - chunk_start > max_len {
+            output.push(&query[chunk_start..chunk_end]);
+            chunk_start = start;
+        }
+        chunk_end = end;
+    }
+    output.push(&query[chunk_start..chunk_end]);
+
+    output
+}
+
+#[cfg(test)]
+mod test {
+    use super::{is_multi_statement, split_statements};
+
+    #[test]
+    fn should_split_at_statement_boundaries() {
+        let query = "SELECT 1; SELECT 2; SELECT 3;";
+        assert_eq!(split_statements(query, 100, false), vec![query]);
+        assert_eq!(
+            split_statements(query, 19, false),
+            vec!["SELECT 1; SELECT 2;", " SELECT 3;"]
+        );
+        assert_eq!(
+            split_statements(query, 5, false),
+            vec!["SELECT 1;", " SELECT 2;", " SELECT 3;"]
+        );
+        assert_eq!(
+            split_statements("SELECT 1; SELECT 2", 9, false),
+            vec!["SELECT 1;", " SELECT 2"]
+        );
+    }
+
+    #[test]
+    fn should_detect_multi_statement_queries() {
+        assert!(!is_multi_statement("SELECT 1", false));
+        assert!(!is_multi_statement("SELECT 1; ", false));
+        assert!(!is_multi_statement("SELECT ';'; -- SELECT 2", false));
+        assert!(is_multi_statement("SELECT 1; SELECT 2", false));
+    }
+
+    #[test]
+    fn should_not_split_string_literals_and_identifiers() {
+        let query = r#"SELECT ';', '\';', ''';', ";", "\";", `;`, `;``;`; SELECT 2;"#;
+        assert_eq!(
+            split_statements(query, 1, false),
+            vec![
+                r#"SELECT ';', '\';', ''';', ";", "\";", `;`, `;``;`;"#,
+                " SELECT 2;"
+            ]
+        );
+    }
+
+    #[test]
+    fn should_respect_no_backslash_escapes() {
+        let query = r#"SELECT 'a\'; SELECT "\";"#;
+        assert_eq!(split_statements(query, 1, false), vec![query]);
+        assert!(!is_multi_statement(query, false));
+        assert_eq!(
+            split_statements(query, 1, true),
+            vec![r#"SELECT 'a\';"#, r#" SELECT "\";"#]
+        );
+        assert!(is_multi_statement(query, true));
+    }
+
+    #[test]
+    fn should_not_split_comments() {
+        let query = "SELECT 1 -- ;\n; SELECT 2 # ;\n; SELECT /* ; */ 3; SELECT 4--;\n;";
+        assert_eq!(
+            split_statements(query, 1, false),
+            vec![
+                "SELECT 1 -- ;\n;",
+                " SELECT 2 # ;\n;",
+                " SELECT /* ; */ 3;",
+                " SELECT 4--;",
+            ]
+        );
+    }
+
+    #[test]
+    fn should_skip_empty_statements() {
+        let query = "SELECT 1; ; /* comment; */ ;\n-- ;\n SELECT 2; /* trailing */ ";
+        assert_eq!(
+            split_statements(query, 1, false),
+            vec!["SELECT 1;", "\n-- ;\n SELECT 2;"]
+        );
+        assert_eq!(
+            split_statements("/*!40101 SET NAMES utf8 */; SELECT 1", 1, false),
+            vec!["/*!40101 SET NAMES utf8 */;", " SELECT 1"]
+        );
+        assert_eq!(split_statements(" -- ;\n ", 1, false), vec![" -- ;\n "]);
+    }
+}
@@ -15,13 +15,19 @@ use mysql_common::{
     },
 };
 
-use std::{borrow::Cow, sync::Arc};
+use std::{
+    borrow::Cow,
+    ops::Deref,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
 
 use crate::{
+    conn::with_caller_location,
     consts::{CapabilityFlags, Command},
     error::*,
     queryable::BinaryProtocol,
-    Column, Params, Value,
+    Column, Params, PreparedStmtLimitPolicy, Value,
 };
 
 /// Result of a `StatementLike::to_statement` call.
@@ -40,12 +46,9 @@ pub trait StatementLike: Send + Sync {
 impl StatementLike for str {
     fn to_statement<'a>(&'a self, conn: &'a mut crate::Conn) -> ToStatementResult<'a> {
         let fut = crate::BoxFuture(Box::pin(async move {
-            let (named_params, raw_query) = parse_named_params(self)?;
-            let inner_stmt = match conn.get_cached_stmt(&*raw_query) {
-                Some(inner_stmt) => inner_stmt,
-                None => conn.prepare_statement(raw_query).await?,
-            };
-            Ok(Statement::new(inner_stmt, named_params))
+            conn.prepare_query(self)
+                .await
+                .map(|prepared| prepared.statement)
         }));
         ToStatementResult::Mediate(fut)
     }
@@ -57,11 +60,27 @@ impl StatementLike for Statement {
     }
 }
 
+impl StatementLike for OwnedStatement {
+    fn to_statement<'a>(&'a self, _conn: &'a mut crate::Conn) -> ToStatementResult<'static> {
+        ToStatementResult::Immediate(self.stmt.clone())
+    }
+}
+
+/// Result of a [`crate::Conn::prep_detailed`] call.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Prepared {
+    /// Prepared statement.
+    pub statement: Statement,
+    /// `true` if the statement was taken from the statement cache
+    /// (i.e. no `COM_STMT_PREPARE` was sent to the server).
+    pub cache_hit: bool,
+}
+
 /// Statement data.
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct StmtInner {
     pub(crate) raw_query: Arc<str>,
-    columns: Option<Box<[Column]>>,
+    columns: Option<Arc<[Column]>>,
     params: Option<Box<[Column]>>,
     stmt_packet: StmtPacket,
     connection_id: u32,
@@ -97,7 +116,7 @@ impl StmtInner {
         self.columns = if columns.is_empty() {
             None
         } else {
-            Some(columns.into_boxed_slice())
+            Some(columns.into())
         };
         self
     }
@@ -106,6 +125,11 @@ impl StmtInner {
         self.columns.as_ref().map(AsRef::as_ref).unwrap_or(&[])
     }
 
+    /// Returns columns, if any, to decode rows of a result set without metadata.
+    pub(crate) fn shared_columns(&self) -> Option<Arc<[Column]>> {
+        self.columns.clone()
+    }
+
     pub(crate) fn params(&self) -> &[Column] {
         self.params.as_ref().map(AsRef::as_ref).unwrap_or(&[])
     }
@@ -164,18 +188,137 @@ impl Statement {
         self.inner.connection_id()
     }
 
-    /// Number of parameters.
+    /// Number of parameters, as reported by the server.
+    ///
+    /// It is known right after the statement is prepared, so it could be used to validate
+    /// params before execution. Every occurrence of a named parameter is counted.
     pub fn num_params(&self) -> u16 {
         self.inner.num_params()
     }
 
-    /// Number of columns.
+    /// Number of columns, as reported by the server (`0` for statements without a result set).
     pub fn num_columns(&self) -> u16 {
         self.inner.num_columns()
     }
 }
 
+/// Prepared statement, that is closed when dropped (see [`crate::Conn::prep_owned`]).
+///
+/// Dropped statement is closed right before the next command on its connection,
+/// so it won't leak on early returns.
+#[derive(Debug)]
+pub struct OwnedStatement {
+    stmt: Statement,
+    dropped_stmts: Arc<Mutex<Vec<u32>>>,
+}
+
+impl Deref for OwnedStatement {
+    type Target = Statement;
+
+    fn deref(&self) -> &Self::Target {
+        &self.stmt
+    }
+}
+
+impl Drop for OwnedStatement {
+    fn drop(&mut self) {
+        if let Ok(mut dropped_stmts) = self.dropped_stmts.lock() {
+            dropped_stmts.push(self.stmt.id());
+        }
+    }
+}
+
 impl crate::Conn {
+    /// Prepares the given statement, that will be closed when dropped.
+    ///
+    /// Unlike [`Queryable::prep`], it bypasses the statement cache, so use it for one-off
+    /// statements, that aren't worth caching.
+    ///
+    /// ```rust
+    /// # use mysql_async::{prelude::*, test_misc::get_opts, Conn};
+    /// # #[tokio::main]
+    /// # async fn main() -> mysql_async::Result<()> {
+    /// let mut conn = Conn::new(get_opts()).await?;
+    /// let stmt = conn.prep_owned("SELECT ?").await?;
+    /// let result: Vec<u8> = conn.exec_iter(&stmt, (42,)).await?.collect_and_drop().await?;
+    /// assert_eq!(result, vec![42]);
+    /// // `stmt` is closed here
+    /// # drop(stmt);
+    /// # conn.disconnect().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`Queryable::prep`]: crate::prelude::Queryable::prep
+    pub async fn prep_owned<Q: AsRef<str>>(&mut self, query: Q) -> Result<OwnedStatement> {
+        let stmt_cache_enabled = self.stmt_cache_enabled();
+        self.set_stmt_cache_enabled(false);
+        let stmt = self.get_statement(query.as_ref()).await;
+        self.set_stmt_cache_enabled(stmt_cache_enabled);
+        Ok(OwnedStatement {
+            stmt: stmt?,
+            dropped_stmts: self.dropped_stmts(),
+        })
+    }
+
+    /// Prepares the given statement, and reports whether it was taken from the statement cache.
+    ///
+    /// It's the same as [`Queryable::prep`], but allows to measure statement cache effectiveness.
+    ///
+    /// ```rust
+    /// # use mysql_async::{prelude::*, test_misc::get_opts, Conn};
+    /// # #[tokio::main]
+    /// # async fn main() -> mysql_async::Result<()> {
+    /// let mut conn = Conn::new(get_opts()).await?;
+    /// let prepared = conn.prep_detailed("SELECT ?").await?;
+    /// if !prepared.cache_hit {
+    ///     println!("statement {} was prepared", prepared.statement.id());
+    /// }
+    /// # conn.disconnect().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`Queryable::prep`]: crate::prelude::Queryable::prep
+    #[cfg_attr(feature = "caller-location", track_caller)]
+    pub fn prep_detailed<'a, Q>(&'a mut self, query: Q) -> crate::BoxFuture<'a, Prepared>
+    where
+        Q: AsRef<str> + Sync + Send + 'a,
+    {
+        let location = self.caller_location();
+        crate::BoxFuture(Box::pin(async move {
+            self.remember_caller_location(location);
+
+            let result = self.prepare_query(query.as_ref()).await;
+            // the caller location isn't used if the statement is cached
+            self.take_caller_location();
+            result
+        }))
+    }
+
+    /// Helper, that prepares the given query, unless it's cached.
+    async fn prepare_query(&mut self, query: &str) -> Result<Prepared> {
+        let query = self.rewrite_query(query)?;
+        if let Some((inner_stmt, named_params)) = self.get_cached_named_stmt(&query) {
+            return Ok(Prepared {
+                statement: Statement::new(inner_stmt, named_params),
+                cache_hit: true,
+            });
+        }
+        let (named_params, raw_query) = parse_named_params(&query)?;
+        let (inner_stmt, cache_hit) = match self.get_cached_stmt(&*raw_query) {
+            Some(inner_stmt) => (inner_stmt, true),
+            None => (self.prepare_statement(raw_query).await?, false),
+        };
+        if let Some(ref named_params) = named_params {
+            self.cache_named_stmt(&query, &inner_stmt, named_params.clone());
+        }
+        Ok(Prepared {
+            statement: Statement::new(inner_stmt, named_params),
+            cache_hit,
+        })
+    }
+
     /// Low-level helpers, that reads the given number of column packets terminated by EOF packet.
     ///
     /// Requires `num > 0`.
@@ -207,10 +350,13 @@ impl crate::Conn {
     where
         U: StatementLike + ?Sized,
     {
-        match stmt_like.to_statement(self) {
+        let result = match stmt_like.to_statement(self) {
             ToStatementResult::Immediate(statement) => Ok(statement),
             ToStatementResult::Mediate(statement) => statement.await,
-        }
+        };
+        // the caller location isn't used if the statement is cached
+        self.take_caller_location();
+        result
     }
 
     /// Low-level helper, that prepares the given statement.
@@ -218,12 +364,36 @@ impl crate::Conn {
     /// `raw_query` is a query with `?` placeholders (if any).
     async fn prepare_statement(&mut self, raw_query: Cow<'_, str>) -> Result<Arc<StmtInner>> {
         let raw_query: Arc<str> = raw_query.into_owned().into_boxed_str().into();
-
-        self.write_command_data(Command::COM_STMT_PREPARE, raw_query.as_bytes())
-            .await?;
-
-        let packet = self.read_packet().await?;
+        self.set_last_statement(raw_query.clone());
+        // column definitions are needed to decode rows without metadata
+        self.set_metadata_suppressed(false).await?;
+
+        let tagged_query = with_caller_location(&raw_query, self.take_caller_location());
+        let hinted_query = self.hint_if_in_transaction(&tagged_query);
+        let packet = loop {
+            self.write_command_data(Command::COM_STMT_PREPARE, hinted_query.as_bytes())
+                .await?;
+
+            match self.read_packet().await {
+                Ok(packet) => break packet,
+                // ER_MAX_PREPARED_STMT_COUNT_REACHED
+                Err(Error::Server(ref err)) if err.code == 1461 => {
+                    if self.opts().on_prepared_limit() == PreparedStmtLimitPolicy::EvictCached {
+                        if let Some(stmt) = self.stmt_cache_mut().pop_lru() {
+                            self.close_statement(stmt.id()).await?;
+                            continue;
+                        }
+                    }
+                    return Err(DriverError::PreparedStmtLimitReached {
+                        prepared: self.prepared_stmt_count(),
+                    }
+                    .into());
+                }
+                Err(err) => return Err(err),
+            }
+        };
         let mut inner_stmt = StmtInner::from_payload(&*packet, self.id(), raw_query)?;
+        self.on_stmt_prepared();
 
         if inner_stmt.num_params() > 0 {
             let params = self.read_column_defs(inner_stmt.num_params()).await?;
@@ -253,6 +423,11 @@ impl crate::Conn {
     where
         P: Into<Params>,
     {
+        self.set_last_statement(statement.inner.raw_query.clone());
+        self.set_metadata_suppressed(statement.num_columns() > 0)
+            .await?;
+        self.set_expected_columns(statement.inner.shared_columns());
+        let started = Instant::now();
         let mut params = params.into();
         loop {
             match params {
@@ -308,6 +483,7 @@ impl crate::Conn {
                 }
             }
         }
+        self.log_slow_query(&statement.inner.raw_query, started);
         Ok(())
     }
 
@@ -337,6 +513,8 @@ impl crate::Conn {
     /// Helper, that closes statement with the given id.
     pub(crate) async fn close_statement(&mut self, id: u32) -> Result<()> {
         self.stmt_cache_mut().remove(id);
-        self.write_command_raw(ComStmtClose::new(id).into()).await
+        self.write_command_raw(ComStmtClose::new(id).into()).await?;
+        self.on_stmt_closed();
+        Ok(())
     }
 }
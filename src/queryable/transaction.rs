@@ -6,7 +6,14 @@
 // option. All files in the project carrying such notice may not be copied,
 // modified, or distributed except according to those terms.
 
-use std::fmt;
+use futures_util::FutureExt;
+
+use std::{
+    fmt,
+    future::Future,
+    panic::{self, AssertUnwindSafe},
+    pin::Pin,
+};
 
 use crate::{connection_like::Connection, error::*, queryable::Queryable, Conn};
 
@@ -20,6 +27,23 @@ pub enum TxStatus {
     RequiresRollback,
     /// Connection is not in transaction at the moment.
     None,
+    /// Transaction was rolled back by the server (e.g. it was chosen as a deadlock victim),
+    /// but `Transaction` wasn't committed or rolled back yet.
+    RolledBackByServer,
+}
+
+/// Returns `true` if the server rolls back the current transaction on the given error.
+///
+/// `ER_LOCK_WAIT_TIMEOUT` only rolls back the whole transaction,
+/// if `innodb_rollback_on_timeout` is enabled.
+pub(crate) fn is_implicit_rollback(code: u16, rollback_on_timeout: bool) -> bool {
+    match code {
+        // ER_LOCK_DEADLOCK
+        1213 => true,
+        // ER_LOCK_WAIT_TIMEOUT
+        1205 => rollback_on_timeout,
+        _ => false,
+    }
 }
 
 impl Conn {
@@ -152,30 +176,121 @@ impl<'a> Transaction<'a> {
 
         if let Some(isolation_level) = isolation_level {
             let query = format!("SET TRANSACTION ISOLATION LEVEL {}", isolation_level);
+            let query = conn.with_transaction_hint(&query);
             conn.query_drop(query).await?;
         }
 
         if let Some(readonly) = readonly {
-            if readonly {
-                conn.query_drop("SET TRANSACTION READ ONLY").await?;
+            let query = if readonly {
+                "SET TRANSACTION READ ONLY"
             } else {
-                conn.query_drop("SET TRANSACTION READ WRITE").await?;
-            }
+                "SET TRANSACTION READ WRITE"
+            };
+            let query = conn.with_transaction_hint(query);
+            conn.query_drop(query).await?;
         }
 
-        if consistent_snapshot {
-            conn.query_drop("START TRANSACTION WITH CONSISTENT SNAPSHOT")
-                .await?
+        let query = if consistent_snapshot {
+            "START TRANSACTION WITH CONSISTENT SNAPSHOT"
         } else {
-            conn.query_drop("START TRANSACTION").await?
+            "START TRANSACTION"
         };
+        let query = conn.with_transaction_hint(query);
+        conn.query_drop(query).await?;
 
         conn.set_tx_status(TxStatus::InTransaction);
         Ok(Transaction(conn))
     }
 
+    /// Returns the underlying connection.
+    ///
+    /// Statements executed via the returned connection participate in this transaction,
+    /// so it could be passed to a code, that takes `&mut Conn`. Note, that it's up to the caller
+    /// not to end the transaction via this connection (e.g. by executing `COMMIT`),
+    /// because this transaction won't know about it.
+    pub fn conn_mut(&mut self) -> &mut Conn {
+        &mut self.0
+    }
+
+    /// Runs the given closure within a nested scope of this transaction.
+    ///
+    /// MySql doesn't support nested transactions, so the scope is emulated using an
+    /// auto-named savepoint. The savepoint is released if the closure resolves to `Ok`,
+    /// and changes made within the scope are rolled back to the savepoint if the closure
+    /// resolves to `Err` or panics (the panic is resumed after the rollback). Changes made
+    /// before the scope are kept in either case, and are committed or rolled back
+    /// along with this transaction. Scopes could be nested further.
+    ///
+    /// ```rust
+    /// # use mysql_async::{prelude::*, test_misc::get_opts, Conn, Result, TxOpts};
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<()> {
+    /// let mut conn = Conn::new(get_opts()).await?;
+    /// let mut tx = conn.start_transaction(TxOpts::default()).await?;
+    /// let result = tx
+    ///     .nested(|tx| Box::pin(async move { tx.query_drop("SELECT * FROM missing_table").await }))
+    ///     .await;
+    /// assert!(result.is_err());
+    /// // the transaction is still usable
+    /// tx.commit().await?;
+    /// # conn.disconnect().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn nested<F, T>(&mut self, f: F) -> Result<T>
+    where
+        F: for<'t> FnOnce(
+            &'t mut Transaction<'a>,
+        ) -> Pin<Box<dyn Future<Output = Result<T>> + Send + 't>>,
+    {
+        let savepoint = self.0.next_savepoint_name();
+        self.0
+            .query_drop(format!("SAVEPOINT {}", savepoint))
+            .await?;
+        let result = AssertUnwindSafe(async { f(self).await })
+            .catch_unwind()
+            .await;
+        match result {
+            Ok(Ok(value)) => {
+                self.0
+                    .query_drop(format!("RELEASE SAVEPOINT {}", savepoint))
+                    .await?;
+                Ok(value)
+            }
+            // the savepoint is gone along with the transaction
+            Ok(Err(err)) if self.0.get_tx_status() == TxStatus::RolledBackByServer => Err(err),
+            Ok(Err(err)) => {
+                // Unlike the original error, a rollback error means that changes of the scope
+                // may be committed along with the transaction, so it takes precedence.
+                self.rollback_to_savepoint(&savepoint).await?;
+                Err(err)
+            }
+            Err(payload) => {
+                let _ = self.rollback_to_savepoint(&savepoint).await;
+                panic::resume_unwind(payload)
+            }
+        }
+    }
+
+    /// Rolls back to the given savepoint and releases it.
+    async fn rollback_to_savepoint(&mut self, savepoint: &str) -> Result<()> {
+        self.0
+            .query_drop(format!("ROLLBACK TO SAVEPOINT {}", savepoint))
+            .await?;
+        self.0
+            .query_drop(format!("RELEASE SAVEPOINT {}", savepoint))
+            .await
+    }
+
     /// Performs `COMMIT` query.
+    ///
+    /// Returns [`DriverError::TransactionRolledBack`] without performing a query,
+    /// if the transaction was rolled back by the server (e.g. it was chosen as a deadlock victim).
     pub async fn commit(mut self) -> Result<()> {
+        if self.0.get_tx_status() == TxStatus::RolledBackByServer {
+            self.0.set_tx_status(TxStatus::None);
+            return Err(DriverError::TransactionRolledBack.into());
+        }
         let result = self.0.query_iter("COMMIT").await?;
         result.drop_result().await?;
         self.0.set_tx_status(TxStatus::None);
@@ -183,7 +298,14 @@ impl<'a> Transaction<'a> {
     }
 
     /// Performs `ROLLBACK` query.
+    ///
+    /// The query is skipped, if the transaction was already rolled back by the server
+    /// (e.g. it was chosen as a deadlock victim).
     pub async fn rollback(mut self) -> Result<()> {
+        if self.0.get_tx_status() == TxStatus::RolledBackByServer {
+            self.0.set_tx_status(TxStatus::None);
+            return Ok(());
+        }
         let result = self.0.query_iter("ROLLBACK").await?;
         result.drop_result().await?;
         self.0.set_tx_status(TxStatus::None);
@@ -193,8 +315,11 @@ impl<'a> Transaction<'a> {
 
 impl Drop for Transaction<'_> {
     fn drop(&mut self) {
-        if self.0.get_tx_status() == TxStatus::InTransaction {
-            self.0.set_tx_status(TxStatus::RequiresRollback);
+        match self.0.get_tx_status() {
+            TxStatus::InTransaction => self.0.set_tx_status(TxStatus::RequiresRollback),
+            // there is nothing to roll back
+            TxStatus::RolledBackByServer => self.0.set_tx_status(TxStatus::None),
+            TxStatus::RequiresRollback | TxStatus::None => (),
         }
     }
 }
@@ -0,0 +1,674 @@
+// Copyright (c) 2020 Anatoly Ikorsky
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use mysql_common::{
+    constants::{ColumnFlags, ColumnType},
+    io::ReadMysqlExt,
+    packets::Column,
+    row::{new_row, ColumnIndex, Row},
+    value::{
+        convert::{from_value, FromValue},
+        read_bin_value, read_text_value, Value,
+    },
+};
+
+use std::{any::type_name, cell::RefCell, convert::TryFrom, fmt, io, mem, str, sync::Arc};
+
+use crate::error::{DriverError, Result};
+
+/// Helpers, that resolve columns of a [`Row`] by a table-qualified name.
+///
+/// Useful for join results, where the same column name belongs to different tables
+/// (e.g. `SELECT t.a, u.a FROM t JOIN u ...`).
+pub trait RowExt {
+    /// Returns index of a column with the given name, that belongs to the given table.
+    ///
+    /// `table` is matched against the table alias (`Column::table_str`) and then against
+    /// the original table name (`Column::org_table_str`).
+    fn qualified_index(&self, table: &str, name: &str) -> Option<usize>;
+
+    /// Will copy value of a column with the given table-qualified name and convert it to `T`
+    /// (see [`Row::get`]).
+    ///
+    /// Returns `None` if there is no such column.
+    fn get_by_qualified_name<T: FromValue>(&self, table: &str, name: &str) -> Option<T>;
+
+    /// Returns table-qualified identifiers of columns of this row (e.g. `t.a`).
+    ///
+    /// Identifier of a column, that doesn't belong to a table, is just a column name.
+    fn qualified_column_names(&self) -> Vec<String>;
+
+    /// Will copy value of an integer column and convert it to `T` with an explicit range check.
+    ///
+    /// Signedness of a textual value is taken from `UNSIGNED_FLAG` of the column. Unlike
+    /// [`Row::get`], that fails with an opaque `FromValueError`, a value, that doesn't fit
+    /// into `T` (e.g. a `BIGINT UNSIGNED` value above `i64::MAX` converted to `i64`),
+    /// is reported as [`DriverError::IntegerOverflow`]. Non-integer values (including `NULL`)
+    /// are reported as [`DriverError::FromValue`].
+    ///
+    /// Returns `None` if there is no such column.
+    fn get_int<T, I>(&self, index: I) -> Option<Result<T>>
+    where
+        T: TryFrom<i128>,
+        I: ColumnIndex;
+}
+
+impl RowExt for Row {
+    fn qualified_index(&self, table: &str, name: &str) -> Option<usize> {
+        let columns = self.columns_ref();
+        let named = |i: &usize| columns[*i].name_str() == name;
+        (0..columns.len())
+            .filter(named)
+            .find(|i| columns[*i].table_str() == table)
+            .or_else(|| {
+                (0..columns.len())
+                    .filter(named)
+                    .find(|i| columns[*i].org_table_str() == table)
+            })
+    }
+
+    fn get_by_qualified_name<T: FromValue>(&self, table: &str, name: &str) -> Option<T> {
+        self.qualified_index(table, name)
+            .and_then(|index| self.get(index))
+    }
+
+    fn qualified_column_names(&self) -> Vec<String> {
+        self.columns_ref()
+            .iter()
+            .map(|column| {
+                let table = column.table_str();
+                if table.is_empty() {
+                    column.name_str().into_owned()
+                } else {
+                    format!("{}.{}", table, column.name_str())
+                }
+            })
+            .collect()
+    }
+
+    fn get_int<T, I>(&self, index: I) -> Option<Result<T>>
+    where
+        T: TryFrom<i128>,
+        I: ColumnIndex,
+    {
+        let index = index.idx(self.columns_ref())?;
+        let value = self.as_ref(index)?;
+        let unsigned = self.columns_ref()[index]
+            .flags()
+            .contains(ColumnFlags::UNSIGNED_FLAG);
+        Some(value_to_int(value, unsigned))
+    }
+}
+
+/// Converts the given integer value to `T` (see [`RowExt::get_int`]).
+fn value_to_int<T: TryFrom<i128>>(value: &Value, unsigned: bool) -> Result<T> {
+    let int = match *value {
+        Value::Int(x) => Some(i128::from(x)),
+        Value::UInt(x) => Some(i128::from(x)),
+        Value::Bytes(ref bytes) => str::from_utf8(bytes).ok().and_then(|string| {
+            if unsigned {
+                string.parse::<u64>().ok().map(i128::from)
+            } else {
+                string.parse::<i64>().ok().map(i128::from)
+            }
+        }),
+        _ => None,
+    };
+    match int {
+        Some(int) => T::try_from(int).map_err(|_| {
+            DriverError::IntegerOverflow {
+                value: int.to_string(),
+                target: type_name::<T>(),
+            }
+            .into()
+        }),
+        None => Err(DriverError::FromValue {
+            value: value.clone(),
+        }
+        .into()),
+    }
+}
+
+/// State of a column value of a [`RawRow`].
+#[derive(Debug, Clone, PartialEq)]
+enum Slot {
+    /// Value isn't decoded yet.
+    Raw,
+    Decoded(Value),
+    Taken,
+}
+
+/// Result set row, that decodes its values lazily (see [`QueryResult::next_raw`]).
+///
+/// Row retains the raw row packet, and a value of a column is only decoded
+/// on the first access via [`RawRow::get`] or [`RawRow::take`]. Decoded values are cached.
+/// This is cheaper than [`Row`] if only a few columns of a wide row are needed.
+///
+/// [`QueryResult::next_raw`]: crate::QueryResult::next_raw
+pub struct RawRow {
+    packet: Vec<u8>,
+    columns: Arc<[Column]>,
+    binary: bool,
+    /// Offset of a value of each column in `packet` (`None` for `NULL`).
+    offsets: Vec<Option<usize>>,
+    values: RefCell<Vec<Slot>>,
+}
+
+impl RawRow {
+    /// Creates a row from a text protocol row packet.
+    pub(crate) fn text(packet: Vec<u8>, columns: Arc<[Column]>) -> io::Result<RawRow> {
+        let mut offsets = Vec::with_capacity(columns.len());
+        let mut pos = 0;
+        for _ in 0..columns.len() {
+            match packet.get(pos) {
+                Some(0xfb) => {
+                    offsets.push(None);
+                    pos += 1;
+                }
+                Some(_) => {
+                    offsets.push(Some(pos));
+                    pos += lenenc_str_len(&packet[pos..])?;
+                }
+                None => return Err(unexpected_eof()),
+            }
+        }
+        Ok(RawRow::new(packet, columns, false, offsets))
+    }
+
+    /// Creates a row from a binary protocol row packet.
+    pub(crate) fn binary(packet: Vec<u8>, columns: Arc<[Column]>) -> io::Result<RawRow> {
+        // packet header and null-bitmap with the offset of 2 bits
+        let bitmap_len = (columns.len() + 7 + 2) / 8;
+        if packet.len() < 1 + bitmap_len {
+            return Err(unexpected_eof());
+        }
+
+        let mut offsets = Vec::with_capacity(columns.len());
+        let mut pos = 1 + bitmap_len;
+        for (i, column) in columns.iter().enumerate() {
+            let bit = i + 2;
+            if packet[1 + bit / 8] & (1 << (bit % 8)) != 0 {
+                offsets.push(None);
+            } else {
+                offsets.push(Some(pos));
+                pos += bin_value_len(&packet[pos..], column.column_type())?;
+            }
+        }
+        Ok(RawRow::new(packet, columns, true, offsets))
+    }
+
+    fn new(
+        packet: Vec<u8>,
+        columns: Arc<[Column]>,
+        binary: bool,
+        offsets: Vec<Option<usize>>,
+    ) -> RawRow {
+        RawRow {
+            values: RefCell::new(vec![Slot::Raw; columns.len()]),
+            packet,
+            columns,
+            binary,
+            offsets,
+        }
+    }
+
+    /// Returns length of a row.
+    pub fn len(&self) -> usize {
+        self.columns.len()
+    }
+
+    /// Returns true if the row has a length of 0.
+    pub fn is_empty(&self) -> bool {
+        self.columns.is_empty()
+    }
+
+    /// Returns columns of this row.
+    pub fn columns_ref(&self) -> &[Column] {
+        &self.columns
+    }
+
+    /// Returns columns of this row.
+    pub fn columns(&self) -> Arc<[Column]> {
+        self.columns.clone()
+    }
+
+    /// Will decode (if not yet decoded) and copy a value at index `index` and convert it to `T`.
+    ///
+    /// Returns `None` if there is no value at index `index`, if the value was taken,
+    /// or if it could not be decoded.
+    ///
+    /// # Panics
+    ///
+    /// Same as [`Row::get`], it will panic if the value could not be converted to `T`.
+    pub fn get<T: FromValue, I: ColumnIndex>(&self, index: I) -> Option<T> {
+        let index = index.idx(&self.columns)?;
+        let mut values = self.values.borrow_mut();
+        let slot = values.get_mut(index)?;
+        if let Slot::Raw = slot {
+            *slot = Slot::Decoded(self.decode(index).ok()?);
+        }
+        match slot {
+            Slot::Decoded(value) => Some(from_value(value.clone())),
+            _ => None,
+        }
+    }
+
+    /// Will decode (if not yet decoded) and take a value at index `index`
+    /// and convert it to `T`.
+    ///
+    /// Returns `None` if there is no value at index `index`, if the value was already taken,
+    /// or if it could not be decoded.
+    ///
+    /// # Panics
+    ///
+    /// Same as [`Row::take`], it will panic if the value could not be converted to `T`.
+    pub fn take<T: FromValue, I: ColumnIndex>(&mut self, index: I) -> Option<T> {
+        let index = index.idx(&self.columns)?;
+        let slot = self.values.get_mut().get_mut(index)?;
+        match mem::replace(slot, Slot::Taken) {
+            Slot::Raw => self.decode(index).ok().map(from_value),
+            Slot::Decoded(value) => Some(from_value(value)),
+            Slot::Taken => None,
+        }
+    }
+
+    /// Decodes all the remaining values and converts this row into a [`Row`].
+    ///
+    /// Values, that were taken, will become `NULL`.
+    pub fn into_row(self) -> io::Result<Row> {
+        let mut output = Vec::with_capacity(self.len());
+        for (index, slot) in self.values.borrow_mut().drain(..).enumerate() {
+            output.push(match slot {
+                Slot::Raw => self.decode(index)?,
+                Slot::Decoded(value) => value,
+                Slot::Taken => Value::NULL,
+            });
+        }
+        Ok(new_row(output, self.columns))
+    }
+
+    fn decode(&self, index: usize) -> io::Result<Value> {
+        let offset = match self.offsets[index] {
+            Some(offset) => offset,
+            None => return Ok(Value::NULL),
+        };
+        let mut input = &self.packet[offset..];
+        if self.binary {
+            let column = &self.columns[index];
+            read_bin_value(
+                &mut input,
+                column.column_type(),
+                column.flags().contains(ColumnFlags::UNSIGNED_FLAG),
+            )
+        } else {
+            read_text_value(&mut input)
+        }
+    }
+}
+
+impl fmt::Debug for RawRow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RawRow")
+            .field("columns", &self.columns)
+            .field("binary", &self.binary)
+            .field("values", &self.values.borrow())
+            .finish()
+    }
+}
+
+fn unexpected_eof() -> io::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, "can't parse row packet")
+}
+
+/// Returns the length of a length-encoded string at the beginning of `input`.
+fn lenenc_str_len(input: &[u8]) -> io::Result<usize> {
+    let mut rest = input;
+    let len = rest.read_lenenc_int()? as usize;
+    let header_len = input.len() - rest.len();
+    if rest.len() < len {
+        return Err(unexpected_eof());
+    }
+    Ok(header_len + len)
+}
+
+/// Returns the length of a binary protocol value at the beginning of `input`.
+fn bin_value_len(input: &[u8], column_type: ColumnType) -> io::Result<usize> {
+    let len = match column_type {
+        ColumnType::MYSQL_TYPE_STRING
+        | ColumnType::MYSQL_TYPE_VAR_STRING
+        | ColumnType::MYSQL_TYPE_BLOB
+        | ColumnType::MYSQL_TYPE_TINY_BLOB
+        | ColumnType::MYSQL_TYPE_MEDIUM_BLOB
+        | ColumnType::MYSQL_TYPE_LONG_BLOB
+        | ColumnType::MYSQL_TYPE_SET
+        | ColumnType::MYSQL_TYPE_ENUM
+        | ColumnType::MYSQL_TYPE_DECIMAL
+        | ColumnType::MYSQL_TYPE_VARCHAR
+        | ColumnType::MYSQL_TYPE_BIT
+        | ColumnType::MYSQL_TYPE_NEWDECIMAL
+        | ColumnType::MYSQL_TYPE_GEOMETRY
+        | ColumnType::MYSQL_TYPE_JSON => return lenenc_str_len(input),
+        ColumnType::MYSQL_TYPE_TINY => 1,
+        ColumnType::MYSQL_TYPE_SHORT | ColumnType::MYSQL_TYPE_YEAR => 2,
+        ColumnType::MYSQL_TYPE_LONG
+        | ColumnType::MYSQL_TYPE_INT24
+        | ColumnType::MYSQL_TYPE_FLOAT => 4,
+        ColumnType::MYSQL_TYPE_LONGLONG | ColumnType::MYSQL_TYPE_DOUBLE => 8,
+        ColumnType::MYSQL_TYPE_TIMESTAMP
+        | ColumnType::MYSQL_TYPE_DATE
+        | ColumnType::MYSQL_TYPE_DATETIME
+        | ColumnType::MYSQL_TYPE_TIME => match input.first() {
+            Some(len) => 1 + *len as usize,
+            None => return Err(unexpected_eof()),
+        },
+        ColumnType::MYSQL_TYPE_NULL => 0,
+        x => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unsupported column type {:?}", x),
+            ))
+        }
+    };
+    if input.len() < len {
+        return Err(unexpected_eof());
+    }
+    Ok(len)
+}
+
+#[cfg(test)]
+mod test {
+    use mysql_common::{
+        constants::{ColumnFlags, ColumnType},
+        io::WriteMysqlExt,
+        packets::{column_from_payload, Column},
+        value::Value,
+    };
+
+    use std::sync::Arc;
+
+    use super::RawRow;
+    use crate::{
+        error::{DriverError, Error},
+        prelude::*,
+        queryable::{BinaryProtocol, Protocol, TextProtocol},
+        test_misc::get_opts,
+        Conn, Row,
+    };
+
+    fn column(column_type: ColumnType, flags: ColumnFlags) -> Column {
+        let mut payload = Vec::new();
+        for x in &["def", "db", "t", "t", "c", "c"] {
+            payload.write_lenenc_str(x.as_bytes()).unwrap();
+        }
+        payload.push(0x0c);
+        payload.extend_from_slice(&33_u16.to_le_bytes());
+        payload.extend_from_slice(&255_u32.to_le_bytes());
+        payload.push(column_type as u8);
+        payload.extend_from_slice(&flags.bits().to_le_bytes());
+        payload.extend_from_slice(&[0, 0, 0]);
+        column_from_payload(payload).unwrap()
+    }
+
+    fn columns() -> Arc<[Column]> {
+        vec![
+            column(ColumnType::MYSQL_TYPE_LONGLONG, ColumnFlags::UNSIGNED_FLAG),
+            column(ColumnType::MYSQL_TYPE_VAR_STRING, ColumnFlags::empty()),
+            column(ColumnType::MYSQL_TYPE_TINY, ColumnFlags::empty()),
+            column(ColumnType::MYSQL_TYPE_DOUBLE, ColumnFlags::empty()),
+            column(ColumnType::MYSQL_TYPE_DATETIME, ColumnFlags::empty()),
+            column(ColumnType::MYSQL_TYPE_TIME, ColumnFlags::empty()),
+            column(ColumnType::MYSQL_TYPE_BLOB, ColumnFlags::empty()),
+        ]
+        .into()
+    }
+
+    fn text_packet() -> Vec<u8> {
+        let mut packet = Vec::new();
+        for x in &[
+            "18446744073709551615",
+            "foo",
+            "-1",
+            "1.5",
+            "2020-01-02 03:04:05",
+        ] {
+            packet.write_lenenc_str(x.as_bytes()).unwrap();
+        }
+        packet.push(0xfb);
+        packet.write_lenenc_str(&[b'x'; 300]).unwrap();
+        packet
+    }
+
+    fn binary_packet() -> Vec<u8> {
+        // header and null-bitmap (TIME column is NULL)
+        let mut packet = vec![0x00, 0b1000_0000, 0b0000_0000];
+        packet.extend_from_slice(&u64::MAX.to_le_bytes());
+        packet.write_lenenc_str(b"foo").unwrap();
+        packet.push(0xff);
+        packet.extend_from_slice(&1.5_f64.to_le_bytes());
+        packet.extend_from_slice(&[7, 0xe4, 0x07, 1, 2, 3, 4, 5]);
+        packet.write_lenenc_str(&[b'x'; 300]).unwrap();
+        packet
+    }
+
+    #[test]
+    fn should_decode_raw_row_same_as_row() {
+        let text = TextProtocol::read_result_set_row(&text_packet(), columns()).unwrap();
+        let binary = BinaryProtocol::read_result_set_row(&binary_packet(), columns()).unwrap();
+
+        for (packet, row, binary) in vec![
+            (text_packet(), text, false),
+            (binary_packet(), binary, true),
+        ] {
+            let raw = if binary {
+                RawRow::binary(packet.clone(), columns()).unwrap()
+            } else {
+                RawRow::text(packet.clone(), columns()).unwrap()
+            };
+            assert_eq!(raw.len(), row.len());
+            // access in reverse order to check, that offsets are independent of decoding
+            for i in (0..row.len()).rev() {
+                assert_eq!(raw.get::<Value, _>(i), row.get::<Value, _>(i));
+            }
+            assert_eq!(raw.get::<Value, _>(row.len()), None);
+            assert_eq!(raw.into_row().unwrap().unwrap(), row.clone().unwrap());
+
+            let mut raw = if binary {
+                RawRow::binary(packet, columns()).unwrap()
+            } else {
+                RawRow::text(packet, columns()).unwrap()
+            };
+            assert_eq!(raw.take::<String, _>(1), Some("foo".into()));
+            assert_eq!(raw.take::<Value, _>(1), None);
+            assert_eq!(raw.get::<Value, _>(1), None);
+            assert_eq!(raw.get::<u64, _>(0), Some(u64::MAX));
+            assert_eq!(raw.take::<u64, _>(0), Some(u64::MAX));
+            let row = raw.into_row().unwrap();
+            assert_eq!(row.as_ref(0), Some(&Value::NULL));
+            assert_eq!(row.as_ref(1), Some(&Value::NULL));
+        }
+    }
+
+    #[test]
+    fn should_check_range_of_integers() {
+        let text = TextProtocol::read_result_set_row(&text_packet(), columns()).unwrap();
+        let binary = BinaryProtocol::read_result_set_row(&binary_packet(), columns()).unwrap();
+
+        for row in vec![text, binary] {
+            assert_eq!(row.get_int::<u64, _>(0).unwrap().unwrap(), u64::MAX);
+            assert_eq!(row.get_int::<i128, _>(0).unwrap().unwrap(), u64::MAX.into());
+            match row.get_int::<i64, _>(0).unwrap() {
+                Err(Error::Driver(DriverError::IntegerOverflow { value, target })) => {
+                    assert_eq!(value, "18446744073709551615");
+                    assert_eq!(target, "i64");
+                }
+                other => panic!("unexpected result: {:?}", other),
+            }
+
+            assert_eq!(row.get_int::<i8, _>(2).unwrap().unwrap(), -1);
+            match row.get_int::<u8, _>(2).unwrap() {
+                Err(Error::Driver(DriverError::IntegerOverflow { value, target })) => {
+                    assert_eq!(value, "-1");
+                    assert_eq!(target, "u8");
+                }
+                other => panic!("unexpected result: {:?}", other),
+            }
+
+            match row.get_int::<i64, _>(1).unwrap() {
+                Err(Error::Driver(DriverError::FromValue { .. })) => (),
+                other => panic!("unexpected result: {:?}", other),
+            }
+            assert!(row.get_int::<i64, _>(row.len()).is_none());
+        }
+    }
+
+    #[test]
+    fn should_reject_truncated_raw_row() {
+        let mut packet = text_packet();
+        packet.truncate(packet.len() - 1);
+        assert!(RawRow::text(packet, columns()).is_err());
+
+        let mut packet = binary_packet();
+        packet.truncate(packet.len() - 1);
+        assert!(RawRow::binary(packet, columns()).is_err());
+    }
+
+    #[tokio::test]
+    async fn should_read_raw_rows() -> super::super::Result<()> {
+        let mut conn = Conn::new(get_opts()).await?;
+
+        let mut result = conn
+            .query_iter("SELECT 1, 'foo', NULL UNION ALL SELECT 2, 'bar', NULL")
+            .await?;
+        let mut rows = Vec::new();
+        while let Some(row) = result.next_raw().await? {
+            rows.push((
+                row.get::<u8, _>(0).unwrap(),
+                row.get::<String, _>(1).unwrap(),
+                row.get::<Option<u8>, _>(2).unwrap(),
+            ));
+        }
+        assert_eq!(rows, vec![(1, "foo".into(), None), (2, "bar".into(), None)]);
+
+        let stmt = conn.prep("SELECT ?, ?").await?;
+        let mut result = conn.exec_iter(&stmt, (42_u8, "baz")).await?;
+        let row = result.next_raw().await?.unwrap();
+        assert_eq!(row.get::<String, _>(1).unwrap(), "baz");
+        assert_eq!(row.get::<u8, _>(0).unwrap(), 42);
+        assert!(result.next_raw().await?.is_none());
+
+        conn.disconnect().await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn should_check_range_of_unsigned_bigint() -> super::super::Result<()> {
+        let mut conn = Conn::new(get_opts()).await?;
+        conn.query_drop("CREATE TEMPORARY TABLE ids (id BIGINT UNSIGNED)")
+            .await?;
+        conn.query_drop("INSERT INTO ids VALUES (18446744073709551615)")
+            .await?;
+
+        let query = "SELECT id FROM ids";
+        let text: Row = conn.query_first(query).await?.unwrap();
+        let binary: Row = conn.exec_first(query, ()).await?.unwrap();
+        for row in vec![text, binary] {
+            assert_eq!(row.get_int::<u64, _>("id").unwrap()?, u64::MAX);
+            match row.get_int::<i64, _>("id").unwrap() {
+                Err(Error::Driver(DriverError::IntegerOverflow { value, target })) => {
+                    assert_eq!(value, "18446744073709551615");
+                    assert_eq!(target, "i64");
+                }
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+
+        conn.disconnect().await?;
+        Ok(())
+    }
+
+    #[cfg(feature = "nightly")]
+    mod bench {
+        use mysql_common::{
+            constants::{ColumnFlags, ColumnType},
+            io::WriteMysqlExt,
+            packets::Column,
+        };
+
+        use std::sync::Arc;
+
+        use super::{super::RawRow, column};
+        use crate::queryable::{Protocol, TextProtocol};
+
+        fn wide_row() -> (Vec<u8>, Arc<[Column]>) {
+            let columns: Vec<_> = (0..50)
+                .map(|_| column(ColumnType::MYSQL_TYPE_VAR_STRING, ColumnFlags::empty()))
+                .collect();
+            let mut packet = Vec::new();
+            for i in 0..50 {
+                packet
+                    .write_lenenc_str(format!("value of the column {}", i).as_bytes())
+                    .unwrap();
+            }
+            (packet, columns.into())
+        }
+
+        #[bench]
+        fn eager_2_of_50_columns(bencher: &mut test::Bencher) {
+            let (packet, columns) = wide_row();
+            bencher.iter(|| {
+                let packet = packet.clone();
+                let row = TextProtocol::read_result_set_row(&packet, columns.clone()).unwrap();
+                (row.get::<String, _>(0), row.get::<String, _>(49))
+            });
+        }
+
+        #[bench]
+        fn lazy_2_of_50_columns(bencher: &mut test::Bencher) {
+            let (packet, columns) = wide_row();
+            bencher.iter(|| {
+                let row = RawRow::text(packet.clone(), columns.clone()).unwrap();
+                (row.get::<String, _>(0), row.get::<String, _>(49))
+            });
+        }
+    }
+
+    #[tokio::test]
+    async fn should_get_by_qualified_name() -> super::super::Result<()> {
+        let mut conn = Conn::new(get_opts()).await?;
+        conn.query_drop("CREATE TEMPORARY TABLE foo (id INT, a TEXT)")
+            .await?;
+        conn.query_drop("CREATE TEMPORARY TABLE bar (id INT, a TEXT)")
+            .await?;
+        conn.query_drop("INSERT INTO foo VALUES (1, 'foo')").await?;
+        conn.query_drop("INSERT INTO bar VALUES (1, 'bar')").await?;
+
+        let row: Row = conn
+            .query_first("SELECT f.a, bar.a, 42 AS a FROM foo f JOIN bar USING (id)")
+            .await?
+            .unwrap();
+
+        assert_eq!(row.qualified_column_names(), vec!["f.a", "bar.a", "a"]);
+        assert_eq!(
+            row.get_by_qualified_name::<String>("f", "a").unwrap(),
+            "foo"
+        );
+        assert_eq!(
+            row.get_by_qualified_name::<String>("foo", "a").unwrap(),
+            "foo"
+        );
+        assert_eq!(
+            row.get_by_qualified_name::<String>("bar", "a").unwrap(),
+            "bar"
+        );
+        assert_eq!(row.qualified_index("f", "id"), None);
+        assert_eq!(row.qualified_index("baz", "a"), None);
+
+        conn.disconnect().await?;
+        Ok(())
+    }
+}
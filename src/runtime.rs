@@ -0,0 +1,79 @@
+// Copyright (c) 2020 Anatoly Ikorsky
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Async runtime primitives, used by the library.
+//!
+//! Library code should spawn tasks and create timers through this module,
+//! so that the runtime is defined in a single place.
+//!
+//! Tokio is the only supported runtime. Sockets and the packet codec (see `io`) still use
+//! tokio types directly, so this module is not a runtime abstraction.
+
+use std::{future::Future, time::Duration};
+
+/// Interval stream (see [`interval`]).
+pub(crate) type Interval = tokio::time::Interval;
+
+/// Spawns the given future on the current runtime.
+///
+/// # Panic
+///
+/// Will panic if called outside of a runtime context.
+pub(crate) fn spawn<F>(future: F)
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    tokio::spawn(future);
+}
+
+/// Spawns the given future on the current runtime, if any.
+///
+/// Returns `false` if called outside of a runtime context.
+pub(crate) fn try_spawn<F>(future: F) -> bool
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    match tokio::runtime::Handle::try_current() {
+        Ok(handle) => {
+            handle.spawn(future);
+            true
+        }
+        Err(_) => false,
+    }
+}
+
+/// Awaits the given future for at most `duration`.
+///
+/// Returns `None` if the future didn't complete in time.
+pub(crate) async fn timeout<F: Future>(duration: Duration, future: F) -> Option<F::Output> {
+    tokio::time::timeout(duration, future).await.ok()
+}
+
+/// Returns a stream, that yields every `period`, starting immediately.
+pub(crate) fn interval(period: Duration) -> Interval {
+    tokio::time::interval(period)
+}
+
+#[cfg(test)]
+mod test {
+    use super::try_spawn;
+
+    #[test]
+    fn should_not_spawn_outside_of_runtime() {
+        assert!(!try_spawn(async {}));
+    }
+
+    #[tokio::test]
+    async fn should_spawn_within_runtime() {
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        assert!(try_spawn(async move { tx.send(42).unwrap() }));
+        assert_eq!(rx.await.unwrap(), 42);
+    }
+}
@@ -0,0 +1,159 @@
+// Copyright (c) 2020 Anatoly Ikorsky
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use std::time::Duration;
+
+/// Query, that took longer than [`crate::Opts::slow_query_threshold`]
+/// (see [`crate::Opts::slow_query_logger`]).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlowQuery {
+    digest: String,
+    elapsed: Duration,
+}
+
+impl SlowQuery {
+    pub(crate) fn new(query: &str, no_backslash_escapes: bool, elapsed: Duration) -> Self {
+        Self {
+            digest: digest(query, no_backslash_escapes),
+            elapsed,
+        }
+    }
+
+    /// Returns the digest of the query text.
+    ///
+    /// String and numeric literals are replaced with `?` and whitespaces are collapsed,
+    /// so the digest doesn't contain values, e.g. `SELECT * FROM t WHERE id = ?`
+    /// for `SELECT * FROM t WHERE id = 42`.
+    pub fn digest(&self) -> &str {
+        &self.digest
+    }
+
+    /// Returns the execution time of the query.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+}
+
+/// Replaces string and numeric literals of the given query with `?` and collapses whitespaces.
+///
+/// Backslash is an escape character within string literals,
+/// unless `no_backslash_escapes` is `true` (see `NO_BACKSLASH_ESCAPES` SQL mode).
+fn digest(query: &str, no_backslash_escapes: bool) -> String {
+    let bytes = query.trim().as_bytes();
+    let len = bytes.len();
+
+    let mut output = Vec::with_capacity(len);
+    let mut i = 0;
+
+    while i < len {
+        match bytes[i] {
+            quote @ b'\'' | quote @ b'"' | quote @ b'`' => {
+                let start = i;
+                i += 1;
+                while i < len {
+                    if bytes[i] == b'\\' && quote != b'`' && !no_backslash_escapes {
+                        i += 2;
+                    } else if bytes[i] == quote {
+                        if bytes.get(i + 1) == Some(&quote) {
+                            i += 2;
+                        } else {
+                            break;
+                        }
+                    } else {
+                        i += 1;
+                    }
+                }
+                i = std::cmp::min(i + 1, len);
+                if quote == b'`' {
+                    // quoted identifier
+                    output.extend_from_slice(&bytes[start..i]);
+                } else {
+                    output.push(b'?');
+                }
+            }
+            x if x.is_ascii_whitespace() => {
+                while i < len && bytes[i].is_ascii_whitespace() {
+                    i += 1;
+                }
+                output.push(b' ');
+            }
+            x if x.is_ascii_digit()
+                && !output
+                    .last()
+                    .map(|x| x.is_ascii_alphanumeric() || *x == b'_' || *x == b'$')
+                    .unwrap_or(false) =>
+            {
+                while i < len && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'.') {
+                    i += 1;
+                }
+                output.push(b'?');
+            }
+            x => {
+                output.push(x);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&output).into_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use super::{digest, SlowQuery};
+    use crate::{prelude::*, test_misc::get_opts, Conn, OptsBuilder};
+
+    #[test]
+    fn should_compute_query_digest() {
+        assert_eq!(
+            digest(
+                "SELECT *\n  FROM t1 WHERE id = 42 AND name = 'secret'",
+                false
+            ),
+            "SELECT * FROM t1 WHERE id = ? AND name = ?"
+        );
+        assert_eq!(
+            digest(r#" SELECT "a\"b", 'it''s', 1.5e3, -7 FROM `t 1` "#, false),
+            "SELECT ?, ?, ?, -? FROM `t 1`"
+        );
+        assert_eq!(digest(r"SELECT 'a\', 1", true), "SELECT ?, ?");
+        assert_eq!(digest("SELECT 'unterminated", false), "SELECT ?");
+    }
+
+    #[tokio::test]
+    async fn should_log_slow_queries() -> super::super::Result<()> {
+        let logged = Arc::new(Mutex::new(Vec::<SlowQuery>::new()));
+        let logged_clone = logged.clone();
+        let opts = OptsBuilder::from_opts(get_opts())
+            .slow_query_threshold(Some(Duration::from_millis(500)))
+            .slow_query_logger(move |query: SlowQuery| logged_clone.lock().unwrap().push(query));
+        let mut conn = Conn::new(opts).await?;
+
+        conn.query_drop("SELECT 1").await?;
+        assert!(logged.lock().unwrap().is_empty());
+
+        conn.query_drop("SELECT SLEEP(1)").await?;
+        conn.exec_drop("SELECT SLEEP(?)", (1,)).await?;
+
+        let logged = logged.lock().unwrap().clone();
+        assert_eq!(logged.len(), 2);
+        assert_eq!(logged[0].digest(), "SELECT SLEEP(?)");
+        assert_eq!(logged[1].digest(), "SELECT SLEEP(?)");
+        for query in logged {
+            assert!(query.elapsed() >= Duration::from_millis(500));
+        }
+
+        conn.disconnect().await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,252 @@
+// Copyright (c) 2020 Anatoly Ikorsky
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use mysql_common::{
+    chrono::Duration,
+    value::{
+        convert::{from_value_opt, ConvIr, FromValue, FromValueError},
+        Value,
+    },
+};
+
+use std::convert::TryFrom;
+
+use crate::error::{DriverError, Error};
+
+/// Largest absolute value of a MySQL `TIME` value in microseconds (`838:59:59`).
+const MAX_MICROS: i64 = (838 * 3600 + 59 * 60 + 59) * 1_000_000;
+
+/// Wrapper that maps a MySQL `TIME` value to a signed duration (`chrono::Duration`).
+///
+/// MySQL `TIME` is a span within the `-838:59:59..=838:59:59` range with up to microsecond
+/// precision, so it is represented as a duration rather than as a time of day.
+/// Sub-microsecond precision is truncated.
+///
+/// Use `TimeSpan::try_from` to create a value from either `chrono::Duration` or
+/// `std::time::Duration`. It will fail with `DriverError::TimeOutOfRange` if the given
+/// duration doesn't fit into the `TIME` range.
+///
+/// ```rust
+/// # use mysql_async::{chrono::Duration, from_value, TimeSpan, Value};
+/// # use std::convert::TryFrom;
+/// let span = TimeSpan::try_from(-Duration::milliseconds(90_061_500)).unwrap();
+/// assert_eq!(Value::from(span), Value::Time(true, 1, 1, 1, 1, 500_000));
+///
+/// let value = Value::Bytes(b"-25:01:01.500000".to_vec());
+/// assert_eq!(from_value::<TimeSpan>(value), span);
+///
+/// assert!(TimeSpan::try_from(Duration::hours(839)).is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TimeSpan(Duration);
+
+impl TimeSpan {
+    /// Returns the wrapped value.
+    pub fn into_inner(self) -> Duration {
+        self.0
+    }
+
+    /// Returns `true` if this span is negative.
+    pub fn is_negative(&self) -> bool {
+        self.0 < Duration::zero()
+    }
+}
+
+impl AsRef<Duration> for TimeSpan {
+    fn as_ref(&self) -> &Duration {
+        &self.0
+    }
+}
+
+/// Returns the given number of microseconds as a duration, if it fits into the `TIME` range.
+fn micros_to_span(micros: i64) -> Option<TimeSpan> {
+    if (-MAX_MICROS..=MAX_MICROS).contains(&micros) {
+        Some(TimeSpan(Duration::microseconds(micros)))
+    } else {
+        None
+    }
+}
+
+impl TryFrom<Duration> for TimeSpan {
+    type Error = Error;
+
+    fn try_from(duration: Duration) -> Result<Self, Self::Error> {
+        // `num_microseconds` truncates towards zero
+        duration
+            .num_microseconds()
+            .and_then(micros_to_span)
+            .ok_or_else(|| DriverError::TimeOutOfRange.into())
+    }
+}
+
+impl TryFrom<std::time::Duration> for TimeSpan {
+    type Error = Error;
+
+    fn try_from(duration: std::time::Duration) -> Result<Self, Self::Error> {
+        i64::try_from(duration.as_micros())
+            .ok()
+            .and_then(micros_to_span)
+            .ok_or_else(|| DriverError::TimeOutOfRange.into())
+    }
+}
+
+impl From<TimeSpan> for Value {
+    fn from(span: TimeSpan) -> Value {
+        // range is checked by `TimeSpan::try_from`
+        let micros = span.0.num_microseconds().expect("out of range time span");
+        let abs = micros.abs();
+        let secs = abs / 1_000_000;
+        Value::Time(
+            micros < 0,
+            (secs / 86_400) as u32,
+            (secs % 86_400 / 3_600) as u8,
+            (secs % 3_600 / 60) as u8,
+            (secs % 60) as u8,
+            (abs % 1_000_000) as u32,
+        )
+    }
+}
+
+/// Intermediate result of a `Value`-to-`TimeSpan` conversion.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeSpanIr {
+    value: Value,
+    output: TimeSpan,
+}
+
+impl ConvIr<TimeSpan> for TimeSpanIr {
+    fn new(v: Value) -> Result<Self, FromValueError> {
+        let output = from_value_opt::<Duration>(v.clone())
+            .ok()
+            .and_then(|duration| duration.num_microseconds())
+            .and_then(micros_to_span);
+        match output {
+            Some(output) => Ok(TimeSpanIr { value: v, output }),
+            None => Err(FromValueError(v)),
+        }
+    }
+
+    fn commit(self) -> TimeSpan {
+        self.output
+    }
+
+    fn rollback(self) -> Value {
+        self.value
+    }
+}
+
+impl FromValue for TimeSpan {
+    type Intermediate = TimeSpanIr;
+}
+
+#[cfg(test)]
+mod test {
+    use mysql_common::{
+        chrono::Duration,
+        value::{convert::from_value_opt, Value},
+    };
+
+    use std::convert::TryFrom;
+
+    use super::TimeSpan;
+    use crate::{
+        error::{DriverError, Error},
+        prelude::*,
+        test_misc::get_opts,
+        Conn,
+    };
+
+    fn span(hours: i64, minutes: i64, seconds: i64, micros: i64) -> Duration {
+        Duration::hours(hours)
+            + Duration::minutes(minutes)
+            + Duration::seconds(seconds)
+            + Duration::microseconds(micros)
+    }
+
+    #[test]
+    fn should_convert_time_span_to_value() {
+        let positive = TimeSpan::try_from(span(838, 59, 59, 0)).unwrap();
+        assert_eq!(Value::from(positive), Value::Time(false, 34, 22, 59, 59, 0));
+
+        let negative = TimeSpan::try_from(-span(1, 2, 3, 4)).unwrap();
+        assert!(negative.is_negative());
+        assert_eq!(Value::from(negative), Value::Time(true, 0, 1, 2, 3, 4));
+
+        let fractional =
+            TimeSpan::try_from(std::time::Duration::from_nanos(1_500_000_999)).unwrap();
+        assert_eq!(
+            Value::from(fractional),
+            Value::Time(false, 0, 0, 0, 1, 500_000)
+        );
+    }
+
+    #[test]
+    fn should_reject_out_of_range_time_spans() {
+        for duration in vec![span(838, 59, 59, 1), -span(839, 0, 0, 0)] {
+            match TimeSpan::try_from(duration) {
+                Err(Error::Driver(DriverError::TimeOutOfRange)) => (),
+                other => panic!("{:?}", other),
+            }
+        }
+        match TimeSpan::try_from(std::time::Duration::from_secs(u64::MAX)) {
+            Err(Error::Driver(DriverError::TimeOutOfRange)) => (),
+            other => panic!("{:?}", other),
+        }
+        assert!(from_value_opt::<TimeSpan>(Value::Time(false, 35, 0, 0, 0, 0)).is_err());
+    }
+
+    #[test]
+    fn should_convert_value_to_time_span() {
+        let parsed = from_value_opt::<TimeSpan>(Value::Time(true, 1, 2, 3, 4, 5)).unwrap();
+        assert_eq!(parsed.into_inner(), -span(26, 3, 4, 5));
+
+        let parsed = from_value_opt::<TimeSpan>(Value::Bytes(b"838:59:59".to_vec())).unwrap();
+        assert_eq!(parsed.into_inner(), span(838, 59, 59, 0));
+
+        let parsed =
+            from_value_opt::<TimeSpan>(Value::Bytes(b"-00:00:01.250000".to_vec())).unwrap();
+        assert_eq!(parsed.into_inner(), -span(0, 0, 1, 250_000));
+
+        assert!(from_value_opt::<TimeSpan>(Value::Int(1)).is_err());
+    }
+
+    #[tokio::test]
+    async fn should_round_trip_time_spans() -> crate::Result<()> {
+        let mut conn = Conn::new(get_opts()).await?;
+        conn.query_drop("CREATE TEMPORARY TABLE spans (id INT, t TIME(6))")
+            .await?;
+
+        let spans = vec![
+            span(838, 59, 59, 0),
+            -span(838, 59, 59, 0),
+            span(0, 0, 1, 500_000),
+            -span(25, 1, 1, 123_456),
+        ];
+        for (id, duration) in spans.iter().enumerate() {
+            conn.exec_drop(
+                "INSERT INTO spans (id, t) VALUES (?, ?)",
+                (id, TimeSpan::try_from(*duration)?),
+            )
+            .await?;
+        }
+
+        let query = "SELECT t FROM spans ORDER BY id";
+        let text: Vec<TimeSpan> = conn.query(query).await?;
+        let binary: Vec<TimeSpan> = conn.exec(query, ()).await?;
+        for result in vec![text, binary] {
+            let result = result
+                .into_iter()
+                .map(TimeSpan::into_inner)
+                .collect::<Vec<_>>();
+            assert_eq!(result, spans);
+        }
+
+        conn.disconnect().await?;
+        Ok(())
+    }
+}
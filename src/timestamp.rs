@@ -0,0 +1,401 @@
+// Copyright (c) 2020 Anatoly Ikorsky
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use mysql_common::{
+    chrono::{Datelike, NaiveDateTime, Timelike},
+    time::{Date, OffsetDateTime, Time, UtcOffset},
+    value::{
+        convert::{from_value_opt, ConvIr, FromValue, FromValueError},
+        Value,
+    },
+};
+
+use std::{
+    convert::TryFrom,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::error::{DriverError, Error};
+
+/// Largest year representable by MySQL `DATETIME` and `DATE` types.
+const MAX_YEAR: u16 = 9999;
+
+/// UTC year, month, day, hour, minute, second and microsecond of a timestamp.
+type DateTimeParts = (u16, u8, u8, u8, u8, u8, u32);
+
+/// Wrapper that binds an absolute point in time as a MySQL `DATETIME`/`TIMESTAMP` value.
+///
+/// Supported inner types are `std::time::SystemTime` and `time::OffsetDateTime`.
+///
+/// Values are converted to UTC on the way to the server and interpreted as UTC on the way back,
+/// so the session `time_zone` must be `'+00:00'` for `TIMESTAMP` columns to store the expected
+/// instant. Sub-second precision is kept down to microseconds (nanoseconds are truncated).
+///
+/// Use `Timestamp::try_from` to create a value. It will fail with
+/// `DriverError::TimestampOutOfRange` if the year of the given instant (in UTC) is outside
+/// of the `0..=9999` range.
+///
+/// ```rust
+/// # use mysql_async::{Timestamp, Value};
+/// # use std::{convert::TryFrom, time::{Duration, UNIX_EPOCH}};
+/// let ts = Timestamp::try_from(UNIX_EPOCH + Duration::from_micros(1_500_000)).unwrap();
+/// assert_eq!(Value::from(ts), Value::Date(1970, 1, 1, 0, 0, 1, 500_000));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Timestamp<T> {
+    inner: T,
+    // checked at construction, so that conversion to `Value` can't fail
+    utc: DateTimeParts,
+}
+
+impl<T> Timestamp<T> {
+    /// Returns the wrapped value.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T> AsRef<T> for Timestamp<T> {
+    fn as_ref(&self) -> &T {
+        &self.inner
+    }
+}
+
+/// Returns parts of the given UTC date-time, if its year is within the `0..=9999` range.
+fn utc_parts(utc: OffsetDateTime) -> Option<DateTimeParts> {
+    match u16::try_from(utc.year()) {
+        Ok(year) if year <= MAX_YEAR => Some((
+            year,
+            utc.month(),
+            utc.day(),
+            utc.hour(),
+            utc.minute(),
+            utc.second(),
+            utc.microsecond(),
+        )),
+        _ => None,
+    }
+}
+
+/// Converts the given instant to UTC, truncated to microseconds.
+fn system_time_to_utc(time: SystemTime) -> Result<OffsetDateTime, Error> {
+    // Seconds since the epoch of 10000-01-01T00:00:00Z.
+    const MAX_SECS: u64 = 253_402_300_800;
+
+    let nanos = match time.duration_since(UNIX_EPOCH) {
+        Ok(since) if since.as_secs() < MAX_SECS => since.as_nanos() as i128,
+        Ok(_) => return Err(DriverError::TimestampOutOfRange.into()),
+        Err(err) if err.duration().as_secs() <= MAX_SECS => -(err.duration().as_nanos() as i128),
+        Err(_) => return Err(DriverError::TimestampOutOfRange.into()),
+    };
+
+    Ok(OffsetDateTime::from_unix_timestamp_nanos(
+        nanos - nanos.rem_euclid(1_000),
+    ))
+}
+
+impl TryFrom<SystemTime> for Timestamp<SystemTime> {
+    type Error = Error;
+
+    fn try_from(time: SystemTime) -> Result<Self, Self::Error> {
+        match system_time_to_utc(time).map(utc_parts) {
+            Ok(Some(utc)) => Ok(Timestamp { inner: time, utc }),
+            Ok(None) | Err(_) => Err(DriverError::TimestampOutOfRange.into()),
+        }
+    }
+}
+
+impl TryFrom<OffsetDateTime> for Timestamp<OffsetDateTime> {
+    type Error = Error;
+
+    fn try_from(time: OffsetDateTime) -> Result<Self, Self::Error> {
+        match utc_parts(time.to_offset(UtcOffset::UTC)) {
+            Some(utc) => Ok(Timestamp { inner: time, utc }),
+            None => Err(DriverError::TimestampOutOfRange.into()),
+        }
+    }
+}
+
+impl<T> From<Timestamp<T>> for Value {
+    fn from(Timestamp { utc, .. }: Timestamp<T>) -> Value {
+        let (year, month, day, hour, minute, second, micros) = utc;
+        Value::Date(year, month, day, hour, minute, second, micros)
+    }
+}
+
+/// Intermediate result of a `Value`-to-`Timestamp<T>` conversion.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimestampIr<T> {
+    value: Value,
+    output: Timestamp<T>,
+}
+
+// `NaiveDateTime` is used here because, unlike `PrimitiveDateTime`, it keeps fractional seconds
+// of text protocol values.
+fn parse_utc(value: Value) -> Result<(Value, OffsetDateTime, DateTimeParts), FromValueError> {
+    match from_value_opt::<NaiveDateTime>(value.clone()) {
+        Ok(datetime) => {
+            let date = Date::try_from_ymd(
+                datetime.year(),
+                datetime.month() as u8,
+                datetime.day() as u8,
+            );
+            let time = Time::try_from_hms_nano(
+                datetime.hour() as u8,
+                datetime.minute() as u8,
+                datetime.second() as u8,
+                datetime.nanosecond(),
+            );
+            let utc = match (date, time) {
+                (Ok(date), Ok(time)) => date.with_time(time).assume_utc(),
+                _ => return Err(FromValueError(value)),
+            };
+            match utc_parts(utc) {
+                Some(parts) => Ok((value, utc, parts)),
+                None => Err(FromValueError(value)),
+            }
+        }
+        Err(_) => Err(FromValueError(value)),
+    }
+}
+
+impl ConvIr<Timestamp<OffsetDateTime>> for TimestampIr<OffsetDateTime> {
+    fn new(v: Value) -> Result<Self, FromValueError> {
+        let (value, inner, utc) = parse_utc(v)?;
+        let output = Timestamp { inner, utc };
+        Ok(TimestampIr { value, output })
+    }
+
+    fn commit(self) -> Timestamp<OffsetDateTime> {
+        self.output
+    }
+
+    fn rollback(self) -> Value {
+        self.value
+    }
+}
+
+impl ConvIr<Timestamp<SystemTime>> for TimestampIr<SystemTime> {
+    fn new(v: Value) -> Result<Self, FromValueError> {
+        let (value, inner, utc) = parse_utc(v)?;
+        let nanos = inner.unix_timestamp_nanos();
+        // nanoseconds since the epoch overflow `u64` after the year 2554
+        let since = u64::try_from(nanos.abs() / 1_000_000_000)
+            .map(|secs| Duration::new(secs, (nanos.abs() % 1_000_000_000) as u32));
+        let output = match since {
+            Ok(since) if nanos >= 0 => UNIX_EPOCH.checked_add(since),
+            Ok(since) => UNIX_EPOCH.checked_sub(since),
+            Err(_) => None,
+        };
+        match output {
+            Some(inner) => Ok(TimestampIr {
+                value,
+                output: Timestamp { inner, utc },
+            }),
+            None => Err(FromValueError(value)),
+        }
+    }
+
+    fn commit(self) -> Timestamp<SystemTime> {
+        self.output
+    }
+
+    fn rollback(self) -> Value {
+        self.value
+    }
+}
+
+impl FromValue for Timestamp<OffsetDateTime> {
+    type Intermediate = TimestampIr<OffsetDateTime>;
+}
+
+impl FromValue for Timestamp<SystemTime> {
+    type Intermediate = TimestampIr<SystemTime>;
+}
+
+#[cfg(test)]
+mod test {
+    use mysql_common::time::{Date, OffsetDateTime, PrimitiveDateTime, Time, UtcOffset};
+
+    use std::{
+        convert::TryFrom,
+        time::{Duration, SystemTime, UNIX_EPOCH},
+    };
+
+    use super::Timestamp;
+
+    use crate::{
+        error::{DriverError, Error},
+        from_value, from_value_opt,
+        prelude::*,
+        test_misc::get_opts,
+        Conn, FromValueError, Value,
+    };
+
+    fn datetime(ymd: (i32, u8, u8), hms: (u8, u8, u8), micro: u32) -> PrimitiveDateTime {
+        Date::try_from_ymd(ymd.0, ymd.1, ymd.2)
+            .unwrap()
+            .with_time(Time::try_from_hms_micro(hms.0, hms.1, hms.2, micro).unwrap())
+    }
+
+    #[test]
+    fn should_convert_system_time_to_value() {
+        let before_epoch = UNIX_EPOCH - Duration::from_nanos(1_500_000_500);
+        assert_eq!(
+            Value::from(Timestamp::try_from(before_epoch).unwrap()),
+            Value::Date(1969, 12, 31, 23, 59, 58, 499_999),
+        );
+
+        let ts = UNIX_EPOCH + Duration::from_nanos(1_600_000_000_123_456_789);
+        assert_eq!(
+            Value::from(Timestamp::try_from(ts).unwrap()),
+            Value::Date(2020, 9, 13, 12, 26, 40, 123_456),
+        );
+    }
+
+    #[test]
+    fn should_convert_offset_date_time_to_utc_value() {
+        let ts = datetime((2020, 1, 2), (1, 2, 3), 4).assume_offset(UtcOffset::hours(3));
+        assert_eq!(
+            Value::from(Timestamp::try_from(ts).unwrap()),
+            Value::Date(2020, 1, 1, 22, 2, 3, 4),
+        );
+    }
+
+    #[test]
+    fn should_reject_out_of_range_timestamps() {
+        let year_10000 = UNIX_EPOCH + Duration::from_secs(253_402_300_800);
+        match Timestamp::try_from(year_10000) {
+            Err(Error::Driver(DriverError::TimestampOutOfRange)) => (),
+            other => panic!("{:?}", other),
+        }
+
+        let last_second = UNIX_EPOCH + Duration::from_secs(253_402_300_799);
+        assert_eq!(
+            Value::from(Timestamp::try_from(last_second).unwrap()),
+            Value::Date(9999, 12, 31, 23, 59, 59, 0),
+        );
+
+        let ts = datetime((9999, 12, 31), (23, 30, 0), 0).assume_offset(UtcOffset::hours(-1));
+        match Timestamp::try_from(ts) {
+            Err(Error::Driver(DriverError::TimestampOutOfRange)) => (),
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn should_convert_value_to_timestamp() {
+        let value = Value::Date(1969, 12, 31, 23, 59, 58, 500_000);
+        let system_time = from_value::<Timestamp<SystemTime>>(value.clone()).into_inner();
+        assert_eq!(system_time, UNIX_EPOCH - Duration::from_millis(1_500));
+
+        let offset = from_value::<Timestamp<OffsetDateTime>>(value).into_inner();
+        assert_eq!(offset.unix_timestamp_nanos(), -1_500_000_000);
+
+        let value = Value::Bytes(b"2020-01-02 03:04:05.000006".to_vec());
+        let offset = from_value::<Timestamp<OffsetDateTime>>(value).into_inner();
+        assert_eq!(offset, datetime((2020, 1, 2), (3, 4, 5), 6).assume_utc());
+    }
+
+    #[test]
+    fn should_convert_max_value_to_system_time() {
+        let value = Value::Date(9999, 12, 31, 23, 59, 59, 999_999);
+        let system_time = from_value::<Timestamp<SystemTime>>(value.clone()).into_inner();
+        assert_eq!(
+            system_time,
+            UNIX_EPOCH + Duration::new(253_402_300_799, 999_999_000)
+        );
+        assert_eq!(
+            Value::from(Timestamp::try_from(system_time).unwrap()),
+            value
+        );
+
+        let value = Value::Date(0, 1, 1, 0, 0, 0, 0);
+        let system_time = from_value::<Timestamp<SystemTime>>(value.clone()).into_inner();
+        assert_eq!(
+            system_time,
+            UNIX_EPOCH - Duration::from_secs(62_167_219_200)
+        );
+    }
+
+    #[test]
+    fn should_reject_out_of_range_values() {
+        for value in vec![
+            Value::Date(10000, 1, 1, 0, 0, 0, 0),
+            Value::Date(20000, 6, 15, 12, 0, 0, 0),
+            Value::Bytes(b"10000-01-01 00:00:00".to_vec()),
+        ] {
+            assert_eq!(
+                from_value_opt::<Timestamp<SystemTime>>(value.clone()),
+                Err(FromValueError(value.clone()))
+            );
+            assert_eq!(
+                from_value_opt::<Timestamp<OffsetDateTime>>(value.clone()),
+                Err(FromValueError(value))
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn should_round_trip_timestamps() -> crate::Result<()> {
+        let mut conn = Conn::new(get_opts()).await?;
+        conn.query_drop("SET time_zone = '+00:00'").await?;
+        conn.query_drop(
+            "CREATE TEMPORARY TABLE timestamps (
+                dt DATETIME(6), ts TIMESTAMP(6) NULL, d DATE
+            )",
+        )
+        .await?;
+
+        let system_time = UNIX_EPOCH + Duration::from_micros(1_600_000_000_123_456);
+        let offset = datetime((2020, 1, 2), (1, 2, 3), 4).assume_offset(UtcOffset::hours(3));
+        let date = Date::try_from_ymd(2020, 1, 2).unwrap();
+
+        for (dt, ts) in vec![
+            (
+                Value::from(Timestamp::try_from(system_time)?),
+                Value::from(Timestamp::try_from(system_time)?),
+            ),
+            (
+                Value::from(Timestamp::try_from(offset)?),
+                Value::from(Timestamp::try_from(offset)?),
+            ),
+        ] {
+            conn.exec_drop(
+                "INSERT INTO timestamps (dt, ts, d) VALUES (?, ?, ?)",
+                (dt, ts, date),
+            )
+            .await?;
+        }
+
+        let rows: Vec<(Timestamp<SystemTime>, Timestamp<SystemTime>, Date)> = conn
+            .exec("SELECT dt, ts, d FROM timestamps ORDER BY dt", ())
+            .await?;
+        let offset_rows: Vec<(Timestamp<OffsetDateTime>, Timestamp<OffsetDateTime>)> = conn
+            .query("SELECT dt, ts FROM timestamps ORDER BY dt")
+            .await?;
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].0.into_inner(), SystemTime::from(offset));
+        assert_eq!(rows[0].1.into_inner(), SystemTime::from(offset));
+        assert_eq!(rows[0].2, date);
+        assert_eq!(rows[1].0.into_inner(), system_time);
+        assert_eq!(rows[1].1.into_inner(), system_time);
+        assert_eq!(rows[1].2, date);
+
+        assert_eq!(offset_rows[0].0.into_inner(), offset);
+        assert_eq!(offset_rows[0].1.into_inner(), offset);
+        assert_eq!(
+            offset_rows[1].0.into_inner(),
+            OffsetDateTime::from(system_time)
+        );
+
+        conn.disconnect().await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,308 @@
+// Copyright (c) 2020 Anatoly Ikorsky
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use mysql_common::value::Value;
+
+use std::{borrow::Cow, cmp::Ordering, str::from_utf8};
+
+/// Helpers for client-side processing of [`Value`]s.
+pub trait ValueExt {
+    /// Compares two values the way MySQL compares them in `ORDER BY`.
+    ///
+    /// *   `NULL` is less than any other value;
+    /// *   numbers are compared numerically, strings compared with numbers are converted
+    ///     to numbers (using the longest numeric prefix, so `'abc'` is `0`);
+    /// *   strings are compared case-insensitively if `collation` is a `_ci` collation
+    ///     (e.g. `utf8mb4_general_ci`), and byte-wise otherwise;
+    /// *   dates and times are compared chronologically, and textually if compared with
+    ///     a string.
+    ///
+    /// ```rust
+    /// # use mysql_async::{prelude::*, Value};
+    /// # use std::cmp::Ordering;
+    /// let mut values = vec![Value::from("b"), Value::NULL, Value::from("A")];
+    /// values.sort_by(|x, y| x.compare(y, "utf8mb4_general_ci"));
+    /// assert_eq!(values, vec![Value::NULL, Value::from("A"), Value::from("b")]);
+    ///
+    /// assert_eq!(Value::from("10").compare(&Value::Int(9), "binary"), Ordering::Greater);
+    /// ```
+    fn compare(&self, other: &Value, collation: &str) -> Ordering;
+}
+
+/// Numeric representation of a value, if it is a number.
+enum Number {
+    Int(i128),
+    Float(f64),
+}
+
+impl Number {
+    fn of(value: &Value) -> Option<Number> {
+        match *value {
+            Value::Int(x) => Some(Number::Int(x.into())),
+            Value::UInt(x) => Some(Number::Int(x.into())),
+            Value::Float(x) => Some(Number::Float(x.into())),
+            Value::Double(x) => Some(Number::Float(x)),
+            _ => None,
+        }
+    }
+
+    /// Converts the given string to a number using its longest numeric prefix.
+    fn parse(bytes: &[u8]) -> Number {
+        let start = bytes
+            .iter()
+            .position(|x| !x.is_ascii_whitespace())
+            .unwrap_or(bytes.len());
+        let bytes = &bytes[start..];
+        let prefix = &bytes[..numeric_prefix_len(bytes)];
+        let number = from_utf8(prefix).ok().and_then(|x| x.parse::<f64>().ok());
+        Number::Float(number.unwrap_or(0.0))
+    }
+
+    fn as_f64(&self) -> f64 {
+        match *self {
+            Number::Int(x) => x as f64,
+            Number::Float(x) => x,
+        }
+    }
+
+    fn compare(&self, other: &Number) -> Ordering {
+        match (self, other) {
+            (Number::Int(x), Number::Int(y)) => x.cmp(y),
+            _ => self
+                .as_f64()
+                .partial_cmp(&other.as_f64())
+                .unwrap_or(Ordering::Equal),
+        }
+    }
+}
+
+/// Returns the length of the numeric prefix (`[+-]digits[.digits][e[+-]digits]`)
+/// of the given string.
+///
+/// Note that `inf` and `NaN` aren't numbers for MySQL.
+fn numeric_prefix_len(bytes: &[u8]) -> usize {
+    let digits = |from: usize| {
+        bytes
+            .iter()
+            .skip(from)
+            .take_while(|x| x.is_ascii_digit())
+            .count()
+    };
+
+    let mut end = match bytes.first() {
+        Some(b'+') | Some(b'-') => 1,
+        _ => 0,
+    };
+    let int_digits = digits(end);
+    end += int_digits;
+    let frac_digits = match bytes.get(end) {
+        Some(b'.') => digits(end + 1),
+        _ => 0,
+    };
+    if int_digits + frac_digits == 0 {
+        return 0;
+    }
+    if bytes.get(end) == Some(&b'.') {
+        end += 1 + frac_digits;
+    }
+
+    if let Some(b'e') | Some(b'E') = bytes.get(end) {
+        let mut exponent = end + 1;
+        if let Some(b'+') | Some(b'-') = bytes.get(exponent) {
+            exponent += 1;
+        }
+        let exponent_digits = digits(exponent);
+        if exponent_digits > 0 {
+            end = exponent + exponent_digits;
+        }
+    }
+    end
+}
+
+/// Returns the number of microseconds of a `TIME` value.
+fn time_to_micros(negative: bool, days: u32, h: u8, i: u8, s: u8, us: u32) -> i64 {
+    let secs = i64::from(days) * 86_400 + i64::from(h) * 3_600 + i64::from(i) * 60 + i64::from(s);
+    let micros = secs * 1_000_000 + i64::from(us);
+    if negative {
+        -micros
+    } else {
+        micros
+    }
+}
+
+/// Returns a textual representation of the given value (without quotes).
+fn to_text(value: &Value) -> Cow<'_, [u8]> {
+    match *value {
+        Value::Bytes(ref bytes) => Cow::Borrowed(bytes),
+        _ => Cow::Owned(value.as_sql(true).trim_matches('\'').as_bytes().to_vec()),
+    }
+}
+
+fn compare_strings(x: &[u8], y: &[u8], collation: &str) -> Ordering {
+    if !collation.to_ascii_lowercase().ends_with("_ci") {
+        return x.cmp(y);
+    }
+    match (from_utf8(x), from_utf8(y)) {
+        (Ok(x), Ok(y)) => x
+            .chars()
+            .flat_map(char::to_lowercase)
+            .cmp(y.chars().flat_map(char::to_lowercase)),
+        _ => x.to_ascii_lowercase().cmp(&y.to_ascii_lowercase()),
+    }
+}
+
+impl ValueExt for Value {
+    fn compare(&self, other: &Value, collation: &str) -> Ordering {
+        match (self, other) {
+            (Value::NULL, Value::NULL) => Ordering::Equal,
+            (Value::NULL, _) => Ordering::Less,
+            (_, Value::NULL) => Ordering::Greater,
+            (
+                &Value::Date(y1, m1, d1, h1, i1, s1, u1),
+                &Value::Date(y2, m2, d2, h2, i2, s2, u2),
+            ) => (y1, m1, d1, h1, i1, s1, u1).cmp(&(y2, m2, d2, h2, i2, s2, u2)),
+            (&Value::Time(n1, d1, h1, i1, s1, u1), &Value::Time(n2, d2, h2, i2, s2, u2)) => {
+                time_to_micros(n1, d1, h1, i1, s1, u1).cmp(&time_to_micros(n2, d2, h2, i2, s2, u2))
+            }
+            (Value::Bytes(x), Value::Bytes(y)) => compare_strings(x, y, collation),
+            _ => match (Number::of(self), Number::of(other)) {
+                (Some(x), Some(y)) => x.compare(&y),
+                (Some(x), None) => match other {
+                    Value::Bytes(y) => x.compare(&Number::parse(y)),
+                    _ => compare_strings(&to_text(self), &to_text(other), collation),
+                },
+                (None, Some(y)) => match self {
+                    Value::Bytes(x) => Number::parse(x).compare(&y),
+                    _ => compare_strings(&to_text(self), &to_text(other), collation),
+                },
+                (None, None) => compare_strings(&to_text(self), &to_text(other), collation),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use mysql_common::value::Value;
+
+    use std::cmp::Ordering;
+
+    use super::ValueExt;
+
+    #[test]
+    fn should_order_nulls_first() {
+        assert_eq!(Value::NULL.compare(&Value::NULL, "binary"), Ordering::Equal);
+        assert_eq!(
+            Value::NULL.compare(&Value::Int(-1), "binary"),
+            Ordering::Less
+        );
+        assert_eq!(
+            Value::from("").compare(&Value::NULL, "binary"),
+            Ordering::Greater
+        );
+    }
+
+    #[test]
+    fn should_compare_numbers() {
+        let mut values = vec![
+            Value::Double(2.5),
+            Value::UInt(u64::MAX),
+            Value::Int(-3),
+            Value::Float(0.5),
+            Value::Int(2),
+        ];
+        values.sort_by(|x, y| x.compare(y, "binary"));
+        assert_eq!(
+            values,
+            vec![
+                Value::Int(-3),
+                Value::Float(0.5),
+                Value::Int(2),
+                Value::Double(2.5),
+                Value::UInt(u64::MAX),
+            ]
+        );
+
+        // strings are converted to numbers
+        assert_eq!(
+            Value::from("10").compare(&Value::Int(9), "binary"),
+            Ordering::Greater
+        );
+        assert_eq!(
+            Value::Int(12).compare(&Value::from("12abc"), "binary"),
+            Ordering::Equal
+        );
+        assert_eq!(
+            Value::from("1e3").compare(&Value::Int(999), "binary"),
+            Ordering::Greater
+        );
+        assert_eq!(
+            Value::from("abc").compare(&Value::Int(0), "binary"),
+            Ordering::Equal
+        );
+        assert_eq!(
+            Value::from("inf").compare(&Value::Int(1), "binary"),
+            Ordering::Less
+        );
+    }
+
+    #[test]
+    fn should_find_numeric_prefix() {
+        use super::numeric_prefix_len;
+
+        assert_eq!(numeric_prefix_len(b"12abc"), 2);
+        assert_eq!(numeric_prefix_len(b"-1.5e+3x"), 7);
+        assert_eq!(numeric_prefix_len(b"+.5"), 3);
+        assert_eq!(numeric_prefix_len(b"1.e"), 2);
+        assert_eq!(numeric_prefix_len(b"1e-"), 1);
+        assert_eq!(numeric_prefix_len(b"-."), 0);
+        assert_eq!(numeric_prefix_len(b"inf"), 0);
+        assert_eq!(numeric_prefix_len(b""), 0);
+
+        // long strings are scanned once
+        let mut long = vec![b'1'; 1_000_000];
+        long.extend_from_slice(&[b'x'; 1_000_000]);
+        assert_eq!(numeric_prefix_len(&long), 1_000_000);
+        assert_eq!(
+            Value::Bytes(vec![b'x'; 1_000_000]).compare(&Value::Int(0), "binary"),
+            Ordering::Equal
+        );
+    }
+
+    #[test]
+    fn should_compare_strings() {
+        let a = Value::from("apple");
+        let b = Value::from("Banana");
+        assert_eq!(a.compare(&b, "utf8mb4_general_ci"), Ordering::Less);
+        assert_eq!(a.compare(&b, "utf8mb4_bin"), Ordering::Greater);
+        assert_eq!(
+            Value::from("ÄBC").compare(&Value::from("äbc"), "utf8mb4_0900_AI_CI"),
+            Ordering::Equal
+        );
+        // "10" < "9" as strings
+        assert_eq!(
+            Value::from("10").compare(&Value::from("9"), "binary"),
+            Ordering::Less
+        );
+    }
+
+    #[test]
+    fn should_compare_temporal_values() {
+        let earlier = Value::Date(2020, 1, 2, 3, 4, 5, 6);
+        let later = Value::Date(2020, 1, 2, 3, 4, 5, 7);
+        assert_eq!(earlier.compare(&later, "binary"), Ordering::Less);
+        assert_eq!(
+            Value::Time(true, 0, 1, 0, 0, 0).compare(&Value::Time(false, 0, 0, 0, 1, 0), "binary"),
+            Ordering::Less
+        );
+        assert_eq!(
+            Value::Date(2020, 1, 2, 0, 0, 0, 0).compare(&Value::from("2020-01-02"), "binary"),
+            Ordering::Equal
+        );
+    }
+}
@@ -0,0 +1,178 @@
+// Copyright (c) 2020 Anatoly Ikorsky
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use mysql_common::value::{
+    convert::{ConvIr, FromValue, FromValueError},
+    Value,
+};
+
+use std::convert::TryInto;
+
+/// Wrapper for a `VECTOR` value (MySql 9.0+, MariaDB 11.7+).
+///
+/// The server represents a vector as an array of little-endian `f32` values,
+/// so `VECTOR` columns are returned as byte strings. The dimension is checked by the server,
+/// i.e. inserting a vector of a wrong dimension is a server error.
+///
+/// ```rust
+/// # use mysql_async::{from_value_opt, Vector, Value};
+/// let value = Value::from(Vector(vec![1.0, -0.5]));
+/// assert_eq!(value, Value::Bytes(vec![0, 0, 0x80, 0x3f, 0, 0, 0, 0xbf]));
+/// assert_eq!(from_value_opt::<Vector>(value).unwrap(), Vector(vec![1.0, -0.5]));
+/// // length of a byte string must be a multiple of 4
+/// assert!(from_value_opt::<Vector>(Value::Bytes(vec![0; 3])).is_err());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, PartialOrd)]
+pub struct Vector(pub Vec<f32>);
+
+impl Vector {
+    /// Returns the wrapped value.
+    pub fn into_inner(self) -> Vec<f32> {
+        self.0
+    }
+
+    /// Returns the number of dimensions.
+    pub fn dimension(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl std::ops::Deref for Vector {
+    type Target = [f32];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl From<Vec<f32>> for Vector {
+    fn from(x: Vec<f32>) -> Self {
+        Vector(x)
+    }
+}
+
+impl From<Vector> for Value {
+    fn from(Vector(x): Vector) -> Value {
+        let mut bytes = Vec::with_capacity(x.len() * 4);
+        for value in x {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        Value::Bytes(bytes)
+    }
+}
+
+/// Interprets the given `VECTOR` value as an array of `f32` values.
+fn value_to_vec(v: &Value) -> Option<Vec<f32>> {
+    match *v {
+        Value::Bytes(ref bytes) if bytes.len() % 4 == 0 => Some(
+            bytes
+                .chunks(4)
+                .map(|chunk| f32::from_le_bytes(chunk.try_into().unwrap()))
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+/// Intermediate result of a `Value`-to-`Vector` conversion.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VectorIr {
+    value: Value,
+    output: Vec<f32>,
+}
+
+impl ConvIr<Vector> for VectorIr {
+    fn new(v: Value) -> Result<Self, FromValueError> {
+        match value_to_vec(&v) {
+            Some(output) => Ok(VectorIr { value: v, output }),
+            None => Err(FromValueError(v)),
+        }
+    }
+
+    fn commit(self) -> Vector {
+        Vector(self.output)
+    }
+
+    fn rollback(self) -> Value {
+        self.value
+    }
+}
+
+impl FromValue for Vector {
+    type Intermediate = VectorIr;
+}
+
+#[cfg(test)]
+mod test {
+    use mysql_common::value::{convert::from_value_opt, Value};
+
+    use super::Vector;
+    use crate::{prelude::*, test_misc::get_opts, Conn, Error};
+
+    #[test]
+    fn should_convert_vector() {
+        let vector = Vector(vec![0.0, 1.5, -2.25, f32::MAX]);
+        let value = Value::from(vector.clone());
+        match value {
+            Value::Bytes(ref bytes) => assert_eq!(bytes.len(), 16),
+            _ => panic!("unexpected value: {:?}", value),
+        }
+        assert_eq!(from_value_opt::<Vector>(value).unwrap(), vector);
+
+        assert_eq!(Value::from(Vector::default()), Value::Bytes(vec![]));
+        assert_eq!(
+            from_value_opt::<Vector>(Value::Bytes(vec![])).unwrap(),
+            Vector::default()
+        );
+        assert!(from_value_opt::<Vector>(Value::Bytes(vec![0; 5])).is_err());
+        assert!(from_value_opt::<Vector>(Value::Int(1)).is_err());
+        assert!(from_value_opt::<Vector>(Value::NULL).is_err());
+        assert_eq!(from_value_opt::<Option<Vector>>(Value::NULL).unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn should_decode_vector_columns() -> super::super::Result<()> {
+        let mut conn = Conn::new(get_opts()).await?;
+        let version: String = conn.query_first("SELECT VERSION()").await?.unwrap();
+        let min_version = if version.contains("MariaDB") {
+            (11, 7, 0)
+        } else {
+            (9, 0, 0)
+        };
+        if conn.server_version() < min_version {
+            return conn.disconnect().await;
+        }
+
+        conn.query_drop("CREATE TEMPORARY TABLE vectors (id INT, v VECTOR(4))")
+            .await?;
+        let vector = Vector(vec![1.0, -0.5, 0.25, 1e-3]);
+        conn.exec_drop(
+            "INSERT INTO vectors (id, v) VALUES (?, ?)",
+            (1, vector.clone()),
+        )
+        .await?;
+
+        let query = "SELECT v FROM vectors WHERE id = 1";
+        let text: Option<Vector> = conn.query_first(query).await?;
+        assert_eq!(text.as_ref(), Some(&vector));
+        let binary: Option<Vector> = conn.exec_first(query, ()).await?;
+        assert_eq!(binary.as_ref(), Some(&vector));
+
+        // dimension mismatch
+        let result = conn
+            .exec_drop(
+                "INSERT INTO vectors (id, v) VALUES (?, ?)",
+                (2, Vector(vec![1.0, 2.0, 3.0])),
+            )
+            .await;
+        assert!(matches!(result, Err(Error::Server(_))));
+
+        conn.disconnect().await?;
+        Ok(())
+    }
+}
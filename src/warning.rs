@@ -0,0 +1,51 @@
+// Copyright (c) 2020 Anatoly Ikorsky
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use mysql_common::row::{
+    convert::{FromRow, FromRowError},
+    Row,
+};
+
+use std::fmt;
+
+use crate::{error::*, prelude::Queryable, Conn};
+
+/// A row of the `SHOW WARNINGS` output.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Warning {
+    /// `Note`, `Warning` or `Error`.
+    pub level: String,
+    /// Error code of the condition.
+    pub code: u16,
+    /// Message of the condition.
+    pub message: String,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({}): {}", self.level, self.code, self.message)
+    }
+}
+
+impl FromRow for Warning {
+    fn from_row_opt(row: Row) -> std::result::Result<Self, FromRowError> {
+        let (level, code, message) = FromRow::from_row_opt(row)?;
+        Ok(Warning {
+            level,
+            code,
+            message,
+        })
+    }
+}
+
+impl Conn {
+    /// Reads warnings of the last statement using `SHOW WARNINGS`.
+    pub(crate) async fn show_warnings(&mut self) -> Result<Vec<Warning>> {
+        self.query("SHOW WARNINGS").await
+    }
+}
@@ -0,0 +1,173 @@
+// Copyright (c) 2020 Anatoly Ikorsky
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use mysql_common::value::{
+    convert::{ConvIr, FromValue, FromValueError},
+    Value,
+};
+
+use std::str::from_utf8;
+
+/// MySQL `YEAR` value, that is normalized to a 4-digit year.
+///
+/// Two-digit years (e.g. values of a legacy `YEAR(2)` column) are converted
+/// the same way MySQL converts them: `70`-`99` to `1970`-`1999` and `00`-`69`
+/// to `2000`-`2069`. The zero year `0000` (a numeric `0`) stays `0`.
+///
+/// `Year` is bound as an integer.
+///
+/// ```rust
+/// # use mysql_async::{from_value, Value, Year};
+/// assert_eq!(from_value::<Year>(Value::Bytes(b"1999".to_vec())), Year(1999));
+/// assert_eq!(from_value::<Year>(Value::Bytes(b"05".to_vec())), Year(2005));
+/// assert_eq!(from_value::<Year>(Value::Int(85)), Year(1985));
+/// assert_eq!(Value::from(Year(2024)), Value::UInt(2024));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Year(pub u16);
+
+impl Year {
+    /// Returns the 4-digit year.
+    pub fn into_inner(self) -> u16 {
+        self.0
+    }
+}
+
+impl From<Year> for Value {
+    fn from(Year(x): Year) -> Value {
+        Value::UInt(x.into())
+    }
+}
+
+/// Normalizes a year, that is given using `digits` digits.
+fn normalize(year: u64, digits: usize) -> Option<u16> {
+    match year {
+        0..=69 if digits <= 2 => Some(2000 + year as u16),
+        70..=99 if digits <= 2 => Some(1900 + year as u16),
+        0..=9999 => Some(year as u16),
+        _ => None,
+    }
+}
+
+/// Interprets the given value as a `YEAR` value.
+fn value_to_year(v: &Value) -> Option<u16> {
+    match *v {
+        Value::Bytes(ref bytes) => {
+            let text = from_utf8(bytes).ok()?.trim();
+            if text.is_empty() || !text.bytes().all(|x| x.is_ascii_digit()) {
+                return None;
+            }
+            normalize(text.parse().ok()?, text.len())
+        }
+        // numeric zero is the zero year
+        Value::Int(0) | Value::UInt(0) => Some(0),
+        Value::Int(x) if x > 0 => normalize(x as u64, if x < 100 { 2 } else { 4 }),
+        Value::UInt(x) => normalize(x, if x < 100 { 2 } else { 4 }),
+        _ => None,
+    }
+}
+
+/// Intermediate result of a `Value`-to-`Year` conversion.
+#[derive(Debug, Clone, PartialEq)]
+pub struct YearIr {
+    value: Value,
+    output: u16,
+}
+
+impl ConvIr<Year> for YearIr {
+    fn new(v: Value) -> Result<Self, FromValueError> {
+        match value_to_year(&v) {
+            Some(output) => Ok(YearIr { value: v, output }),
+            None => Err(FromValueError(v)),
+        }
+    }
+
+    fn commit(self) -> Year {
+        Year(self.output)
+    }
+
+    fn rollback(self) -> Value {
+        self.value
+    }
+}
+
+impl FromValue for Year {
+    type Intermediate = YearIr;
+}
+
+#[cfg(test)]
+mod test {
+    use mysql_common::value::{convert::from_value_opt, Value};
+
+    use super::Year;
+    use crate::{prelude::*, test_misc::get_opts, Conn, Row};
+
+    #[test]
+    fn should_normalize_years() {
+        let cases: Vec<(Value, u16)> = vec![
+            (Value::Bytes(b"2024".to_vec()), 2024),
+            (Value::Bytes(b"1901".to_vec()), 1901),
+            (Value::Bytes(b"0000".to_vec()), 0),
+            (Value::Bytes(b"00".to_vec()), 2000),
+            (Value::Bytes(b"69".to_vec()), 2069),
+            (Value::Bytes(b"70".to_vec()), 1970),
+            (Value::Bytes(b"99".to_vec()), 1999),
+            (Value::Int(0), 0),
+            (Value::Int(5), 2005),
+            (Value::Int(85), 1985),
+            (Value::Int(2155), 2155),
+            (Value::UInt(69), 2069),
+            (Value::UInt(1999), 1999),
+        ];
+        for (value, year) in cases {
+            assert_eq!(from_value_opt::<Year>(value).unwrap(), Year(year));
+        }
+
+        for value in vec![
+            Value::NULL,
+            Value::Int(-1),
+            Value::UInt(10_000),
+            Value::Bytes(b"".to_vec()),
+            Value::Bytes(b"20x4".to_vec()),
+            Value::Bytes(b"-5".to_vec()),
+            Value::Double(2024.0),
+        ] {
+            assert!(from_value_opt::<Year>(value).is_err());
+        }
+
+        assert_eq!(Value::from(Year(1985)), Value::UInt(1985));
+    }
+
+    #[tokio::test]
+    async fn should_read_year_columns() -> super::super::Result<()> {
+        let mut conn = Conn::new(get_opts()).await?;
+        conn.query_drop("CREATE TEMPORARY TABLE years (id INT, y YEAR)")
+            .await?;
+        // 2-digit values are stored as 4-digit years by the server
+        conn.query_drop("INSERT INTO years VALUES (1, 85), (2, '05'), (3, 1999), (4, 0)")
+            .await?;
+        conn.exec_drop("INSERT INTO years VALUES (5, ?)", (Year(2024),))
+            .await?;
+
+        let expected = vec![Year(1985), Year(2005), Year(1999), Year(0), Year(2024)];
+        let query = "SELECT y FROM years ORDER BY id";
+        let text: Vec<Year> = conn.query(query).await?;
+        let binary: Vec<Year> = conn.exec(query, ()).await?;
+        assert_eq!(text, expected);
+        assert_eq!(binary, expected);
+
+        // 2-digit values, that aren't normalized by the server
+        let row: Row = conn.query_first("SELECT '85', '05', 85, 5").await?.unwrap();
+        for (i, year) in [1985, 2005, 1985, 2005].iter().enumerate() {
+            assert_eq!(row.get::<Year, _>(i), Some(Year(*year)));
+        }
+
+        conn.disconnect().await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,192 @@
+// Copyright (c) 2020 Anatoly Ikorsky
+//
+// Licensed under the Apache License, Version 2.0
+// <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the MIT
+// license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. All files in the project carrying such notice may not be copied,
+// modified, or distributed except according to those terms.
+
+use mysql_common::value::{
+    convert::{from_value_opt, ConvIr, FromValue, FromValueError},
+    Value,
+};
+
+/// Wrapper that reads the MySQL zero date (`0000-00-00`) as `None`.
+///
+/// The zero date is only stored if the `NO_ZERO_DATE` SQL mode is disabled. It isn't
+/// a valid date, so conversion of the zero date to `chrono::NaiveDate` or
+/// `chrono::NaiveDateTime` fails. Use this wrapper to read it as `None` instead
+/// (`NULL` is also `None`). Other values are converted to `T` as usual, e.g. a `DATETIME(6)`
+/// value is converted to `chrono::NaiveDateTime` with microsecond precision.
+///
+/// `None` is bound as `NULL`.
+///
+/// ```rust
+/// # use mysql_async::{chrono::NaiveDate, from_value, MaybeZeroDate, Value};
+/// let date: MaybeZeroDate<NaiveDate> = from_value(Value::Date(0, 0, 0, 0, 0, 0, 0));
+/// assert_eq!(date.into_inner(), None);
+///
+/// let date: MaybeZeroDate<NaiveDate> = from_value(Value::from("2020-01-02"));
+/// assert_eq!(date.into_inner(), NaiveDate::from_ymd_opt(2020, 1, 2));
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MaybeZeroDate<T>(pub Option<T>);
+
+impl<T> MaybeZeroDate<T> {
+    /// Returns the wrapped value.
+    pub fn into_inner(self) -> Option<T> {
+        self.0
+    }
+}
+
+impl<T> From<Option<T>> for MaybeZeroDate<T> {
+    fn from(x: Option<T>) -> Self {
+        MaybeZeroDate(x)
+    }
+}
+
+impl<T: Into<Value>> From<MaybeZeroDate<T>> for Value {
+    fn from(MaybeZeroDate(x): MaybeZeroDate<T>) -> Value {
+        x.map(Into::into).unwrap_or(Value::NULL)
+    }
+}
+
+/// Returns `true` if the given value is the zero date (or the zero datetime).
+fn is_zero_date(value: &Value) -> bool {
+    match *value {
+        Value::Date(0, 0, 0, 0, 0, 0, 0) => true,
+        Value::Bytes(ref bytes) => {
+            bytes.starts_with(b"0000-00-00")
+                && bytes
+                    .iter()
+                    .all(|x| matches!(x, b'0' | b'-' | b' ' | b':' | b'.'))
+        }
+        _ => false,
+    }
+}
+
+/// Intermediate result of a `Value`-to-`MaybeZeroDate<T>` conversion.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MaybeZeroDateIr<T> {
+    value: Value,
+    output: Option<T>,
+}
+
+impl<T: FromValue> ConvIr<MaybeZeroDate<T>> for MaybeZeroDateIr<T> {
+    fn new(v: Value) -> Result<Self, FromValueError> {
+        if v == Value::NULL || is_zero_date(&v) {
+            return Ok(MaybeZeroDateIr {
+                value: v,
+                output: None,
+            });
+        }
+        match from_value_opt::<T>(v.clone()) {
+            Ok(output) => Ok(MaybeZeroDateIr {
+                value: v,
+                output: Some(output),
+            }),
+            Err(_) => Err(FromValueError(v)),
+        }
+    }
+
+    fn commit(self) -> MaybeZeroDate<T> {
+        MaybeZeroDate(self.output)
+    }
+
+    fn rollback(self) -> Value {
+        self.value
+    }
+}
+
+impl<T: FromValue> FromValue for MaybeZeroDate<T> {
+    type Intermediate = MaybeZeroDateIr<T>;
+}
+
+#[cfg(test)]
+mod test {
+    use mysql_common::{
+        chrono::{NaiveDate, NaiveDateTime},
+        value::{convert::from_value_opt, Value},
+    };
+
+    use super::MaybeZeroDate;
+    use crate::{prelude::*, test_misc::get_opts, Conn, Row};
+
+    #[test]
+    fn should_convert_zero_date() {
+        for value in vec![
+            Value::NULL,
+            Value::Date(0, 0, 0, 0, 0, 0, 0),
+            Value::from("0000-00-00"),
+            Value::from("0000-00-00 00:00:00.000000"),
+        ] {
+            let date = from_value_opt::<MaybeZeroDate<NaiveDateTime>>(value).unwrap();
+            assert_eq!(date, MaybeZeroDate(None));
+        }
+
+        let datetime = NaiveDate::from_ymd_opt(2020, 1, 2)
+            .and_then(|date| date.and_hms_micro_opt(3, 4, 5, 123_456))
+            .unwrap();
+        for value in vec![
+            Value::from(datetime),
+            Value::from("2020-01-02 03:04:05.123456"),
+        ] {
+            let date = from_value_opt::<MaybeZeroDate<NaiveDateTime>>(value).unwrap();
+            assert_eq!(date, MaybeZeroDate(Some(datetime)));
+        }
+
+        // zero parts of a non-zero date aren't allowed
+        assert!(from_value_opt::<MaybeZeroDate<NaiveDate>>(Value::from("2020-00-00")).is_err());
+        assert!(from_value_opt::<NaiveDate>(Value::Date(0, 0, 0, 0, 0, 0, 0)).is_err());
+
+        assert_eq!(Value::from(MaybeZeroDate::<NaiveDate>(None)), Value::NULL);
+        assert_eq!(
+            Value::from(MaybeZeroDate(Some(datetime))),
+            Value::Date(2020, 1, 2, 3, 4, 5, 123_456)
+        );
+    }
+
+    #[tokio::test]
+    async fn should_round_trip_microseconds_and_zero_dates() -> super::super::Result<()> {
+        let mut conn = Conn::new(get_opts()).await?;
+        // zero dates are rejected in the strict mode
+        conn.query_drop("SET SESSION sql_mode = ''").await?;
+        conn.query_drop(
+            "CREATE TEMPORARY TABLE dates (id INT, dt DATETIME(6), ts TIMESTAMP(6) NULL, d DATE)",
+        )
+        .await?;
+
+        let datetime = NaiveDate::from_ymd_opt(2020, 1, 2)
+            .and_then(|date| date.and_hms_micro_opt(3, 4, 5, 123_456))
+            .unwrap();
+        conn.exec_drop(
+            "INSERT INTO dates VALUES (1, ?, ?, ?)",
+            (datetime, datetime, datetime.date()),
+        )
+        .await?;
+        conn.query_drop(
+            "INSERT INTO dates VALUES (2, '0000-00-00 00:00:00', '0000-00-00 00:00:00', '0000-00-00')",
+        )
+        .await?;
+
+        let query = "SELECT dt, ts, d FROM dates ORDER BY id";
+        let text: Vec<Row> = conn.query(query).await?;
+        let binary: Vec<Row> = conn.exec(query, ()).await?;
+        for rows in vec![text, binary] {
+            assert_eq!(rows[0].get::<NaiveDateTime, _>(0), Some(datetime));
+            assert_eq!(rows[0].get::<NaiveDateTime, _>(1), Some(datetime));
+            assert_eq!(rows[0].get::<NaiveDate, _>(2), Some(datetime.date()));
+
+            assert!(rows[1].get_opt::<NaiveDateTime, _>(0).unwrap().is_err());
+            for i in 0..2 {
+                let date: MaybeZeroDate<NaiveDateTime> = rows[1].get(i).unwrap();
+                assert_eq!(date, MaybeZeroDate(None));
+            }
+            let date: MaybeZeroDate<NaiveDate> = rows[1].get(2).unwrap();
+            assert_eq!(date, MaybeZeroDate(None));
+        }
+
+        conn.disconnect().await?;
+        Ok(())
+    }
+}
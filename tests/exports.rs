@@ -1,15 +1,22 @@
 #[allow(unused_imports)]
 use mysql_async::{
-    chrono, consts, from_row, from_row_opt, from_value, from_value_opt,
+    bigdecimal, chrono, consts, from_row, from_row_opt, from_value, from_value_opt,
     futures::{DisconnectPool, GetConn},
-    params,
+    mysql_enum, params,
     prelude::{
         BatchQuery, ConvIr, FromRow, FromValue, LocalInfileHandler, Protocol, Query, Queryable,
-        StatementLike, ToValue,
+        RowExt, StatementLike, ToValue, ValueExt,
     },
-    time, uuid, BinaryProtocol, BoxFuture, Column, Conn, Deserialized, DriverError, Error,
-    FromRowError, FromValueError, IoError, IsolationLevel, Opts, OptsBuilder, Params, ParseError,
-    Pool, PoolConstraints, PoolOpts, QueryResult, Result, Row, Serialized, ServerError, SslOpts,
-    Statement, TextProtocol, Transaction, TxOpts, UrlError, Value, WhiteListFsLocalInfileHandler,
+    rust_decimal, time, uuid, BinaryProtocol, Bit, BoxFuture, BufferedResult, BufferedResultSet,
+    BulkDmlInfo, CircuitBreakerOpts, ClientCertResolver, ClientIdentity, Column, Conn,
+    ConnAbortHandle, ConnectPhase, ConnectPhaseError, ConnectionLimiter, Deserialized, DriverError,
+    DuplicateKeyInfo, EnumIr, Error, ExactDecimal, FrameDirection, FromRowError, FromValueError,
+    HandshakeFrame, Health, IoError, IsolationLevel, MaybeZeroDate, MaybeZeroDateIr, MysqlEnum,
+    Opts, OptsBuilder, OwnedStatement, Params, ParamsBuilder, ParseError, Pool, PoolBuilder,
+    PoolConstraints, PoolOpts, Prepared, PreparedStmtLimitPolicy, ProxyProtocolVersion,
+    QueryResult, QueryRewriter, RawRow, Result, ResultSetsError, Row, Serialized, ServerError, Set,
+    SetIr, SlowQuery, SlowQueryLogger, SslOpts, Statement, StmtWarmupError, TextProtocol, TimeSpan,
+    TimeSpanIr, Timestamp, Transaction, TxOpts, UpsertOutcome, UrlError, Value, Vector, VectorIr,
+    Warning, WhiteListFsLocalInfileHandler, Year, YearIr, DEFAULT_BUFFER_SIZE,
     DEFAULT_INACTIVE_CONNECTION_TTL, DEFAULT_TTL_CHECK_INTERVAL,
 };